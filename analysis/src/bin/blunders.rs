@@ -0,0 +1,199 @@
+//! Walks a game from an imported replay, searches every position the player faced with a fixed
+//! node budget and reports the placements the bot evaluates worst compared to its own choice.
+//!
+//! Usage: `blunders REPLAY [--round N] [--player N] [--nodes N] [--top N] [--evaluator FILE]`
+//!
+//! `--round` and `--player` pick the game, counting from 0 (default the first player of the
+//! first round). `--nodes` sets the nodes searched per position (default 10000) and `--top` how
+//! many mistakes are listed (default 10). Every mistake comes with a fumen showing the player's
+//! placement and then the bot's.
+use battle::{ ImportedReplay, ImportedPlacement };
+use cold_clear::evaluation::{ Evaluator, EvaluatorConfig };
+use libtetris::{ Board, FallingPiece, Piece, RotationState };
+
+struct Settings {
+    replay: String,
+    round: usize,
+    player: usize,
+    nodes: u32,
+    top: usize,
+    evaluator: EvaluatorConfig
+}
+
+/// A placement the bot disagreed with.
+struct Mistake {
+    index: usize,
+    played: FallingPiece,
+    best: FallingPiece,
+    /// How much worse the bot evaluates the played placement than its own, or `None` if the bot
+    /// doesn't consider the played placement at all.
+    loss: Option<i64>
+}
+
+fn parse_args() -> Settings {
+    let mut settings = Settings {
+        replay: String::new(),
+        round: 0,
+        player: 0,
+        nodes: 10_000,
+        top: 10,
+        evaluator: EvaluatorConfig::default()
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--round" => settings.round = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--round requires a number"),
+            "--player" => settings.player = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--player requires a number"),
+            "--nodes" => settings.nodes = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--nodes requires a positive number"),
+            "--top" => settings.top = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--top requires a number"),
+            "--evaluator" => {
+                let path = args.next().expect("--evaluator requires a file");
+                let file = std::fs::File::open(&path).unwrap_or_else(
+                    |e| panic!("could not open {}: {}", path, e)
+                );
+                settings.evaluator = serde_json::from_reader(file).unwrap_or_else(
+                    |e| panic!("{} contained invalid data: {}", path, e)
+                );
+            }
+            _ if settings.replay.is_empty() => settings.replay = arg,
+            _ => eprintln!("ignoring unknown argument {}", arg)
+        }
+    }
+    if settings.replay.is_empty() {
+        eprintln!("usage: blunders REPLAY [--round N] [--player N] [--nodes N] [--top N]");
+        std::process::exit(1);
+    }
+    settings
+}
+
+fn main() {
+    let settings = parse_args();
+    let eval = settings.evaluator.build();
+    let replay = ImportedReplay::load(&settings.replay).unwrap_or_else(
+        |e| panic!("could not load {}: {}", settings.replay, e)
+    );
+    let game = replay.rounds.get(settings.round)
+        .and_then(|round| round.get(settings.player))
+        .expect("the replay has no such game");
+    println!("Analyzing {} placements by {}", game.placements.len(), game.username);
+    if game.desynced {
+        println!("The replay went out of sync, so the later placements may not be the real ones");
+    }
+
+    let mut mistakes = vec![];
+    for (index, placement) in game.placements.iter().enumerate() {
+        if let Some(mistake) = review(index, placement, &eval, settings.nodes) {
+            mistakes.push(mistake);
+        }
+    }
+    let considered = mistakes.iter().filter(|m| m.loss.is_some()).count();
+    let total_loss: i64 = mistakes.iter().filter_map(|m| m.loss).sum();
+    println!(
+        "The bot disagreed with {} placements, losing {} in total, and didn't consider {} more",
+        considered, total_loss, mistakes.len() - considered
+    );
+
+    // placements the bot didn't consider are sorted last
+    mistakes.sort_by_key(|m| std::cmp::Reverse(m.loss.unwrap_or(-1)));
+    for mistake in mistakes.iter().take(settings.top) {
+        let board = &game.placements[mistake.index].board;
+        let loss = match mistake.loss {
+            Some(loss) => loss.to_string(),
+            None => "unknown".to_owned()
+        };
+        println!(
+            "#{}: played {}, the bot prefers {}, loss {}",
+            mistake.index + 1, describe(mistake.played), describe(mistake.best), loss
+        );
+        println!("  {}", fumen_link(board, mistake.played, mistake.best));
+    }
+}
+
+/// Searches the position before the placement and compares the placement to the bot's choice.
+fn review<E: Evaluator>(
+    index: usize, placement: &ImportedPlacement, eval: &E, nodes: u32
+) -> Option<Mistake> {
+    let options = cold_clear::Options {
+        max_nodes: nodes,
+        ..Default::default()
+    };
+    let mut bot = cold_clear::BotState::<E>::new(placement.board.clone(), options);
+    bot.force_analysis_line(vec![placement.placement]);
+    while let Ok(thinker) = bot.think() {
+        bot.finish_thinking(thinker.think(eval));
+    }
+
+    let candidates = bot.candidates();
+    let best = candidates.first()?;
+    if best.placement.same_location(&placement.placement) {
+        return None
+    }
+    let played = candidates.iter().find(|c| c.placement.same_location(&placement.placement));
+    Some(Mistake {
+        index,
+        played: placement.placement,
+        best: best.placement,
+        loss: played.map(|c| best.evaluation - c.evaluation)
+    })
+}
+
+fn describe(placement: FallingPiece) -> String {
+    let rotation = match placement.kind.1 {
+        RotationState::North => "north",
+        RotationState::East => "east",
+        RotationState::South => "south",
+        RotationState::West => "west"
+    };
+    format!("{} {} at {},{}", placement.kind.0.to_char(), rotation, placement.x, placement.y)
+}
+
+/// A fumen of the board with the played placement on the first page and the bot's on the second.
+fn fumen_link(board: &Board, played: FallingPiece, best: FallingPiece) -> String {
+    let field = board.get_field();
+    let mut fumen = fumen::Fumen::default();
+    for &(placement, comment) in &[(played, "played"), (best, "bot")] {
+        let page = fumen.add_page();
+        for y in 0..23 {
+            for x in 0..10 {
+                page.field[y][x] = if field[y][x] {
+                    fumen::CellColor::Grey
+                } else {
+                    fumen::CellColor::Empty
+                };
+            }
+        }
+        page.piece = Some(fumen_piece(placement));
+        page.comment = Some(comment.to_owned());
+    }
+    format!("https://fumen.zui.jp/?{}", fumen.encode())
+}
+
+fn fumen_piece(placement: FallingPiece) -> fumen::Piece {
+    fumen::Piece {
+        kind: match placement.kind.0 {
+            Piece::I => fumen::PieceType::I,
+            Piece::O => fumen::PieceType::O,
+            Piece::T => fumen::PieceType::T,
+            Piece::L => fumen::PieceType::L,
+            Piece::J => fumen::PieceType::J,
+            Piece::S => fumen::PieceType::S,
+            Piece::Z => fumen::PieceType::Z
+        },
+        rotation: match placement.kind.1 {
+            RotationState::North => fumen::RotationState::North,
+            RotationState::East => fumen::RotationState::East,
+            RotationState::South => fumen::RotationState::South,
+            RotationState::West => fumen::RotationState::West
+        },
+        x: placement.x as u32,
+        y: placement.y as u32
+    }
+}
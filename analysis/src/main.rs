@@ -0,0 +1,256 @@
+//! A line based analysis protocol on stdin and stdout, so the bot can be used from scripts and
+//! other languages without bindings.
+//!
+//! Commands:
+//! - `position ascii ROWS` sets the field. `ROWS` are rows separated by `/`, top row first, where
+//!   `.` and `_` are empty cells and anything else is filled. Missing cells are empty.
+//! - `position fumen DATA` sets the field to the first page of a fumen.
+//! - `position replay FILE ROUND PLAYER N` sets the whole position, queue and state included,
+//!   to the board the player had before their `N`th placement (counting from 0) in a TETR.IO,
+//!   Jstris or Nullpomino replay. Rounds and players count from 0 as well.
+//! - `queue PIECES` sets the next queue, for example `queue TIOLJ`. `queue` alone clears it.
+//! - `hold PIECE` sets the hold piece; `hold none` clears it.
+//! - `state b2b on|off` and `state combo N` set the back-to-back status and combo count.
+//! - `go movetime MS` or `go nodes N` searches the position and prints the result.
+//! - `isready` prints `readyok`.
+//! - `quit` exits.
+//!
+//! `go` prints `info nodes N depth D eval E`, then `pv` followed by the placements the bot
+//! expects to make and `fumen` followed by a fumen of them, then `bestmove` followed by the
+//! placement to make now, or `bestmove none` if there is no move. Placements are written as `PIECE:ROTATION:X:Y`, with `X` and `Y` the
+//! position of the piece's rotation center counting from the bottom left. The best move is
+//! followed by `hold` if the hold piece is used. Errors are printed as `error MESSAGE`.
+//!
+//! Searching starts over from the current position every time, so `go` doesn't change it.
+use cold_clear::evaluation::{ Evaluator, EvaluatorConfig };
+use enumset::EnumSet;
+use libtetris::{ Board, FallingPiece, Piece, RotationState };
+use std::io::prelude::*;
+use std::time::{ Duration, Instant };
+
+struct Position {
+    field: [[bool; 10]; 40],
+    queue: Vec<Piece>,
+    hold: Option<Piece>,
+    b2b: bool,
+    combo: u32
+}
+
+enum Limit {
+    Time(Duration),
+    Nodes(u32)
+}
+
+fn main() {
+    let mut evaluator = EvaluatorConfig::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--evaluator" => {
+                let path = args.next().expect("--evaluator requires a file");
+                let file = std::fs::File::open(&path).unwrap_or_else(
+                    |e| panic!("could not open {}: {}", path, e)
+                );
+                evaluator = serde_json::from_reader(file).unwrap_or_else(
+                    |e| panic!("{} contained invalid data: {}", path, e)
+                );
+            }
+            _ => eprintln!("ignoring unknown argument {}", arg)
+        }
+    }
+    let eval = evaluator.build();
+
+    let mut position = Position {
+        field: [[false; 10]; 40],
+        queue: vec![],
+        hold: None,
+        b2b: false,
+        combo: 0
+    };
+    let stdin = std::io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break
+        };
+        let mut words = line.split_whitespace();
+        let result = match words.next() {
+            None => Ok(()),
+            Some("quit") => break,
+            Some("isready") => {
+                println!("readyok");
+                Ok(())
+            }
+            Some("position") => set_position(&mut position, words),
+            Some("queue") => parse_pieces(words.next().unwrap_or(""))
+                .map(|queue| position.queue = queue),
+            Some("hold") => match words.next() {
+                Some("none") => {
+                    position.hold = None;
+                    Ok(())
+                }
+                Some(piece) => parse_pieces(piece).and_then(|pieces| match *pieces {
+                    [piece] => {
+                        position.hold = Some(piece);
+                        Ok(())
+                    }
+                    _ => Err(format!("expected one piece, got {}", piece))
+                }),
+                None => Err("hold requires a piece or none".to_owned())
+            },
+            Some("state") => set_state(&mut position, words),
+            Some("go") => parse_limit(words).and_then(|limit| go(&position, &eval, limit)),
+            Some(command) => Err(format!("unknown command {}", command))
+        };
+        if let Err(e) = result {
+            println!("error {}", e);
+        }
+        std::io::stdout().flush().ok();
+    }
+}
+
+fn set_position<'a>(
+    position: &mut Position, mut words: impl Iterator<Item=&'a str>
+) -> Result<(), String> {
+    let mut field = [[false; 10]; 40];
+    match (words.next(), words.next()) {
+        (Some("ascii"), Some(rows)) => {
+            let rows: Vec<_> = rows.split('/').collect();
+            if rows.len() > 40 {
+                return Err("the field has at most 40 rows".to_owned())
+            }
+            for (y, row) in rows.iter().rev().enumerate() {
+                for (x, cell) in row.chars().take(10).enumerate() {
+                    field[y][x] = cell != '.' && cell != '_';
+                }
+            }
+        }
+        (Some("fumen"), Some(data)) => {
+            let fumen = fumen::Fumen::decode(data).map_err(|_| "invalid fumen".to_owned())?;
+            let page = fumen.pages.first().ok_or_else(|| "fumen has no pages".to_owned())?;
+            for (y, row) in page.field.iter().enumerate().take(40) {
+                for x in 0..10 {
+                    field[y][x] = row[x] != fumen::CellColor::Empty;
+                }
+            }
+        }
+        (Some("replay"), Some(path)) => {
+            let mut index = || words.next().and_then(|v| v.parse::<usize>().ok());
+            let (round, player, n) = match (index(), index(), index()) {
+                (Some(round), Some(player), Some(n)) => (round, player, n),
+                _ => return Err("expected position replay FILE ROUND PLAYER N".to_owned())
+            };
+            let replay = battle::ImportedReplay::load(path)?;
+            let placement = replay.rounds.get(round)
+                .and_then(|round| round.get(player))
+                .and_then(|game| game.placements.get(n))
+                .ok_or_else(|| "the replay has no such placement".to_owned())?;
+            let board = &placement.board;
+            field = board.get_field();
+            position.queue = board.next_queue().collect();
+            position.hold = board.hold_piece;
+            position.b2b = board.b2b_bonus;
+            position.combo = board.combo;
+        }
+        (Some("ascii"), None) => {}
+        _ => return Err("expected position ascii, position fumen or position replay".to_owned())
+    }
+    position.field = field;
+    Ok(())
+}
+
+fn set_state<'a>(
+    position: &mut Position, mut words: impl Iterator<Item=&'a str>
+) -> Result<(), String> {
+    match (words.next(), words.next()) {
+        (Some("b2b"), Some("on")) => position.b2b = true,
+        (Some("b2b"), Some("off")) => position.b2b = false,
+        (Some("combo"), Some(n)) => position.combo = n.parse()
+            .map_err(|_| format!("invalid combo {}", n))?,
+        _ => return Err("expected state b2b on|off or state combo N".to_owned())
+    }
+    Ok(())
+}
+
+fn parse_pieces(s: &str) -> Result<Vec<Piece>, String> {
+    s.chars().map(|c| match c.to_ascii_uppercase() {
+        'I' => Ok(Piece::I),
+        'O' => Ok(Piece::O),
+        'T' => Ok(Piece::T),
+        'L' => Ok(Piece::L),
+        'J' => Ok(Piece::J),
+        'S' => Ok(Piece::S),
+        'Z' => Ok(Piece::Z),
+        _ => Err(format!("invalid piece {}", c))
+    }).collect()
+}
+
+fn parse_limit<'a>(mut words: impl Iterator<Item=&'a str>) -> Result<Limit, String> {
+    match (words.next(), words.next()) {
+        (Some("movetime"), Some(ms)) => ms.parse()
+            .map(|ms| Limit::Time(Duration::from_millis(ms)))
+            .map_err(|_| format!("invalid movetime {}", ms)),
+        (Some("nodes"), Some(nodes)) => nodes.parse()
+            .map(Limit::Nodes)
+            .map_err(|_| format!("invalid node count {}", nodes)),
+        _ => Err("expected go movetime MS or go nodes N".to_owned())
+    }
+}
+
+fn go<E: Evaluator>(position: &Position, eval: &E, limit: Limit) -> Result<(), String> {
+    if position.queue.is_empty() {
+        return Err("the queue is empty".to_owned())
+    }
+    let mut board = Board::new_with_state(
+        position.field, EnumSet::all(), position.hold, position.b2b, position.combo
+    );
+    for &piece in &position.queue {
+        board.add_next_piece(piece);
+    }
+    let options = cold_clear::Options {
+        max_nodes: match limit {
+            Limit::Nodes(nodes) => nodes,
+            Limit::Time(_) => cold_clear::Options::default().max_nodes
+        },
+        ..Default::default()
+    };
+    let mut bot = cold_clear::BotState::<E>::new(board.clone(), options);
+    let start = Instant::now();
+    while let Ok(thinker) = bot.think() {
+        bot.finish_thinking(thinker.think(eval));
+        if let Limit::Time(time) = limit {
+            if start.elapsed() >= time {
+                break
+            }
+        }
+    }
+
+    let found = bot.force_next_move(eval, None, 0, |mv, info| {
+        if let cold_clear::Info::Normal(info) = &info {
+            println!("info nodes {} depth {} eval {}", info.nodes, info.depth, info.evaluation);
+        }
+        let pv: Vec<_> = info.plan().iter().map(|&(placement, _)| format_placement(placement))
+            .collect();
+        println!("pv {}", pv.join(" "));
+        println!("fumen {}", cold_clear::plan_fumen(&board, info.plan()));
+        println!(
+            "bestmove {}{}",
+            format_placement(mv.expected_location),
+            if mv.hold { " hold" } else { "" }
+        );
+    });
+    if !found {
+        println!("bestmove none");
+    }
+    Ok(())
+}
+
+fn format_placement(placement: FallingPiece) -> String {
+    let rotation = match placement.kind.1 {
+        RotationState::North => "north",
+        RotationState::East => "east",
+        RotationState::South => "south",
+        RotationState::West => "west"
+    };
+    format!("{}:{}:{}:{}", placement.kind.0.to_char(), rotation, placement.x, placement.y)
+}
@@ -5,6 +5,33 @@ use serde::{ Serialize, Deserialize };
 use crate::{ Game, GameConfig, Event };
 use libtetris::Controller;
 
+type Seed = <Pcg64Mcg as SeedableRng>::Seed;
+
+/// Seeds for every random choice made in a battle, derived from a single master seed so that
+/// the same master seed always reproduces the same game.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Seeds {
+    pub p1_pieces: Seed,
+    pub p2_pieces: Seed,
+    pub garbage: Seed,
+    /// Seeds for the search of bots playing as each player; see `cold_clear::Options::seed`.
+    pub p1_bot: u64,
+    pub p2_bot: u64
+}
+
+impl Seeds {
+    pub fn from_master(seed: u64) -> Self {
+        let mut rng = Pcg64Mcg::seed_from_u64(seed);
+        Seeds {
+            p1_pieces: rng.gen(),
+            p2_pieces: rng.gen(),
+            garbage: rng.gen(),
+            p1_bot: rng.gen(),
+            p2_bot: rng.gen()
+        }
+    }
+}
+
 pub struct Battle {
     pub player_1: Game,
     pub player_2: Game,
@@ -12,7 +39,8 @@ pub struct Battle {
     p2_rng: Pcg64Mcg,
     garbage_rng: Pcg64Mcg,
     pub time: u32,
-    pub replay: Replay
+    pub replay: Replay,
+    frame_hashes: Vec<(u8, u8)>
 }
 
 impl Battle {
@@ -31,14 +59,21 @@ impl Battle {
             replay: Replay {
                 p1_name: String::new(), p2_name: String::new(),
                 p1_config, p2_config, p1_seed, p2_seed, garbage_seed,
-                updates: VecDeque::new()
+                updates: VecDeque::new(),
+                checkpoints: vec![]
             },
             player_1, player_2,
             p1_rng, p2_rng, garbage_rng,
             time: 0,
+            frame_hashes: vec![]
         }
     }
 
+    /// Creates a battle whose piece queues and garbage holes are generated from `seeds`.
+    pub fn with_seeds(p1_config: GameConfig, p2_config: GameConfig, seeds: &Seeds) -> Self {
+        Battle::new(p1_config, p2_config, seeds.p1_pieces, seeds.p2_pieces, seeds.garbage)
+    }
+
     pub fn update(&mut self, p1: Controller, p2: Controller) -> BattleUpdate {
         self.time += 1;
 
@@ -49,15 +84,27 @@ impl Battle {
 
         for event in &p1_events {
             if let &Event::GarbageSent(amt) = event {
-                self.player_2.garbage_queue += amt;
+                self.player_2.receive_garbage(amt);
             }
         }
         for event in &p2_events {
             if let &Event::GarbageSent(amt) = event {
-                self.player_1.garbage_queue += amt;
+                self.player_1.receive_garbage(amt);
             }
         }
 
+        let p1_hash = self.player_1.state_hash();
+        let p2_hash = self.player_2.state_hash();
+        self.frame_hashes.push((p1_hash as u8, p2_hash as u8));
+        if self.time % CHECKPOINT_INTERVAL == 0 {
+            self.replay.checkpoints.push(Checkpoint {
+                frame: self.time,
+                p1: p1_hash,
+                p2: p2_hash,
+                frame_hashes: std::mem::replace(&mut self.frame_hashes, vec![])
+            });
+        }
+
         BattleUpdate {
             player_1: PlayerUpdate {
                 events: p1_events,
@@ -94,5 +141,22 @@ pub struct Replay {
     pub garbage_seed: <Pcg64Mcg as SeedableRng>::Seed,
     pub p1_config: GameConfig,
     pub p2_config: GameConfig,
-    pub updates: VecDeque<(Controller, Controller)>
+    pub updates: VecDeque<(Controller, Controller)>,
+    /// The state of both players every `CHECKPOINT_INTERVAL` frames, so re-simulating the replay
+    /// can detect when the rules no longer play out the same way.
+    pub checkpoints: Vec<Checkpoint>
+}
+
+/// Frames between each checkpoint recorded in a replay.
+pub const CHECKPOINT_INTERVAL: u32 = 60;
+
+/// The state hashes of both players after a frame; see `Game::state_hash`.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub frame: u32,
+    pub p1: u64,
+    pub p2: u64,
+    /// The low byte of both players' state hashes on every frame since the previous checkpoint,
+    /// ending with this one, so verifying a replay can find the exact frame it diverged on.
+    pub frame_hashes: Vec<(u8, u8)>
 }
\ No newline at end of file
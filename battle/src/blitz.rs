@@ -0,0 +1,104 @@
+use rand_pcg::Pcg64Mcg;
+use rand::prelude::*;
+use serde::{ Serialize, Deserialize };
+use crate::{ Game, GameConfig, Event, Seeds };
+use libtetris::Controller;
+
+/// The length of a Blitz game in ticks (1 minute).
+pub const BLITZ_TICKS: u32 = 3600;
+
+/// The number of lines cleared to go up a level.
+pub const BLITZ_LEVEL_LINES: u32 = 3;
+
+pub const BLITZ_MAX_LEVEL: u32 = 15;
+
+/// A single player score attack with a short time limit and a level that rises quickly.
+///
+/// Placements score the guideline points of `LockResult::score` at the current level, which
+/// starts at 1 and goes up every `BLITZ_LEVEL_LINES` lines cleared until `BLITZ_MAX_LEVEL`, so
+/// clears score more the later they are made. Drop points are the same as in `Ultra` and aren't
+/// multiplied by the level. Topping out ends the game early, keeping the score reached so far.
+pub struct Blitz {
+    pub game: Game,
+    piece_rng: Pcg64Mcg,
+    garbage_rng: Pcg64Mcg,
+    pub score: u32,
+    pub level: u32,
+    pub lines: u32,
+    pub time: u32,
+    /// Cells the current piece has been soft dropped.
+    soft_dropped: u32,
+    topped_out: bool
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BlitzUpdate {
+    pub events: Vec<Event>,
+    pub score: u32,
+    pub level: u32,
+    /// Whether the level went up during this update.
+    pub leveled_up: bool,
+    pub time: u32,
+    pub finished: bool
+}
+
+impl Blitz {
+    /// Creates a game whose pieces and garbage holes are derived from `seed`.
+    pub fn new(config: GameConfig, seed: u64) -> Self {
+        let seeds = Seeds::from_master(seed);
+        let mut piece_rng = Pcg64Mcg::from_seed(seeds.p1_pieces);
+        Blitz {
+            game: Game::new(config, &mut piece_rng),
+            piece_rng,
+            garbage_rng: Pcg64Mcg::from_seed(seeds.garbage),
+            score: 0,
+            level: 1,
+            lines: 0,
+            time: 0,
+            soft_dropped: 0,
+            topped_out: false
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.topped_out || self.time >= BLITZ_TICKS
+    }
+
+    pub fn time_left(&self) -> u32 {
+        BLITZ_TICKS.saturating_sub(self.time)
+    }
+
+    /// Advances the game by one tick. Once the game is finished, the controller is ignored and no
+    /// events are produced.
+    pub fn update(&mut self, controller: Controller) -> BlitzUpdate {
+        let mut events = vec![];
+        let level = self.level;
+        if !self.is_finished() {
+            self.time += 1;
+            events = self.game.update(controller, &mut self.piece_rng, &mut self.garbage_rng);
+            for event in &events {
+                match event {
+                    Event::SoftDropped => self.soft_dropped += 1,
+                    Event::PiecePlaced { locked, hard_drop_distance, .. } => {
+                        self.score += locked.score(self.level)
+                            + 2 * hard_drop_distance.unwrap_or(0) as u32
+                            + self.soft_dropped;
+                        self.soft_dropped = 0;
+                        self.lines += locked.cleared_lines.len() as u32;
+                        self.level = (1 + self.lines / BLITZ_LEVEL_LINES).min(BLITZ_MAX_LEVEL);
+                    }
+                    Event::GameOver => self.topped_out = true,
+                    _ => {}
+                }
+            }
+        }
+        BlitzUpdate {
+            events,
+            score: self.score,
+            level: self.level,
+            leveled_up: self.level != level,
+            time: self.time,
+            finished: self.is_finished()
+        }
+    }
+}
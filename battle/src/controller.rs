@@ -21,6 +21,19 @@ impl PieceMoveExecutor {
         }
     }
 
+    /// Waits `delay` frames of the piece falling before the first input.
+    pub fn with_delay(mut self, delay: u32) -> Self {
+        self.input_timer = delay;
+        self
+    }
+
+    /// Plays the move without pressing hold in games where hold is disabled. The piece then lands
+    /// somewhere other than where the move expects, so the caller resyncs the bot.
+    pub fn with_hold_enabled(mut self, hold_enabled: bool) -> Self {
+        self.needs_hold &= hold_enabled;
+        self
+    }
+
     pub fn update<R: Row>(
         &mut self, controller: &mut Controller, board: &Board<R>, events: &[Event]
     ) -> Option<FallingPiece> {
@@ -40,6 +53,8 @@ impl PieceMoveExecutor {
                             None => {
                                 self.controller = Default::default();
                                 self.controller.hard_drop = true;
+                                // brings the piece down in games without hard drop
+                                self.controller.soft_drop = true;
                             }
                             Some(PieceMovement::SonicDrop) => {
                                 self.controller.right = false;
@@ -0,0 +1,127 @@
+use rand_pcg::Pcg64Mcg;
+use rand::prelude::*;
+use serde::{ Serialize, Deserialize };
+use crate::{ Game, GameConfig, Event, Seeds };
+use libtetris::Controller;
+
+/// The length of a dig race in ticks (2 minutes).
+pub const DIG_RACE_TICKS: u32 = 7200;
+
+/// A single player race to dig through as much garbage as possible before time runs out.
+///
+/// The bottom of the board is kept filled with `rows` rows of cheese: garbage with the hole in a
+/// different random column every row. Whenever garbage rows are cleared, new ones are added below
+/// after the placement. The holes come from their own random number generator, so the same seed
+/// always produces the same sequence of cheese no matter how it is dug.
+pub struct DigRace {
+    pub game: Game,
+    piece_rng: Pcg64Mcg,
+    garbage_rng: Pcg64Mcg,
+    cheese_rng: Pcg64Mcg,
+    pub rows: u32,
+    /// The number of cheese rows currently at the bottom of the board.
+    garbage_rows: u32,
+    last_hole: usize,
+    /// Cheese rows cleared so far.
+    pub dug: u32,
+    pub time: u32,
+    topped_out: bool
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DigRaceUpdate {
+    pub events: Vec<Event>,
+    pub dug: u32,
+    pub time: u32,
+    pub finished: bool
+}
+
+impl DigRace {
+    /// Creates a dig race with `rows` rows of cheese. The pieces and the cheese are derived from
+    /// `seed`.
+    pub fn new(config: GameConfig, rows: u32, seed: u64) -> Self {
+        let seeds = Seeds::from_master(seed);
+        let mut piece_rng = Pcg64Mcg::from_seed(seeds.p1_pieces);
+        let mut cheese_rng = Pcg64Mcg::from_seed(seeds.garbage);
+        let mut race = DigRace {
+            game: Game::new(config, &mut piece_rng),
+            piece_rng,
+            garbage_rng: Pcg64Mcg::from_seed(seeds.p2_pieces),
+            last_hole: cheese_rng.gen_range(0, 10),
+            cheese_rng,
+            rows,
+            garbage_rows: 0,
+            dug: 0,
+            time: 0,
+            topped_out: false
+        };
+        race.regenerate();
+        race
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.topped_out || self.time >= DIG_RACE_TICKS
+    }
+
+    /// Cheese rows dug per minute so far.
+    pub fn lines_per_minute(&self) -> f64 {
+        self.dug as f64 * 3600.0 / self.time.max(1) as f64
+    }
+
+    /// Advances the race by one tick. Once the race is finished, the controller is ignored and no
+    /// events are produced.
+    pub fn update(&mut self, controller: Controller) -> DigRaceUpdate {
+        let mut events = vec![];
+        if !self.is_finished() {
+            self.time += 1;
+            events = self.game.update(controller, &mut self.piece_rng, &mut self.garbage_rng);
+            let mut placed = false;
+            for event in &events {
+                match event {
+                    Event::PiecePlaced { locked, .. } => {
+                        placed = true;
+                        let dug = locked.cleared_lines.iter()
+                            .filter(|&&y| y < self.garbage_rows as i32)
+                            .count() as u32;
+                        self.garbage_rows -= dug;
+                        self.dug += dug;
+                    }
+                    Event::GameOver => self.topped_out = true,
+                    _ => {}
+                }
+            }
+            if placed && !self.topped_out {
+                let columns = self.regenerate();
+                if !columns.is_empty() {
+                    events.push(Event::GarbageAdded(columns));
+                }
+            }
+        }
+        DigRaceUpdate {
+            events,
+            dug: self.dug,
+            time: self.time,
+            finished: self.is_finished()
+        }
+    }
+
+    /// Adds cheese rows until there are `rows` of them, returning the hole of each added row.
+    fn regenerate(&mut self) -> Vec<usize> {
+        let mut columns = vec![];
+        while self.garbage_rows < self.rows {
+            // every row has its hole in a different column than the row above it
+            let mut hole = self.cheese_rng.gen_range(0, 9);
+            if hole >= self.last_hole {
+                hole += 1;
+            }
+            self.last_hole = hole;
+            columns.push(hole);
+            self.garbage_rows += 1;
+            if self.game.board.add_garbage(hole) {
+                self.topped_out = true;
+                break
+            }
+        }
+        columns
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{ Serialize, Deserialize };
 use libtetris::*;
 use rand::prelude::*;
+use std::collections::VecDeque;
 use crate::GameConfig;
 
 pub struct Game {
@@ -13,8 +14,17 @@ pub struct Game {
     left_das: u32,
     right_das: u32,
     going_right: bool,
+    /// Shift and rotation key presses since the last piece was placed or held.
+    presses: u32,
     pub garbage_queue: u32,
-    pub attacking: u32
+    /// Garbage in `garbage_queue` that can't enter the board yet as (lines, ticks left), oldest
+    /// first.
+    delayed_garbage: VecDeque<(u32, u32)>,
+    pub attacking: u32,
+    /// Ticks since the game started.
+    time: u32,
+    /// The earliest tick the next piece can spawn, enforcing `GameConfig::min_piece_interval`.
+    next_spawn: u32
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -33,9 +43,14 @@ pub enum Event {
     PiecePlaced {
         piece: FallingPiece,
         locked: LockResult,
-        hard_drop_distance: Option<i32>
+        hard_drop_distance: Option<i32>,
+        /// Key presses beyond `libtetris::finesse_presses` for the placement, or 0 if the piece
+        /// couldn't have been hard dropped into place.
+        finesse_faults: u32
     },
     GarbageSent(u32),
+    /// Margin time changed the attack multiplier, in percent.
+    AttackMultiplier(u32),
     GarbageAdded(Vec<usize>),
     GameOver
 }
@@ -60,9 +75,13 @@ struct FallingState {
 impl Game {
     pub fn new(config: GameConfig, piece_rng: &mut impl Rng) -> Self {
         let mut board = Board::new();
+        board.set_randomizer(config.randomizer);
         for _ in 0..config.next_queue_size {
             board.add_next_piece(board.generate_next_piece(piece_rng));
         }
+        for _ in 0..config.starting_garbage {
+            board.add_garbage(piece_rng.gen_range(0, 10));
+        }
         Game {
             board, config,
             prev: Default::default(),
@@ -71,23 +90,87 @@ impl Game {
             left_das: config.delayed_auto_shift,
             right_das: config.delayed_auto_shift,
             going_right: false,
+            presses: 0,
             state: GameState::SpawnDelay(config.spawn_delay),
             garbage_queue: 0,
-            attacking: 0
+            delayed_garbage: VecDeque::new(),
+            attacking: 0,
+            time: 0,
+            next_spawn: 0
+        }
+    }
+
+    /// Queues garbage sent by the opponent.
+    pub fn receive_garbage(&mut self, lines: u32) {
+        self.garbage_queue += lines;
+        if self.config.garbage_delay > 0 {
+            self.delayed_garbage.push_back((lines, self.config.garbage_delay));
+        }
+    }
+
+    /// The percentage attacks are currently scaled by; see `GameConfig::margin_time`.
+    pub fn attack_multiplier(&self) -> u32 {
+        self.config.attack_multiplier(self.time)
+    }
+
+    /// A hash of the board, queue, hold piece, falling piece and garbage queue, used to check
+    /// that replays re-simulate the same way. This is FNV-1a so it is stable across platforms and
+    /// compiler versions.
+    pub fn state_hash(&self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325u64;
+        let mut write = |value: i64| {
+            for &byte in &value.to_le_bytes() {
+                hash = (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3);
+            }
+        };
+        for row in self.board.get_field().iter() {
+            write(row.iter().enumerate().map(|(x, &c)| (c as i64) << x).sum());
+        }
+        write(self.board.hold_piece.map_or(-1, |p| p as i64));
+        for piece in self.board.next_queue() {
+            write(piece as i64);
+        }
+        write(self.board.b2b_bonus as i64);
+        write(self.board.combo as i64);
+        write(self.garbage_queue as i64);
+        if let GameState::Falling(falling) = &self.state {
+            write(falling.piece.kind.0 as i64);
+            write(falling.piece.kind.1 as i64);
+            write(falling.piece.x as i64);
+            write(falling.piece.y as i64);
         }
+        hash
+    }
+
+    /// Garbage that can enter the board now.
+    fn active_garbage(&self) -> u32 {
+        self.garbage_queue - self.delayed_garbage.iter().map(|&(lines, _)| lines).sum::<u32>()
     }
 
     pub fn update(
         &mut self, current: Controller, piece_rng: &mut impl Rng, garbage_rng: &mut impl Rng
     ) -> Vec<Event> {
+        self.time += 1;
+        for (_, ticks) in &mut self.delayed_garbage {
+            *ticks -= 1;
+        }
+        while self.delayed_garbage.front().map_or(false, |&(_, ticks)| ticks == 0) {
+            self.delayed_garbage.pop_front();
+        }
+
         update_input(&mut self.used.left, self.prev.left, current.left);
         update_input(&mut self.used.right, self.prev.right, current.right);
         update_input(&mut self.used.rotate_right, self.prev.rotate_right, current.rotate_right);
         update_input(&mut self.used.rotate_left, self.prev.rotate_left, current.rotate_left);
         update_input(&mut self.used.soft_drop, self.prev.soft_drop, current.soft_drop);
         update_input(&mut self.used.hold, self.prev.hold, current.hold);
-        self.used.hard_drop = !self.prev.hard_drop && current.hard_drop;
-        self.used.soft_drop = current.soft_drop;
+        self.used.hard_drop = self.config.hard_drop_enabled
+            && !self.prev.hard_drop && current.hard_drop;
+        self.used.soft_drop = self.config.soft_drop_enabled && current.soft_drop;
+        self.presses += (!self.prev.left && current.left) as u32
+            + (!self.prev.right && current.right) as u32
+            + (!self.prev.rotate_left && current.rotate_left) as u32
+            + (!self.prev.rotate_right && current.rotate_right) as u32;
 
         if !self.prev.left && current.left {
             self.going_right = false;
@@ -139,8 +222,10 @@ impl Game {
 
         self.prev = current;
 
-        match self.state {
+        let mut events = match self.state {
+            GameState::SpawnDelay(0) if self.time < self.next_spawn => vec![],
             GameState::SpawnDelay(0) => {
+                self.next_spawn = self.time + self.config.min_piece_interval;
                 let mut events = vec![];
                 if self.config.spawn_delay == 0 {
                     events.push(Event::FrameBeforePieceSpawns);
@@ -157,10 +242,8 @@ impl Game {
                         lock_delay: 30,
                         soft_drop_delay: 0
                     });
-                    let mut ghost = spawned;
-                    ghost.sonic_drop(&self.board);
                     events.push(Event::PieceSpawned { new_in_queue: new_piece });
-                    events.push(Event::PieceFalling(spawned, ghost));
+                    events.push(Event::PieceFalling(spawned, self.board.ghost(&spawned)));
                 } else {
                     self.state = GameState::GameOver;
                     events.push(Event::GameOver);
@@ -196,8 +279,9 @@ impl Game {
                 let was_on_stack = self.board.on_stack(&falling.piece);
 
                 // Hold
-                if !self.did_hold && self.used.hold {
+                if self.config.hold_enabled && !self.did_hold && self.used.hold {
                     self.did_hold = true;
+                    self.presses = 0;
                     events.push(Event::PieceHeld(falling.piece.kind.0));
                     if let Some(piece) = self.board.hold(falling.piece.kind.0) {
                         // Piece in hold; the piece spawns instantly
@@ -210,8 +294,7 @@ impl Game {
                                 lock_delay: 30,
                                 soft_drop_delay: 0
                             };
-                            let mut ghost = spawned;
-                            ghost.sonic_drop(&self.board);
+                            let ghost = self.board.ghost(&spawned);
                             events.push(Event::PieceFalling(spawned, ghost));
                         } else {
                             // Hold piece couldn't spawn; Block Out
@@ -343,13 +426,18 @@ impl Game {
                     }
                 }
 
-                let mut ghost = falling.piece;
-                ghost.sonic_drop(&self.board);
+                let ghost = self.board.ghost(&falling.piece);
                 events.push(Event::PieceFalling(falling.piece, ghost));
 
                 events
             }
+        };
+
+        let multiplier = self.attack_multiplier();
+        if multiplier != self.config.attack_multiplier(self.time - 1) {
+            events.push(Event::AttackMultiplier(multiplier));
         }
+        events
     }
 
     fn lock(
@@ -360,12 +448,20 @@ impl Game {
         dist: Option<i32>
     ) {
         self.did_hold = false;
+        let hard_droppable = falling.piece.tspin == TspinStatus::None
+            && self.board.above_stack(&falling.piece);
+        let finesse_faults = match finesse_presses(&falling.piece) {
+            Some(fewest) if hard_droppable => self.presses.saturating_sub(fewest),
+            _ => 0
+        };
+        self.presses = 0;
         let locked = self.board.lock_piece(falling.piece);
 
         events.push(Event::PiecePlaced {
             piece: falling.piece,
             locked: locked.clone(),
-            hard_drop_distance: dist
+            hard_drop_distance: dist,
+            finesse_faults
         });
 
         if locked.locked_out {
@@ -375,37 +471,50 @@ impl Game {
             self.state = GameState::SpawnDelay(self.config.spawn_delay);
             self.deal_garbage(events, garbage_rng);
         } else {
-            self.attacking += locked.garbage_sent;
+            let attack = self.config.attack_table.attack(&locked);
+            self.attacking += attack * self.attack_multiplier() / 100;
             self.state = GameState::LineClearDelay(self.config.line_clear_delay);
         }
     }
 
     fn deal_garbage(&mut self, events: &mut Vec<Event>, rng: &mut impl Rng) {
-        if self.attacking > self.garbage_queue {
-            self.attacking -= self.garbage_queue;
-            self.garbage_queue = 0;
-        } else {
-            self.garbage_queue -= self.attacking;
-            self.attacking = 0;
+        if self.config.garbage_cancelling {
+            // the oldest garbage is cancelled first, so delayed garbage is cancelled last
+            let cancelled = self.attacking.min(self.garbage_queue);
+            let mut delayed_cancelled = cancelled.saturating_sub(self.active_garbage());
+            self.attacking -= cancelled;
+            self.garbage_queue -= cancelled;
+            while delayed_cancelled > 0 {
+                let (lines, _) = self.delayed_garbage.front_mut().unwrap();
+                let n = (*lines).min(delayed_cancelled);
+                *lines -= n;
+                delayed_cancelled -= n;
+                if *lines == 0 {
+                    self.delayed_garbage.pop_front();
+                }
+            }
         }
-        if self.garbage_queue > 0 {
+        let active = self.active_garbage();
+        if active > 0 {
             let mut dead = false;
             let mut col = rng.gen_range(0, 10);
             let mut garbage_columns = vec![];
-            for _ in 0..self.garbage_queue.min(self.config.max_garbage_add) {
+            for _ in 0..active.min(self.config.max_garbage_add) {
                 if rng.gen_bool(1.0/3.0) {
                     col = rng.gen_range(0, 10);
                 }
                 garbage_columns.push(col);
                 dead |= self.board.add_garbage(col);
             }
-            self.garbage_queue -= self.garbage_queue.min(self.config.max_garbage_add);
+            self.garbage_queue -= active.min(self.config.max_garbage_add);
             events.push(Event::GarbageAdded(garbage_columns));
             if dead {
                 events.push(Event::GameOver);
                 self.state = GameState::GameOver;
+                return
             }
-        } else if self.attacking > 0 {
+        }
+        if self.attacking > 0 {
             events.push(Event::GarbageSent(self.attacking));
             self.attacking = 0;
         }
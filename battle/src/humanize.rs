@@ -0,0 +1,120 @@
+use libtetris::{ Board, Row, Piece, FallingPiece, PieceMovement, SpawnRule };
+use rand::prelude::*;
+use rand_pcg::Pcg64Mcg;
+use std::collections::VecDeque;
+use crate::PieceMoveExecutor;
+
+/// Makes a bot's moves look like a human played them, by turning them into `PieceMoveExecutor`s
+/// that react late, input slowly, waste inputs and sometimes misdrop.
+///
+/// Everything is controlled by `skill`, between 0 (a beginner) and 1 (a frame-perfect machine).
+/// A misdrop puts the piece somewhere other than the move's expected location. The usual check
+/// of the executor's result against the expected location then resets the bot, so it plans
+/// again from the misdropped board.
+pub struct Humanizer {
+    skill: f64,
+    rng: Pcg64Mcg
+}
+
+impl Humanizer {
+    pub fn new(skill: f64, seed: u64) -> Self {
+        Humanizer {
+            skill: skill.max(0.0).min(1.0),
+            rng: Pcg64Mcg::seed_from_u64(seed)
+        }
+    }
+
+    /// Frames between the piece spawning and the first input, on average.
+    pub fn reaction_delay(&self) -> u32 {
+        self.lerp(30.0, 0.0).round() as u32
+    }
+
+    /// Frames between inputs; see `PieceMoveExecutor::new`.
+    pub fn speed_limit(&self) -> u32 {
+        self.lerp(6.0, 0.0).round() as u32
+    }
+
+    /// The chance of wasting inputs on a piece.
+    pub fn finesse_fault_chance(&self) -> f64 {
+        self.lerp(0.3, 0.0)
+    }
+
+    /// The chance of misdropping a piece.
+    pub fn misdrop_chance(&self) -> f64 {
+        self.lerp(0.08, 0.0)
+    }
+
+    /// Creates an executor for a move like `PieceMoveExecutor::new`, with the mistakes and delays
+    /// of this humanizer's skill level. `piece` is the piece the move places, which spawns on
+    /// `board`. The executor inputs no faster than `speed_limit`, or slower if the skill level
+    /// calls for it.
+    pub fn executor<R: Row>(
+        &mut self,
+        board: &Board<R>,
+        piece: Piece,
+        hold: bool,
+        mut inputs: VecDeque<PieceMovement>,
+        speed_limit: u32
+    ) -> PieceMoveExecutor {
+        use PieceMovement::*;
+
+        if self.rng.gen_bool(self.finesse_fault_chance()) {
+            // inputs that cancel each other out, like a human correcting a wrong turn
+            let (wrong, undo) = *[(Left, Right), (Right, Left), (Cw, Ccw), (Ccw, Cw)]
+                .choose(&mut self.rng).unwrap();
+            // turning back doesn't undo a turn that kicked the piece somewhere else
+            let cancels = match wrong {
+                Cw | Ccw => SpawnRule::Row19Or20.spawn(piece, board).map_or(false, |mut spawned| {
+                    turn_in_place(&mut spawned, board, wrong)
+                        && turn_in_place(&mut spawned, board, undo)
+                }),
+                _ => true
+            };
+            if cancels {
+                inputs.push_front(undo);
+                inputs.push_front(wrong);
+            }
+        }
+
+        if self.rng.gen_bool(self.misdrop_chance()) {
+            let shifts = inputs.iter().filter(|&&m| m == Left || m == Right).count();
+            if shifts > 0 && self.rng.gen() {
+                // one shift too few
+                let skip = self.rng.gen_range(0, shifts);
+                let index = inputs.iter()
+                    .enumerate()
+                    .filter(|&(_, &m)| m == Left || m == Right)
+                    .nth(skip)
+                    .map(|(i, _)| i)
+                    .unwrap();
+                inputs.remove(index);
+            } else {
+                // one shift too many before the piece is dropped
+                let extra = if self.rng.gen() { Left } else { Right };
+                let index = inputs.iter()
+                    .position(|&m| m == SonicDrop)
+                    .unwrap_or(inputs.len());
+                inputs.insert(index, extra);
+            }
+        }
+
+        let reaction = self.reaction_delay() as f64 * self.rng.gen_range(0.5, 1.5);
+        PieceMoveExecutor::new(hold, inputs, self.speed_limit().max(speed_limit))
+            .with_delay(reaction.round() as u32)
+    }
+
+    /// Interpolates from the value at skill 0 to the value at skill 1.
+    fn lerp(&self, beginner: f64, perfect: f64) -> f64 {
+        beginner + (perfect - beginner) * self.skill
+    }
+}
+
+/// Rotates the piece, returning whether it turned without being kicked.
+fn turn_in_place<R: Row>(piece: &mut FallingPiece, board: &Board<R>, turn: PieceMovement) -> bool {
+    let (x, y) = (piece.x, piece.y);
+    let turned = match turn {
+        PieceMovement::Cw => piece.cw(board),
+        _ => piece.ccw(board)
+    };
+    turned && piece.x == x && piece.y == y
+}
@@ -0,0 +1,190 @@
+use std::path::Path;
+use libtetris::{ Board, FallingPiece, LockResult, Piece, SpawnRule };
+
+/// Games imported from the replay file of another client.
+///
+/// None of the supported clients store placements, so they are replayed from the recorded
+/// inputs with the piece sequence regenerated from the game's seed. Each client's timing is only
+/// approximated, so games can go out of sync; see `ImportedGame::desynced`. The details of each
+/// format are documented on its parse function.
+#[derive(Clone, Debug)]
+pub struct ImportedReplay {
+    /// The games of every round. Single player replays have one round with one game.
+    pub rounds: Vec<Vec<ImportedGame>>
+}
+
+#[derive(Clone, Debug)]
+pub struct ImportedGame {
+    pub username: String,
+    pub placements: Vec<ImportedPlacement>,
+    /// Whether the replay went out of sync with the game, noticed by an input arriving while
+    /// there was no piece. A game that goes out of sync can also end early by topping out.
+    pub desynced: bool
+}
+
+#[derive(Clone, Debug)]
+pub struct ImportedPlacement {
+    /// When the piece was hard dropped, in frames for TETR.IO and Nullpomino and milliseconds
+    /// for Jstris.
+    pub time: u32,
+    /// The board when the piece spawned. The queue starts with the piece being placed, followed
+    /// by the next pieces visible to the player.
+    pub board: Board,
+    pub placement: FallingPiece,
+    pub hold: bool,
+    pub lock: LockResult,
+    /// Lines of garbage that entered the board after the placement.
+    pub garbage: u32
+}
+
+impl ImportedReplay {
+    /// Loads a replay, picking the format from the file extension: `.ttr` and `.ttrm` for
+    /// TETR.IO, `.rep` for Nullpomino and anything else for Jstris.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ttr") | Some("ttrm") => ImportedReplay::parse_tetrio(&data),
+            Some("rep") => ImportedReplay::parse_nullpomino(&data),
+            _ => ImportedReplay::parse_jstris(&data)
+        }
+    }
+
+    pub fn parse_tetrio(json: &str) -> Result<Self, String> {
+        crate::tetrio::parse(json).map(|rounds| ImportedReplay { rounds })
+    }
+
+    pub fn parse_jstris(json: &str) -> Result<Self, String> {
+        crate::jstris::parse(json).map(|game| ImportedReplay { rounds: vec![vec![game]] })
+    }
+
+    pub fn parse_nullpomino(properties: &str) -> Result<Self, String> {
+        crate::nullpomino::parse(properties)
+            .map(|game| ImportedReplay { rounds: vec![vec![game]] })
+    }
+}
+
+/// The part of replaying inputs that all clients share: the board, the falling piece and
+/// recording placements.
+pub(crate) struct Playback {
+    pub board: Board,
+    pub piece: Option<FallingPiece>,
+    /// Whether the player topped out.
+    pub over: bool,
+    pub game: ImportedGame,
+    pieces: Box<dyn Iterator<Item=Piece>>,
+    next_pieces: usize,
+    spawned_board: Board,
+    held: bool
+}
+
+impl Playback {
+    pub fn new(
+        pieces: impl Iterator<Item=Piece> + 'static, next_pieces: usize, username: String
+    ) -> Self {
+        Playback {
+            board: Board::new(),
+            piece: None,
+            over: false,
+            game: ImportedGame {
+                username,
+                placements: vec![],
+                desynced: false
+            },
+            pieces: Box::new(pieces),
+            next_pieces,
+            spawned_board: Board::new(),
+            held: false
+        }
+    }
+
+    /// Spawns the next piece, ending the game if it couldn't spawn.
+    pub fn spawn(&mut self) {
+        while self.board.next_queue().count() <= self.next_pieces {
+            match self.pieces.next() {
+                Some(piece) => self.board.add_next_piece(piece),
+                None => break
+            }
+        }
+        self.spawned_board = self.board.clone();
+        self.held = false;
+        match self.board.advance_queue() {
+            Some(piece) => self.spawn_piece(piece),
+            None => self.over = true
+        }
+    }
+
+    fn spawn_piece(&mut self, piece: Piece) {
+        self.piece = SpawnRule::Row19Or20.spawn(piece, &self.board);
+        self.over |= self.piece.is_none();
+    }
+
+    /// Applies `f` to the falling piece, returning false if there is none.
+    pub fn with_piece(&mut self, f: impl FnOnce(&mut FallingPiece, &Board)) -> bool {
+        match &mut self.piece {
+            Some(piece) => {
+                f(piece, &self.board);
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Rotates the falling piece by 180 degrees using the kicks of two quarter turns.
+    pub fn rotate_180(&mut self) -> bool {
+        self.with_piece(|piece, board| {
+            let mut turned = *piece;
+            if turned.cw(board) && turned.cw(board) {
+                *piece = turned;
+            }
+        })
+    }
+
+    /// Holds the falling piece, returning false if there is none. Holding twice does nothing.
+    pub fn hold(&mut self) -> bool {
+        let piece = match self.piece {
+            Some(piece) => piece,
+            None => return false
+        };
+        if self.held {
+            return true
+        }
+        self.held = true;
+        let next = match self.board.hold(piece.kind.0) {
+            Some(next) => next,
+            None => match self.board.advance_queue() {
+                Some(next) => next,
+                None => return false
+            }
+        };
+        self.spawn_piece(next);
+        true
+    }
+
+    /// Hard drops the falling piece and records the placement without spawning the next piece,
+    /// so garbage can enter first.
+    pub fn lock(&mut self, time: u32) -> Option<LockResult> {
+        let mut piece = self.piece.take()?;
+        piece.sonic_drop(&self.board);
+        let lock = self.board.lock_piece(piece);
+        self.over |= lock.locked_out;
+        self.game.placements.push(ImportedPlacement {
+            time,
+            board: self.spawned_board.clone(),
+            placement: piece,
+            hold: self.held,
+            lock: lock.clone(),
+            garbage: 0
+        });
+        Some(lock)
+    }
+
+    /// Adds a line of garbage with the hole in `column`, or a solid line if `column` is 10 or
+    /// more. The line is counted towards the last placement.
+    pub fn add_garbage(&mut self, column: usize) {
+        self.over |= self.board.add_garbage(column);
+        if let Some(last) = self.game.placements.last_mut() {
+            last.garbage += 1;
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use serde_json::Value;
+use libtetris::Piece;
+use crate::import::{ ImportedGame, Playback };
+
+/// The number of next pieces Jstris shows.
+const NEXT_PIECES: usize = 5;
+
+const MOVE_LEFT: u8 = 0;
+const MOVE_RIGHT: u8 = 1;
+const DAS_LEFT: u8 = 2;
+const DAS_RIGHT: u8 = 3;
+const ROTATE_LEFT: u8 = 4;
+const ROTATE_RIGHT: u8 = 5;
+const ROTATE_180: u8 = 6;
+const HARD_DROP: u8 = 7;
+const SOFT_DROP_BEGIN_END: u8 = 8;
+const GRAVITY_STEP: u8 = 9;
+const HOLD_BLOCK: u8 = 10;
+const GARBAGE_ADD: u8 = 11;
+const SGARBAGE_ADD: u8 = 12;
+const ARR_MOVE: u8 = 14;
+
+/// Reads a Jstris replay, the decompressed JSON object with the game settings under `c` and the
+/// base64 encoded actions under `d`.
+///
+/// Every action is four bytes: the time in milliseconds in the first 20 bits, wrapping around,
+/// then the action in 4 bits and its data in a byte. Moves to the wall are recorded as their own
+/// actions, so handling doesn't need to be simulated, but soft drop is treated as instant.
+/// Garbage actions add `data >> 4` lines with the hole in column `data & 15`.
+pub(crate) fn parse(json: &str) -> Result<ImportedGame, String> {
+    let root: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let config = &root["c"];
+    let seed = match &config["seed"] {
+        Value::String(seed) => seed.clone(),
+        Value::Number(seed) => seed.to_string(),
+        _ => return Err("replay has no seed".to_owned())
+    };
+    let data = root["d"].as_str().ok_or_else(|| "replay has no actions".to_owned())?;
+    let data = base64::decode(data).map_err(|e| e.to_string())?;
+
+    let mut rng = Alea::new(&seed);
+    let pieces = std::iter::repeat_with(move || {
+        // Jstris numbers the pieces in this order
+        use Piece::*;
+        let mut bag = vec![I, O, T, L, J, S, Z];
+        let mut drawn = Vec::with_capacity(bag.len());
+        while !bag.is_empty() {
+            drawn.push(bag.remove((rng.next() * bag.len() as f64) as usize));
+        }
+        drawn
+    }).flatten();
+    let username = config["name"].as_str().unwrap_or("").to_owned();
+    let mut playback = Playback::new(pieces, NEXT_PIECES, username);
+    playback.spawn();
+
+    let mut soft_drop = false;
+    let mut last_time = 0;
+    let mut wraps = 0;
+    for action in data.chunks_exact(4) {
+        let mut time = ((action[0] as u32) << 12)
+            | ((action[1] as u32) << 4)
+            | (action[2] >> 4) as u32;
+        if time < last_time {
+            wraps += 1 << 20;
+        }
+        last_time = time;
+        time += wraps;
+        let kind = action[2] & 15;
+        let data = action[3];
+
+        let ok = match kind {
+            MOVE_LEFT | MOVE_RIGHT | ARR_MOVE => {
+                let dx = if kind == MOVE_LEFT || (kind == ARR_MOVE && data == 0) { -1 } else { 1 };
+                playback.with_piece(|piece, board| {
+                    piece.shift(board, dx, 0);
+                })
+            }
+            DAS_LEFT | DAS_RIGHT => {
+                let dx = if kind == DAS_LEFT { -1 } else { 1 };
+                playback.with_piece(|piece, board| while piece.shift(board, dx, 0) {})
+            }
+            ROTATE_LEFT => playback.with_piece(|piece, board| {
+                piece.ccw(board);
+            }),
+            ROTATE_RIGHT => playback.with_piece(|piece, board| {
+                piece.cw(board);
+            }),
+            ROTATE_180 => playback.rotate_180(),
+            SOFT_DROP_BEGIN_END => {
+                soft_drop = !soft_drop;
+                true
+            }
+            GRAVITY_STEP => playback.with_piece(|piece, board| {
+                piece.shift(board, 0, -1);
+            }),
+            HOLD_BLOCK => playback.hold(),
+            HARD_DROP => {
+                let locked = playback.lock(time).is_some();
+                if !playback.over {
+                    playback.spawn();
+                }
+                locked
+            }
+            GARBAGE_ADD | SGARBAGE_ADD => {
+                let column = if kind == SGARBAGE_ADD { 10 } else { (data & 15) as usize };
+                for _ in 0..data >> 4 {
+                    playback.add_garbage(column);
+                }
+                true
+            }
+            _ => true
+        };
+        if soft_drop {
+            playback.with_piece(|piece, board| {
+                piece.sonic_drop(board);
+            });
+        }
+        if !ok {
+            playback.game.desynced = true;
+        }
+        if !ok || playback.over {
+            break
+        }
+    }
+    Ok(playback.game)
+}
+
+/// Johannes Baagøe's Alea generator, which Jstris generates the piece sequence with.
+struct Alea {
+    s0: f64,
+    s1: f64,
+    s2: f64,
+    c: f64
+}
+
+impl Alea {
+    fn new(seed: &str) -> Self {
+        let mut n = 0xefc8249d_u32 as f64;
+        let mut mash = |data: &str| {
+            for c in data.encode_utf16() {
+                n += c as f64;
+                let mut h = 0.02519603282416938 * n;
+                n = (h as u64 % 0x1_0000_0000) as f64;
+                h -= n;
+                h *= n;
+                n = (h as u64 % 0x1_0000_0000) as f64;
+                h -= n;
+                n += h * 4294967296.0;
+            }
+            (n as u64 % 0x1_0000_0000) as f64 * 2.3283064365386963e-10
+        };
+        let mut s0 = mash(" ");
+        let mut s1 = mash(" ");
+        let mut s2 = mash(" ");
+        s0 -= mash(seed);
+        if s0 < 0.0 {
+            s0 += 1.0;
+        }
+        s1 -= mash(seed);
+        if s1 < 0.0 {
+            s1 += 1.0;
+        }
+        s2 -= mash(seed);
+        if s2 < 0.0 {
+            s2 += 1.0;
+        }
+        Alea { s0, s1, s2, c: 1.0 }
+    }
+
+    fn next(&mut self) -> f64 {
+        let t = 2091639.0 * self.s0 + self.c * 2.3283064365386963e-10;
+        self.s0 = self.s1;
+        self.s1 = self.s2;
+        self.c = t.floor();
+        self.s2 = t - self.c;
+        self.s2
+    }
+}
@@ -1,29 +1,91 @@
 use serde::{ Serialize, Deserialize };
 
 mod battle;
-pub use battle::{ Battle, BattleUpdate, PlayerUpdate, Replay };
+pub use battle::{
+    Battle, BattleUpdate, PlayerUpdate, Replay, Seeds, Checkpoint, CHECKPOINT_INTERVAL
+};
+mod blitz;
+pub use blitz::{ Blitz, BlitzUpdate, BLITZ_TICKS, BLITZ_LEVEL_LINES, BLITZ_MAX_LEVEL };
 mod controller;
 pub use controller::PieceMoveExecutor;
+mod dig;
+pub use dig::{ DigRace, DigRaceUpdate, DIG_RACE_TICKS };
 mod game;
 pub use game::{ Event, Game };
+mod humanize;
+pub use humanize::Humanizer;
+mod import;
+pub use import::{ ImportedReplay, ImportedGame, ImportedPlacement };
+mod jstris;
+mod nullpomino;
+mod recorded;
+pub use recorded::RecordedInput;
+mod replay;
+pub use replay::{ ReplayPlayer, Divergence, REPLAY_VERSION };
+mod royale;
+pub use royale::{ Royale, RoyaleUpdate, Targeting };
+mod stats;
+pub use stats::{
+    GameStats, PlayerStats, StatsRecorder, Summary, PlayerSummary, OpenerSummary,
+    SPIKE_WINDOW, OPENING_PLACEMENTS
+};
+mod survival;
+pub use survival::{ Survival, SurvivalSchedule, SurvivalUpdate };
+mod tetrio;
+mod training;
+pub use training::TrainingRecord;
+mod ultra;
+pub use ultra::{ Ultra, UltraUpdate, ULTRA_TICKS };
 
 /// Units are in ticks
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct GameConfig {
+    /// Entry delay (ARE) between a piece locking and the next piece spawning.
     pub spawn_delay: u32,
     pub line_clear_delay: u32,
     pub delayed_auto_shift: u32,
     pub auto_repeat_rate: u32,
     pub soft_drop_speed: u32,
     pub lock_delay: u32,
-    /// Measured in 1/100 of a tick
+    /// Time for a piece to fall one cell, measured in 1/100 of a tick
     pub gravity: i32,
 
     pub next_queue_size: u32,
+    /// The most garbage lines that can enter the board after one piece.
     pub max_garbage_add: u32,
+    /// The number of times moving or rotating a piece on the ground can reset the lock delay.
     pub move_lock_rule: u32,
-    pub garbage_blocking: bool
+    /// Whether garbage only enters after a piece that doesn't clear lines. Otherwise it also
+    /// enters after the line clear delay.
+    pub garbage_blocking: bool,
+    /// Ticks after garbage is received before it can enter the board.
+    pub garbage_delay: u32,
+    /// Whether outgoing attacks cancel queued garbage before being sent to the opponent.
+    pub garbage_cancelling: bool,
+    /// Ticks after the game starts before attacks start getting stronger, or `None` to disable
+    /// margin time.
+    pub margin_time: Option<u32>,
+    /// Ticks between each increase of the attack multiplier once margin time has started.
+    pub margin_interval: u32,
+    /// How much the attack multiplier increases each interval, in percent.
+    pub margin_step: u32,
+    /// The randomizer generating the pieces.
+    pub randomizer: libtetris::RandomizerKind,
+    pub attack_table: AttackTable,
+    /// The fewest ticks between two pieces spawning, capping the pieces per second. 0 for no cap.
+    pub min_piece_interval: u32,
+    /// Lines of garbage on the board when the game starts.
+    pub starting_garbage: u32,
+    /// Whether the hold key works. Bots should have their `use_hold` option set to match, so they
+    /// don't plan moves that need hold, and executors should be told with
+    /// `PieceMoveExecutor::with_hold_enabled`.
+    pub hold_enabled: bool,
+    /// Whether the hard drop key works. Without it, pieces only lock through lock delay, like in
+    /// older games.
+    pub hard_drop_enabled: bool,
+    /// Whether the soft drop key works.
+    pub soft_drop_enabled: bool
 }
 
 impl Default for GameConfig {
@@ -40,12 +102,43 @@ impl Default for GameConfig {
             next_queue_size: 5,
             max_garbage_add: 10,
             move_lock_rule: 15,
-            garbage_blocking: false
+            garbage_blocking: false,
+            garbage_delay: 0,
+            garbage_cancelling: true,
+            margin_time: None,
+            margin_interval: 960,
+            margin_step: 25,
+            randomizer: libtetris::RandomizerKind::SevenBag,
+            attack_table: AttackTable::default(),
+            min_piece_interval: 0,
+            starting_garbage: 0,
+            hold_enabled: true,
+            hard_drop_enabled: true,
+            soft_drop_enabled: true
         }
     }
 }
 
 impl GameConfig {
+    /// Whether pieces reach the bottom of the playfield as soon as they spawn, so a bot should
+    /// plan for 20G movement.
+    pub fn instant_gravity(&self) -> bool {
+        // 20 cells per tick
+        self.gravity <= 5
+    }
+
+    /// The percentage attacks are scaled by after `time` ticks. This is 100 until margin time
+    /// starts, then increases by `margin_step` at the start of every interval.
+    pub fn attack_multiplier(&self, time: u32) -> u32 {
+        match self.margin_time {
+            Some(start) if time >= start => {
+                let steps = (time - start) / self.margin_interval.max(1) + 1;
+                100 + steps * self.margin_step
+            }
+            _ => 100
+        }
+    }
+
     pub fn fast_config() -> Self {
         GameConfig {
             spawn_delay: 0,
@@ -58,7 +151,81 @@ impl GameConfig {
             next_queue_size: 5,
             max_garbage_add: 20,
             move_lock_rule: 15,
-            garbage_blocking: true
+            garbage_blocking: true,
+            garbage_delay: 0,
+            garbage_cancelling: true,
+            margin_time: None,
+            margin_interval: 960,
+            margin_step: 25,
+            randomizer: libtetris::RandomizerKind::SevenBag,
+            attack_table: AttackTable::default(),
+            min_piece_interval: 0,
+            starting_garbage: 0,
+            hold_enabled: true,
+            hard_drop_enabled: true,
+            soft_drop_enabled: true
+        }
+    }
+}
+
+/// The attack sent by each kind of placement.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttackTable {
+    /// Attack for clearing 1 to 4 lines.
+    pub clears: [u32; 4],
+    /// Attack for T-spins clearing 0 to 3 lines.
+    pub tspins: [u32; 4],
+    /// Attack for mini T-spins clearing 0 to 2 lines.
+    pub mini_tspins: [u32; 3],
+    pub back_to_back: u32,
+    /// Attack added by each combo, starting from the first clear; the last entry is used for
+    /// longer combos.
+    pub combo: [u32; 12],
+    /// Attack for a perfect clear, replacing all other attack.
+    pub perfect_clear: u32
+}
+
+impl Default for AttackTable {
+    fn default() -> Self {
+        // The values built into libtetris
+        AttackTable {
+            clears: [0, 1, 2, 4],
+            tspins: [0, 2, 4, 6],
+            mini_tspins: [0, 0, 1],
+            back_to_back: 1,
+            combo: libtetris::COMBO_GARBAGE,
+            perfect_clear: 10
+        }
+    }
+}
+
+impl AttackTable {
+    pub fn attack(&self, lock: &libtetris::LockResult) -> u32 {
+        use libtetris::PlacementKind::*;
+        if lock.perfect_clear {
+            return self.perfect_clear;
+        }
+        let mut attack = match lock.placement_kind {
+            None => 0,
+            Clear1 => self.clears[0],
+            Clear2 => self.clears[1],
+            Clear3 => self.clears[2],
+            Clear4 => self.clears[3],
+            Tspin => self.tspins[0],
+            Tspin1 => self.tspins[1],
+            Tspin2 => self.tspins[2],
+            Tspin3 => self.tspins[3],
+            MiniTspin => self.mini_tspins[0],
+            MiniTspin1 => self.mini_tspins[1],
+            MiniTspin2 => self.mini_tspins[2]
+        };
+        if lock.b2b {
+            attack += self.back_to_back;
+        }
+        if let Some(combo) = lock.combo {
+            attack += self.combo[(combo as usize).min(self.combo.len() - 1)];
         }
+        attack
     }
 }
\ No newline at end of file
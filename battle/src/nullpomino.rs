@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use libtetris::Piece;
+use crate::import::{ ImportedGame, Playback };
+
+/// The number of next pieces Nullpomino shows by default.
+const NEXT_PIECES: usize = 6;
+
+const BUTTON_UP: u32 = 1;
+const BUTTON_DOWN: u32 = 1 << 1;
+const BUTTON_LEFT: u32 = 1 << 2;
+const BUTTON_RIGHT: u32 = 1 << 3;
+const BUTTON_A: u32 = 1 << 4;
+const BUTTON_B: u32 = 1 << 5;
+const BUTTON_C: u32 = 1 << 6;
+const BUTTON_D: u32 = 1 << 7;
+const BUTTON_E: u32 = 1 << 8;
+
+/// Reads the first player of a Nullpomino replay, a Java properties file.
+///
+/// The buttons held every frame are stored under `0.r.FRAME` whenever they change, up to the
+/// frame stored under `0.r.max`. The buttons are read with the default mapping: up hard drops,
+/// down soft drops, A and C rotate counterclockwise, B rotates clockwise, D holds and E rotates
+/// 180 degrees. Pieces come from a 7-bag drawn with `java.util.Random` seeded by the hexadecimal
+/// `0.replay.randSeed`. Entry delay, gravity and lock delay aren't simulated, soft drop is
+/// treated as instant, and garbage isn't recorded in replays so it isn't imported.
+pub(crate) fn parse(properties: &str) -> Result<ImportedGame, String> {
+    let properties: HashMap<_, _> = properties.lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with('!'))
+        .filter_map(|line| {
+            let split = line.find(|c: char| c == '=' || c == ':')?;
+            Some((line[..split].trim(), line[split+1..].trim()))
+        })
+        .collect();
+    let number = |key: &str, default: u32| properties.get(key)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default);
+
+    let seed = properties.get("0.replay.randSeed")
+        .and_then(|seed| i64::from_str_radix(seed, 16).ok())
+        .ok_or_else(|| "replay has no seed".to_owned())?;
+    let max = number("0.r.max", 0);
+    let das = number("0.speed.das", 14);
+    let arr = number("0.ruleopt.dasARR", 1);
+
+    let mut rng = JavaRandom::new(seed);
+    let pieces = std::iter::repeat_with(move || {
+        // Nullpomino numbers the pieces in this order
+        use Piece::*;
+        let mut bag = vec![I, L, O, Z, T, J, S];
+        let mut drawn = Vec::with_capacity(bag.len());
+        while !bag.is_empty() {
+            drawn.push(bag.remove(rng.next_int(bag.len() as i32) as usize));
+        }
+        drawn
+    }).flatten();
+    let username = properties.get("0.playerName").copied().unwrap_or("").to_owned();
+    let mut playback = Playback::new(pieces, NEXT_PIECES, username);
+    playback.spawn();
+
+    let mut held = 0;
+    // frames the current horizontal direction has been held for
+    let mut charge = 0;
+    for frame in 0..max {
+        let buttons = number(&format!("0.r.{}", frame), held);
+        let pressed = buttons & !held;
+        held = buttons;
+
+        let dx = match (buttons & BUTTON_LEFT != 0, buttons & BUTTON_RIGHT != 0) {
+            (true, false) => -1,
+            (false, true) => 1,
+            _ => 0
+        };
+        if pressed & (BUTTON_LEFT | BUTTON_RIGHT) != 0 {
+            charge = 0;
+        }
+        let mut ok = true;
+        if dx != 0 {
+            let moves = if charge == 0 {
+                1
+            } else if charge < das {
+                0
+            } else if arr == 0 {
+                10
+            } else if (charge - das) % arr == 0 {
+                1
+            } else {
+                0
+            };
+            charge += 1;
+            ok &= playback.with_piece(|piece, board| {
+                for _ in 0..moves {
+                    piece.shift(board, dx, 0);
+                }
+            });
+        }
+        if pressed & (BUTTON_A | BUTTON_C) != 0 {
+            ok &= playback.with_piece(|piece, board| {
+                piece.ccw(board);
+            });
+        }
+        if pressed & BUTTON_B != 0 {
+            ok &= playback.with_piece(|piece, board| {
+                piece.cw(board);
+            });
+        }
+        if pressed & BUTTON_E != 0 {
+            ok &= playback.rotate_180();
+        }
+        if pressed & BUTTON_D != 0 {
+            ok &= playback.hold();
+        }
+        if buttons & BUTTON_DOWN != 0 {
+            playback.with_piece(|piece, board| {
+                piece.sonic_drop(board);
+            });
+        }
+        if pressed & BUTTON_UP != 0 {
+            ok &= playback.lock(frame).is_some();
+            if !playback.over {
+                playback.spawn();
+            }
+        }
+        if !ok {
+            playback.game.desynced = true;
+        }
+        if !ok || playback.over {
+            break
+        }
+    }
+    Ok(playback.game)
+}
+
+/// A port of `java.util.Random`, which Nullpomino generates the piece sequence with.
+struct JavaRandom(i64);
+
+impl JavaRandom {
+    const MULTIPLIER: i64 = 0x5DEECE66D;
+    const MASK: i64 = (1 << 48) - 1;
+
+    fn new(seed: i64) -> Self {
+        JavaRandom((seed ^ JavaRandom::MULTIPLIER) & JavaRandom::MASK)
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        self.0 = self.0.wrapping_mul(JavaRandom::MULTIPLIER).wrapping_add(0xB) & JavaRandom::MASK;
+        (self.0 >> (48 - bits)) as i32
+    }
+
+    fn next_int(&mut self, bound: i32) -> i32 {
+        if bound & -bound == bound {
+            return ((bound as i64 * self.next(31) as i64) >> 31) as i32
+        }
+        loop {
+            let bits = self.next(31);
+            let value = bits % bound;
+            if bits.wrapping_sub(value).wrapping_add(bound - 1) >= 0 {
+                return value
+            }
+        }
+    }
+}
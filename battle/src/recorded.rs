@@ -0,0 +1,52 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use libtetris::{ Controller, ControllerRun };
+
+/// Plays back a recorded stream of controller states, such as a capture of a human's inputs, so
+/// the player can be simulated in a `Battle` against a bot.
+///
+/// Each call to `next_frame` gives the controller state for one `Battle::update`, starting with
+/// the first frame of the battle. The battle records its own `Replay` as usual, so the game can
+/// be analyzed like any other.
+pub struct RecordedInput {
+    frames: VecDeque<Controller>
+}
+
+impl RecordedInput {
+    pub fn new(frames: impl IntoIterator<Item = Controller>) -> Self {
+        RecordedInput {
+            frames: frames.into_iter().collect()
+        }
+    }
+
+    pub fn from_runs(runs: &[ControllerRun]) -> Self {
+        RecordedInput::new(ControllerRun::decode(runs))
+    }
+
+    /// Loads a JSON list of `ControllerRun`s. Plain controller bit vectors are read as a single
+    /// frame, so a list with one bit vector per frame works too.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        let runs: Vec<ControllerRun> = serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|e| e.to_string())?;
+        Ok(RecordedInput::from_runs(&runs))
+    }
+
+    /// The controller state of the next frame. Once the recording runs out, no keys are pressed.
+    pub fn next_frame(&mut self) -> Controller {
+        self.frames.pop_front().unwrap_or_default()
+    }
+
+    /// The number of recorded frames that haven't been played yet.
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+impl Iterator for RecordedInput {
+    type Item = Controller;
+
+    fn next(&mut self) -> Option<Controller> {
+        self.frames.pop_front()
+    }
+}
@@ -0,0 +1,564 @@
+use std::collections::VecDeque;
+use std::io::{ Read, Write };
+use std::path::Path;
+use rand_pcg::Pcg64Mcg;
+use rand::SeedableRng;
+use serde::{ Serialize, Deserialize };
+use serde::de::DeserializeOwned;
+use libtetris::{ Controller, ControllerRun, RandomizerKind };
+use crate::{ Battle, BattleUpdate, Replay, Checkpoint, GameConfig, AttackTable };
+
+const MAGIC: &[u8; 4] = b"CCRP";
+
+/// The version of the replay format written by `Replay::write_to`. Bumped whenever the encoding
+/// of `Replay` or `GameConfig` changes, with the config of every older version frozen below so
+/// those files still decode.
+///
+/// Version 1 has no checkpoints. Version 2 stores the controller states of every frame, while
+/// version 3 stores each player's inputs as `ControllerRun`s. Version 4 follows the replay with
+/// extra data, such as bot info, in the same compressed stream, and version 5 stores per-frame
+/// hashes in each checkpoint. Version 6 stores the attack table, piece interval, starting garbage
+/// and hold settings of `GameConfig`, and version 7 its hard and soft drop settings. Files without
+/// the magic bytes were saved before the format was versioned.
+pub const REPLAY_VERSION: u32 = 7;
+
+/// The `GameConfig` of replays saved before the format was versioned.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+struct ConfigV0 {
+    spawn_delay: u32,
+    line_clear_delay: u32,
+    delayed_auto_shift: u32,
+    auto_repeat_rate: u32,
+    soft_drop_speed: u32,
+    lock_delay: u32,
+    gravity: i32,
+    next_queue_size: u32,
+    max_garbage_add: u32,
+    move_lock_rule: u32,
+    garbage_blocking: bool
+}
+
+/// The `GameConfig` of versions 1, 2, 4 and 5.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+struct ConfigV1 {
+    spawn_delay: u32,
+    line_clear_delay: u32,
+    delayed_auto_shift: u32,
+    auto_repeat_rate: u32,
+    soft_drop_speed: u32,
+    lock_delay: u32,
+    gravity: i32,
+    next_queue_size: u32,
+    max_garbage_add: u32,
+    move_lock_rule: u32,
+    garbage_blocking: bool,
+    garbage_delay: u32,
+    garbage_cancelling: bool,
+    margin_time: Option<u32>,
+    margin_interval: u32,
+    margin_step: u32,
+    randomizer: RandomizerKind
+}
+
+/// The `GameConfig` of versions 3 and 6.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+struct ConfigV3 {
+    spawn_delay: u32,
+    line_clear_delay: u32,
+    delayed_auto_shift: u32,
+    auto_repeat_rate: u32,
+    soft_drop_speed: u32,
+    lock_delay: u32,
+    gravity: i32,
+    next_queue_size: u32,
+    max_garbage_add: u32,
+    move_lock_rule: u32,
+    garbage_blocking: bool,
+    garbage_delay: u32,
+    garbage_cancelling: bool,
+    margin_time: Option<u32>,
+    margin_interval: u32,
+    margin_step: u32,
+    randomizer: RandomizerKind,
+    attack_table: AttackTable,
+    min_piece_interval: u32,
+    starting_garbage: u32,
+    hold_enabled: bool
+}
+
+/// The `GameConfig` of version 7.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+struct ConfigV7 {
+    spawn_delay: u32,
+    line_clear_delay: u32,
+    delayed_auto_shift: u32,
+    auto_repeat_rate: u32,
+    soft_drop_speed: u32,
+    lock_delay: u32,
+    gravity: i32,
+    next_queue_size: u32,
+    max_garbage_add: u32,
+    move_lock_rule: u32,
+    garbage_blocking: bool,
+    garbage_delay: u32,
+    garbage_cancelling: bool,
+    margin_time: Option<u32>,
+    margin_interval: u32,
+    margin_step: u32,
+    randomizer: RandomizerKind,
+    attack_table: AttackTable,
+    min_piece_interval: u32,
+    starting_garbage: u32,
+    hold_enabled: bool,
+    hard_drop_enabled: bool,
+    soft_drop_enabled: bool
+}
+
+/// The `Checkpoint` of versions 2 to 4, which has no per-frame hashes.
+#[derive(Serialize, Deserialize)]
+struct CheckpointV2 {
+    frame: u32,
+    p1: u64,
+    p2: u64
+}
+
+impl From<CheckpointV2> for Checkpoint {
+    fn from(c: CheckpointV2) -> Self {
+        Checkpoint {
+            frame: c.frame,
+            p1: c.p1,
+            p2: c.p2,
+            frame_hashes: vec![]
+        }
+    }
+}
+
+impl From<ConfigV0> for GameConfig {
+    fn from(c: ConfigV0) -> Self {
+        GameConfig {
+            spawn_delay: c.spawn_delay,
+            line_clear_delay: c.line_clear_delay,
+            delayed_auto_shift: c.delayed_auto_shift,
+            auto_repeat_rate: c.auto_repeat_rate,
+            soft_drop_speed: c.soft_drop_speed,
+            lock_delay: c.lock_delay,
+            gravity: c.gravity,
+            next_queue_size: c.next_queue_size,
+            max_garbage_add: c.max_garbage_add,
+            move_lock_rule: c.move_lock_rule,
+            garbage_blocking: c.garbage_blocking,
+            ..GameConfig::default()
+        }
+    }
+}
+
+impl From<ConfigV1> for GameConfig {
+    fn from(c: ConfigV1) -> Self {
+        GameConfig {
+            spawn_delay: c.spawn_delay,
+            line_clear_delay: c.line_clear_delay,
+            delayed_auto_shift: c.delayed_auto_shift,
+            auto_repeat_rate: c.auto_repeat_rate,
+            soft_drop_speed: c.soft_drop_speed,
+            lock_delay: c.lock_delay,
+            gravity: c.gravity,
+            next_queue_size: c.next_queue_size,
+            max_garbage_add: c.max_garbage_add,
+            move_lock_rule: c.move_lock_rule,
+            garbage_blocking: c.garbage_blocking,
+            garbage_delay: c.garbage_delay,
+            garbage_cancelling: c.garbage_cancelling,
+            margin_time: c.margin_time,
+            margin_interval: c.margin_interval,
+            margin_step: c.margin_step,
+            randomizer: c.randomizer,
+            ..GameConfig::default()
+        }
+    }
+}
+
+impl From<ConfigV3> for GameConfig {
+    fn from(c: ConfigV3) -> Self {
+        GameConfig {
+            spawn_delay: c.spawn_delay,
+            line_clear_delay: c.line_clear_delay,
+            delayed_auto_shift: c.delayed_auto_shift,
+            auto_repeat_rate: c.auto_repeat_rate,
+            soft_drop_speed: c.soft_drop_speed,
+            lock_delay: c.lock_delay,
+            gravity: c.gravity,
+            next_queue_size: c.next_queue_size,
+            max_garbage_add: c.max_garbage_add,
+            move_lock_rule: c.move_lock_rule,
+            garbage_blocking: c.garbage_blocking,
+            garbage_delay: c.garbage_delay,
+            garbage_cancelling: c.garbage_cancelling,
+            margin_time: c.margin_time,
+            margin_interval: c.margin_interval,
+            margin_step: c.margin_step,
+            randomizer: c.randomizer,
+            attack_table: c.attack_table,
+            min_piece_interval: c.min_piece_interval,
+            starting_garbage: c.starting_garbage,
+            hold_enabled: c.hold_enabled,
+            ..GameConfig::default()
+        }
+    }
+}
+
+impl From<GameConfig> for ConfigV7 {
+    fn from(c: GameConfig) -> Self {
+        ConfigV7 {
+            spawn_delay: c.spawn_delay,
+            line_clear_delay: c.line_clear_delay,
+            delayed_auto_shift: c.delayed_auto_shift,
+            auto_repeat_rate: c.auto_repeat_rate,
+            soft_drop_speed: c.soft_drop_speed,
+            lock_delay: c.lock_delay,
+            gravity: c.gravity,
+            next_queue_size: c.next_queue_size,
+            max_garbage_add: c.max_garbage_add,
+            move_lock_rule: c.move_lock_rule,
+            garbage_blocking: c.garbage_blocking,
+            garbage_delay: c.garbage_delay,
+            garbage_cancelling: c.garbage_cancelling,
+            margin_time: c.margin_time,
+            margin_interval: c.margin_interval,
+            margin_step: c.margin_step,
+            randomizer: c.randomizer,
+            attack_table: c.attack_table,
+            min_piece_interval: c.min_piece_interval,
+            starting_garbage: c.starting_garbage,
+            hold_enabled: c.hold_enabled,
+            hard_drop_enabled: c.hard_drop_enabled,
+            soft_drop_enabled: c.soft_drop_enabled
+        }
+    }
+}
+
+impl From<ConfigV7> for GameConfig {
+    fn from(c: ConfigV7) -> Self {
+        GameConfig {
+            spawn_delay: c.spawn_delay,
+            line_clear_delay: c.line_clear_delay,
+            delayed_auto_shift: c.delayed_auto_shift,
+            auto_repeat_rate: c.auto_repeat_rate,
+            soft_drop_speed: c.soft_drop_speed,
+            lock_delay: c.lock_delay,
+            gravity: c.gravity,
+            next_queue_size: c.next_queue_size,
+            max_garbage_add: c.max_garbage_add,
+            move_lock_rule: c.move_lock_rule,
+            garbage_blocking: c.garbage_blocking,
+            garbage_delay: c.garbage_delay,
+            garbage_cancelling: c.garbage_cancelling,
+            margin_time: c.margin_time,
+            margin_interval: c.margin_interval,
+            margin_step: c.margin_step,
+            randomizer: c.randomizer,
+            attack_table: c.attack_table,
+            min_piece_interval: c.min_piece_interval,
+            starting_garbage: c.starting_garbage,
+            hold_enabled: c.hold_enabled,
+            hard_drop_enabled: c.hard_drop_enabled,
+            soft_drop_enabled: c.soft_drop_enabled
+        }
+    }
+}
+
+/// The encoding of unversioned replays and version 1: the controller states of every frame.
+#[derive(Serialize, Deserialize)]
+struct FrameReplay<C> {
+    p1_name: String,
+    p2_name: String,
+    p1_seed: <Pcg64Mcg as SeedableRng>::Seed,
+    p2_seed: <Pcg64Mcg as SeedableRng>::Seed,
+    garbage_seed: <Pcg64Mcg as SeedableRng>::Seed,
+    p1_config: C,
+    p2_config: C,
+    updates: VecDeque<(Controller, Controller)>
+}
+
+/// The encoding of version 2: the controller states of every frame, followed by checkpoints.
+#[derive(Serialize, Deserialize)]
+struct CheckpointReplay<C> {
+    p1_name: String,
+    p2_name: String,
+    p1_seed: <Pcg64Mcg as SeedableRng>::Seed,
+    p2_seed: <Pcg64Mcg as SeedableRng>::Seed,
+    garbage_seed: <Pcg64Mcg as SeedableRng>::Seed,
+    p1_config: C,
+    p2_config: C,
+    updates: VecDeque<(Controller, Controller)>,
+    checkpoints: Vec<CheckpointV2>
+}
+
+/// The encoding of version 3 onwards: each player's inputs as `ControllerRun`s.
+#[derive(Serialize, Deserialize)]
+struct RunReplay<C, K> {
+    p1_name: String,
+    p2_name: String,
+    p1_seed: <Pcg64Mcg as SeedableRng>::Seed,
+    p2_seed: <Pcg64Mcg as SeedableRng>::Seed,
+    garbage_seed: <Pcg64Mcg as SeedableRng>::Seed,
+    p1_config: C,
+    p2_config: C,
+    p1_inputs: Vec<ControllerRun>,
+    p2_inputs: Vec<ControllerRun>,
+    checkpoints: Vec<K>
+}
+
+impl<C: Into<GameConfig>> FrameReplay<C> {
+    fn into_replay(self) -> Replay {
+        Replay {
+            p1_name: self.p1_name,
+            p2_name: self.p2_name,
+            p1_seed: self.p1_seed,
+            p2_seed: self.p2_seed,
+            garbage_seed: self.garbage_seed,
+            p1_config: self.p1_config.into(),
+            p2_config: self.p2_config.into(),
+            updates: self.updates,
+            checkpoints: vec![]
+        }
+    }
+}
+
+impl<C: Into<GameConfig>> CheckpointReplay<C> {
+    fn into_replay(self) -> Replay {
+        Replay {
+            p1_name: self.p1_name,
+            p2_name: self.p2_name,
+            p1_seed: self.p1_seed,
+            p2_seed: self.p2_seed,
+            garbage_seed: self.garbage_seed,
+            p1_config: self.p1_config.into(),
+            p2_config: self.p2_config.into(),
+            updates: self.updates,
+            checkpoints: self.checkpoints.into_iter().map(Into::into).collect()
+        }
+    }
+}
+
+impl<C: Into<GameConfig> + From<GameConfig>> RunReplay<C, Checkpoint> {
+    fn new(replay: &Replay) -> Self {
+        RunReplay {
+            p1_name: replay.p1_name.clone(),
+            p2_name: replay.p2_name.clone(),
+            p1_seed: replay.p1_seed,
+            p2_seed: replay.p2_seed,
+            garbage_seed: replay.garbage_seed,
+            p1_config: replay.p1_config.into(),
+            p2_config: replay.p2_config.into(),
+            p1_inputs: ControllerRun::encode(replay.updates.iter().map(|&(p1, _)| p1)),
+            p2_inputs: ControllerRun::encode(replay.updates.iter().map(|&(_, p2)| p2)),
+            checkpoints: replay.checkpoints.clone()
+        }
+    }
+}
+
+impl<C: Into<GameConfig>, K: Into<Checkpoint>> RunReplay<C, K> {
+    fn into_replay(self) -> Result<Replay, String> {
+        let p1_frames = self.p1_inputs.iter().map(|run| run.frames as u64).sum::<u64>();
+        let p2_frames = self.p2_inputs.iter().map(|run| run.frames as u64).sum::<u64>();
+        if p1_frames != p2_frames {
+            return Err(format!(
+                "player 1 has {} frames of inputs but player 2 has {}", p1_frames, p2_frames
+            ));
+        }
+        Ok(Replay {
+            p1_name: self.p1_name,
+            p2_name: self.p2_name,
+            p1_seed: self.p1_seed,
+            p2_seed: self.p2_seed,
+            garbage_seed: self.garbage_seed,
+            p1_config: self.p1_config.into(),
+            p2_config: self.p2_config.into(),
+            updates: ControllerRun::decode(&self.p1_inputs)
+                .zip(ControllerRun::decode(&self.p2_inputs))
+                .collect(),
+            checkpoints: self.checkpoints.into_iter().map(Into::into).collect()
+        })
+    }
+}
+
+fn decode<T: DeserializeOwned>(from: impl Read) -> Result<T, String> {
+    bincode::deserialize_from(from).map_err(|e| e.to_string())
+}
+
+impl Replay {
+    /// Writes the replay as the magic bytes `CCRP` and the format version as a little-endian u32,
+    /// followed by the deflate-compressed bincode encoding of the replay.
+    pub fn write_to(&self, to: impl Write) -> Result<(), String> {
+        self.write_with(&(), to)
+    }
+
+    /// Like `write_to`, but also stores `extra` after the replay. Read it back with `read_with`.
+    pub fn write_with(&self, extra: &impl Serialize, mut to: impl Write) -> Result<(), String> {
+        to.write_all(MAGIC).map_err(|e| e.to_string())?;
+        to.write_all(&REPLAY_VERSION.to_le_bytes()).map_err(|e| e.to_string())?;
+        let mut encoder = libflate::deflate::Encoder::new(to);
+        let encoded = RunReplay::<ConfigV7, Checkpoint>::new(self);
+        bincode::serialize_into(&mut encoder, &encoded).map_err(|e| e.to_string())?;
+        bincode::serialize_into(&mut encoder, extra).map_err(|e| e.to_string())?;
+        encoder.finish().into_result().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn read_from(from: impl Read) -> Result<Self, String> {
+        Replay::read_with(from).map(|(replay, ())| replay)
+    }
+
+    /// Reads a replay along with the extra data stored by `write_with`. Versions that can't store
+    /// extra data give the default value instead.
+    pub fn read_with<T: DeserializeOwned + Default>(
+        mut from: impl Read
+    ) -> Result<(Self, T), String> {
+        let mut magic = [0; 4];
+        from.read_exact(&mut magic).map_err(|e| e.to_string())?;
+        if &magic != MAGIC {
+            // Unversioned replays are just the compressed replay followed by the extra data, so
+            // the bytes we read were already part of the compressed stream
+            let mut decoder = libflate::deflate::Decoder::new((&magic[..]).chain(from));
+            let replay = decode::<FrameReplay<ConfigV0>>(&mut decoder)?.into_replay();
+            return Ok((replay, decode(&mut decoder)?));
+        }
+        let mut version = [0; 4];
+        from.read_exact(&mut version).map_err(|e| e.to_string())?;
+        let version = u32::from_le_bytes(version);
+        let mut decoder = libflate::deflate::Decoder::new(from);
+        let replay = match version {
+            1 => decode::<FrameReplay<ConfigV1>>(&mut decoder)?.into_replay(),
+            2 => decode::<CheckpointReplay<ConfigV1>>(&mut decoder)?.into_replay(),
+            3 => decode::<RunReplay<ConfigV3, CheckpointV2>>(&mut decoder)?.into_replay()?,
+            4 => decode::<RunReplay<ConfigV1, CheckpointV2>>(&mut decoder)?.into_replay()?,
+            5 => decode::<RunReplay<ConfigV1, Checkpoint>>(&mut decoder)?.into_replay()?,
+            6 => decode::<RunReplay<ConfigV3, Checkpoint>>(&mut decoder)?.into_replay()?,
+            7 => decode::<RunReplay<ConfigV7, Checkpoint>>(&mut decoder)?.into_replay()?,
+            _ => return Err(format!(
+                "unsupported replay version {} (newest supported is {})", version, REPLAY_VERSION
+            ))
+        };
+        let extra = match version {
+            1..=3 => T::default(),
+            _ => decode(&mut decoder)?
+        };
+        Ok((replay, extra))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        self.save_with(&(), path)
+    }
+
+    pub fn save_with(&self, extra: &impl Serialize, path: impl AsRef<Path>) -> Result<(), String> {
+        let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+        self.write_with(extra, std::io::BufWriter::new(file))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        Replay::load_with(path).map(|(replay, ())| replay)
+    }
+
+    pub fn load_with<T: DeserializeOwned + Default>(
+        path: impl AsRef<Path>
+    ) -> Result<(Self, T), String> {
+        let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        Replay::read_with(std::io::BufReader::new(file))
+    }
+}
+
+/// Re-simulates a replay by feeding its recorded inputs back through a `Battle`, yielding the
+/// update of every frame.
+///
+/// Since battles are deterministic given their seeds, the battle's own `replay` matches the
+/// original once every frame has been played.
+pub struct ReplayPlayer {
+    pub battle: Battle,
+    updates: VecDeque<(Controller, Controller)>,
+    checkpoints: Vec<Checkpoint>
+}
+
+/// The first frame of a replay that didn't match the re-simulated game. Checkpoints saved before
+/// version 5 have no per-frame hashes, so for those replays this is the first checkpoint that
+/// didn't match, at most `CHECKPOINT_INTERVAL` frames after the divergence.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Divergence {
+    pub frame: u32,
+    pub p1_diverged: bool,
+    pub p2_diverged: bool
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let players = match (self.p1_diverged, self.p2_diverged) {
+            (true, true) => "both players",
+            (true, false) => "player 1",
+            _ => "player 2"
+        };
+        write!(f, "{} diverged from the replay by frame {}", players, self.frame)
+    }
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        let mut battle = Battle::new(
+            replay.p1_config, replay.p2_config,
+            replay.p1_seed, replay.p2_seed,
+            replay.garbage_seed
+        );
+        battle.replay.p1_name = replay.p1_name;
+        battle.replay.p2_name = replay.p2_name;
+        ReplayPlayer {
+            battle,
+            updates: replay.updates,
+            checkpoints: replay.checkpoints
+        }
+    }
+
+    /// The number of frames that haven't been played yet.
+    pub fn remaining(&self) -> usize {
+        self.updates.len()
+    }
+
+    /// Plays every remaining frame, returning the battle in its final state.
+    pub fn finish(mut self) -> Battle {
+        while self.next().is_some() {}
+        self.battle
+    }
+
+    /// Plays every remaining frame and compares the state of both players against the
+    /// checkpoints stored in the replay, reporting the first frame that doesn't match.
+    pub fn verify(mut self) -> Result<Battle, Divergence> {
+        let expected = std::mem::replace(&mut self.checkpoints, vec![]);
+        let battle = self.finish();
+        let mut actual = battle.replay.checkpoints.iter();
+        for expected in &expected {
+            let actual = actual.next().filter(|actual| actual.frame == expected.frame);
+            let (p1_diverged, p2_diverged) = match actual {
+                Some(actual) => (actual.p1 != expected.p1, actual.p2 != expected.p2),
+                None => (true, true)
+            };
+            if p1_diverged || p2_diverged {
+                // Every earlier checkpoint matched, so the first frame whose hash differs in this
+                // checkpoint's window is where the game diverged
+                let window_start = (expected.frame + 1)
+                    .saturating_sub(expected.frame_hashes.len() as u32);
+                let frame = actual.and_then(|actual| expected.frame_hashes.iter()
+                    .zip(&actual.frame_hashes)
+                    .position(|(e, a)| p1_diverged && e.0 != a.0 || p2_diverged && e.1 != a.1)
+                ).map_or(expected.frame, |i| window_start + i as u32);
+                return Err(Divergence { frame, p1_diverged, p2_diverged });
+            }
+        }
+        Ok(battle)
+    }
+}
+
+impl Iterator for ReplayPlayer {
+    type Item = BattleUpdate;
+
+    fn next(&mut self) -> Option<BattleUpdate> {
+        let (p1, p2) = self.updates.pop_front()?;
+        Some(self.battle.update(p1, p2))
+    }
+}
@@ -0,0 +1,230 @@
+use rand_pcg::Pcg64Mcg;
+use rand::prelude::*;
+use serde::{ Serialize, Deserialize };
+use crate::{ Game, GameConfig, Event, PlayerUpdate };
+use libtetris::Controller;
+
+/// How a player in a `Royale` chooses who receives their attacks.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Targeting {
+    /// A random opponent, chosen again after every attack.
+    Random,
+    /// Every opponent currently targeting this player, each receiving the full attack. Opponents
+    /// using `EvenSpread` attack everyone, so they always count. Falls back to a random opponent
+    /// when nobody is targeting this player.
+    Attackers,
+    /// The opponent with the most KOs.
+    Badges,
+    /// The attack is split evenly between all opponents.
+    EvenSpread
+}
+
+impl Default for Targeting {
+    fn default() -> Self {
+        Targeting::Random
+    }
+}
+
+/// A battle between any number of players.
+///
+/// Attacks go to the opponents chosen by each player's `Targeting`. When a player tops out, the
+/// last player to send them garbage is credited with the KO.
+pub struct Royale {
+    pub players: Vec<Game>,
+    targeting: Vec<Targeting>,
+    /// The opponent each player is attacking next, if they attack a single opponent.
+    targets: Vec<Option<usize>>,
+    last_attacker: Vec<Option<usize>>,
+    kos: Vec<u32>,
+    alive: Vec<bool>,
+    piece_rngs: Vec<Pcg64Mcg>,
+    garbage_rng: Pcg64Mcg,
+    target_rng: Pcg64Mcg,
+    pub time: u32
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RoyaleUpdate {
+    pub players: Vec<PlayerUpdate>,
+    /// The players that topped out during this update, with the player credited with the KO.
+    pub knocked_out: Vec<(usize, Option<usize>)>,
+    pub time: u32
+}
+
+impl Royale {
+    /// Creates a battle between the given players. Piece queues, garbage holes and random
+    /// targeting are all derived from `seed`.
+    pub fn new(players: Vec<(GameConfig, Targeting)>, seed: u64) -> Self {
+        let mut seeds = Pcg64Mcg::seed_from_u64(seed);
+        let mut piece_rngs: Vec<_> = players.iter()
+            .map(|_| Pcg64Mcg::from_seed(seeds.gen()))
+            .collect();
+        let garbage_rng = Pcg64Mcg::from_seed(seeds.gen());
+        let target_rng = Pcg64Mcg::from_seed(seeds.gen());
+        let games = players.iter()
+            .zip(&mut piece_rngs)
+            .map(|(&(config, _), rng)| Game::new(config, rng))
+            .collect();
+
+        let count = players.len();
+        let mut royale = Royale {
+            players: games,
+            targeting: players.iter().map(|&(_, targeting)| targeting).collect(),
+            targets: vec![None; count],
+            last_attacker: vec![None; count],
+            kos: vec![0; count],
+            alive: vec![true; count],
+            piece_rngs, garbage_rng, target_rng,
+            time: 0
+        };
+        for player in 0..count {
+            royale.targets[player] = royale.choose_target(player);
+        }
+        royale
+    }
+
+    /// Advances the battle by one tick. `controllers` has one entry per player; the entries of
+    /// players that were knocked out are ignored.
+    /// 
+    /// Panics if `controllers` doesn't have one entry per player.
+    pub fn update(&mut self, controllers: &[Controller]) -> RoyaleUpdate {
+        assert_eq!(
+            controllers.len(), self.players.len(),
+            "expected one controller per player"
+        );
+        self.time += 1;
+
+        let mut events = Vec::with_capacity(self.players.len());
+        for (player, &controller) in controllers.iter().enumerate() {
+            events.push(if self.alive[player] {
+                self.players[player].update(
+                    controller, &mut self.piece_rngs[player], &mut self.garbage_rng
+                )
+            } else {
+                vec![]
+            });
+        }
+
+        let mut knocked_out = vec![];
+        for (player, events) in events.iter().enumerate() {
+            for event in events {
+                match *event {
+                    Event::GarbageSent(lines) if self.alive[player] => {
+                        for (target, lines) in self.recipients(player, lines) {
+                            self.players[target].receive_garbage(lines);
+                            self.last_attacker[target] = Some(player);
+                        }
+                        if self.targeting[player] != Targeting::EvenSpread {
+                            self.targets[player] = self.choose_target(player);
+                        }
+                    }
+                    Event::GameOver if self.alive[player] => {
+                        self.alive[player] = false;
+                        let credited = self.last_attacker[player].filter(|&p| self.alive[p]);
+                        if let Some(credited) = credited {
+                            self.kos[credited] += 1;
+                        }
+                        knocked_out.push((player, credited));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        RoyaleUpdate {
+            players: events.into_iter().zip(&self.players).map(|(events, game)| PlayerUpdate {
+                events,
+                garbage_queue: game.garbage_queue
+            }).collect(),
+            knocked_out,
+            time: self.time
+        }
+    }
+
+    pub fn is_alive(&self, player: usize) -> bool {
+        self.alive[player]
+    }
+
+    pub fn kos(&self, player: usize) -> u32 {
+        self.kos[player]
+    }
+
+    /// The opponent the player is attacking next, if they attack a single opponent.
+    pub fn target(&self, player: usize) -> Option<usize> {
+        self.targets[player]
+    }
+
+    pub fn set_targeting(&mut self, player: usize, targeting: Targeting) {
+        self.targeting[player] = targeting;
+        self.targets[player] = self.choose_target(player);
+    }
+
+    /// The last player standing, once every other player has been knocked out.
+    pub fn winner(&self) -> Option<usize> {
+        let mut alive = (0..self.players.len()).filter(|&p| self.alive[p]);
+        match (alive.next(), alive.next()) {
+            (Some(winner), None) => Some(winner),
+            _ => None
+        }
+    }
+
+    fn opponents(&self, player: usize) -> Vec<usize> {
+        (0..self.players.len()).filter(|&p| p != player && self.alive[p]).collect()
+    }
+
+    fn choose_target(&mut self, player: usize) -> Option<usize> {
+        let opponents = self.opponents(player);
+        match self.targeting[player] {
+            Targeting::EvenSpread => None,
+            Targeting::Badges => {
+                let most = opponents.iter().map(|&p| self.kos[p]).max()?;
+                let leaders: Vec<_> = opponents.into_iter()
+                    .filter(|&p| self.kos[p] == most)
+                    .collect();
+                leaders.choose(&mut self.target_rng).copied()
+            }
+            Targeting::Random | Targeting::Attackers => {
+                opponents.choose(&mut self.target_rng).copied()
+            }
+        }
+    }
+
+    /// Decides who receives an attack, returning each recipient with the lines they receive.
+    fn recipients(&mut self, player: usize, lines: u32) -> Vec<(usize, u32)> {
+        match self.targeting[player] {
+            Targeting::EvenSpread => {
+                let mut opponents = self.opponents(player);
+                if opponents.is_empty() {
+                    return vec![];
+                }
+                // the lines that don't divide evenly go to random opponents
+                opponents.shuffle(&mut self.target_rng);
+                let count = opponents.len() as u32;
+                opponents.into_iter().enumerate()
+                    .map(|(i, p)| (p, lines / count + ((i as u32) < lines % count) as u32))
+                    .filter(|&(_, lines)| lines > 0)
+                    .collect()
+            }
+            Targeting::Attackers => {
+                let attackers: Vec<_> = self.opponents(player).into_iter()
+                    .filter(|&p| self.targets[p] == Some(player)
+                        || self.targeting[p] == Targeting::EvenSpread)
+                    .collect();
+                if attackers.is_empty() {
+                    self.single_recipient(player, lines)
+                } else {
+                    attackers.into_iter().map(|p| (p, lines)).collect()
+                }
+            }
+            Targeting::Random | Targeting::Badges => self.single_recipient(player, lines)
+        }
+    }
+
+    fn single_recipient(&mut self, player: usize, lines: u32) -> Vec<(usize, u32)> {
+        let target = match self.targets[player] {
+            Some(target) if self.alive[target] => Some(target),
+            _ => self.choose_target(player)
+        };
+        target.map(|target| (target, lines)).into_iter().collect()
+    }
+}
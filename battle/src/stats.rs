@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+use serde::{ Serialize, Deserialize };
+use libtetris::{ FallingPiece, PlacementKind };
+use crate::{ BattleUpdate, Event, PlayerUpdate };
+
+const TICKS_PER_SECOND: f64 = 60.0;
+
+/// Attacks sent at most this many ticks apart count as part of the same spike.
+pub const SPIKE_WINDOW: u32 = 60;
+
+/// The number of placements at the start of a game kept for recognizing the opener.
+pub const OPENING_PLACEMENTS: usize = 14;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub pieces: u32,
+    /// Lines sent to the opponent, after cancelling.
+    pub attack: u32,
+    /// The number of spikes, each being a run of attacks at most `SPIKE_WINDOW` ticks apart.
+    pub spikes: u32,
+    pub spike_lines: u32,
+    pub largest_spike: u32,
+    pub garbage_received: u32,
+    /// Received garbage cancelled by this player's attacks.
+    pub garbage_cancelled: u32,
+    /// Received garbage that entered the board.
+    pub garbage_tanked: u32,
+    /// The tick this player first sent an attack.
+    pub first_attack: Option<u32>,
+    /// The first `OPENING_PLACEMENTS` placements of the game.
+    pub opening: Vec<FallingPiece>,
+    /// The opener recognized from `opening`. Filled in by the harness, which knows the openers.
+    pub opener: Option<String>
+}
+
+impl PlayerStats {
+    pub fn pps(&self, ticks: u32) -> f64 {
+        self.pieces as f64 * TICKS_PER_SECOND / ticks.max(1) as f64
+    }
+
+    pub fn apm(&self, ticks: u32) -> f64 {
+        self.attack as f64 * TICKS_PER_SECOND * 60.0 / ticks.max(1) as f64
+    }
+
+    pub fn average_spike(&self) -> f64 {
+        self.spike_lines as f64 / self.spikes.max(1) as f64
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GameStats {
+    pub ticks: u32,
+    /// `Some(true)` if player 1 won, `None` if the game didn't finish.
+    pub p1_won: Option<bool>,
+    /// The placement that sent the winner's last attack.
+    pub kill_move: Option<PlacementKind>,
+    pub p1: PlayerStats,
+    pub p2: PlayerStats
+}
+
+impl GameStats {
+    pub const CSV_HEADER: &'static str = "ticks,winner,kill_move,\
+        p1_pieces,p1_pps,p1_attack,p1_apm,p1_spikes,p1_average_spike,p1_largest_spike,\
+        p1_received,p1_cancelled,p1_tanked,p1_first_attack,p1_opener,\
+        p2_pieces,p2_pps,p2_attack,p2_apm,p2_spikes,p2_average_spike,p2_largest_spike,\
+        p2_received,p2_cancelled,p2_tanked,p2_first_attack,p2_opener";
+
+    /// Formats the stats as a row matching `CSV_HEADER`.
+    pub fn to_csv(&self) -> String {
+        let winner = match self.p1_won {
+            Some(true) => "1",
+            Some(false) => "2",
+            None => ""
+        };
+        let mut row = format!(
+            "{},{},{}", self.ticks, winner, self.kill_move.map_or("", PlacementKind::short_name)
+        );
+        for p in &[&self.p1, &self.p2] {
+            row.push_str(&format!(
+                ",{},{:.3},{},{:.2},{},{:.2},{},{},{},{},{},{}",
+                p.pieces, p.pps(self.ticks), p.attack, p.apm(self.ticks),
+                p.spikes, p.average_spike(), p.largest_spike,
+                p.garbage_received, p.garbage_cancelled, p.garbage_tanked,
+                p.first_attack.map_or(String::new(), |t| t.to_string()),
+                p.opener.as_deref().unwrap_or("")
+            ));
+        }
+        row
+    }
+}
+
+/// Collects `GameStats` from the updates of a `Battle`.
+#[derive(Default)]
+pub struct StatsRecorder {
+    stats: GameStats,
+    players: [Tracker; 2]
+}
+
+#[derive(Default)]
+struct Tracker {
+    last_placed: Option<PlacementKind>,
+    last_attack: Option<PlacementKind>,
+    last_attack_time: u32,
+    spike: u32,
+    garbage_queue: u32
+}
+
+impl StatsRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, update: &BattleUpdate) {
+        self.stats.ticks = update.time;
+        self.player_update(0, &update.player_1, update.time);
+        self.player_update(1, &update.player_2, update.time);
+    }
+
+    fn player_update(&mut self, player: usize, update: &PlayerUpdate, time: u32) {
+        let opponent_last_attack = self.players[1 - player].last_attack;
+        let stats = &mut self.stats;
+        let (me, opponent) = match player {
+            0 => (&mut stats.p1, &mut stats.p2),
+            _ => (&mut stats.p2, &mut stats.p1)
+        };
+        let tracker = &mut self.players[player];
+        tracker.garbage_queue = update.garbage_queue;
+        for event in &update.events {
+            match event {
+                Event::PiecePlaced { piece, locked, .. } => {
+                    me.pieces += 1;
+                    if me.opening.len() < OPENING_PLACEMENTS {
+                        me.opening.push(*piece);
+                    }
+                    tracker.last_placed = Some(locked.placement_kind);
+                }
+                &Event::GarbageSent(lines) => {
+                    me.attack += lines;
+                    opponent.garbage_received += lines;
+                    me.first_attack.get_or_insert(time);
+                    if tracker.spike == 0 || time - tracker.last_attack_time > SPIKE_WINDOW {
+                        end_spike(me, tracker);
+                    }
+                    tracker.spike += lines;
+                    tracker.last_attack_time = time;
+                    tracker.last_attack = tracker.last_placed;
+                }
+                Event::GarbageAdded(columns) => me.garbage_tanked += columns.len() as u32,
+                Event::GameOver if stats.p1_won.is_none() => {
+                    stats.p1_won = Some(player == 1);
+                    stats.kill_move = opponent_last_attack;
+                    return
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> GameStats {
+        for (stats, tracker) in [&mut self.stats.p1, &mut self.stats.p2].iter_mut()
+            .zip(self.players.iter_mut())
+        {
+            end_spike(stats, tracker);
+            stats.garbage_cancelled = stats.garbage_received
+                .saturating_sub(stats.garbage_tanked + tracker.garbage_queue);
+        }
+        self.stats
+    }
+}
+
+fn end_spike(stats: &mut PlayerStats, tracker: &mut Tracker) {
+    if tracker.spike > 0 {
+        stats.spikes += 1;
+        stats.spike_lines += tracker.spike;
+        stats.largest_spike = stats.largest_spike.max(tracker.spike);
+        tracker.spike = 0;
+    }
+}
+
+/// Stats of one player over many games.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PlayerSummary {
+    pub wins: u32,
+    pub pps: f64,
+    pub apm: f64,
+    pub average_spike: f64,
+    pub garbage_cancelled: u64,
+    pub garbage_tanked: u64,
+    /// The average tick of the first attack, over the games where this player attacked.
+    pub time_to_first_attack: f64,
+    /// How often each kind of placement ended a game this player won.
+    pub kill_moves: BTreeMap<&'static str, u32>,
+    /// Games and wins for each opener this player was recognized using.
+    pub openers: BTreeMap<String, OpenerSummary>
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct OpenerSummary {
+    pub games: u32,
+    pub wins: u32,
+    pub win_rate: f64
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Summary {
+    pub games: u32,
+    pub p1: PlayerSummary,
+    pub p2: PlayerSummary
+}
+
+impl Summary {
+    pub fn new(games: &[GameStats]) -> Self {
+        Summary {
+            games: games.len() as u32,
+            p1: summarize(games, true),
+            p2: summarize(games, false)
+        }
+    }
+}
+
+fn player_stats(game: &GameStats, p1: bool) -> &PlayerStats {
+    if p1 { &game.p1 } else { &game.p2 }
+}
+
+fn summarize(games: &[GameStats], p1: bool) -> PlayerSummary {
+    let ticks: u64 = games.iter().map(|g| g.ticks as u64).sum();
+    let minutes = ticks.max(1) as f64 / TICKS_PER_SECOND / 60.0;
+    let pieces: u64 = games.iter().map(|g| player_stats(g, p1).pieces as u64).sum();
+    let attack: u64 = games.iter().map(|g| player_stats(g, p1).attack as u64).sum();
+    let spikes: u64 = games.iter().map(|g| player_stats(g, p1).spikes as u64).sum();
+    let spike_lines: u64 = games.iter().map(|g| player_stats(g, p1).spike_lines as u64).sum();
+    let first_attacks: Vec<_> = games.iter()
+        .filter_map(|g| player_stats(g, p1).first_attack)
+        .collect();
+
+    let mut summary = PlayerSummary {
+        pps: pieces as f64 / minutes / 60.0,
+        apm: attack as f64 / minutes,
+        average_spike: spike_lines as f64 / spikes.max(1) as f64,
+        garbage_cancelled: games.iter().map(|g| player_stats(g, p1).garbage_cancelled as u64).sum(),
+        garbage_tanked: games.iter().map(|g| player_stats(g, p1).garbage_tanked as u64).sum(),
+        time_to_first_attack: first_attacks.iter().map(|&t| t as f64).sum::<f64>()
+            / first_attacks.len().max(1) as f64,
+        ..Default::default()
+    };
+    for game in games.iter().filter(|g| g.p1_won == Some(p1)) {
+        summary.wins += 1;
+        if let Some(kind) = game.kill_move {
+            *summary.kill_moves.entry(kind.short_name()).or_default() += 1;
+        }
+    }
+    for game in games {
+        if let Some(opener) = &player_stats(game, p1).opener {
+            let entry = summary.openers.entry(opener.clone()).or_default();
+            entry.games += 1;
+            entry.wins += (game.p1_won == Some(p1)) as u32;
+        }
+    }
+    for opener in summary.openers.values_mut() {
+        opener.win_rate = opener.wins as f64 / opener.games as f64;
+    }
+    summary
+}
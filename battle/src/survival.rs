@@ -0,0 +1,128 @@
+use rand_pcg::Pcg64Mcg;
+use rand::prelude::*;
+use serde::{ Serialize, Deserialize };
+use crate::{ Game, GameConfig, Event, Seeds };
+use libtetris::Controller;
+
+/// When garbage arrives in a `Survival` game.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SurvivalSchedule {
+    /// Ticks until the first garbage arrives and between the first few attacks.
+    pub initial_interval: u32,
+    /// The interval shrinks by this percentage after every attack, down to `min_interval`.
+    pub acceleration: u32,
+    pub min_interval: u32,
+    /// Lines in each attack at the start of the game.
+    pub initial_lines: u32,
+    /// Attacks grow by one line every this many ticks, up to `max_lines`.
+    pub lines_growth: u32,
+    pub max_lines: u32
+}
+
+impl Default for SurvivalSchedule {
+    fn default() -> Self {
+        SurvivalSchedule {
+            initial_interval: 300,
+            acceleration: 5,
+            min_interval: 60,
+            initial_lines: 1,
+            lines_growth: 1800,
+            max_lines: 4
+        }
+    }
+}
+
+impl SurvivalSchedule {
+    /// The lines in an attack arriving at `time`.
+    pub fn lines(&self, time: u32) -> u32 {
+        (self.initial_lines + time / self.lines_growth.max(1)).min(self.max_lines)
+    }
+}
+
+/// A single player game where garbage arrives on a schedule that keeps accelerating, played for
+/// as long as possible.
+///
+/// Attacks go into the garbage queue like attacks from an opponent, so line clears cancel them.
+pub struct Survival {
+    pub game: Game,
+    piece_rng: Pcg64Mcg,
+    garbage_rng: Pcg64Mcg,
+    pub schedule: SurvivalSchedule,
+    interval: u32,
+    next_attack: u32,
+    /// Lines of garbage sent by the schedule so far.
+    pub received: u32,
+    pub time: u32,
+    topped_out: bool
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SurvivalUpdate {
+    pub events: Vec<Event>,
+    /// Lines of garbage the schedule sent during this update.
+    pub attack: u32,
+    pub garbage_queue: u32,
+    pub time: u32,
+    pub finished: bool
+}
+
+impl Survival {
+    /// Creates a game whose pieces and garbage holes are derived from `seed`.
+    pub fn new(config: GameConfig, schedule: SurvivalSchedule, seed: u64) -> Self {
+        let seeds = Seeds::from_master(seed);
+        let mut piece_rng = Pcg64Mcg::from_seed(seeds.p1_pieces);
+        Survival {
+            game: Game::new(config, &mut piece_rng),
+            piece_rng,
+            garbage_rng: Pcg64Mcg::from_seed(seeds.garbage),
+            schedule,
+            interval: schedule.initial_interval,
+            next_attack: schedule.initial_interval,
+            received: 0,
+            time: 0,
+            topped_out: false
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.topped_out
+    }
+
+    /// The number of ticks until the next attack arrives.
+    pub fn time_to_attack(&self) -> u32 {
+        self.next_attack.saturating_sub(self.time)
+    }
+
+    /// Advances the game by one tick. Once the game is finished, the controller is ignored and no
+    /// events are produced.
+    pub fn update(&mut self, controller: Controller) -> SurvivalUpdate {
+        let mut events = vec![];
+        let mut attack = 0;
+        if !self.is_finished() {
+            self.time += 1;
+            if self.time >= self.next_attack {
+                attack = self.schedule.lines(self.time);
+                self.game.receive_garbage(attack);
+                self.received += attack;
+                self.interval = (self.interval * (100 - self.schedule.acceleration.min(100)) / 100)
+                    .max(self.schedule.min_interval)
+                    .max(1);
+                self.next_attack = self.time + self.interval;
+            }
+            events = self.game.update(controller, &mut self.piece_rng, &mut self.garbage_rng);
+            for event in &events {
+                if let Event::GameOver = event {
+                    self.topped_out = true;
+                }
+            }
+        }
+        SurvivalUpdate {
+            events,
+            attack,
+            garbage_queue: self.game.garbage_queue,
+            time: self.time,
+            finished: self.is_finished()
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::VecDeque;
+use serde_json::Value;
+use libtetris::Piece;
+use crate::AttackTable;
+use crate::import::{ ImportedGame, Playback };
+
+/// The number of next pieces TETR.IO shows.
+const NEXT_PIECES: usize = 5;
+
+/// Reads a TETR.IO replay. Multiplayer replays (`.ttrm`) have several rounds of several players
+/// each, and single player replays (`.ttr`) are read as one round with one player.
+///
+/// Placements are replayed from the key presses with the player's handling settings and the
+/// garbage they received. Gravity and lock delay aren't simulated, and soft drop is treated as
+/// instant.
+pub(crate) fn parse(json: &str) -> Result<Vec<Vec<ImportedGame>>, String> {
+    let root: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    match &root["data"] {
+        Value::Array(rounds) => rounds.iter().map(|round| {
+            let replays = round["replays"].as_array()
+                .ok_or_else(|| "round has no replays".to_owned())?;
+            replays.iter().enumerate().map(|(i, replay)| {
+                let username = round["board"][i]["user"]["username"].as_str();
+                replay_game(&replay["events"], username)
+            }).collect::<Result<Vec<_>, String>>()
+        }).collect(),
+        data @ Value::Object(_) => Ok(vec![vec![
+            replay_game(&data["events"], root["user"]["username"].as_str())?
+        ]]),
+        _ => Err("not a TETR.IO replay".to_owned())
+    }
+}
+
+/// The pseudorandom number generator TETR.IO generates the piece sequence with.
+struct TetrioRng(i64);
+
+impl TetrioRng {
+    fn new(seed: i64) -> Self {
+        let mut seed = seed % 2147483647;
+        if seed <= 0 {
+            seed += 2147483646;
+        }
+        TetrioRng(seed)
+    }
+
+    fn next(&mut self) -> i64 {
+        self.0 = 16807 * self.0 % 2147483647;
+        self.0
+    }
+
+    fn next_float(&mut self) -> f64 {
+        (self.next() - 1) as f64 / 2147483646.0
+    }
+
+    fn next_bag(&mut self) -> Vec<Piece> {
+        use Piece::*;
+        let mut bag = vec![Z, L, O, S, I, J, T];
+        for i in (1..bag.len()).rev() {
+            let j = (self.next_float() * (i + 1) as f64) as usize;
+            bag.swap(i, j);
+        }
+        bag
+    }
+}
+
+struct Handling {
+    das: f64,
+    arr: f64
+}
+
+/// A horizontal movement key being held.
+struct Shift {
+    dx: i32,
+    /// The time auto-repeat is counted from.
+    start: f64,
+    /// Auto-repeated moves so far.
+    moved: u32
+}
+
+struct Garbage {
+    lines: u32,
+    column: usize,
+    /// The time the garbage can enter the board.
+    ready: f64
+}
+
+struct Simulation {
+    playback: Playback,
+    handling: Handling,
+    garbage_cap: u32,
+    garbage_speed: f64,
+    attack_table: AttackTable,
+    garbage: VecDeque<Garbage>,
+
+    left: bool,
+    right: bool,
+    soft_drop: bool,
+    shift: Option<Shift>
+}
+
+fn replay_game(events: &Value, username: Option<&str>) -> Result<ImportedGame, String> {
+    let events = events.as_array().ok_or_else(|| "replay has no events".to_owned())?;
+    let full = events.iter()
+        .find(|e| e["type"] == "full")
+        .ok_or_else(|| "replay has no initial state".to_owned())?;
+    let options = &full["data"]["options"];
+    if let Some(bag) = options["bagtype"].as_str() {
+        if bag != "7-bag" {
+            return Err(format!("unsupported randomizer {}", bag));
+        }
+    }
+    let seed = options["seed"].as_f64().ok_or_else(|| "replay has no seed".to_owned())? as i64;
+    let handling = &options["handling"];
+
+    // garbage confirmed by the receiving client is the most accurate, but older replays only
+    // record the attacks as they were sent
+    let confirmed = events.iter().any(|e| e["data"]["data"]["type"] == "interaction_confirm");
+
+    let mut rng = TetrioRng::new(seed);
+    let pieces = std::iter::repeat_with(move || rng.next_bag()).flatten();
+    let username = username.or_else(|| options["username"].as_str()).unwrap_or("");
+    let mut sim = Simulation {
+        playback: Playback::new(pieces, NEXT_PIECES, username.to_owned()),
+        handling: Handling {
+            das: handling["das"].as_f64().unwrap_or(10.0),
+            arr: handling["arr"].as_f64().unwrap_or(2.0)
+        },
+        garbage_cap: options["garbagecap"].as_f64().unwrap_or(8.0) as u32,
+        garbage_speed: options["garbagespeed"].as_f64().unwrap_or(20.0),
+        attack_table: AttackTable::default(),
+        garbage: VecDeque::new(),
+        left: false,
+        right: false,
+        soft_drop: false,
+        shift: None
+    };
+
+    let mut started = false;
+    for event in events {
+        let frame = event["frame"].as_f64().unwrap_or(0.0);
+        let data = &event["data"];
+        let time = frame + data["subframe"].as_f64().unwrap_or(0.0);
+        let key = data["key"].as_str().unwrap_or("");
+        let ok = match event["type"].as_str().unwrap_or("") {
+            "start" | "full" if !started => {
+                started = true;
+                sim.spawn(time);
+                true
+            }
+            "keydown" => sim.key_down(key, time),
+            "keyup" => sim.key_up(key, time),
+            "ige" => {
+                let ige = &data["data"];
+                match (ige["type"].as_str(), confirmed) {
+                    (Some("interaction_confirm"), true) if ige["data"]["type"] == "garbage" => {
+                        sim.receive(&ige["data"]["amt"], &ige["data"]["column"], time)
+                    }
+                    (Some("attack"), false) => sim.receive(&ige["lines"], &ige["column"], time),
+                    _ => {}
+                }
+                true
+            }
+            "end" => break,
+            _ => true
+        };
+        if !ok {
+            sim.playback.game.desynced = true;
+        }
+        if !ok || sim.playback.over {
+            break
+        }
+    }
+    Ok(sim.playback.game)
+}
+
+impl Simulation {
+    fn spawn(&mut self, time: f64) {
+        self.playback.spawn();
+        self.spawned(time);
+    }
+
+    /// Charged auto-shift carries over to a new piece.
+    fn spawned(&mut self, time: f64) {
+        if let Some(shift) = &mut self.shift {
+            shift.moved = 0;
+            if time - shift.start >= self.handling.das {
+                shift.start = time - self.handling.das;
+            }
+        }
+        self.catch_up(time);
+    }
+
+    /// Applies the auto-shift and soft drop of the keys being held up to `time`.
+    fn catch_up(&mut self, time: f64) {
+        let handling = &self.handling;
+        let shift = &mut self.shift;
+        let soft_drop = self.soft_drop;
+        self.playback.with_piece(|piece, board| {
+            if let Some(shift) = shift {
+                let charged = time - shift.start - handling.das;
+                if charged >= 0.0 {
+                    let total = if handling.arr <= 0.0 {
+                        10
+                    } else {
+                        (charged / handling.arr) as u32 + 1
+                    };
+                    while shift.moved < total {
+                        shift.moved += 1;
+                        if !piece.shift(board, shift.dx, 0) {
+                            // the piece stays against the wall until the next spawn
+                            shift.moved = total;
+                        }
+                    }
+                }
+            }
+            if soft_drop {
+                piece.sonic_drop(board);
+            }
+        });
+    }
+
+    /// Handles a key being pressed, returning false if the game went out of sync.
+    fn key_down(&mut self, key: &str, time: f64) -> bool {
+        self.catch_up(time);
+        let ok = match key {
+            "moveLeft" | "moveRight" => {
+                let dx = if key == "moveLeft" { -1 } else { 1 };
+                if dx < 0 {
+                    self.left = true;
+                } else {
+                    self.right = true;
+                }
+                self.shift = Some(Shift { dx, start: time, moved: 0 });
+                self.playback.with_piece(|piece, board| {
+                    piece.shift(board, dx, 0);
+                })
+            }
+            "softDrop" => {
+                self.soft_drop = true;
+                true
+            }
+            "rotateCW" => self.playback.with_piece(|piece, board| {
+                piece.cw(board);
+            }),
+            "rotateCCW" => self.playback.with_piece(|piece, board| {
+                piece.ccw(board);
+            }),
+            "rotate180" => self.playback.rotate_180(),
+            "hold" => {
+                let ok = self.playback.hold();
+                self.spawned(time);
+                return ok
+            }
+            "hardDrop" => return self.hard_drop(time),
+            _ => true
+        };
+        self.catch_up(time);
+        ok
+    }
+
+    fn key_up(&mut self, key: &str, time: f64) -> bool {
+        self.catch_up(time);
+        match key {
+            "moveLeft" | "moveRight" => {
+                let dx = if key == "moveLeft" { -1 } else { 1 };
+                if dx < 0 {
+                    self.left = false;
+                } else {
+                    self.right = false;
+                }
+                if self.shift.as_ref().map_or(false, |s| s.dx == dx) {
+                    // the other direction takes over if it's still held
+                    let other = if dx < 0 { self.right } else { self.left };
+                    self.shift = if other {
+                        Some(Shift { dx: -dx, start: time, moved: 0 })
+                    } else {
+                        None
+                    };
+                }
+            }
+            "softDrop" => self.soft_drop = false,
+            _ => {}
+        }
+        true
+    }
+
+    fn hard_drop(&mut self, time: f64) -> bool {
+        let lock = match self.playback.lock(time as u32) {
+            Some(lock) => lock,
+            None => return false
+        };
+
+        if lock.placement_kind.is_clear() {
+            let mut attack = self.attack_table.attack(&lock);
+            while attack > 0 {
+                let front = match self.garbage.front_mut() {
+                    Some(front) => front,
+                    None => break
+                };
+                let cancelled = attack.min(front.lines);
+                attack -= cancelled;
+                front.lines -= cancelled;
+                if front.lines == 0 {
+                    self.garbage.pop_front();
+                }
+            }
+        } else {
+            let mut entered = 0;
+            while entered < self.garbage_cap {
+                let front = match self.garbage.front_mut() {
+                    Some(front) if front.ready <= time => front,
+                    _ => break
+                };
+                let lines = front.lines.min(self.garbage_cap - entered);
+                for _ in 0..lines {
+                    self.playback.add_garbage(front.column);
+                }
+                entered += lines;
+                front.lines -= lines;
+                if front.lines == 0 {
+                    self.garbage.pop_front();
+                }
+            }
+        }
+
+        if !self.playback.over {
+            self.spawn(time);
+        }
+        true
+    }
+
+    fn receive(&mut self, lines: &Value, column: &Value, time: f64) {
+        let lines = lines.as_u64().unwrap_or(0) as u32;
+        if lines == 0 {
+            return
+        }
+        self.garbage.push_back(Garbage {
+            lines,
+            column: column.as_u64().unwrap_or(0).min(9) as usize,
+            ready: time + self.garbage_speed
+        });
+    }
+}
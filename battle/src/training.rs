@@ -0,0 +1,60 @@
+use std::io::Write;
+use libtetris::{ Board, FallingPiece, Row };
+
+/// A decision a bot made during self-play, for training networks on.
+pub struct TrainingRecord {
+    /// The board when the move was chosen. The queue starts with the piece to place.
+    pub board: Board,
+    pub placement: FallingPiece,
+    pub hold: bool,
+    /// The search's evaluation of the chosen move.
+    pub evaluation: i64,
+    /// Whether the player that made the move won the game.
+    pub won: bool
+}
+
+/// The number of pieces of the queue written, including the piece to place.
+const QUEUE_LENGTH: usize = 6;
+
+/// Written in place of a piece when there is none.
+const NO_PIECE: u8 = 7;
+
+impl TrainingRecord {
+    /// The size of a record in bytes.
+    pub const SIZE: usize = 40 * 2 + 1 + QUEUE_LENGTH + 2 + 5 + 8 + 1;
+
+    /// Writes the record as `SIZE` bytes, with multi-byte values in little endian:
+    ///
+    /// - 40 `u16` rows, bottom row first, with bit `x` set if cell `x` is filled
+    /// - the hold piece
+    /// - the piece to place and the next 5 pieces
+    /// - whether back-to-back is active and the combo, capped at 255, as `u8`s
+    /// - the placement's piece, rotation, x, y and whether hold was used, as `u8`s
+    /// - the evaluation as an `i64`
+    /// - 1 if the player won and 0 if they lost
+    ///
+    /// Pieces are numbered in the order I, O, T, L, J, S, Z, with 7 meaning no piece, and
+    /// rotations in the order north, south, east, west.
+    pub fn write(&self, w: &mut impl Write) -> std::io::Result<()> {
+        let mut bytes = Vec::with_capacity(TrainingRecord::SIZE);
+        for y in 0..40 {
+            let row = self.board.get_row(y).bits();
+            bytes.extend_from_slice(&row.to_le_bytes());
+        }
+        bytes.push(self.board.hold_piece.map_or(NO_PIECE, |p| p as u8));
+        let mut queue = self.board.next_queue();
+        for _ in 0..QUEUE_LENGTH {
+            bytes.push(queue.next().map_or(NO_PIECE, |p| p as u8));
+        }
+        bytes.push(self.board.b2b_bonus as u8);
+        bytes.push(self.board.combo.min(255) as u8);
+        bytes.push(self.placement.kind.0 as u8);
+        bytes.push(self.placement.kind.1 as u8);
+        bytes.push(self.placement.x as u8);
+        bytes.push(self.placement.y as u8);
+        bytes.push(self.hold as u8);
+        bytes.extend_from_slice(&self.evaluation.to_le_bytes());
+        bytes.push(self.won as u8);
+        w.write_all(&bytes)
+    }
+}
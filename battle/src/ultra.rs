@@ -0,0 +1,91 @@
+use rand_pcg::Pcg64Mcg;
+use rand::prelude::*;
+use serde::{ Serialize, Deserialize };
+use crate::{ Game, GameConfig, Event, Seeds };
+use libtetris::Controller;
+
+/// The length of an Ultra game in ticks (2 minutes).
+pub const ULTRA_TICKS: u32 = 7200;
+
+/// A single player score attack: score as many points as possible before time runs out.
+///
+/// Placements score the guideline points of `LockResult::score` at level 1. Soft dropping scores 1
+/// point per cell and hard dropping 2 points per cell. Topping out ends the game early, keeping
+/// the score reached so far.
+pub struct Ultra {
+    pub game: Game,
+    piece_rng: Pcg64Mcg,
+    garbage_rng: Pcg64Mcg,
+    pub score: u32,
+    /// The most points scored by a single placement, including drop points.
+    pub best_placement: u32,
+    pub time: u32,
+    /// Cells the current piece has been soft dropped.
+    soft_dropped: u32,
+    topped_out: bool
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UltraUpdate {
+    pub events: Vec<Event>,
+    pub score: u32,
+    pub time: u32,
+    pub finished: bool
+}
+
+impl Ultra {
+    /// Creates a game whose pieces and garbage holes are derived from `seed`.
+    pub fn new(config: GameConfig, seed: u64) -> Self {
+        let seeds = Seeds::from_master(seed);
+        let mut piece_rng = Pcg64Mcg::from_seed(seeds.p1_pieces);
+        Ultra {
+            game: Game::new(config, &mut piece_rng),
+            piece_rng,
+            garbage_rng: Pcg64Mcg::from_seed(seeds.garbage),
+            score: 0,
+            best_placement: 0,
+            time: 0,
+            soft_dropped: 0,
+            topped_out: false
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.topped_out || self.time >= ULTRA_TICKS
+    }
+
+    pub fn time_left(&self) -> u32 {
+        ULTRA_TICKS.saturating_sub(self.time)
+    }
+
+    /// Advances the game by one tick. Once the game is finished, the controller is ignored and no
+    /// events are produced.
+    pub fn update(&mut self, controller: Controller) -> UltraUpdate {
+        let mut events = vec![];
+        if !self.is_finished() {
+            self.time += 1;
+            events = self.game.update(controller, &mut self.piece_rng, &mut self.garbage_rng);
+            for event in &events {
+                match event {
+                    Event::SoftDropped => self.soft_dropped += 1,
+                    Event::PiecePlaced { locked, hard_drop_distance, .. } => {
+                        let points = locked.score(1)
+                            + 2 * hard_drop_distance.unwrap_or(0) as u32
+                            + self.soft_dropped;
+                        self.soft_dropped = 0;
+                        self.score += points;
+                        self.best_placement = self.best_placement.max(points);
+                    }
+                    Event::GameOver => self.topped_out = true,
+                    _ => {}
+                }
+            }
+        }
+        UltraUpdate {
+            events,
+            score: self.score,
+            time: self.time,
+            finished: self.is_finished()
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use cold_clear::evaluation::{ Evaluator, Placement, Standard };
+use cold_clear::moves::{ MovementMode, Timing, drop_distances, find_moves };
+use libtetris::{ Board, Piece, SpawnRule };
+use std::time::{ Duration, Instant };
+
+/// Command line settings.
+///
+/// `--seconds N` sets how long the movegen and evaluation measurements run for (default 5).
+/// `--nodes N` sets how many nodes are searched from every board for the tree measurement
+/// (default 100000).
+struct Settings {
+    seconds: u64,
+    nodes: u32
+}
+
+fn parse_args() -> Settings {
+    let mut settings = Settings {
+        seconds: 5,
+        nodes: 100_000
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--seconds" => settings.seconds = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--seconds requires a positive number"),
+            "--nodes" => settings.nodes = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--nodes requires a positive number"),
+            _ => eprintln!("ignoring unknown argument {}", arg)
+        }
+    }
+    settings
+}
+
+/// The boards every measurement is run on, top row first.
+///
+/// These should stay the same so that numbers from different versions can be compared.
+const BOARDS: &[(&str, &[&str])] = &[
+    ("empty", &[]),
+    ("tslot", &[
+        "........##",
+        "#...#....#",
+        "##.####..#",
+        "###.######",
+        "####.#####",
+    ]),
+    ("garbage", &[
+        "..##...#..",
+        "#.###.####",
+        "#########.",
+        ".#########",
+        "#####.####",
+        "#####.####",
+        "##.#######",
+        "########.#",
+    ]),
+    ("tall", &[
+        "....#.....",
+        "....##..##",
+        "#...######",
+        "##.#######",
+        "#.########",
+        "##.#######",
+        "######.###",
+        "###.######",
+        "#.########",
+        "########.#",
+        "###.######",
+        "#######.##",
+        "##.#######",
+        "######.###",
+    ])
+];
+
+const QUEUE: &[Piece] = &[
+    Piece::T, Piece::I, Piece::O, Piece::L, Piece::J, Piece::S, Piece::Z,
+    Piece::L, Piece::S, Piece::T, Piece::Z, Piece::O, Piece::J, Piece::I
+];
+
+const PIECES: &[Piece] = &[
+    Piece::I, Piece::O, Piece::T, Piece::L, Piece::J, Piece::S, Piece::Z
+];
+
+fn main() {
+    let settings = parse_args();
+    let duration = Duration::from_secs(settings.seconds);
+    let boards: Vec<_> = BOARDS.iter().map(|&(name, rows)| (name, board(rows))).collect();
+
+    let (placements, elapsed) = repeat_for(duration, || boards.iter()
+        .map(|(_, board)| PIECES.iter()
+            .filter_map(|&piece| SpawnRule::Row19Or20.spawn(piece, board))
+            .map(|spawned| {
+                find_moves(board, spawned, MovementMode::ZeroG, Timing::default()).len()
+            })
+            .sum::<usize>())
+        .sum()
+    );
+    report("movegen", placements, "placements", elapsed);
+
+    let eval = Standard::default();
+    let batch = evaluation_batch(&boards);
+    let (evaluations, elapsed) = repeat_for(duration, || eval.evaluate_batch(&batch).len());
+    report("evaluation", evaluations, "evaluations", elapsed);
+
+    let mut nodes = 0;
+    let mut elapsed = Duration::default();
+    for (name, board) in &boards {
+        let mut board = board.clone();
+        for &piece in QUEUE {
+            board.add_next_piece(piece);
+        }
+        let options = cold_clear::Options {
+            max_nodes: settings.nodes,
+            seed: Some(0),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let mut bot = cold_clear::BotState::<Standard>::new(board, options);
+        while let Ok(thinker) = bot.think() {
+            bot.finish_thinking(thinker.think(&eval));
+        }
+        let time = start.elapsed();
+        report(&format!("tree ({})", name), bot.nodes() as usize, "nodes", time);
+        nodes += bot.nodes() as usize;
+        elapsed += time;
+    }
+    report("tree", nodes, "nodes", elapsed);
+}
+
+fn board(rows: &[&str]) -> Board {
+    let mut field = [[false; 10]; 40];
+    for (y, row) in rows.iter().rev().enumerate() {
+        for (x, cell) in row.chars().enumerate() {
+            field[y][x] = cell == '#';
+        }
+    }
+    let mut board = Board::new();
+    board.set_field(field);
+    board
+}
+
+/// Every placement of every piece on every board, as given to the evaluator during search.
+fn evaluation_batch(boards: &[(&str, Board)]) -> Vec<Placement> {
+    let mut batch = vec![];
+    for (_, board) in boards {
+        for &piece in PIECES {
+            let spawned = match SpawnRule::Row19Or20.spawn(piece, board) {
+                Some(spawned) => spawned,
+                None => continue
+            };
+            for mv in find_moves(board, spawned, MovementMode::ZeroG, Timing::default()) {
+                let mut result = board.clone();
+                let lock = result.lock_piece(mv.location);
+                let (soft_drop, hard_drop) = drop_distances(board, spawned, &mv.inputs.movements);
+                batch.push(Placement {
+                    lock,
+                    board: result,
+                    move_time: mv.inputs.time,
+                    placed: piece,
+                    soft_drop,
+                    hard_drop,
+                    incoming: Default::default(),
+                    opponent: Default::default(),
+                    attack_multiplier: 100,
+                    score_multiplier: 1
+                });
+            }
+        }
+    }
+    batch
+}
+
+/// Runs `f` until `duration` has passed, returning the total amount of work it reported doing
+/// and how long that took.
+fn repeat_for(duration: Duration, mut f: impl FnMut() -> usize) -> (usize, Duration) {
+    let start = Instant::now();
+    let mut work = 0;
+    while start.elapsed() < duration {
+        work += f();
+    }
+    (work, start.elapsed())
+}
+
+fn report(name: &str, work: usize, unit: &str, elapsed: Duration) {
+    println!(
+        "{:<20} {:>12.0} {}/sec ({} in {:.2?})",
+        name, work as f64 / elapsed.as_secs_f64(), unit, work, elapsed
+    );
+}
@@ -44,8 +44,10 @@
 //! by only de/allocating in large chunks, using `bumpalo` for the children lists is the main
 //! optimization there. `bumpalo` has an interesting strategy for allocating new chunks of memory,
 //! if need be we can replace the `Vec` we're using as a slab with something with a similar
-//! allocation strategy. If allocator performance is still an issue, we could pool the bump
-//! allocation arenas and the slab `Vec`s, but this seems unlikely.
+//! allocation strategy. The arenas of discarded generations are kept in `DagState.spare_arenas`
+//! and reset for reuse by new generations, so a long game only allocates a handful of them.
+//! `ArenaStats` records how often that works out. The slab `Vec`s and hashmaps borrow from their
+//! arena, so they can't outlive it and aren't pooled.
 //! 
 //! ## Some overall memory usage stuff
 //! 
@@ -92,7 +94,37 @@ pub struct DagState<E: 'static, R: 'static> {
     generations: VecDeque<rented::Generation<E, R>>,
     root: u32,
     gens_passed: u32,
-    use_hold: bool
+    use_hold: bool,
+    rng: StdRng,
+    spare_arenas: Vec<Box<bumpalo::Bump>>,
+    arena_stats: ArenaStats
+}
+
+/// The size of the search tree; see `Interface::memory_usage`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MemoryUsage {
+    pub nodes: u32,
+    /// Approximately how many bytes the tree takes up, not counting memory reserved in advance
+    /// that isn't used yet.
+    pub bytes: u64
+}
+
+/// The number of arenas of discarded generations that are kept for reuse.
+const MAX_SPARE_ARENAS: usize = 8;
+
+/// How many generation arenas were freshly allocated and how many were reused.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ArenaStats {
+    pub allocated: u64,
+    pub reused: u64
+}
+
+impl ArenaStats {
+    /// The fraction of generations that reused the arena of a discarded generation.
+    pub fn reuse_rate(&self) -> f64 {
+        let total = self.allocated + self.reused;
+        if total == 0 { 0.0 } else { self.reused as f64 / total as f64 }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -107,6 +139,26 @@ pub struct ChildData<E, R> {
     pub board: Board,
     pub evaluation: E,
     pub reward: R,
+    /// The garbage expected to arrive right after the placement, which is already on `board`.
+    pub garbage: Option<Garbage>
+}
+
+/// Incoming garbage the search expects to arrive after a placement. It is added to the board
+/// whenever the search passes through the placement, so deeper placements are found on the board
+/// with the garbage.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Garbage {
+    /// The column of the holes.
+    pub column: u8,
+    pub lines: u8
+}
+
+impl Garbage {
+    pub fn add_to(self, board: &mut Board) {
+        for _ in 0..self.lines {
+            board.add_garbage(self.column as usize);
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -133,13 +185,19 @@ struct Generation<'c, E, R> {
     nodes: Vec<Node<'c, E>>,
     children: Children<'c, R>,
     deduplicator: HashMap<SimplifiedBoard<'c>, u32>,
+    /// An estimate of the bytes allocated in the arenas for this generation. The lists of
+    /// children leading to this generation are counted here even though they live in the arena of
+    /// the previous generation.
+    arena_bytes: usize
 }
 
 enum Children<'c, R> {
     // we need to know the piece to resolve speculations computed before a new piece was added,
     // but given to us after a new piece was added.
     Known(Piece, Vec<Option<&'c mut [Child<R>]>>),
-    Speculated(Vec<Option<EnumMap<Piece, Option<&'c mut [Child<R>]>>>>)
+    /// For each node, the children for each possible next piece along with the piece's weight
+    /// under the randomizer.
+    Speculated(Vec<Option<EnumMap<Piece, Option<(u32, &'c mut [Child<R>])>>>>)
 }
 
 struct Node<'c, E> {
@@ -153,7 +211,8 @@ struct Child<R> {
     placement: FallingPiece,
     reward: R,
     original_rank: u32,
-    node: u32
+    node: u32,
+    garbage: Option<Garbage>
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -167,26 +226,34 @@ struct SimplifiedBoard<'c> {
 }
 
 impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
-    pub fn new(board: Board, use_hold: bool) -> Self {
+    /// Creates a search tree. With a `seed`, the random choices of the search are reproducible.
+    pub fn new(board: Board, use_hold: bool, seed: Option<u64>) -> Self {
         let mut this = DagState {
             board,
             generations: VecDeque::new(),
             root: 0,
             gens_passed: 0,
-            use_hold
+            use_hold,
+            rng: match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy()
+            },
+            spare_arenas: vec![],
+            arena_stats: ArenaStats::default()
         };
         this.init_generations();
         this
     }
 
     fn init_generations(&mut self) {
-        let mut next_pieces = self.board.next_queue();
+        let mut next_pieces: VecDeque<_> = self.board.next_queue().collect();
         // if hold is enabled and hold is empty, the generation piece is later than normal.
         if self.use_hold && self.board.hold_piece.is_none() {
-            next_pieces.next().expect("Not enough next pieces provided to initialize");
+            next_pieces.pop_front().expect("Not enough next pieces provided to initialize");
         }
+        let arena = self.take_arena();
         self.generations.push_back(rented::Generation::new(
-            Box::new(bumpalo::Bump::new()),
+            arena,
             |bump| Generation {
                 nodes: vec![Node {
                     parents: BumpVec::new_in(bump),
@@ -194,29 +261,105 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                     marked: false,
                     death: false
                 }],
-                children: match next_pieces.next() {
+                children: match next_pieces.pop_front() {
                     Some(p) => Children::Known(p, vec![None]),
                     None => Children::Speculated(vec![None])
                 },
                 // nothing new will ever be put in the root generation, so we won't bother to
                 // put anything in the hashmap.
-                deduplicator: HashMap::new()
+                deduplicator: HashMap::new(),
+                arena_bytes: 0
             }
         ));
         // initialize the remaining known generations
         for piece in next_pieces {
-            self.generations.push_back(rented::Generation::known(piece));
+            let arena = self.take_arena();
+            self.generations.push_back(rented::Generation::known(arena, piece));
         }
     }
 
+    /// Gets an empty arena for a new generation, reusing one from a discarded generation if
+    /// possible.
+    fn take_arena(&mut self) -> Box<bumpalo::Bump> {
+        match self.spare_arenas.pop() {
+            Some(mut arena) => {
+                arena.reset();
+                self.arena_stats.reused += 1;
+                arena
+            }
+            None => {
+                self.arena_stats.allocated += 1;
+                Box::new(bumpalo::Bump::with_capacity(1 << 20))
+            }
+        }
+    }
+
+    /// Discards a generation, keeping its arena for reuse.
+    fn retire(&mut self, generation: rented::Generation<E, R>) {
+        if self.spare_arenas.len() < MAX_SPARE_ARENAS {
+            self.spare_arenas.push(generation.into_head());
+        }
+    }
+
+    /// Discards every generation.
+    fn retire_all(&mut self) {
+        while let Some(generation) = self.generations.pop_front() {
+            self.retire(generation);
+        }
+    }
+
+    pub fn arena_stats(&self) -> ArenaStats {
+        self.arena_stats
+    }
+
+    /// An estimate of the memory used by the search tree.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            nodes: self.nodes(),
+            bytes: self.generations.iter()
+                .map(|gen| gen.rent(|gen| gen.memory_usage()) as u64)
+                .sum()
+        }
+    }
+
+    /// Throws away the deepest generation to save memory, turning the nodes of the generation
+    /// before it back into leaves. Returns whether a generation was thrown away.
+    /// 
+    /// Only speculated generations are thrown away, since a known generation would lose the
+    /// piece it belongs to. The root and its children are always kept.
+    pub fn shed_deepest_generation(&mut self) -> bool {
+        if self.generations.len() <= 2 {
+            return false
+        }
+        let speculated = self.generations.back().unwrap().rent(|gen| match gen.children {
+            Children::Speculated(_) => true,
+            Children::Known(_, _) => false
+        });
+        if !speculated {
+            return false
+        }
+        let shed = self.generations.pop_back().unwrap();
+        self.retire(shed);
+        self.generations.back_mut().unwrap().rent_mut(|gen| match &mut gen.children {
+            Children::Known(_, children) => for c in children {
+                *c = None;
+            }
+            Children::Speculated(children) => for c in children {
+                *c = None;
+            }
+        });
+        true
+    }
+
     pub fn find_and_mark_leaf(
         &mut self, forced_analysis_lines: &mut Vec<Vec<FallingPiece>>
     ) -> Option<(NodeId, Board)> {
+        span!("find_leaf");
         for i in (0..forced_analysis_lines.len()).rev() {
             // Attempt to search forced lines first
             let mut path = &*forced_analysis_lines[i];
             let mut done = false;
-            let choice = self.find_and_mark_leaf_with_chooser(|_, children| {
+            let choice = self.find_and_mark_leaf_with_chooser(|_, _, children| {
                 if let &[next, ref rest @ ..] = path {
                     for child in children {
                         if next.same_location(&child.placement) {
@@ -243,7 +386,7 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
             }
         }
 
-        self.find_and_mark_leaf_with_chooser(|next_gen_nodes, children| {
+        self.find_and_mark_leaf_with_chooser(|rng, next_gen_nodes, children| {
             // Since children is sorted best-to-worst, the minimum evaluation will be the last item
             // in the iterator. filter_map allows us to ignore death nodes.
             let evaluation = &child_eval_fn(next_gen_nodes);
@@ -255,14 +398,17 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
             );
             // Choose a node randomly (the Monte-Carlo part)
             let sampler = rand::distributions::WeightedIndex::new(weights).ok()?;
-            Some(&children[thread_rng().sample(sampler)])
+            Some(&children[rng.sample(sampler)])
         })
     }
 
     fn find_and_mark_leaf_with_chooser(
         &mut self,
-        mut chooser: impl for<'a> FnMut(&[Node<E>], &'a [Child<R>]) -> Option<&'a Child<R>>
+        mut chooser: impl for<'a> FnMut(
+            &mut StdRng, &[Node<E>], &'a [Child<R>]
+        ) -> Option<&'a Child<R>>
     ) -> Option<(NodeId, Board)> {
+        let rng = &mut self.rng;
         let mut board = self.board.clone();
         let mut gen_index = 0;
         let mut node_key = self.root as usize;
@@ -272,16 +418,19 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                 Children::Known(_, childrens) => childrens[node_key].as_deref(),
                 Children::Speculated(childrens) => {
                     // We must select a single group of children to search further. We do this by
-                    // finding the set of valid next pieces and randomly selecting one uniformly.
-                    // We then take the group of children associated with that piece.
+                    // finding the set of valid next pieces and randomly selecting one according
+                    // to how likely the randomizer is to deal it. We then take the group of
+                    // children associated with that piece.
                     let children = childrens[node_key].as_ref()?;
                     let mut pick_from = ArrayVec::<[_; 7]>::new();
                     for (p, c) in children {
-                        if let Some(c) = c {
-                            pick_from.push((p, &**c));
+                        if let Some((weight, c)) = c {
+                            pick_from.push((p, *weight, &**c));
                         }
                     }
-                    let (piece, children) = *pick_from.choose(&mut thread_rng()).unwrap();
+                    let (piece, _, children) = *pick_from.choose_weighted(
+                        &mut *rng, |&(_, weight, _)| weight
+                    ).unwrap();
                     board.add_next_piece(piece);
                     Some(children)
                 }
@@ -291,10 +440,11 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                 // Branch case. Call the chooser to pick the branch to take.
                 self.generations[gen_index+1].rent(|gen| {
                     let child = chooser(
+                        &mut *rng,
                         &gen.nodes,
                         children
                     )?;
-                    advance(&mut board, child.placement);
+                    advance_child(&mut board, child);
                     gen_index += 1;
                     node_key = child.node as usize;
                     Some(())
@@ -314,11 +464,16 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
     }
 
     pub fn update_known(&mut self, node: NodeId, children: Vec<ChildData<E, R>>) {
+        span!("update_known");
         // make sure we weren't given a NodeId for an expired node. it could happen.
         if node.generation < self.gens_passed {
             return
         }
         let gen = (node.generation - self.gens_passed) as usize;
+        // or for a node in a generation that was shed to save memory
+        if gen >= self.generations.len() {
+            return
+        }
 
         let use_hold = self.use_hold;
         let [parent_gen, child_gen] = self.get_gen_and_next(gen);
@@ -338,13 +493,18 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
 
     pub fn update_speculated(
         &mut self, node: NodeId,
-        mut children: EnumMap<Piece, Option<Vec<ChildData<E, R>>>>
+        mut children: EnumMap<Piece, Option<(u32, Vec<ChildData<E, R>>)>>
     ) {
+        span!("update_speculated");
         // make sure we weren't given a NodeId for an expired node. it could happen.
         if node.generation < self.gens_passed {
             return
         }
         let gen = (node.generation - self.gens_passed) as usize;
+        // or for a node in a generation that was shed to save memory
+        if gen >= self.generations.len() {
+            return
+        }
 
         let use_hold = self.use_hold;
         let [parent_gen, child_gen] = self.get_gen_and_next(gen);
@@ -352,7 +512,7 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         parent_gen.rent_all_mut(|current| child_gen.rent_all_mut(|mut next| {
             match &mut current.data.children {
                 // Deal with the case that the generation has been resolved 
-                Children::Known(piece, c) => if let Some(children) = children[*piece].take() {
+                Children::Known(piece, c) => if let Some((_, children)) = children[*piece].take() {
                     c[node.slab_key as usize] = Some(build_children(
                         current.arena,
                         &mut next,
@@ -364,10 +524,10 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                 Children::Speculated(c) => {
                     let mut childs = EnumMap::new();
                     for (p, data) in children {
-                        if let Some(data) = data {
-                            childs[p] = Some(build_children(
+                        if let Some((weight, data)) = data {
+                            childs[p] = Some((weight, build_children(
                                 current.arena, &mut next, data, node.slab_key, use_hold
-                            ));
+                            )));
                         }
                     }
                     c[node.slab_key as usize] = Some(childs);
@@ -380,6 +540,7 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
     }
 
     fn backpropogate(&mut self, mut gen: usize, mut to_update: Vec<usize>) {
+        span!("backpropagate");
         // Use a queue to iterate in breadth-first order. This allows us to know that we shouldn't
         // add an element to the queue if it's already present; we know that all of its children
         // will have been processed first before we get to the parent node.
@@ -432,18 +593,19 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                         Children::Speculated(children) => {
                             if let Some(children) = children[node_id as usize].as_mut() {
                                 // The eval of a speculated node should be the expected value,
-                                // which is actually just the average since each piece in the bag
-                                // has an equal probability of being chosen. So we'll track the
-                                // number of pieces in the bag, the total score, etc. to calculate
-                                // it later. We track the eval of the worst possibility to later
-                                // use for death evaluations.
-                                let mut possibilities = 0;
+                                // which is the average of the possibilities weighted by how
+                                // likely the randomizer is to deal each piece. So we'll track the
+                                // total weight, the weighted total score, etc. to calculate it
+                                // later. We track the eval of the worst possibility to later use
+                                // for death evaluations.
+                                let mut total_weight = 0;
                                 let mut total = E::default();
-                                let mut deaths = 0;
+                                let mut death_weight = 0;
                                 let mut worst = None;
-                                for children in children.values_mut()
+                                for (weight, children) in children.values_mut()
                                         .filter_map(Option::as_mut) {
-                                    possibilities += 1;
+                                    let weight = *weight as usize;
+                                    total_weight += weight;
                                     match process_children(children) {
                                         Some(eval) => {
                                             match worst {
@@ -451,14 +613,14 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                                                 Some(v) if eval < v => worst = Some(eval.clone()),
                                                 _ => {}
                                             }
-                                            total = total + eval;
+                                            total = total + eval * weight;
                                         },
-                                        None => deaths += 1
+                                        None => death_weight += weight
                                     }
                                 }
                                 worst.map(|worst| (
-                                    total + worst.modify_death() * deaths
-                                ) / possibilities)
+                                    total + worst.modify_death() * death_weight
+                                ) / total_weight)
                             } else {
                                 // returns from closure and continues the loop
                                 return
@@ -494,7 +656,8 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
             // we're expanding into boards that belong in a generation that doesn't exist yet.
             // since it doesn't exist, we're missing some next queue information, so it's a
             // speculated generation.
-            self.generations.push_back(rented::Generation::speculated());
+            let arena = self.take_arena();
+            self.generations.push_back(rented::Generation::speculated(arena));
         }
 
         fn get2_mut<T>(s: &mut [T], i: usize) -> [&mut T; 2] {
@@ -516,13 +679,15 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
     pub fn unmark(&mut self, node: NodeId) {
         // make sure we weren't given a NodeId for an expired node. it could happen.
         if node.generation >= self.gens_passed {
-            self.generations[(node.generation - self.gens_passed) as usize].rent_mut(
-                |gen| gen.nodes[node.slab_key as usize].marked = false
-            );
+            let gen = (node.generation - self.gens_passed) as usize;
+            if let Some(gen) = self.generations.get_mut(gen) {
+                gen.rent_mut(|gen| gen.nodes[node.slab_key as usize].marked = false);
+            }
         }
     }
 
     pub fn add_next_piece(&mut self, piece: Piece) {
+        span!("resolve_speculation");
         self.board.add_next_piece(piece);
         // resolve a speculated generation if possible
         for (i, gen) in self.generations.iter_mut().enumerate() {
@@ -532,7 +697,7 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                     let mut newchildren = Vec::with_capacity(childs.len());
                     for (j, child) in std::mem::take(childs).into_iter().enumerate() {
                         newchildren.push(child.and_then(|mut cases|
-                            std::mem::take(&mut cases[piece])
+                            std::mem::take(&mut cases[piece]).map(|(_, children)| children)
                         ));
                         to_update.push(j);
                     }
@@ -547,18 +712,38 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
             }
         }
         // if are no speculated generations, add a known generation
-        self.generations.push_back(rented::Generation::known(piece));
+        let arena = self.take_arena();
+        self.generations.push_back(rented::Generation::known(arena, piece));
     }
 
     pub fn get_plan(&self) -> Vec<(FallingPiece, LockResult)> {
-        let mut node = self.root;
-        let mut plan = vec![];
+        self.follow_plan(0, self.root, self.board.clone())
+    }
+
+    /// The expected line of play if the given placement is played instead of the best move.
+    pub fn get_variation(&self, mv: FallingPiece) -> Vec<(FallingPiece, LockResult)> {
+        let (node, garbage) = match self.root_child(mv) {
+            Some(child) => child,
+            None => return vec![]
+        };
         let mut board = self.board.clone();
-        for gen in &self.generations {
+        let mut plan = vec![(mv, advance(&mut board, mv))];
+        if let Some(garbage) = garbage {
+            garbage.add_to(&mut board);
+        }
+        plan.extend(self.follow_plan(1, node, board));
+        plan
+    }
+
+    fn follow_plan(
+        &self, skip: usize, mut node: u32, mut board: Board
+    ) -> Vec<(FallingPiece, LockResult)> {
+        let mut plan = vec![];
+        for gen in self.generations.iter().skip(skip) {
             let done = gen.rent(|gen| match &gen.children {
                 Children::Known(_, c) => match c[node as usize].as_ref().and_then(|c| c.first()) {
                     Some(child) => {
-                        plan.push((child.placement, advance(&mut board, child.placement)));
+                        plan.push((child.placement, advance_child(&mut board, child)));
                         node = child.node;
                         false
                     }
@@ -602,12 +787,31 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
 
         self.gens_passed += self.generations.len() as u32 + 1;
         self.root = 0;
-        self.generations.clear();
+        self.retire_all();
         self.init_generations();
 
         garbage_lines
     }
 
+    /// Replaces the whole game state, discarding the search tree.
+    pub fn rebuild(&mut self, board: Board) {
+        self.board = board;
+        self.restart();
+    }
+
+    /// Discards the search tree, for example because hold was enabled or disabled.
+    pub fn restart_with_hold(&mut self, use_hold: bool) {
+        self.use_hold = use_hold;
+        self.restart();
+    }
+
+    fn restart(&mut self) {
+        self.gens_passed += self.generations.len() as u32 + 1;
+        self.root = 0;
+        self.retire_all();
+        self.init_generations();
+    }
+
     pub fn get_next_candidates(&self) -> Vec<MoveCandidate<E>> {
         if self.generations.len() < 2 { return vec![]; }
         self.generations[0].rent(|gen| self.generations[1].rent(|child_gen| {
@@ -636,26 +840,109 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
     }
 
     pub fn advance_move(&mut self, mv: FallingPiece) {
-        let new_root = self.generations[0].rent(|gen|
+        let (new_root, garbage) = self.root_child(mv).expect("An invalid move was chosen");
+
+        self.root = new_root;
+        advance(&mut self.board, mv);
+        if let Some(generation) = self.generations.pop_front() {
+            self.retire(generation);
+        }
+        self.gens_passed += 1;
+        if garbage.is_some() {
+            // the game reports garbage when it arrives, and until then the placements below
+            // the new root don't fit its board
+            self.restart();
+        }
+    }
+
+    /// Advances by a placement that was played without the bot choosing it. If the placement is
+    /// one of the root's children the tree is kept, otherwise it is rebuilt from the resulting
+    /// position. Returns whether the tree was kept.
+    pub fn play_move(&mut self, mv: FallingPiece) -> bool {
+        if self.root_child(mv).is_some() {
+            self.advance_move(mv);
+            return true
+        }
+        // the same cells might have been reached with a different rotation state or spin
+        let known = self.generations[0].rent(|gen|
+            if let Children::Known(_, children) = &gen.children {
+                children[self.root as usize].as_ref().and_then(
+                    |children| children.iter().find(|c| c.placement.same_location(&mv))
+                ).map(|c| c.placement)
+            } else {
+                None
+            }
+        );
+        match known {
+            Some(placement) if placement.tspin == mv.tspin => {
+                self.advance_move(placement);
+                true
+            }
+            _ => {
+                let mut board = self.board.clone();
+                advance(&mut board, mv);
+                self.rebuild(board);
+                false
+            }
+        }
+    }
+
+    /// The node reached by placing `mv` from the root and the garbage expected to arrive after it.
+    fn root_child(&self, mv: FallingPiece) -> Option<(u32, Option<Garbage>)> {
+        self.generations[0].rent(|gen|
             if let Children::Known(_, children) = &gen.children {
                 children[self.root as usize].as_ref().and_then(
                     |children| children.iter().find(|c| c.placement == mv)
-                ).map(|c| c.node)
+                ).map(|c| (c.node, c.garbage))
             } else {
                 None
             }
-        ).expect("An invalid move was chosen");
+        )
+    }
 
-        self.root = new_root;
-        advance(&mut self.board, mv);
-        self.generations.pop_front();
-        self.gens_passed += 1;
+    /// Whether `node` is the node of the current board state.
+    pub fn is_root(&self, node: &NodeId) -> bool {
+        node.generation == self.gens_passed && node.slab_key == self.root
+    }
+
+    /// The number of pieces placed between the root and `node`.
+    pub fn node_depth(&self, node: &NodeId) -> u32 {
+        node.generation - self.gens_passed
+    }
+
+    /// The number of pieces that aren't known yet which the children of `node` depend on.
+    pub fn unknown_pieces(&self, node: &NodeId) -> u32 {
+        let gen = (node.generation - self.gens_passed) as usize;
+        self.generations.iter()
+            .take(gen + 1)
+            .filter(|gen| gen.rent(|gen| match gen.children {
+                Children::Speculated(_) => true,
+                Children::Known(_, _) => false
+            }))
+            .count() as u32
     }
 
     pub fn nodes(&self) -> u32 {
         self.generations.iter().map(|gen| gen.rent(|gen| gen.nodes.len() as u32)).sum()
     }
 
+    /// The number of nodes that depend on a piece that isn't known yet.
+    pub fn speculated_nodes(&self) -> u32 {
+        let mut speculated = false;
+        let mut nodes = 0;
+        for gen in &self.generations {
+            gen.rent(|gen| {
+                if speculated {
+                    nodes += gen.nodes.len() as u32;
+                }
+                if let Children::Speculated(_) = gen.children {
+                    speculated = true;
+                }
+            });
+        }
+        nodes
+    }
+
     pub fn depth(&self) -> u32 {
         let mut depth = self.generations.len() as u32 - 1;
         for gen in self.generations.iter().rev() {
@@ -700,6 +987,15 @@ where
     }
 }
 
+/// Like `advance`, but also adds the garbage expected to arrive after the child's placement.
+fn advance_child<R>(board: &mut Board, child: &Child<R>) -> LockResult {
+    let result = advance(board, child.placement);
+    if let Some(garbage) = child.garbage {
+        garbage.add_to(board);
+    }
+    result
+}
+
 /// keeps queue state consistent while arbitrarily placing pieces
 fn advance(board: &mut Board, placement: FallingPiece) -> LockResult {
     let result = board.lock_piece(placement);
@@ -719,6 +1015,7 @@ fn build_children<'arena, E: Evaluation<R> + 'static, R: Clone + 'static>(
     parent: u32,
     hold_allowed: bool
 ) -> &'arena mut [Child<R>] {
+    children_gen.data.arena_bytes += children.len() * std::mem::size_of::<Child<R>>();
     // sort best to worst
     children.sort_by_key(
         |c| std::cmp::Reverse(c.evaluation.clone() + c.reward.clone())
@@ -766,39 +1063,59 @@ fn build_children<'arena, E: Evaluation<R> + 'static, R: Clone + 'static>(
                         grid: children_gen.arena.alloc_slice_copy(&simple_grid),
                         ..simple_board
                     }, node as u32);
+                    children_gen.data.arena_bytes += std::mem::size_of_val(&*simple_grid);
                     node as u32
                 }
             };
             children_gen.data.nodes[node as usize].parents.push(parent);
+            children_gen.data.arena_bytes += std::mem::size_of::<u32>();
 
             Child {
                 placement: data.mv,
                 original_rank: i as u32,
                 reward: data.reward,
-                node
+                node,
+                garbage: data.garbage
             }
         }
     ))
 }
 
+impl<'c, E, R> Generation<'c, E, R> {
+    fn memory_usage(&self) -> usize {
+        use std::mem::size_of;
+        let children = match &self.children {
+            Children::Known(_, c) => c.len() * size_of::<Option<&mut [Child<R>]>>(),
+            Children::Speculated(c) =>
+                c.len() * size_of::<Option<EnumMap<Piece, Option<(u32, &mut [Child<R>])>>>>()
+        };
+        self.nodes.len() * size_of::<Node<E>>()
+            + children
+            + self.deduplicator.len() * size_of::<(SimplifiedBoard, u32)>()
+            + self.arena_bytes
+    }
+}
+
 impl<E: 'static, R: 'static> rented::Generation<E, R> {
-    pub fn known(piece: Piece) -> Self {
+    pub fn known(arena: Box<bumpalo::Bump>, piece: Piece) -> Self {
         rented::Generation::new(
-            Box::new(bumpalo::Bump::with_capacity(1 << 20)),
+            arena,
             |_| Generation {
                 nodes: Vec::with_capacity(1 << 17),
                 deduplicator: HashMap::with_capacity(1 << 17),
+                arena_bytes: 0,
                 children: Children::Known(piece, Vec::with_capacity(1 << 17))
             }
         )
     }
 
-    pub fn speculated() -> Self {
+    pub fn speculated(arena: Box<bumpalo::Bump>) -> Self {
         rented::Generation::new(
-            Box::new(bumpalo::Bump::with_capacity(1 << 20)),
+            arena,
             |_| Generation {
                 nodes: Vec::with_capacity(1 << 17),
                 deduplicator: HashMap::with_capacity(1 << 17),
+                arena_bytes: 0,
                 children: Children::Speculated(Vec::with_capacity(1 << 17))
             }
         )
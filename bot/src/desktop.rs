@@ -1,34 +1,181 @@
-use crossbeam_channel::{ Sender, Receiver, TryRecvError, unbounded, select };
-use std::sync::Arc;
+use crossbeam_channel::{ Sender, Receiver, TryRecvError, unbounded, select, at, never };
+use std::sync::{ Arc, Mutex };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ Context, Poll, Waker };
+use std::time::{ Duration, Instant };
+use std::fs::{ File, OpenOptions };
+use std::path::Path;
+use std::io::Write;
+use std::hash::{ Hash, Hasher };
+use std::collections::hash_map::DefaultHasher;
+use rand::Rng;
+use serde::Serialize;
 use libtetris::*;
 use opening_book::Book;
 use crate::evaluation::Evaluator;
 use crate::moves::Move;
-use crate::modes::ModeSwitchedBot;
-use crate::{ Options, Info, BotMsg, BotPollState };
+use crate::modes::{ ModeSwitchedBot, resync_board };
+use crate::{ Options, Info, BotMsg, BotPollState, Candidate, Snapshot, MemoryUsage };
 
 pub struct Interface {
     send: Sender<BotMsg>,
-    recv: Receiver<(Move, Info)>,
+    recv: MoveReceiver,
+    candidates: Arc<Mutex<Vec<Candidate>>>,
+    memory_usage: Arc<Mutex<MemoryUsage>>,
+    subscribers: Arc<Mutex<Vec<Sender<Event>>>>,
+    decision_log: Arc<Mutex<Option<File>>>,
+    /// The task waiting on the future returned by `next_move_async`, if any.
+    waker: Arc<Mutex<Option<Waker>>>
+}
+
+/// Something that happened in the bot thread; see `Interface::subscribe`.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// The bot provided a move. The move is also returned by `poll_next_move` and friends as
+    /// usual.
+    MoveDecided(Move, Info),
+    /// The bot found that it cannot survive. No more moves will be provided.
+    Death,
+    /// The plan changed to one that includes a perfect clear.
+    PerfectClearPlanned(Vec<(FallingPiece, LockResult)>),
+    /// The sequence of moves the bot expects to make changed.
+    PlanChanged(Vec<(FallingPiece, LockResult)>)
 }
 
 impl Interface {
     /// Launches a bot thread with the specified starting board and options.
+    /// 
+    /// To choose the evaluator at runtime, pass a `Box<DynEvaluator>`, for example one built from
+    /// an `EvaluatorConfig`.
     pub fn launch(
         board: Board,
         options: Options,
         evaluator: impl Evaluator + Send + 'static,
         book: Option<Arc<Book>>
+    ) -> Self {
+        Interface::launch_in(None, board, options, evaluator, book)
+    }
+
+    /// Launches a bot thread that thinks on the given thread pool instead of its own.
+    pub(crate) fn launch_in(
+        pool: Option<Arc<rayon::ThreadPool>>,
+        board: Board,
+        options: Options,
+        evaluator: impl Evaluator + Send + 'static,
+        book: Option<Arc<Book>>
     ) -> Self {
         let (bot_send, recv) = unbounded();
         let (send, bot_recv) = unbounded();
-        std::thread::spawn(move || run(bot_recv, bot_send, board, evaluator, options, book));
+        let candidates = Arc::new(Mutex::new(vec![]));
+        let memory_usage = Arc::new(Mutex::new(MemoryUsage::default()));
+        let subscribers = Arc::new(Mutex::new(vec![]));
+        let decision_log = Arc::new(Mutex::new(None));
+        let waker = Arc::new(Mutex::new(None));
+        let bot_send = MoveSender {
+            send: Some(bot_send),
+            waker: waker.clone(),
+            request: Cell::new(0)
+        };
+        let recv = MoveReceiver {
+            recv,
+            requests: Arc::new(Mutex::new(0)),
+            abandoned: Arc::new(AtomicU64::new(0))
+        };
+        let outputs = Outputs {
+            candidates: candidates.clone(),
+            memory_usage: memory_usage.clone(),
+            subscribers: subscribers.clone(),
+            decision_log: decision_log.clone()
+        };
+        std::thread::spawn(move || run(
+            bot_recv, bot_send, outputs, pool, board, evaluator, options, book
+        ));
 
         Interface {
-            send, recv
+            send, recv, candidates, memory_usage, subscribers, decision_log, waker
         }
     }
 
+    /// Launches a bot that continues from a snapshot taken with `snapshot`. See `launch`.
+    pub fn restore(
+        snapshot: Snapshot,
+        evaluator: impl Evaluator + Send + 'static,
+        book: Option<Arc<Book>>
+    ) -> Self {
+        let interface = Interface::launch(snapshot.board, snapshot.options, evaluator, book);
+        interface.send.send(BotMsg::IncomingGarbage(snapshot.incoming)).ok();
+        interface
+    }
+
+    /// Returns the state needed to continue from the position the bot will make its next move
+    /// from, for example to save it with `Snapshot::save` and `restore` it later. Blocks until
+    /// the bot thread responds; returns `None` if the bot thread has stopped.
+    pub fn snapshot(&self) -> Option<Snapshot> {
+        let (send, recv) = crossbeam_channel::bounded(1);
+        self.send.send(BotMsg::Snapshot(send)).ok()?;
+        recv.recv().ok()
+    }
+
+    /// Appends a JSON line describing every decision the bot makes from now on to the given file,
+    /// creating it if needed. Replaces the file given to any previous call.
+    /// 
+    /// Each line has the hash of the field, the hold piece, the queue, back-to-back and combo
+    /// status before the move, the chosen placement, whether hold was used, and for normal
+    /// decisions the evaluation, node count, depth and time spent.
+    pub fn log_decisions(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        *self.decision_log.lock().unwrap() = Some(file);
+        Ok(())
+    }
+
+    /// Stops logging decisions started by `log_decisions`.
+    pub fn stop_logging_decisions(&self) {
+        *self.decision_log.lock().unwrap() = None;
+    }
+
+    /// Returns a channel that receives an `Event` whenever a move is decided, the bot dies, or
+    /// its plan changes, so frontends can drive effects without polling.
+    /// 
+    /// Plan changes are checked a few times per second while the bot is thinking. Any number of
+    /// channels can be subscribed; dropping the receiver unsubscribes it.
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (send, recv) = unbounded();
+        self.subscribers.lock().unwrap().push(send);
+        recv
+    }
+
+    /// Returns the moves the bot is currently leaning towards, best first, for example to draw a
+    /// live overlay while the bot is thinking.
+    /// 
+    /// The list is refreshed every `Options::candidate_interval` milliseconds while the bot is
+    /// thinking and is always empty if that option is `None`. It can be out of date by up to that
+    /// long, so it may briefly show moves for the previous piece after a move is provided.
+    pub fn candidates(&self) -> Vec<Candidate> {
+        self.candidates.lock().unwrap().clone()
+    }
+
+    /// Breaks the evaluation of one of the moves the bot is considering for its next piece down
+    /// into named terms, like `Info::explanation` does for the chosen move, for example to show
+    /// why the bot dislikes a move the player picked. See `Evaluator::explain`.
+    /// 
+    /// Blocks until the bot thread responds. Returns `None` if the bot isn't considering
+    /// `placement`, for example because it hasn't looked at the position yet, or if the bot thread
+    /// has stopped.
+    pub fn explain_candidate(&self, placement: FallingPiece) -> Option<Vec<(String, i32)>> {
+        let (send, recv) = crossbeam_channel::bounded(1);
+        self.send.send(BotMsg::ExplainCandidate(placement, send)).ok()?;
+        recv.recv().ok()?
+    }
+
+    /// Returns the size of the bot's search tree as of the last time the bot thread handled a
+    /// message or a thinking result. See `Options::max_memory` to limit it.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        *self.memory_usage.lock().unwrap()
+    }
+
     /// Request the bot to provide a move as soon as possible.
     /// 
     /// In most cases, "as soon as possible" is a very short amount of time, and is only longer if
@@ -47,7 +194,16 @@ impl Interface {
     /// Once a move is chosen, the bot will update its internal state to the result of the piece
     /// being placed correctly and the move will become available by calling `poll_next_move`.
     pub fn request_next_move(&self, incoming: u32) {
-        self.send.send(BotMsg::NextMove(incoming)).ok();
+        self.recv.request(&self.send, BotMsg::NextMove(incoming));
+    }
+
+    /// Requests a move like `request_next_move`, but once `deadline` passes the bot provides the
+    /// best move it has found so far even if it hasn't reached the minimum amount of thinking.
+    /// 
+    /// The move is still only provided once the bot knows of at least one move, so it can be late
+    /// if the bot has no information about the next piece yet.
+    pub fn request_move_by(&self, incoming: u32, deadline: Instant) {
+        self.recv.request(&self.send, BotMsg::NextMoveBy(incoming, deadline));
     }
 
     /// Checks to see if the bot has provided the previously requested move yet.
@@ -69,7 +225,26 @@ impl Interface {
     /// 
     /// `None` is returned if the bot is dead.
     pub fn block_next_move(&self) -> Option<(Move, Info)> {
-        self.recv.recv().ok()
+        self.recv.recv()
+    }
+
+    /// Requests a move like `request_next_move` and returns a future that resolves to it.
+    /// 
+    /// The future works with any executor. `None` is returned if the bot is dead. Don't mix this
+    /// with `poll_next_move` or `block_next_move` for the same request, and only wait on one of
+    /// these futures at a time.
+    /// 
+    /// Dropping the future before it resolves cancels the request like `cancel_next_move`, and
+    /// the move is discarded if it was already provided.
+    pub fn next_move_async(&self, incoming: u32) -> NextMove {
+        let request = self.recv.request(&self.send, BotMsg::NextMove(incoming));
+        NextMove {
+            recv: self.recv.clone(),
+            request,
+            send: self.send.clone(),
+            waker: self.waker.clone(),
+            done: false
+        }
     }
 
     /// Adds a new piece to the end of the queue.
@@ -96,51 +271,379 @@ impl Interface {
         }).ok();
     }
 
+    /// Replaces the whole game state, including the hold piece and the next queue.
+    /// 
+    /// Use this to recover when the game has diverged from what the bot expects, for example
+    /// after a misdrop or a missed piece. The bot keeps its options and evaluator but throws away
+    /// its search tree. `queue` starts with the piece the bot should place next. With the 7-bag
+    /// randomizer, `bag` holds the pieces that were left in the bag before the first piece of
+    /// `queue` was drawn from it, or is empty for a full bag; the other randomizers rebuild their
+    /// state from `queue` instead. If hold is enabled and both `hold` and `queue` are empty, this
+    /// does nothing.
+    pub fn resync(
+        &self,
+        field: [[bool; 10]; 40],
+        b2b_active: bool,
+        combo: u32,
+        hold: Option<Piece>,
+        queue: Vec<Piece>,
+        bag: enumset::EnumSet<Piece>
+    ) {
+        self.send.send(BotMsg::Resync {
+            field, b2b: b2b_active, combo, hold, queue, bag
+        }).ok();
+    }
+
+    /// Tells the bot that the given placement was played, whether or not the bot suggested it.
+    /// 
+    /// Use this for analysis and for games where a human and the bot take turns controlling the
+    /// same board. If the bot had already considered the placement, it keeps the relevant part of
+    /// its search tree; otherwise it starts over from the resulting position. A pending move
+    /// request is then answered for the new position. Placements that couldn't have been played
+    /// with the current piece or hold piece are ignored.
+    pub fn play_move(&self, placement: FallingPiece) {
+        self.send.send(BotMsg::PlayMove(placement)).ok();
+    }
+
+    /// Changes the options of the running bot. New thinking cycles use the new options.
+    /// 
+    /// The number of threads can't be changed and keeps its original value. Changing `use_hold`,
+    /// `mode` or `spawn_rule` makes the bot throw away its search tree. Hold can't be enabled
+    /// while the queue is empty and no piece is held. Changing `game_time` restarts the clock.
+    pub fn set_options(&self, options: Options) {
+        self.send.send(BotMsg::SetOptions(options)).ok();
+    }
+
     /// Specifies a line that Cold Clear should analyze before making any moves.
     pub fn force_analysis_line(&self, path: Vec<FallingPiece>) {
         self.send.send(BotMsg::ForceAnalysisLine(path)).ok();
     }
+
+    /// Tells the bot how many lines of garbage are queued against it and how many pieces it can
+    /// place before they arrive.
+    /// 
+    /// The bot plays more defensively the more garbage is queued and the sooner it arrives. Call
+    /// this again with zero lines once the garbage has been received or cancelled.
+    pub fn set_incoming_garbage(&self, lines: u32, delay: u32) {
+        self.send.send(BotMsg::IncomingGarbage(crate::evaluation::Incoming {
+            lines, delay
+        })).ok();
+    }
+
+    /// Tells the bot its opponent placed a piece that sent `attack` lines of garbage, or 0 if the
+    /// piece didn't attack.
+    ///
+    /// From these the bot learns how fast the opponent plays and how hard it spikes. While no
+    /// garbage is queued against the bot, it plays as if the opponent's expected next attack were
+    /// incoming, which makes the defensive terms of the evaluator and the search prepare for it.
+    pub fn observe_opponent(&self, attack: u32) {
+        self.send.send(BotMsg::OpponentPlaced(attack)).ok();
+    }
+
+    /// Tells the bot how tall the opponent's stack is and how much garbage is queued against it.
+    ///
+    /// Evaluators that plan spikes, like `Standard` with the `banked_spike` and `lethal_spike`
+    /// weights, value keeping back-to-back and combo going more the closer the opponent is to
+    /// topping out, and look for an attack big enough to finish them.
+    pub fn set_opponent_state(&self, height: u32, incoming: u32) {
+        self.send.send(BotMsg::OpponentState(crate::evaluation::OpponentState {
+            height, incoming
+        })).ok();
+    }
+
+    /// Tells the bot the percentage its attacks are currently scaled by, for example when margin
+    /// time has made attacks stronger. The default is 100.
+    /// 
+    /// Line clears are valued in proportion, so the bot favors attacking quickly over building
+    /// once attacks are stronger.
+    pub fn set_attack_multiplier(&self, multiplier: u32) {
+        self.send.send(BotMsg::AttackMultiplier(multiplier)).ok();
+    }
+
+    /// Tells the bot the factor points are currently multiplied by, such as the level in a Blitz
+    /// game. The default is 1. Only evaluators that score points, like `ScoreAttack`, use it.
+    pub fn set_score_multiplier(&self, multiplier: u32) {
+        self.send.send(BotMsg::ScoreMultiplier(multiplier)).ok();
+    }
+
+    /// Stops the bot from thinking until `resume` is called, for example while the game is
+    /// paused.
+    /// 
+    /// Thinking cycles that are already running are allowed to finish. Moves are not provided
+    /// while paused; a move requested while paused is provided after resuming.
+    pub fn pause(&self) {
+        self.send.send(BotMsg::Pause).ok();
+    }
+
+    /// Resumes thinking after `pause`.
+    pub fn resume(&self) {
+        self.send.send(BotMsg::Resume).ok();
+    }
+
+    /// Cancels a move requested with `request_next_move` that hasn't been provided yet. The bot
+    /// keeps its search tree and continues thinking.
+    /// 
+    /// If the move was provided before the bot received the cancellation, it can still be
+    /// retrieved with `poll_next_move`.
+    pub fn cancel_next_move(&self) {
+        self.send.send(BotMsg::CancelMove).ok();
+    }
+}
+
+/// Future returned by `Interface::next_move_async`.
+pub struct NextMove {
+    recv: MoveReceiver,
+    request: u64,
+    send: Sender<BotMsg>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    done: bool
+}
+
+impl Future for NextMove {
+    type Output = Option<(Move, Info)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        // register before checking, so a move provided in between still wakes the task
+        *self.waker.lock().unwrap() = Some(cx.waker().clone());
+        let result = match self.recv.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Disconnected) => None,
+            Err(TryRecvError::Empty) => return Poll::Pending
+        };
+        self.waker.lock().unwrap().take();
+        self.done = true;
+        Poll::Ready(result)
+    }
+}
+
+impl Drop for NextMove {
+    fn drop(&mut self) {
+        if !self.done {
+            self.waker.lock().unwrap().take();
+            self.recv.abandoned.fetch_max(self.request, Ordering::SeqCst);
+            self.send.send(BotMsg::CancelMove).ok();
+        }
+    }
+}
+
+/// The interface's end of the channel moves are provided through. Moves are tagged with the
+/// number of the request they answer, so the moves of dropped `NextMove` futures can be told
+/// apart from the ones still wanted.
+#[derive(Clone)]
+struct MoveReceiver {
+    recv: Receiver<(u64, Move, Info)>,
+    /// The number of move requests sent to the bot thread.
+    requests: Arc<Mutex<u64>>,
+    /// Moves answering this request or an earlier one were abandoned and are discarded.
+    abandoned: Arc<AtomicU64>
+}
+
+impl MoveReceiver {
+    /// Sends a move request to the bot thread and returns its number.
+    fn request(&self, send: &Sender<BotMsg>, msg: BotMsg) -> u64 {
+        // numbered while locked, so requests reach the bot thread in the order they're numbered
+        let mut requests = self.requests.lock().unwrap();
+        *requests += 1;
+        send.send(msg).ok();
+        *requests
+    }
+
+    fn wanted(&self, request: u64) -> bool {
+        request > self.abandoned.load(Ordering::SeqCst)
+    }
+
+    fn try_recv(&self) -> Result<(Move, Info), TryRecvError> {
+        loop {
+            let (request, mv, info) = self.recv.try_recv()?;
+            if self.wanted(request) {
+                return Ok((mv, info))
+            }
+        }
+    }
+
+    fn recv(&self) -> Option<(Move, Info)> {
+        loop {
+            let (request, mv, info) = self.recv.recv().ok()?;
+            if self.wanted(request) {
+                return Some((mv, info))
+            }
+        }
+    }
+}
+
+/// The bot thread's end of the channel moves are provided through. Wakes the task waiting on
+/// `Interface::next_move_async` when a move is provided or the bot thread stops.
+struct MoveSender {
+    send: Option<Sender<(u64, Move, Info)>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    /// The number of move requests received, counted the same way `MoveReceiver` numbers them.
+    request: Cell<u64>
+}
+
+impl MoveSender {
+    /// Counts a move request received from the interface. Provided moves answer the latest one.
+    fn request_received(&self) {
+        self.request.set(self.request.get() + 1);
+    }
+
+    fn send(&self, mv: Move, info: Info) {
+        if let Some(send) = &self.send {
+            send.send((self.request.get(), mv, info)).ok();
+        }
+        self.wake();
+    }
+
+    fn wake(&self) {
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for MoveSender {
+    fn drop(&mut self) {
+        // disconnect first, so the woken task sees that the bot stopped
+        self.send = None;
+        self.wake();
+    }
 }
 
 fn run(
     recv: Receiver<BotMsg>,
-    send: Sender<(Move, Info)>,
+    send: MoveSender,
+    outputs: Outputs,
+    pool: Option<Arc<rayon::ThreadPool>>,
     mut board: Board,
     eval: impl Evaluator + 'static,
-    options: Options,
+    mut options: Options,
     book: Option<Arc<Book>>
 ) {
     if options.threads == 0 {
         panic!("Invalid number of threads: 0");
     }
 
+    // messages for the bot that arrive before it exists, handed over to it once it does
+    let mut buffered = vec![];
     while board.next_queue().next().is_none() {
         match recv.recv() {
             Err(_) => return,
             Ok(BotMsg::NewPiece(piece)) => board.add_next_piece(piece),
+            Ok(BotMsg::Resync { field, b2b, combo, hold, queue, bag }) => {
+                board = resync_board(&board, field, b2b, combo, hold, queue, bag);
+            }
             Ok(BotMsg::Reset { field, b2b, combo }) =>{
                 board.set_field(field);
                 board.combo = combo;
                 board.b2b_bonus = b2b;
             }
-            Ok(BotMsg::NextMove(_)) => {}
+            Ok(BotMsg::NextMove(_)) => send.request_received(),
             Ok(BotMsg::ForceAnalysisLine(_)) => {}
+            Ok(BotMsg::PlayMove(_)) => {}
+            Ok(BotMsg::SetOptions(new)) => options = Options {
+                threads: options.threads,
+                ..new
+            },
+            Ok(msg @ BotMsg::IncomingGarbage(_)) | Ok(msg @ BotMsg::AttackMultiplier(_)) |
+            Ok(msg @ BotMsg::ScoreMultiplier(_)) | Ok(msg @ BotMsg::OpponentPlaced(_)) |
+            Ok(msg @ BotMsg::OpponentState(_)) | Ok(msg @ BotMsg::Pause) |
+            Ok(msg @ BotMsg::Resume) | Ok(msg @ BotMsg::CancelMove) => buffered.push(msg),
+            Ok(msg @ BotMsg::NextMoveBy(_, _)) => {
+                send.request_received();
+                buffered.push(msg);
+            }
+            Ok(BotMsg::Snapshot(reply)) => {
+                let incoming = buffered.iter().rev().find_map(|msg| match msg {
+                    BotMsg::IncomingGarbage(incoming) => Some(*incoming),
+                    _ => None
+                });
+                reply.send(Snapshot {
+                    board: board.clone(),
+                    options,
+                    incoming: incoming.unwrap_or_default()
+                }).ok();
+            }
+            Ok(BotMsg::ExplainCandidate(_, reply)) => {
+                reply.send(None).ok();
+            }
         }
     }
 
     let mut bot = ModeSwitchedBot::new(board, options, book.as_deref());
 
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(options.threads as usize)
-        .build().unwrap();
+    let pool = pool.unwrap_or_else(|| Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(options.threads as usize)
+            .build().unwrap()
+    ));
 
     let (result_send, result_recv) = unbounded();
 
     let eval = Arc::new(eval);
+    let mut deadline = None;
+    let mut clock = options.game_time.map(|ms| Duration::from_millis(ms as u64));
+    // incoming garbage of a move request held back until its time budget is used up
+    let mut budgeted = None;
+    let mut requested_at = None;
+    let mut last_published = Instant::now();
+    let mut last_plan_check = Instant::now();
+    let mut plan = vec![];
+    // a decided move held back by the pps limit, and when it may be provided
+    let mut held_move = None;
+    let mut last_move_at = None;
+    let Outputs { candidates, memory_usage, subscribers, decision_log } = outputs;
+    let emit = |event: Event| subscribers.lock().unwrap().retain(
+        |s| s.send(event.clone()).is_ok()
+    );
+    let provide = |mv: Move, info: Info| {
+        emit(Event::MoveDecided(mv.clone(), info.clone()));
+        send.send(mv, info);
+    };
+    for msg in buffered {
+        match &msg {
+            BotMsg::NextMoveBy(_, time) => {
+                requested_at = Some(Instant::now());
+                deadline = Some(*time);
+            }
+            BotMsg::CancelMove => {
+                requested_at = None;
+                deadline = None;
+            }
+            _ => {}
+        }
+        bot.message(msg);
+    }
     loop {
+        if let Some(incoming) = budgeted {
+            if bot.move_is_forced() {
+                budgeted = None;
+                bot.message(BotMsg::NextMove(incoming));
+                bot.hurry();
+            }
+        }
+
         let new_tasks = bot.think(
             &eval,
-            |mv, info| { send.send((mv, info)).ok(); }
+            |mv, mut info, board| {
+                let elapsed = requested_at.take()
+                    .map_or(Duration::from_secs(0), |start: Instant| start.elapsed());
+                if let Some(clock) = &mut clock {
+                    *clock = clock.checked_sub(elapsed).unwrap_or_default()
+                        + Duration::from_millis(options.time_increment as u64);
+                }
+                if let Info::Normal(info) = &mut info {
+                    info.time = elapsed.as_millis() as u32;
+                }
+                if let Some(file) = &mut *decision_log.lock().unwrap() {
+                    log_decision(file, board, &mv, &info);
+                }
+                match earliest_move(&options, last_move_at) {
+                    Some(time) if time > Instant::now() => held_move = Some((time, mv, info)),
+                    _ => {
+                        last_move_at = Some(Instant::now());
+                        provide(mv, info);
+                    }
+                }
+            }
         );
         for task in new_tasks {
             let result_send = result_send.clone();
@@ -150,16 +653,226 @@ fn run(
             });
         }
 
+        let wake = match (deadline, &held_move) {
+            (Some(deadline), Some((time, _, _))) => Some(deadline.min(*time)),
+            (Some(deadline), None) => Some(deadline),
+            (None, Some((time, _, _))) => Some(*time),
+            (None, None) => None
+        };
+        let timeout = wake.map_or_else(never, at);
         select! {
             recv(result_recv) -> result => bot.task_complete(result.unwrap()),
             recv(recv) -> msg => match msg {
-                Ok(msg) => bot.message(msg),
+                Ok(msg) => match msg {
+                    BotMsg::NextMove(incoming) => {
+                        send.request_received();
+                        let now = Instant::now();
+                        requested_at = Some(now);
+                        match time_budget(&options, clock, bot.danger(incoming)) {
+                            Some(budget) => {
+                                bot.move_requested();
+                                deadline = Some(now + budget);
+                                budgeted = Some(incoming);
+                            }
+                            None => bot.message(msg)
+                        }
+                    }
+                    BotMsg::NextMoveBy(_, time) => {
+                        send.request_received();
+                        requested_at = Some(Instant::now());
+                        deadline = Some(time);
+                        bot.message(msg);
+                    }
+                    BotMsg::SetOptions(new) => {
+                        if new.game_time != options.game_time {
+                            clock = new.game_time.map(|ms| Duration::from_millis(ms as u64));
+                        }
+                        options = Options {
+                            threads: options.threads,
+                            ..new
+                        };
+                        bot.message(msg);
+                    }
+                    BotMsg::ExplainCandidate(placement, reply) => {
+                        reply.send(bot.explain_candidate(&eval, placement)).ok();
+                    }
+                    BotMsg::CancelMove => {
+                        deadline = None;
+                        budgeted = None;
+                        requested_at = None;
+                        bot.message(msg);
+                    }
+                    _ => bot.message(msg)
+                }
                 Err(_) => break
+            },
+            recv(timeout) -> _ => {
+                let now = Instant::now();
+                if held_move.as_ref().map_or(false, |&(time, _, _)| time <= now) {
+                    let (_, mv, info) = held_move.take().unwrap();
+                    last_move_at = Some(now);
+                    provide(mv, info);
+                }
+                if deadline.map_or(false, |deadline| deadline <= now) {
+                    deadline = None;
+                    if let Some(incoming) = budgeted.take() {
+                        bot.message(BotMsg::NextMove(incoming));
+                    }
+                    bot.hurry();
+                }
+            }
+        }
+
+        *memory_usage.lock().unwrap() = bot.memory_usage();
+
+        if let Some(interval) = options.candidate_interval {
+            if last_published.elapsed() >= Duration::from_millis(interval as u64) {
+                *candidates.lock().unwrap() = bot.candidates();
+                last_published = Instant::now();
+            }
+        }
+
+        if last_plan_check.elapsed() >= PLAN_CHECK_INTERVAL &&
+                !subscribers.lock().unwrap().is_empty() {
+            last_plan_check = Instant::now();
+            let new_plan = bot.plan();
+            if new_plan != plan {
+                let has_pc = |plan: &[(FallingPiece, LockResult)]|
+                    plan.iter().any(|(_, lock)| lock.perfect_clear);
+                if has_pc(&new_plan) && !has_pc(&plan) {
+                    emit(Event::PerfectClearPlanned(new_plan.clone()));
+                }
+                emit(Event::PlanChanged(new_plan.clone()));
+                plan = new_plan;
             }
         }
 
         if bot.is_dead() {
+            emit(Event::Death);
             break
         }
     }
-}
\ No newline at end of file
+}
+
+/// State shared between an `Interface` and its bot thread.
+struct Outputs {
+    candidates: Arc<Mutex<Vec<Candidate>>>,
+    memory_usage: Arc<Mutex<MemoryUsage>>,
+    subscribers: Arc<Mutex<Vec<Sender<Event>>>>,
+    decision_log: Arc<Mutex<Option<File>>>
+}
+
+#[derive(Serialize)]
+struct Decision<'a> {
+    field_hash: u64,
+    hold: Option<Piece>,
+    queue: Vec<Piece>,
+    b2b: bool,
+    combo: u32,
+    placement: FallingPiece,
+    used_hold: bool,
+    kind: &'static str,
+    evaluation: Option<i64>,
+    nodes: Option<u32>,
+    depth: Option<u32>,
+    time: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    book: Option<&'a str>
+}
+
+fn log_decision(file: &mut File, board: &Board, mv: &Move, info: &Info) {
+    let mut hasher = DefaultHasher::new();
+    for row in board.get_field().iter() {
+        row.hash(&mut hasher);
+    }
+    let normal = match info {
+        Info::Normal(info) => Some(info),
+        _ => None
+    };
+    let decision = Decision {
+        field_hash: hasher.finish(),
+        hold: board.hold_piece,
+        queue: board.next_queue().collect(),
+        b2b: board.b2b_bonus,
+        combo: board.combo,
+        placement: mv.expected_location,
+        used_hold: mv.hold,
+        kind: match info {
+            Info::Normal(_) => "normal",
+            Info::Book(_) => "book",
+            Info::PcLoop(_) => "pc_loop"
+        },
+        evaluation: normal.map(|i| i.evaluation),
+        nodes: normal.map(|i| i.nodes),
+        depth: normal.map(|i| i.depth),
+        time: normal.map(|i| i.time),
+        book: match info {
+            Info::Book(info) => Some(info.name.as_str()),
+            _ => None
+        }
+    };
+    // logging must never take the bot down, so write errors are ignored
+    if let Ok(line) = serde_json::to_string(&decision) {
+        writeln!(file, "{}", line).ok();
+    }
+}
+
+const PLAN_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// When the next move may be provided under `Options::pps_limit`, if there is a limit.
+fn earliest_move(options: &Options, last_move_at: Option<Instant>) -> Option<Instant> {
+    let pps = options.pps_limit.filter(|&pps| pps > 0.0)?;
+    let jitter = options.pps_jitter.max(0.0).min(1.0);
+    let factor = if jitter > 0.0 {
+        rand::thread_rng().gen_range(1.0 - jitter, 1.0 + jitter)
+    } else {
+        1.0
+    };
+    Some(last_move_at? + Duration::from_secs_f64(factor / pps))
+}
+
+/// Decides how long to think about a move given the time management options and how dangerous the
+/// position is, as given by `ModeSwitchedBot::danger`.
+fn time_budget(options: &Options, clock: Option<Duration>, danger: f64) -> Option<Duration> {
+    let per_move = options.move_time.map(|ms| Duration::from_millis(ms as u64));
+    // plan for about 30 more moves, but never use more than a quarter of the clock on one move
+    let from_clock = clock.map(|c| c / 30 + Duration::from_millis(options.time_increment as u64));
+    let cap = clock.map(|c| c / 4);
+
+    let budget = match (per_move, from_clock) {
+        (Some(a), Some(b)) => a.min(b),
+        (Some(a), None) => a,
+        (None, Some(b)) => b,
+        (None, None) => return None
+    };
+    // calm positions get half the usual time and the most dangerous ones twice as much
+    let budget = budget.mul_f64(0.5 + 1.5 * danger);
+    Some(match cap {
+        Some(cap) => budget.min(cap),
+        None => budget
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::Standard;
+
+    #[test]
+    fn dropped_future_does_not_answer_the_next_request() {
+        let options = Options { use_hold: false, ..Options::default() };
+        let bot = Interface::launch(Board::new(), options, Standard::default(), None);
+        for &piece in &[Piece::I, Piece::O, Piece::T, Piece::S, Piece::Z, Piece::L, Piece::J] {
+            bot.add_next_piece(piece);
+        }
+        let future = bot.next_move_async(0);
+        while bot.recv.recv.is_empty() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        drop(future);
+
+        bot.request_next_move(0);
+        let (mv, _) = bot.block_next_move().unwrap();
+        assert_eq!(mv.expected_location.kind.0, Piece::O);
+    }
+}
@@ -0,0 +1,129 @@
+use libtetris::*;
+use serde::{ Serialize, Deserialize };
+use super::*;
+
+/// One objective of a `Blend`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Component {
+    pub name: String,
+    pub weights: Standard,
+    /// How much this objective counts, in percent.
+    pub blend: i32
+}
+
+/// An evaluator that mixes several objectives, each described by its own set of standard weights.
+///
+/// The evaluation is the weighted sum of the evaluations of each component, so new play styles can
+/// be built by adjusting `blend` instead of writing a new evaluator. The default blend consists of
+/// the attack, survival, perfect clear and combo objectives based on the corresponding presets,
+/// with only attack and survival enabled.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Blend {
+    pub components: Vec<Component>
+}
+
+impl Blend {
+    /// Sets the blend of the component with the given name, returning `false` if there is no such
+    /// component.
+    pub fn set_blend(&mut self, name: &str, blend: i32) -> bool {
+        match self.components.iter_mut().find(|c| c.name == name) {
+            Some(component) => {
+                component.blend = blend;
+                true
+            }
+            None => false
+        }
+    }
+
+    fn active(&self) -> impl Iterator<Item=&Component> {
+        self.components.iter().filter(|c| c.blend != 0)
+    }
+}
+
+impl Default for Blend {
+    fn default() -> Self {
+        let component = |name: &str, weights, blend| Component {
+            name: name.to_owned(), weights, blend
+        };
+        Blend {
+            components: vec![
+                component("attack", Standard::aggressive(), 50),
+                component("survival", Standard::defensive(), 50),
+                component("perfect clear", Standard::pc_hunter(), 0),
+                component("combo", Standard::four_wide(), 0)
+            ]
+        }
+    }
+}
+
+impl Evaluator for Blend {
+    type Value = Value;
+    type Reward = Reward;
+
+    fn name(&self) -> String {
+        let mut info = "Blend".to_owned();
+        for c in self.active() {
+            info.push_str(&format!("\n{} {}%", c.name, c.blend));
+        }
+        info
+    }
+
+    fn pick_move(
+        &self, candidates: Vec<MoveCandidate<Value>>, incoming: u32
+    ) -> MoveCandidate<Value> {
+        match self.active().max_by_key(|c| c.blend) {
+            Some(c) => c.weights.pick_move(candidates, incoming),
+            None => candidates.into_iter().next().unwrap()
+        }
+    }
+
+    fn evaluate(
+        &self, lock: &LockResult, board: &Board, move_time: u32, placed: Piece
+    ) -> (Value, Reward) {
+        let mut value = Value::default();
+        let mut reward = Reward {
+            value: 0,
+            attack: if lock.placement_kind.is_clear() { lock.garbage_sent as i32 } else { -1 }
+        };
+        for c in self.active() {
+            let (v, r) = c.weights.evaluate(lock, board, move_time, placed);
+            value.value += v.value * c.blend / 100;
+            reward.value += r.value * c.blend / 100;
+        }
+        (value, reward)
+    }
+
+    fn explain(&self, placement: &Placement) -> Vec<(String, i32)> {
+        let mut terms = vec![];
+        for c in self.active() {
+            for (name, v) in c.weights.explain(placement) {
+                terms.push((format!("{}: {}", c.name, name), v * c.blend / 100));
+            }
+        }
+        terms
+    }
+
+    fn evaluate_batch(&self, batch: &[Placement]) -> Vec<(Value, Reward)> {
+        let mut results: Vec<_> = batch.iter().map(|p| (Value::default(), Reward {
+            value: 0,
+            attack: if p.lock.placement_kind.is_clear() {
+                (p.lock.garbage_sent * p.attack_multiplier / 100) as i32
+            } else {
+                -1
+            }
+        })).collect();
+        for c in self.active() {
+            let evaluations = c.weights.evaluate_batch(batch);
+            for ((value, reward), (v, r)) in results.iter_mut().zip(evaluations) {
+                value.value += v.value * c.blend / 100;
+                reward.value += r.value * c.blend / 100;
+            }
+        }
+        results
+    }
+
+    fn pc_opportunity(&self, board: &Board) -> bool {
+        self.active().any(|c| c.weights.pc_opportunity(board))
+    }
+}
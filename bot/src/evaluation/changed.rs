@@ -723,4 +723,8 @@ impl Evaluation<Reward> for Value {
         self.value = self.value.max(new_result.value);
         self.spike = self.spike.max(new_result.spike);
     }
+
+    fn score(&self) -> i64 {
+        self.value as i64
+    }
 }
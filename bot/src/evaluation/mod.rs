@@ -1,9 +1,22 @@
 use libtetris::{ LockResult, Board, Piece };
-use crate::dag::MoveCandidate;
+use serde::{ Serialize, Deserialize };
+pub use crate::dag::MoveCandidate;
 
 mod standard;
-pub use self::standard::Standard;
+pub use self::standard::{ Standard, Value, Reward };
 pub mod changed;
+mod presets;
+pub use self::presets::Preset;
+mod score;
+pub use self::score::ScoreAttack;
+mod sprint;
+pub use self::sprint::Sprint;
+pub mod blend;
+pub use self::blend::Blend;
+pub mod surface;
+pub use self::surface::Surface;
+#[cfg(feature = "nn")]
+pub mod network;
 
 pub trait Evaluator : Send + Sync {
     type Value: Evaluation<Self::Reward> + Send + 'static;
@@ -15,11 +28,83 @@ pub trait Evaluator : Send + Sync {
         &self, lock: &LockResult, board: &Board, move_time: u32, placed: Piece
     ) -> (Self::Value, Self::Reward);
 
+    /// Evaluates several placements at once. The results are in the same order as `batch`.
+    /// 
+    /// The bot always evaluates all children of a node through this method, so evaluators that
+    /// benefit from batching (such as neural networks) should override it.
+    fn evaluate_batch(&self, batch: &[Placement]) -> Vec<(Self::Value, Self::Reward)> {
+        batch.iter()
+            .map(|p| self.evaluate(&p.lock, &p.board, p.move_time, p.placed))
+            .collect()
+    }
+
     fn pick_move(
         &self, candidates: Vec<MoveCandidate<Self::Value>>, _incoming: u32
     ) -> MoveCandidate<Self::Value> {
         candidates.into_iter().next().unwrap()
     }
+
+    /// Breaks the evaluation of a placement down into named terms, for example
+    /// `("cavity_cells", -173)`. The placement is built the same way as those given to
+    /// `evaluate_batch`, so terms that depend on incoming garbage or the opponent match the
+    /// search.
+    /// 
+    /// Evaluators that can't explain themselves return an empty list.
+    fn explain(&self, _placement: &Placement) -> Vec<(String, i32)> {
+        vec![]
+    }
+    /// Whether the board could be perfect cleared soon. The bot then searches for a guaranteed
+    /// perfect clear with the visible queue before picking a move, if `Options::pc_finder` is set.
+    fn pc_opportunity(&self, _board: &Board) -> bool {
+        false
+    }
+}
+
+/// A placement waiting to be evaluated; see `Evaluator::evaluate_batch`.
+#[derive(Clone, Debug)]
+pub struct Placement {
+    pub lock: LockResult,
+    /// The board after the piece was locked.
+    pub board: Board,
+    pub move_time: u32,
+    pub placed: Piece,
+    /// The number of cells the piece was soft dropped and hard dropped to reach its location; see
+    /// `moves::drop_distances`.
+    pub soft_drop: u32,
+    pub hard_drop: u32,
+    /// The garbage known to be queued against the bot that hasn't arrived yet after this placement.
+    /// Garbage that has arrived is already on `board`.
+    pub incoming: Incoming,
+    /// The opponent's board when the search started, as set by `Interface::set_opponent_state`.
+    pub opponent: OpponentState,
+    /// The percentage attacks are scaled by, as set by `Interface::set_attack_multiplier`.
+    pub attack_multiplier: u32,
+    /// The factor points are multiplied by, as set by `Interface::set_score_multiplier`.
+    pub score_multiplier: u32
+}
+
+/// Garbage queued against the bot, as reported by `Interface::set_incoming_garbage`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Incoming {
+    pub lines: u32,
+    /// The number of pieces that can be placed before the garbage arrives.
+    pub delay: u32
+}
+
+/// The opponent's board, as reported by `Interface::set_opponent_state`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct OpponentState {
+    /// The height of the opponent's highest column.
+    pub height: u32,
+    /// Lines of garbage queued against the opponent.
+    pub incoming: u32
+}
+
+impl OpponentState {
+    /// Lines of garbage the opponent can take before topping out.
+    pub fn room(&self) -> u32 {
+        20u32.saturating_sub(self.height + self.incoming)
+    }
 }
 
 pub trait Evaluation<R> : Eq + Ord + Default + Clone
@@ -32,9 +117,81 @@ pub trait Evaluation<R> : Eq + Ord + Default + Clone
     fn weight(self, min: &Self, rank: usize) -> i64;
 
     fn improve(&mut self, other: Self);
+
+    /// A single number summarizing the evaluation, for display.
+    fn score(&self) -> i64;
 }
 
-impl<T: Evaluator> Evaluator for std::sync::Arc<T> {
+/// An evaluator whose concrete type is chosen at runtime.
+/// 
+/// `Box<DynEvaluator>` implements `Evaluator`, so it can be passed to `Interface::launch` like any
+/// other evaluator. All of the built-in evaluators use the standard `Value` and `Reward` types.
+pub type DynEvaluator = dyn Evaluator<Value = Value, Reward = Reward>;
+
+/// Serializable description of a built-in evaluator, for frontends that pick the evaluator from a
+/// configuration file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum EvaluatorConfig {
+    Standard(Standard),
+    Preset(Preset),
+    ScoreAttack(ScoreAttack),
+    Sprint(Sprint),
+    Blend(Blend),
+    /// Loads a `surface::SurfaceTable` from the file at `table`. If it fails to load, an empty
+    /// table is used.
+    Surface {
+        weights: Standard,
+        table: String,
+        table_weight: i32
+    },
+    /// Loads a `network::Model` from the JSON file at `model`. If no model is given or it fails to
+    /// load, `fallback` is used on its own.
+    #[cfg(feature = "nn")]
+    Network {
+        model: Option<String>,
+        fallback: Standard
+    }
+}
+
+impl EvaluatorConfig {
+    pub fn build(&self) -> Box<DynEvaluator> {
+        match self {
+            EvaluatorConfig::Standard(weights) => Box::new(weights.clone()),
+            EvaluatorConfig::Preset(preset) => Box::new(Standard::preset(*preset)),
+            EvaluatorConfig::ScoreAttack(weights) => Box::new(weights.clone()),
+            EvaluatorConfig::Sprint(weights) => Box::new(weights.clone()),
+            EvaluatorConfig::Blend(blend) => Box::new(blend.clone()),
+            EvaluatorConfig::Surface { weights, table, table_weight } => Box::new(Surface {
+                weights: weights.clone(),
+                table: surface::SurfaceTable::load(table).unwrap_or_else(|e| {
+                    eprintln!("Could not load surface table {}: {}", table, e);
+                    Default::default()
+                }),
+                table_weight: *table_weight
+            }),
+            #[cfg(feature = "nn")]
+            EvaluatorConfig::Network { model, fallback } => {
+                let loaded = model.as_ref().and_then(|path| {
+                    network::Network::load(path, fallback.clone()).map_err(
+                        |e| eprintln!("Could not load network {}: {}", path, e)
+                    ).ok()
+                });
+                Box::new(loaded.unwrap_or_else(|| network::Network {
+                    model: None,
+                    fallback: fallback.clone()
+                }))
+            }
+        }
+    }
+}
+
+impl Default for EvaluatorConfig {
+    fn default() -> Self {
+        EvaluatorConfig::Standard(Standard::default())
+    }
+}
+
+impl<T: Evaluator + ?Sized> Evaluator for Box<T> {
     type Value = T::Value;
     type Reward = T::Reward;
 
@@ -48,9 +205,54 @@ impl<T: Evaluator> Evaluator for std::sync::Arc<T> {
         (**self).evaluate(lock, board, move_time, placed)
     }
 
+    fn evaluate_batch(&self, batch: &[Placement]) -> Vec<(T::Value, T::Reward)> {
+        (**self).evaluate_batch(batch)
+    }
+
     fn pick_move(
         &self, candidates: Vec<MoveCandidate<Self::Value>>, incoming: u32
     ) -> MoveCandidate<Self::Value> {
         (**self).pick_move(candidates, incoming)
     }
+
+    fn explain(&self, placement: &Placement) -> Vec<(String, i32)> {
+        (**self).explain(placement)
+    }
+
+    fn pc_opportunity(&self, board: &Board) -> bool {
+        (**self).pc_opportunity(board)
+    }
+}
+
+impl<T: Evaluator + ?Sized> Evaluator for std::sync::Arc<T> {
+    type Value = T::Value;
+    type Reward = T::Reward;
+
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn evaluate(
+        &self, lock: &LockResult, board: &Board, move_time: u32, placed: Piece
+    ) -> (T::Value, T::Reward) {
+        (**self).evaluate(lock, board, move_time, placed)
+    }
+
+    fn evaluate_batch(&self, batch: &[Placement]) -> Vec<(T::Value, T::Reward)> {
+        (**self).evaluate_batch(batch)
+    }
+
+    fn pick_move(
+        &self, candidates: Vec<MoveCandidate<Self::Value>>, incoming: u32
+    ) -> MoveCandidate<Self::Value> {
+        (**self).pick_move(candidates, incoming)
+    }
+
+    fn explain(&self, placement: &Placement) -> Vec<(String, i32)> {
+        (**self).explain(placement)
+    }
+
+    fn pc_opportunity(&self, board: &Board) -> bool {
+        (**self).pc_opportunity(board)
+    }
 }
\ No newline at end of file
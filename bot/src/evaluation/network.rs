@@ -0,0 +1,181 @@
+use libtetris::*;
+use serde::{ Serialize, Deserialize };
+use super::*;
+
+/// Number of rows of the playfield fed to the network. Anything above this is ignored; the
+/// handcrafted evaluation already punishes stacks that get that tall.
+const ROWS: usize = 20;
+
+/// Size of the network input: one occupancy plane, the column heights, and the back-to-back and
+/// combo state.
+pub const INPUTS: usize = ROWS * 10 + 10 + 2;
+
+/// A small fully connected value network.
+///
+/// Weights are stored row-major, `outputs` rows of `inputs` columns each. Every layer except the
+/// last uses a ReLU activation. The last layer must have exactly one output, which is interpreted
+/// in the same units as the standard evaluator's weights.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Model {
+    pub layers: Vec<Layer>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Layer {
+    pub inputs: usize,
+    pub outputs: usize,
+    pub weights: Vec<f32>,
+    pub biases: Vec<f32>
+}
+
+impl Model {
+    /// Checks that the layer shapes line up with each other and with the board encoding.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut expected = INPUTS;
+        for (i, layer) in self.layers.iter().enumerate() {
+            if layer.inputs != expected {
+                return Err(format!(
+                    "layer {} takes {} inputs, expected {}", i, layer.inputs, expected
+                ));
+            }
+            if layer.weights.len() != layer.inputs * layer.outputs {
+                return Err(format!("layer {} has the wrong number of weights", i));
+            }
+            if layer.biases.len() != layer.outputs {
+                return Err(format!("layer {} has the wrong number of biases", i));
+            }
+            expected = layer.outputs;
+        }
+        if expected != 1 || self.layers.is_empty() {
+            return Err("the last layer must have exactly one output".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Runs the network on `count` inputs laid out one after another in `values`, so that each
+    /// layer's weights are only walked once per batch.
+    fn run_batch(&self, mut values: Vec<f32>, count: usize) -> Vec<f32> {
+        let last = self.layers.len() - 1;
+        for (i, layer) in self.layers.iter().enumerate() {
+            let mut next = Vec::with_capacity(count * layer.outputs);
+            for _ in 0..count {
+                next.extend_from_slice(&layer.biases);
+            }
+            for o in 0..layer.outputs {
+                let row = &layer.weights[o * layer.inputs .. (o+1) * layer.inputs];
+                for b in 0..count {
+                    let input = &values[b * layer.inputs .. (b+1) * layer.inputs];
+                    let out = &mut next[b * layer.outputs + o];
+                    *out += row.iter().zip(input).map(|(w, v)| w * v).sum::<f32>();
+                    if i != last {
+                        *out = out.max(0.0);
+                    }
+                }
+            }
+            values = next;
+        }
+        values
+    }
+}
+
+/// Encodes a board as the network input.
+pub fn encode(board: &Board) -> Vec<f32> {
+    let mut input = Vec::with_capacity(INPUTS);
+    for y in 0..ROWS as i32 {
+        for x in 0..10 {
+            input.push(if board.occupied(x, y) { 1.0 } else { 0.0 });
+        }
+    }
+    for &h in board.column_heights() {
+        input.push(h as f32 / ROWS as f32);
+    }
+    input.push(if board.b2b_bonus { 1.0 } else { 0.0 });
+    input.push(board.combo.min(12) as f32 / 12.0);
+    input
+}
+
+/// An evaluator that scores board states with a `Model`.
+///
+/// Line clear rewards and the spike heuristic still come from the handcrafted evaluation; the
+/// network only replaces the board shape part of the evaluation. Without a model this behaves
+/// exactly like the handcrafted evaluation.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Network {
+    pub model: Option<Model>,
+    pub fallback: Standard
+}
+
+impl Network {
+    /// Loads a model in JSON format. Errors if the file can't be read or the model doesn't match
+    /// the board encoding.
+    pub fn load(path: impl AsRef<std::path::Path>, fallback: Standard) -> Result<Self, String> {
+        let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        let model: Model = serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|e| e.to_string())?;
+        model.validate()?;
+        Ok(Network { model: Some(model), fallback })
+    }
+}
+
+impl Evaluator for Network {
+    type Value = Value;
+    type Reward = Reward;
+
+    fn name(&self) -> String {
+        match self.model {
+            Some(_) => "Network".to_owned(),
+            None => self.fallback.name()
+        }
+    }
+
+    fn pick_move(
+        &self, candidates: Vec<MoveCandidate<Value>>, incoming: u32
+    ) -> MoveCandidate<Value> {
+        self.fallback.pick_move(candidates, incoming)
+    }
+
+    fn evaluate(
+        &self, lock: &LockResult, board: &Board, move_time: u32, placed: Piece
+    ) -> (Value, Reward) {
+        let (value, reward) = self.fallback.evaluate(lock, board, move_time, placed);
+        match &self.model {
+            Some(model) => (Value {
+                value: model.run_batch(encode(board), 1)[0] as i32,
+                ..value
+            }, reward),
+            None => (value, reward)
+        }
+    }
+
+    fn explain(&self, placement: &Placement) -> Vec<(String, i32)> {
+        let mut terms = self.fallback.explain(placement);
+        if let Some(model) = &self.model {
+            // the network replaces all of the board shape terms, which can't be told apart from
+            // the rest here, so just report its output alongside them
+            let output = model.run_batch(encode(&placement.board), 1)[0];
+            terms.push(("network".to_owned(), output as i32));
+        }
+        terms
+    }
+
+    fn pc_opportunity(&self, board: &Board) -> bool {
+        self.fallback.pc_opportunity(board)
+    }
+
+    fn evaluate_batch(&self, batch: &[Placement]) -> Vec<(Value, Reward)> {
+        let handcrafted = self.fallback.evaluate_batch(batch);
+        let model = match &self.model {
+            Some(model) => model,
+            None => return handcrafted
+        };
+        let mut input = Vec::with_capacity(batch.len() * INPUTS);
+        for p in batch {
+            input.extend(encode(&p.board));
+        }
+        let outputs = model.run_batch(input, batch.len());
+        handcrafted.into_iter()
+            .zip(outputs)
+            .map(|((value, reward), v)| (Value { value: v as i32, ..value }, reward))
+            .collect()
+    }
+}
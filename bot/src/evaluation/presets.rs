@@ -0,0 +1,198 @@
+use serde::{ Serialize, Deserialize };
+use super::Standard;
+
+/// Built-in weight sets for the standard evaluator.
+///
+/// These give frontends a handful of "bot personalities" to choose from without having to tune
+/// weights themselves.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Preset {
+    Default,
+    Fast,
+    Aggressive,
+    Defensive,
+    PcHunter,
+    Downstacker,
+    FourWide
+}
+
+impl Preset {
+    pub const ALL: [Preset; 7] = [
+        Preset::Default,
+        Preset::Fast,
+        Preset::Aggressive,
+        Preset::Defensive,
+        Preset::PcHunter,
+        Preset::Downstacker,
+        Preset::FourWide
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Default => "default",
+            Preset::Fast => "fast",
+            Preset::Aggressive => "aggressive",
+            Preset::Defensive => "defensive",
+            Preset::PcHunter => "pc-hunter",
+            Preset::Downstacker => "downstacker",
+            Preset::FourWide => "4-wide"
+        }
+    }
+
+    /// Looks up a preset by name. Case, spaces, hyphens and underscores are ignored, and 4-wide
+    /// can also be spelled four-wide.
+    pub fn from_name(name: &str) -> Option<Preset> {
+        let name: String = name.chars()
+            .filter(|&c| c != ' ' && c != '_' && c != '-')
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        if name == "fourwide" {
+            return Some(Preset::FourWide)
+        }
+        Preset::ALL.iter().copied().find(|p| p.name().replace('-', "") == name)
+    }
+
+    /// Reads the preset named by the `COLD_CLEAR_PRESET` environment variable, if any.
+    pub fn from_env() -> Option<Preset> {
+        Preset::from_name(&std::env::var("COLD_CLEAR_PRESET").ok()?)
+    }
+}
+
+impl std::str::FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        Preset::from_name(s).ok_or_else(|| format!("unknown preset: {}", s))
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Standard {
+    pub fn preset(preset: Preset) -> Self {
+        let mut weights = match preset {
+            Preset::Default => Standard::default(),
+            Preset::Fast => Standard::fast_config(),
+            Preset::Aggressive => Standard::aggressive(),
+            Preset::Defensive => Standard::defensive(),
+            Preset::PcHunter => Standard::pc_hunter(),
+            Preset::Downstacker => Standard::downstacker(),
+            Preset::FourWide => Standard::four_wide()
+        };
+        if preset != Preset::Default {
+            weights.sub_name = Some(preset.name().to_owned());
+        }
+        weights
+    }
+
+    /// Prioritizes T-spins, tetrises and back-to-back, accepting a taller and messier stack.
+    pub fn aggressive() -> Self {
+        Standard {
+            back_to_back: 90,
+            height: -25,
+            top_half: -110,
+            top_quarter: -450,
+            tslot: [10, 180, 260, 480],
+            well_depth: 70,
+            b2b_clear: 160,
+            clear1: -180,
+            clear2: -140,
+            clear3: -90,
+            clear4: 440,
+            tspin1: 140,
+            tspin2: 480,
+            tspin3: 700,
+            b2b_break: -200,
+            hard_clear: 60,
+            lethal_spike: 800,
+            banked_spike: 40,
+            ..Standard::default()
+        }
+    }
+
+    /// Keeps the stack low and clean, clearing lines eagerly whenever it gets tall.
+    pub fn defensive() -> Self {
+        Standard {
+            back_to_back: 30,
+            bumpiness: -30,
+            height: -60,
+            top_half: -250,
+            top_quarter: -800,
+            jeopardy: -20,
+            cavity_cells: -220,
+            overhang_cells: -50,
+            covered_cells: -25,
+            tslot: [4, 90, 120, 250],
+            clear1: -60,
+            clear2: -40,
+            clear3: -20,
+            ..Standard::default()
+        }
+    }
+
+    /// Strongly favors perfect clears and flat, low stacks that lead to them.
+    pub fn pc_hunter() -> Self {
+        Standard {
+            bumpiness: -40,
+            bumpiness_sq: -12,
+            height: -80,
+            cavity_cells: -300,
+            overhang_cells: -80,
+            tslot: [0, 60, 80, 150],
+            well_depth: 20,
+            clear1: -60,
+            clear2: -40,
+            perfect_clear: 3000,
+            stack_pc_damage: false,
+            ..Standard::default()
+        }
+    }
+
+    /// Digs through garbage as quickly as possible instead of setting up attacks. Suitable for
+    /// cheese races.
+    pub fn downstacker() -> Self {
+        Standard {
+            back_to_back: 0,
+            height: -60,
+            cavity_cells: -260,
+            cavity_cells_sq: -8,
+            overhang_cells: -60,
+            covered_cells: -40,
+            covered_cells_sq: -4,
+            tslot: [0, 40, 60, 120],
+            well_depth: 10,
+            max_well_depth: 4,
+            b2b_clear: 0,
+            clear1: 60,
+            clear2: 90,
+            clear3: 120,
+            clear4: 200,
+            wasted_t: 0,
+            garbage_rows: -30,
+            garbage_hole_depth: -25,
+            ..Standard::default()
+        }
+    }
+
+    /// Stacks on the sides and keeps the center open to run long combos.
+    pub fn four_wide() -> Self {
+        Standard {
+            back_to_back: 10,
+            bumpiness: -10,
+            height: -10,
+            top_half: -80,
+            tslot: [0, 40, 60, 100],
+            well_depth: 120,
+            max_well_depth: 20,
+            well_column: [-40, -20, 0, 60, 80, 80, 60, 0, -20, -40],
+            clear1: 40,
+            clear2: 0,
+            combo_garbage: 450,
+            ..Standard::default()
+        }
+    }
+}
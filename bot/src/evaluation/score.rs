@@ -0,0 +1,137 @@
+use libtetris::*;
+use serde::{ Serialize, Deserialize };
+use super::*;
+
+/// An evaluator for score attack modes (Ultra, Blitz) which maximizes guideline points instead of
+/// garbage sent.
+///
+/// Stack shape is judged by `shape`, but all of its line clear, back-to-back, combo and perfect
+/// clear rewards are ignored in favor of the points given by `LockResult::score`, including the
+/// drop points of the move that places the piece. Line clear points are multiplied by the score
+/// multiplier the bot was given, so in modes where it rises quickly (Blitz) the bot weighs them
+/// more heavily against stack shape as the level goes up, building for big clears early and
+/// cashing them in at higher levels.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreAttack {
+    pub shape: Standard,
+    /// Evaluation per 100 points scored.
+    pub points: i32
+}
+
+impl Default for ScoreAttack {
+    fn default() -> Self {
+        ScoreAttack {
+            shape: Standard {
+                b2b_clear: 0,
+                clear1: 0,
+                clear2: 0,
+                clear3: 0,
+                clear4: 0,
+                tspin1: 0,
+                tspin2: 0,
+                tspin3: 0,
+                mini_tspin1: 0,
+                mini_tspin2: 0,
+                perfect_clear: 0,
+                combo_garbage: 0,
+                ..Standard::default()
+            },
+            points: 40
+        }
+    }
+}
+
+impl ScoreAttack {
+    fn points(&self, placement: &Placement) -> i32 {
+        let score = placement.lock.score(
+            placement.score_multiplier, placement.soft_drop, placement.hard_drop
+        );
+        score as i32 * self.points / 100
+    }
+
+    fn evaluate_placement(&self, placement: &Placement) -> (Value, Reward) {
+        let (value, reward) = self.shape.evaluate(
+            &placement.lock, &placement.board, placement.move_time, placement.placed
+        );
+        (value, Reward {
+            value: reward.value + self.points(placement),
+            ..reward
+        })
+    }
+}
+
+impl Evaluator for ScoreAttack {
+    type Value = Value;
+    type Reward = Reward;
+
+    fn name(&self) -> String {
+        "Score Attack".to_owned()
+    }
+
+    fn evaluate(
+        &self, lock: &LockResult, board: &Board, move_time: u32, placed: Piece
+    ) -> (Value, Reward) {
+        let (value, reward) = self.shape.evaluate(lock, board, move_time, placed);
+        (value, Reward {
+            value: reward.value + lock.score(1, 0, 0) as i32 * self.points / 100,
+            ..reward
+        })
+    }
+
+    fn evaluate_batch(&self, batch: &[Placement]) -> Vec<(Value, Reward)> {
+        batch.iter().map(|p| self.evaluate_placement(p)).collect()
+    }
+
+    fn pick_move(
+        &self, candidates: Vec<MoveCandidate<Value>>, incoming: u32
+    ) -> MoveCandidate<Value> {
+        self.shape.pick_move(candidates, incoming)
+    }
+
+    fn explain(&self, placement: &Placement) -> Vec<(String, i32)> {
+        let mut terms = self.shape.explain(placement);
+        let points = self.points(placement);
+        if points != 0 {
+            terms.push(("points".to_owned(), points));
+        }
+        terms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total(eval: &ScoreAttack, placement: &Placement) -> i32 {
+        let (value, reward) = eval.evaluate_batch(std::slice::from_ref(placement)).remove(0);
+        value.value + reward.value
+    }
+
+    #[test]
+    fn soft_drop_points_can_pay_for_a_slower_placement() {
+        let eval = ScoreAttack { points: 100, ..ScoreAttack::default() };
+        let mut board = Board::new();
+        let mut piece = SpawnRule::Row19Or20.spawn(Piece::O, &board).unwrap();
+        piece.sonic_drop(&board);
+        let lock = board.lock_piece(piece);
+        let fast = Placement {
+            lock,
+            board,
+            move_time: 0,
+            placed: Piece::O,
+            soft_drop: 0,
+            hard_drop: 0,
+            incoming: Incoming::default(),
+            opponent: OpponentState::default(),
+            attack_multiplier: 100,
+            score_multiplier: 1
+        };
+        // soft dropping 18 cells takes long enough to lose to the fast placement without the
+        // points it earns
+        let slow = Placement { move_time: 5, ..fast.clone() };
+        assert!(total(&eval, &fast) > total(&eval, &slow));
+        let slow = Placement { soft_drop: 18, ..slow };
+        assert!(total(&eval, &slow) > total(&eval, &fast));
+    }
+}
@@ -0,0 +1,92 @@
+use libtetris::*;
+use serde::{ Serialize, Deserialize };
+use super::*;
+
+/// An evaluator for sprint modes (clearing 40 lines as fast as possible) which minimizes the
+/// estimated time to finish instead of maximizing garbage sent.
+///
+/// Every 10 cells placed eventually become one cleared line, so a sprint takes about the same
+/// number of pieces however it is played. What differs is how long each piece takes to place and
+/// how many separate line clears are needed, so every placement costs the ticks spent moving the
+/// piece plus `piece_delay`, and line clears cost `line_clear_delay` on top. Move times come from
+/// the bot's `Options::timing`, which should match the handling of the game being played.
+///
+/// Stack shape is judged by `shape`, but all of its line clear, T-spin and back-to-back rewards
+/// are ignored.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sprint {
+    pub shape: Standard,
+    /// Evaluation per tick of estimated time.
+    pub time: i32,
+    /// Ticks spent on every piece in addition to moving it, such as the entry delay.
+    pub piece_delay: u32,
+    pub line_clear_delay: u32
+}
+
+impl Default for Sprint {
+    fn default() -> Self {
+        Sprint {
+            shape: Standard {
+                back_to_back: 0,
+                tslot: [0; 4],
+                imminent_tslot: 0,
+                b2b_clear: 0,
+                clear1: 0,
+                clear2: 0,
+                clear3: 0,
+                clear4: 0,
+                tspin1: 0,
+                tspin2: 0,
+                tspin3: 0,
+                mini_tspin1: 0,
+                mini_tspin2: 0,
+                perfect_clear: 0,
+                combo_garbage: 0,
+                move_time: 0,
+                wasted_t: 0,
+                ..Standard::default()
+            },
+            time: 10,
+            piece_delay: 7,
+            line_clear_delay: 35
+        }
+    }
+}
+
+impl Sprint {
+    /// The estimated ticks the placement takes.
+    fn ticks(&self, lock: &LockResult, move_time: u32) -> i32 {
+        let mut ticks = move_time + self.piece_delay;
+        if !lock.cleared_lines.is_empty() {
+            ticks += self.line_clear_delay;
+        }
+        ticks as i32
+    }
+}
+
+impl Evaluator for Sprint {
+    type Value = Value;
+    type Reward = Reward;
+
+    fn name(&self) -> String {
+        "Sprint".to_owned()
+    }
+
+    fn evaluate(
+        &self, lock: &LockResult, board: &Board, move_time: u32, placed: Piece
+    ) -> (Value, Reward) {
+        let (value, reward) = self.shape.evaluate(lock, board, move_time, placed);
+        (value, Reward {
+            value: reward.value - self.ticks(lock, move_time) * self.time,
+            ..reward
+        })
+    }
+
+    fn explain(&self, placement: &Placement) -> Vec<(String, i32)> {
+        let mut terms = self.shape.explain(placement);
+        let ticks = self.ticks(&placement.lock, placement.move_time);
+        terms.push(("time".to_owned(), -ticks * self.time));
+        terms
+    }
+}
@@ -13,15 +13,23 @@ pub struct Standard {
     pub top_half: i32,
     pub top_quarter: i32,
     pub jeopardy: i32,
+    pub incoming_height: i32,
     pub cavity_cells: i32,
     pub cavity_cells_sq: i32,
     pub overhang_cells: i32,
     pub overhang_cells_sq: i32,
     pub covered_cells: i32,
     pub covered_cells_sq: i32,
+    /// Applies to each column outside the well that only one kind of piece can fill without
+    /// leaving a hole, such as a one-wide hole at least three cells deep that needs an I piece.
+    pub dependencies: i32,
+    pub garbage_rows: i32,
+    pub garbage_hole_depth: i32,
     pub tslot: [i32; 4],
     pub well_depth: i32,
     pub max_well_depth: i32,
+    pub pc_opportunity: i32,
+    pub imminent_tslot: i32,
     pub well_column: [i32; 10],
 
     pub b2b_clear: i32,
@@ -38,10 +46,28 @@ pub struct Standard {
     pub combo_garbage: i32,
     pub move_time: i32,
     pub wasted_t: i32,
+    pub b2b_break: i32,
+    pub hard_clear: i32,
+    /// Applies when an attack is big enough to top out the opponent, as reported by
+    /// `Interface::set_opponent_state`.
+    pub lethal_spike: i32,
+    /// Applies to each of back-to-back and the combo (up to 4) kept going, in proportion to how
+    /// close the opponent is to topping out, so attack potential is banked for a burst.
+    pub banked_spike: i32,
 
     pub use_bag: bool,
     pub timed_jeopardy: bool,
     pub stack_pc_damage: bool,
+    /// The height above which `top_half` and `jeopardy` apply.
+    pub top_half_start: i32,
+    /// The height above which `top_quarter` applies.
+    pub top_quarter_start: i32,
+    /// When picking a move, moves that could leave the center columns above this height after
+    /// incoming garbage arrives are avoided in favor of the move with the biggest spike.
+    pub panic_height: i32,
+    /// Forces the well into this column instead of using the lowest column. Bumpiness is measured
+    /// around the chosen column as well.
+    pub preferred_well: Option<usize>,
     pub sub_name: Option<String>
 }
 
@@ -56,19 +82,29 @@ impl Default for Standard {
             top_half: -150,
             top_quarter: -511,
             jeopardy: -11,
+            incoming_height: 0,
             cavity_cells: -173,
             cavity_cells_sq: -3,
             overhang_cells: -34,
             overhang_cells_sq: -1,
             covered_cells: -17,
             covered_cells_sq: -1,
+            dependencies: 0,
+            garbage_rows: 0,
+            garbage_hole_depth: 0,
             tslot: [8, 148, 192, 407],
             well_depth: 57,
             max_well_depth: 17,
+            pc_opportunity: 0,
+            imminent_tslot: 0,
             well_column: [20, 23, 20, 50, 59, 21, 59, 10, -10, 24],
 
             move_time: -3,
             wasted_t: -152,
+            b2b_break: 0,
+            hard_clear: 0,
+            lethal_spike: 0,
+            banked_spike: 0,
             b2b_clear: 104,
             clear1: -143,
             clear2: -100,
@@ -85,6 +121,10 @@ impl Default for Standard {
             use_bag: true,
             timed_jeopardy: true,
             stack_pc_damage: false,
+            top_half_start: 10,
+            top_quarter_start: 15,
+            panic_height: 20,
+            preferred_well: None,
             sub_name: None
         }
     }
@@ -101,15 +141,21 @@ impl Standard {
             top_half: -126,
             top_quarter: -493,
             jeopardy: -11,
+            incoming_height: 0,
             cavity_cells: -176,
             cavity_cells_sq: -6,
             overhang_cells: -47,
             overhang_cells_sq: -9,
             covered_cells: -25,
             covered_cells_sq: 1,
+            dependencies: 0,
+            garbage_rows: 0,
+            garbage_hole_depth: 0,
             tslot: [0, 150, 296, 207],
             well_depth: 158,
             max_well_depth: -2,
+            pc_opportunity: 0,
+            imminent_tslot: 0,
             well_column: [31, 16, -41, 37, 49, 30, 56, 48, -27, 22],
             b2b_clear: 74,
             clear1: -122,
@@ -125,9 +171,17 @@ impl Standard {
             combo_garbage: 272,
             move_time: -1,
             wasted_t: -147,
+            b2b_break: 0,
+            hard_clear: 0,
+            lethal_spike: 0,
+            banked_spike: 0,
             use_bag: true,
             timed_jeopardy: false,
             stack_pc_damage: false,
+            top_half_start: 10,
+            top_quarter_start: 15,
+            panic_height: 20,
+            preferred_well: None,
             sub_name: None
         }
     }
@@ -152,7 +206,7 @@ impl Evaluator for Standard {
         let mut backup = None;
         for mv in candidates.into_iter() {
             if incoming == 0 || mv.board.column_heights()[3..6].iter().all(
-                |h| incoming as i32 - mv.lock.garbage_sent as i32 + h <= 20
+                |h| incoming as i32 - mv.lock.garbage_sent as i32 + h <= self.panic_height
             ) {
                 return mv
             }
@@ -170,56 +224,133 @@ impl Evaluator for Standard {
     fn evaluate(
         &self, lock: &LockResult, board: &Board, move_time: u32, placed: Piece
     ) -> (Value, Reward) {
-        let mut transient_eval = 0;
-        let mut acc_eval = 0;
+        self.evaluate_incoming(
+            lock, board, move_time, placed, Incoming::default(), OpponentState::default(), 100
+        )
+    }
+
+    fn evaluate_batch(&self, batch: &[Placement]) -> Vec<(Value, Reward)> {
+        batch.iter()
+            .map(|p| self.evaluate_incoming(
+                &p.lock, &p.board, p.move_time, p.placed, p.incoming, p.opponent,
+                p.attack_multiplier
+            ))
+            .collect()
+    }
+
+    fn explain(&self, placement: &Placement) -> Vec<(String, i32)> {
+        let p = placement;
+        let (_, _, terms) = self.evaluate_terms(
+            &p.lock, &p.board, p.move_time, p.placed, p.incoming, p.opponent, p.attack_multiplier,
+            true
+        );
+        terms
+    }
+
+    fn pc_opportunity(&self, board: &Board) -> bool {
+        self.pc_opportunity != 0 && pc_opportunity(board) != 0
+    }
+}
+
+impl Standard {
+    fn evaluate_incoming(
+        &self,
+        lock: &LockResult,
+        board: &Board,
+        move_time: u32,
+        placed: Piece,
+        incoming: Incoming,
+        opponent: OpponentState,
+        attack_multiplier: u32
+    ) -> (Value, Reward) {
+        let (value, reward, _) = self.evaluate_terms(
+            lock, board, move_time, placed, incoming, opponent, attack_multiplier, false
+        );
+        (value, reward)
+    }
+
+    fn evaluate_terms(
+        &self,
+        lock: &LockResult,
+        board: &Board,
+        move_time: u32,
+        placed: Piece,
+        incoming: Incoming,
+        opponent: OpponentState,
+        attack_multiplier: u32,
+        explain: bool
+    ) -> (Value, Reward, Vec<(String, i32)>) {
+        let mut transient = Terms::new(explain);
+        let mut acc = Terms::new(explain);
+        // Margin time scales every attack, so rewards for clearing lines scale with it
+        let attack = |v: i32| v * attack_multiplier as i32 / 100;
 
         if lock.perfect_clear {
-            acc_eval += self.perfect_clear;
+            acc.add("perfect_clear", attack(self.perfect_clear));
         }
         if self.stack_pc_damage || !lock.perfect_clear {
             if lock.b2b {
-                acc_eval += self.b2b_clear;
+                acc.add("b2b_clear", attack(self.b2b_clear));
             }
             if let Some(combo) = lock.combo {
                 let combo = combo.min(11) as usize;
-                acc_eval += self.combo_garbage * libtetris::COMBO_GARBAGE[combo] as i32;
+                acc.add(
+                    "combo_garbage",
+                    attack(self.combo_garbage * libtetris::COMBO_GARBAGE[combo] as i32)
+                );
             }
             match lock.placement_kind {
                 PlacementKind::Clear1 => {
-                    acc_eval += self.clear1;
+                    acc.add("clear1", attack(self.clear1));
                 }
                 PlacementKind::Clear2 => {
-                    acc_eval += self.clear2;
+                    acc.add("clear2", attack(self.clear2));
                 }
                 PlacementKind::Clear3 => {
-                    acc_eval += self.clear3;
+                    acc.add("clear3", attack(self.clear3));
                 }
                 PlacementKind::Clear4 => {
-                    acc_eval += self.clear4;
+                    acc.add("clear4", attack(self.clear4));
                 }
                 PlacementKind::Tspin1 => {
-                    acc_eval += self.tspin1;
+                    acc.add("tspin1", attack(self.tspin1));
                 }
                 PlacementKind::Tspin2 => {
-                    acc_eval += self.tspin2;
+                    acc.add("tspin2", attack(self.tspin2));
                 }
                 PlacementKind::Tspin3 => {
-                    acc_eval += self.tspin3;
+                    acc.add("tspin3", attack(self.tspin3));
                 }
                 PlacementKind::MiniTspin1 => {
-                    acc_eval += self.mini_tspin1;
+                    acc.add("mini_tspin1", attack(self.mini_tspin1));
                 }
                 PlacementKind::MiniTspin2 => {
-                    acc_eval += self.mini_tspin2;
+                    acc.add("mini_tspin2", attack(self.mini_tspin2));
                 }
                 _ => {}
             }
         }
 
+        if lock.b2b_broken {
+            acc.add("b2b_break", self.b2b_break);
+        }
+        if lock.placement_kind.is_clear() && lock.placement_kind.is_hard() {
+            acc.add("hard_clear", self.hard_clear);
+        }
+
+        // The opponent's board is only known as it was at the root, so attacks earlier in the
+        // line of play don't count towards topping them out
+        let room = opponent.room();
+        if lock.garbage_sent > 0 && lock.garbage_sent * attack_multiplier / 100 >= room {
+            acc.add("lethal_spike", self.lethal_spike);
+        }
+        let banked = board.b2b_bonus as i32 + board.combo.min(4) as i32;
+        transient.add("banked_spike", self.banked_spike * banked * (20 - room as i32) / 20);
+
         if placed == Piece::T {
             match lock.placement_kind {
                 PlacementKind::Tspin1 | PlacementKind::Tspin2 | PlacementKind::Tspin3 => {}
-                _ => acc_eval += self.wasted_t
+                _ => acc.add("wasted_t", self.wasted_t)
             }
         }
 
@@ -229,20 +360,32 @@ impl Evaluator for Standard {
         } else {
             move_time as i32
         };
-        acc_eval += self.move_time * move_time;
+        acc.add("move_time", self.move_time * move_time);
 
         if board.b2b_bonus {
-            transient_eval += self.back_to_back;
+            transient.add("back_to_back", self.back_to_back);
         }
 
         let highest_point = *board.column_heights().iter().max().unwrap() as i32;
-        transient_eval += self.top_quarter * (highest_point - 15).max(0);
-        transient_eval += self.top_half * (highest_point - 10).max(0);
+        transient.add(
+            "top_quarter", self.top_quarter * (highest_point - self.top_quarter_start).max(0)
+        );
+        transient.add(
+            "top_half", self.top_half * (highest_point - self.top_half_start).max(0)
+        );
 
-        acc_eval += self.jeopardy
-            * (highest_point - 10).max(0)
+        acc.add("jeopardy", self.jeopardy
+            * (highest_point - self.top_half_start).max(0)
             * if self.timed_jeopardy { move_time } else { 10 }
-            / 10;
+            / 10);
+
+        // garbage that arrives sooner is more dangerous
+        let danger = incoming.lines as i32 * 4 / (incoming.delay as i32 + 4);
+        transient.add("incoming_height", self.incoming_height * danger * highest_point / 10);
+
+        if self.pc_opportunity != 0 {
+            transient.add("pc_opportunity", pc_opportunity(board) * self.pc_opportunity / 40);
+        }
 
         let ts = if self.use_bag {
             board.next_bag().contains(Piece::T) as usize
@@ -253,7 +396,7 @@ impl Evaluator for Standard {
         };
 
         let mut board = board.clone();
-        for _ in 0..ts {
+        for i in 0..ts {
             let cutout_location = sky_tslot_left(&board)
                 .or_else(|| sky_tslot_right(&board))
                 .or_else(|| {
@@ -274,9 +417,16 @@ impl Evaluator for Standard {
                 .or_else(|| fin_right(&board));
             let result = match cutout_location {
                 Some(location) => cutout_tslot(board.clone(), location),
-                None => break
+                None => {
+                    if i == 0 {
+                        transient.add(
+                            "imminent_tslot", self.imminent_tslot * imminent_tslot(&board)
+                        );
+                    }
+                    break
+                }
             };
-            transient_eval += self.tslot[result.lines];
+            transient.add("tslot", self.tslot[result.lines]);
             if let Some(b) = result.result {
                 board = b;
             } else {
@@ -285,14 +435,20 @@ impl Evaluator for Standard {
         }
 
         let highest_point = *board.column_heights().iter().max().unwrap() as i32;
-        transient_eval += self.height * highest_point;
+        transient.add("height", self.height * highest_point);
 
-        let mut well = 0;
-        for x in 1..10 {
-            if board.column_heights()[x] <= board.column_heights()[well] {
-                well = x;
+        let well = match self.preferred_well {
+            Some(column) if column < 10 => column,
+            _ => {
+                let mut well = 0;
+                for x in 1..10 {
+                    if board.column_heights()[x] <= board.column_heights()[well] {
+                        well = x;
+                    }
+                }
+                well
             }
-        }
+        };
 
         let mut depth = 0;
         'yloop: for y in board.column_heights()[well] .. 20 {
@@ -304,47 +460,197 @@ impl Evaluator for Standard {
             depth += 1;
         }
         let depth = depth.min(self.max_well_depth);
-        transient_eval += self.well_depth * depth;
+        transient.add("well_depth", self.well_depth * depth);
         if depth != 0 {
-            transient_eval += self.well_column[well];
+            transient.add("well_column", self.well_column[well]);
         }
 
         if self.row_transitions != 0 {
-            transient_eval += self.row_transitions * (0..40)
+            transient.add("row_transitions", self.row_transitions * (0..40)
                 .map(|y| *board.get_row(y))
                 .map(|r| (r | 0b1_00000_00000) ^ (1 | r << 1))
                 .map(|d| d.count_ones() as i32)
-                .sum::<i32>();
+                .sum::<i32>());
+        }
+
+        if self.garbage_rows | self.garbage_hole_depth != 0 {
+            let (rows, depth) = garbage(&board);
+            transient.add("garbage_rows", self.garbage_rows * rows);
+            transient.add("garbage_hole_depth", self.garbage_hole_depth * depth);
+        }
+
+        if self.dependencies != 0 {
+            transient.add("dependencies", self.dependencies * dependencies(&board, well));
         }
 
         if self.bumpiness | self.bumpiness_sq != 0 {
             let (bump, bump_sq) = bumpiness(&board, well);
-            transient_eval += bump * self.bumpiness;
-            transient_eval += bump_sq * self.bumpiness_sq;
+            transient.add("bumpiness", bump * self.bumpiness);
+            transient.add("bumpiness_sq", bump_sq * self.bumpiness_sq);
         }
 
         if self.cavity_cells | self.cavity_cells_sq |
                 self.overhang_cells | self.overhang_cells_sq != 0 {
             let (cavity_cells, overhang_cells) = cavities_and_overhangs(&board);
-            transient_eval += self.cavity_cells * cavity_cells;
-            transient_eval += self.cavity_cells_sq * cavity_cells * cavity_cells;
-            transient_eval += self.overhang_cells * overhang_cells;
-            transient_eval += self.overhang_cells_sq * overhang_cells * overhang_cells;
+            transient.add("cavity_cells", self.cavity_cells * cavity_cells);
+            transient.add("cavity_cells_sq", self.cavity_cells_sq * cavity_cells * cavity_cells);
+            transient.add("overhang_cells", self.overhang_cells * overhang_cells);
+            transient.add(
+                "overhang_cells_sq", self.overhang_cells_sq * overhang_cells * overhang_cells
+            );
         }
 
         if self.covered_cells | self.covered_cells_sq != 0 {
             let (covered_cells, covered_cells_sq) = covered_cells(&board);
-            transient_eval += self.covered_cells * covered_cells;
-            transient_eval += self.covered_cells_sq * covered_cells_sq;
+            transient.add("covered_cells", self.covered_cells * covered_cells);
+            transient.add("covered_cells_sq", self.covered_cells_sq * covered_cells_sq);
         }
 
+        let mut terms = acc.terms;
+        terms.extend(transient.terms);
         (Value {
-            value: transient_eval,
+            value: transient.total,
             spike: 0
         }, Reward {
-            value: acc_eval,
-            attack: if lock.placement_kind.is_clear() { lock.garbage_sent as i32 } else { -1 }
-        })
+            value: acc.total,
+            attack: if lock.placement_kind.is_clear() {
+                attack(lock.garbage_sent as i32)
+            } else {
+                -1
+            }
+        }, terms)
+    }
+}
+
+/// Detects boards that could still be perfect cleared within the bottom 4 rows.
+/// 
+/// The board must have no holes, and the number of empty cells below the perfect clear height must
+/// be a multiple of 4 so that it can be filled exactly with tetrominoes. Returns how close the
+/// lowest possible perfect clear is to completion, from 0 (no perfect clear possible) to 40 (one
+/// piece away).
+fn pc_opportunity(board: &Board) -> i32 {
+    let heights = board.column_heights();
+    let highest = *heights.iter().max().unwrap();
+    if highest > 4 {
+        return 0
+    }
+    if board.column_holes().iter().any(|&h| h != 0) {
+        // there are holes somewhere, which can't be filled without clearing lines first
+        return 0
+    }
+    let filled: i32 = heights.iter().sum();
+    if filled == 0 {
+        return 0
+    }
+    for h in highest.max(1)..=4 {
+        let empty = h * 10 - filled;
+        if empty % 4 == 0 {
+            // a full 4 line perfect clear needs 10 pieces, the last one leaving 4 empty cells
+            return 44 - empty
+        }
+    }
+    0
+}
+
+/// Evaluates the garbage at the bottom of the playfield.
+/// 
+/// Garbage is recognized as the rows from the bottom up that have exactly one empty cell. The first
+/// returned value is the number of such rows. The second returned value is the number of filled
+/// cells directly above the hole in the topmost garbage row, which have to be cleared before it
+/// can be dug out.
+fn garbage(board: &Board) -> (i32, i32) {
+    let mut rows = 0;
+    let mut hole = None;
+    for y in 0..40 {
+        let row = board.get_row(y);
+        if (0..10).filter(|&x| !row.get(x)).count() != 1 {
+            break
+        }
+        rows += 1;
+        hole = (0..10).find(|&x| !row.get(x));
+    }
+    let depth = match hole {
+        Some(x) => (rows..board.column_heights()[x])
+            .filter(|&y| board.occupied(x as i32, y))
+            .count() as i32,
+        None => 0
+    };
+    (rows, depth)
+}
+
+/// Counts the columns outside of the well whose lowest empty cell only one kind of piece can
+/// fill while resting flat on the stack, so without leaving a hole below any of its cells.
+/// 
+/// Each of them is a dependency on getting that piece. A vertical I piece fits into any column, so
+/// it only counts when nothing else fits, as in a one-wide hole at least three cells deep.
+fn dependencies(board: &Board, well: usize) -> i32 {
+    use RotationState::*;
+    let heights = board.column_heights();
+    let mut fitting = [enumset::EnumSet::<Piece>::empty(); 10];
+    for piece in enumset::EnumSet::<Piece>::all() {
+        for &rotation in &[North, East, South, West] {
+            if piece == Piece::I && (rotation == East || rotation == West) {
+                continue
+            }
+            let kind = PieceState(piece, rotation);
+            // the lowest cell of the piece in each column, relative to its center column
+            let mut bottoms = [None; 5];
+            for &(dx, dy) in &kind.cells() {
+                let bottom = &mut bottoms[(dx + 2) as usize];
+                *bottom = Some(bottom.map_or(dy, |b: i32| b.min(dy)));
+            }
+            for x in 0..10 {
+                let mut y = None;
+                let flat = bottoms.iter().enumerate().all(|(i, bottom)| match bottom {
+                    None => true,
+                    Some(dy) => {
+                        let column = x + i as i32 - 2;
+                        if column < 0 || column >= 10 {
+                            return false
+                        }
+                        let at = heights[column as usize] - dy;
+                        *y.get_or_insert(at) == at
+                    }
+                });
+                if !flat {
+                    continue
+                }
+                let placement = FallingPiece {
+                    kind, x, y: y.unwrap(), tspin: TspinStatus::None
+                };
+                if !board.obstructed(&placement) {
+                    for &(column, _) in &placement.cells() {
+                        fitting[column as usize].insert(piece);
+                    }
+                }
+            }
+        }
+    }
+    (0..10).filter(|&x| x != well && fitting[x].len() <= 1).count() as i32
+}
+
+/// Sums up the terms of an evaluation, remembering each nonzero term if the evaluation is being
+/// explained.
+struct Terms {
+    total: i32,
+    explain: bool,
+    terms: Vec<(String, i32)>
+}
+
+impl Terms {
+    fn new(explain: bool) -> Self {
+        Terms {
+            total: 0,
+            explain,
+            terms: vec![]
+        }
+    }
+
+    fn add(&mut self, name: &str, value: i32) {
+        self.total += value;
+        if self.explain && value != 0 {
+            self.terms.push((name.to_owned(), value));
+        }
     }
 }
 
@@ -379,9 +685,12 @@ fn cavities_and_overhangs(board: &Board) -> (i32, i32) {
     let mut cavities = 0;
     let mut overhangs = 0;
 
-    for y in 0..*board.column_heights().iter().max().unwrap() {
-        for x in 0..10 {
-            if board.occupied(x as i32, y) || y >= board.column_heights()[x] {
+    for x in 0..10 {
+        if board.column_holes()[x] == 0 {
+            continue
+        }
+        for y in 0..board.column_heights()[x] {
+            if board.occupied(x as i32, y) {
                 continue
             }
 
@@ -415,6 +724,9 @@ fn covered_cells(board: &Board) -> (i32, i32) {
     let mut covered_sq = 0;
 
     for x in 0..10 {
+        if board.column_holes()[x] == 0 {
+            continue
+        }
         for y in (0..board.column_heights()[x] - 2).rev() {
             if !board.occupied(x as i32, y) {
                 let cells = 6.min(board.column_heights()[x] - y - 1);
@@ -624,6 +936,33 @@ fn cave_tslot(board: &Board, mut starting_point: FallingPiece) -> Option<Falling
     }
 }
 
+/// Looks for a T-spin double slot that is only missing its overhang: a one-wide hole with flat
+/// shoulders on both sides and a tall enough column next to one of them that a single piece can
+/// close the roof.
+/// 
+/// Returns the number of lines a T-spin in the best such slot would clear.
+fn imminent_tslot(board: &Board) -> i32 {
+    let heights = board.column_heights();
+    let mut best = 0;
+    for x in 1..9 {
+        let y = heights[x];
+        if heights[x-1] != y+1 || heights[x+1] != y+1 {
+            continue
+        }
+        let left_wall = x >= 2 && heights[x-2] >= y+3;
+        let right_wall = x <= 7 && heights[x+2] >= y+3;
+        if !left_wall && !right_wall {
+            continue
+        }
+
+        let x = x as i32;
+        let bottom_full = (0..10).all(|cx| cx == x || board.occupied(cx, y));
+        let top_full = (0..10).all(|cx| (cx - x).abs() <= 1 || board.occupied(cx, y+1));
+        best = best.max(bottom_full as i32 + top_full as i32);
+    }
+    best
+}
+
 struct Cutout {
     lines: usize,
     result: Option<Board>
@@ -652,14 +991,14 @@ fn cutout_tslot(mut board: Board, mut piece: FallingPiece) -> Cutout {
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub struct Reward {
-    value: i32,
-    attack: i32
+    pub(crate) value: i32,
+    pub(crate) attack: i32
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Default, Serialize, Deserialize)]
 pub struct Value {
-    value: i32,
-    spike: i32
+    pub(crate) value: i32,
+    pub(crate) spike: i32
 }
 
 impl std::ops::Add for Value {
@@ -723,4 +1062,33 @@ impl Evaluation<Reward> for Value {
         self.value = self.value.max(new_result.value);
         self.spike = self.spike.max(new_result.spike);
     }
+
+    fn score(&self) -> i64 {
+        self.value as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dependencies_count_holes_only_one_piece_fills() {
+        let mut field = [[false; 10]; 40];
+        for row in &mut field[..3] {
+            *row = [true, true, true, true, false, true, true, true, true, false];
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+        // the three deep hole in column 4 needs an I piece, the well in column 9 doesn't count
+        assert_eq!(dependencies(&board, 9), 1);
+
+        // a two deep hole also fits a vertical J or L piece
+        let mut field = [[false; 10]; 40];
+        for row in &mut field[..2] {
+            *row = [true, true, true, true, false, true, true, true, true, true];
+        }
+        board.set_field(field);
+        assert_eq!(dependencies(&board, 9), 0);
+    }
 }
@@ -0,0 +1,140 @@
+use libtetris::*;
+use serde::{ Serialize, Deserialize };
+use super::*;
+
+/// Height differences between neighboring columns are clamped to this range.
+const MAX_DELTA: i32 = 4;
+const DELTAS: usize = 2 * MAX_DELTA as usize + 1;
+
+/// The number of distinct 4-column surface patterns.
+pub const PATTERNS: usize = DELTAS * DELTAS * DELTAS;
+
+/// Returns the pattern index of the 4 columns starting at `x`.
+pub fn pattern(board: &Board, x: usize) -> usize {
+    let heights = &board.column_heights()[x..x+4];
+    let mut index = 0;
+    for pair in heights.windows(2) {
+        let delta = (pair[1] - pair[0]).max(-MAX_DELTA).min(MAX_DELTA) + MAX_DELTA;
+        index = index * DELTAS + delta as usize;
+    }
+    index
+}
+
+/// A table of learned scores for every 4-column surface pattern.
+///
+/// The text format is one integer per line, one line per pattern in pattern index order. Tables
+/// can be built from self-play games with the optimizer's `--surface-table` mode.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct SurfaceTable {
+    pub scores: Vec<i32>
+}
+
+impl Default for SurfaceTable {
+    fn default() -> Self {
+        SurfaceTable {
+            scores: vec![0; PATTERNS]
+        }
+    }
+}
+
+impl SurfaceTable {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let scores = text.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(|l| l.parse().map_err(|e| format!("invalid score {}: {}", l, e)))
+            .collect::<Result<Vec<i32>, _>>()?;
+        if scores.len() != PATTERNS {
+            return Err(format!("expected {} scores, found {}", PATTERNS, scores.len()));
+        }
+        Ok(SurfaceTable { scores })
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        SurfaceTable::parse(&std::fs::read_to_string(path).map_err(|e| e.to_string())?)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for score in &self.scores {
+            text.push_str(&score.to_string());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Sums the scores of every 4-column window of the board's surface.
+    pub fn score(&self, board: &Board) -> i32 {
+        (0..7).map(|x| self.scores[pattern(board, x)]).sum()
+    }
+}
+
+/// The standard evaluator with a learned surface table added on top.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Surface {
+    pub weights: Standard,
+    pub table: SurfaceTable,
+    /// Evaluation per 100 points of surface score.
+    pub table_weight: i32
+}
+
+impl Default for Surface {
+    fn default() -> Self {
+        Surface {
+            weights: Standard::default(),
+            table: SurfaceTable::default(),
+            table_weight: 100
+        }
+    }
+}
+
+impl Evaluator for Surface {
+    type Value = Value;
+    type Reward = Reward;
+
+    fn name(&self) -> String {
+        let mut info = self.weights.name();
+        info.push_str("\nSurface table");
+        info
+    }
+
+    fn pick_move(
+        &self, candidates: Vec<MoveCandidate<Value>>, incoming: u32
+    ) -> MoveCandidate<Value> {
+        self.weights.pick_move(candidates, incoming)
+    }
+
+    fn evaluate(
+        &self, lock: &LockResult, board: &Board, move_time: u32, placed: Piece
+    ) -> (Value, Reward) {
+        let (value, reward) = self.weights.evaluate(lock, board, move_time, placed);
+        (Value {
+            value: value.value + self.table.score(board) * self.table_weight / 100,
+            ..value
+        }, reward)
+    }
+
+    fn evaluate_batch(&self, batch: &[Placement]) -> Vec<(Value, Reward)> {
+        self.weights.evaluate_batch(batch).into_iter()
+            .zip(batch)
+            .map(|((value, reward), p)| (Value {
+                value: value.value + self.table.score(&p.board) * self.table_weight / 100,
+                ..value
+            }, reward))
+            .collect()
+    }
+
+    fn explain(&self, placement: &Placement) -> Vec<(String, i32)> {
+        let mut terms = self.weights.explain(placement);
+        let surface = self.table.score(&placement.board) * self.table_weight / 100;
+        if surface != 0 {
+            terms.push(("surface".to_owned(), surface));
+        }
+        terms
+    }
+
+    fn pc_opportunity(&self, board: &Board) -> bool {
+        self.weights.pc_opportunity(board)
+    }
+}
@@ -0,0 +1,66 @@
+use libtetris::{ Board, FallingPiece, LockResult, Piece, PlacementKind, RotationState };
+
+/// Converts a plan, like the one from `Info::plan`, to a fumen string with one page per
+/// placement, starting from `board`. Every page shows the board before its placement and is
+/// commented with the kind of clear the placement makes, for example `TSD`.
+pub fn plan_fumen(board: &Board, plan: &[(FallingPiece, LockResult)]) -> String {
+    let mut board = board.clone();
+    let mut fumen = fumen::Fumen::default();
+    for &(placement, ref lock) in plan {
+        let page = fumen.add_page();
+        let field = board.get_field();
+        for y in 0..23 {
+            for x in 0..10 {
+                page.field[y][x] = if field[y][x] {
+                    fumen::CellColor::Grey
+                } else {
+                    fumen::CellColor::Empty
+                };
+            }
+        }
+        page.piece = Some(fumen_piece(placement));
+        page.comment = comment(lock);
+        board.lock_piece(placement);
+    }
+    fumen.encode()
+}
+
+fn comment(lock: &LockResult) -> Option<String> {
+    let mut parts = vec![];
+    if lock.placement_kind != PlacementKind::None {
+        parts.push(lock.placement_kind.short_name());
+    }
+    if lock.b2b {
+        parts.push("B2B");
+    }
+    if lock.perfect_clear {
+        parts.push("PC");
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+fn fumen_piece(placement: FallingPiece) -> fumen::Piece {
+    fumen::Piece {
+        kind: match placement.kind.0 {
+            Piece::I => fumen::PieceType::I,
+            Piece::O => fumen::PieceType::O,
+            Piece::T => fumen::PieceType::T,
+            Piece::L => fumen::PieceType::L,
+            Piece::J => fumen::PieceType::J,
+            Piece::S => fumen::PieceType::S,
+            Piece::Z => fumen::PieceType::Z
+        },
+        rotation: match placement.kind.1 {
+            RotationState::North => fumen::RotationState::North,
+            RotationState::East => fumen::RotationState::East,
+            RotationState::South => fumen::RotationState::South,
+            RotationState::West => fumen::RotationState::West
+        },
+        x: placement.x as u32,
+        y: placement.y as u32
+    }
+}
@@ -1,18 +1,35 @@
 use serde::{ Serialize, Deserialize };
-pub use opening_book::Book;
+pub use opening_book::{ Book, Opener };
 
 #[macro_use]
 extern crate rental;
 
+/// Enters a `tracing` span named `$name` until the end of the enclosing block. Does nothing unless
+/// the `tracing` feature is enabled.
+macro_rules! span {
+    ($name:literal) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!($name).entered();
+    };
+}
+
 pub mod evaluation;
 pub mod moves;
+pub mod puzzle;
+mod export;
+mod pcfinder;
 mod modes;
 mod dag;
+mod opponent;
 
 #[cfg(not(target_arch = "wasm32"))]
 mod desktop;
 #[cfg(not(target_arch = "wasm32"))]
-pub use desktop::Interface;
+pub use desktop::{ Interface, NextMove, Event };
+#[cfg(not(target_arch = "wasm32"))]
+mod pool;
+#[cfg(not(target_arch = "wasm32"))]
+pub use pool::BotPool;
 
 #[cfg(target_arch = "wasm32")]
 mod web;
@@ -21,20 +38,74 @@ pub use web::Interface;
 
 use libtetris::*;
 pub use crate::moves::Move;
-pub use crate::modes::normal::{ BotState, ThinkResult, Thinker };
+pub use crate::modes::normal::{ BotState, ThinkResult, Thinker, Candidate };
+pub use crate::dag::{ ArenaStats, MemoryUsage };
+pub use crate::export::plan_fumen;
 pub use crate::modes::pcloop::PcPriority;
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Options {
     pub mode: crate::moves::MovementMode,
+    pub timing: crate::moves::Timing,
     pub spawn_rule: SpawnRule,
+    /// The randomizer generating the pieces, so speculation only considers pieces that can
+    /// actually come next.
+    pub randomizer: RandomizerKind,
     pub use_hold: bool,
     pub speculate: bool,
+    /// How many unknown pieces deep the search speculates. Unlimited when `None`.
+    pub speculation_depth: Option<u32>,
+    /// The number of best placements kept for each possible piece when speculating past the first
+    /// unknown piece. Pruning the deeper speculation lets the search look further ahead with short
+    /// previews. No pruning when `None`.
+    pub speculation_width: Option<u32>,
+    /// Whether to keep thinking while no move is requested. Turning this off saves CPU between
+    /// moves at the cost of starting each move with a smaller search tree.
+    pub ponder: bool,
     pub pcloop: Option<modes::pcloop::PcPriority>,
+    /// Whether to search for a guaranteed perfect clear with the visible queue before each move
+    /// when the evaluator sees a perfect clear opportunity.
+    pub pc_finder: bool,
     pub min_nodes: u32,
     pub max_nodes: u32,
-    pub threads: u32
+    pub threads: u32,
+    /// The number of best moves to report in `Info` along with their expected follow-up, for
+    /// analysis frontends that show alternatives to the chosen move.
+    pub alternatives: u32,
+    /// How often in milliseconds to publish the moves currently being considered for
+    /// `Interface::candidates`. Disabled when `None`.
+    pub candidate_interval: Option<u32>,
+    /// Named weight preset for frontends that let the user pick a bot personality. The bot itself
+    /// only uses the evaluator it is launched with; frontends build it from `selected_preset`.
+    pub preset: Option<evaluation::Preset>,
+    /// Time to spend on each move in milliseconds. When this or `game_time` is set, the bot uses
+    /// its time budget before providing a requested move. The budget shrinks to half in calm
+    /// positions and grows to double when the stack is high or a lot of garbage is queued, but
+    /// never uses more than a quarter of `game_time`. When only one move is possible, the bot
+    /// moves immediately.
+    pub move_time: Option<u32>,
+    /// Total time on the bot's clock for the whole game in milliseconds, chess-clock style.
+    pub game_time: Option<u32>,
+    /// Time added to the clock after each move in milliseconds.
+    pub time_increment: u32,
+    /// Seed for the random choices of the search. Together with a fixed amount of thinking per
+    /// move, for example when driving `BotState` directly, this makes the bot's moves
+    /// reproducible. A random seed is used when `None`.
+    pub seed: Option<u64>,
+    /// Limit on the memory used by the search tree in bytes, as estimated by
+    /// `Interface::memory_usage`. At 90% of the limit the bot stops starting thinking cycles, and
+    /// once the ones in progress finish, throws away its deepest speculated generations until the
+    /// tree is back under that. No limit when `None`.
+    pub max_memory: Option<u64>,
+    /// Most pieces per second the bot plays at, for frontends that want a slower opponent.
+    /// Moves are still decided as usual, but each is held back until enough time has passed
+    /// since the previous move was provided. Only the desktop `Interface` enforces this. No
+    /// limit when `None`.
+    pub pps_limit: Option<f64>,
+    /// How much the time between moves varies under `pps_limit`, as a fraction of the average.
+    /// For example, 0.2 spaces moves between 80% and 120% of `1 / pps_limit` seconds apart.
+    pub pps_jitter: f64
 }
 
 #[derive(Serialize, Deserialize)]
@@ -45,9 +116,63 @@ enum BotMsg {
         b2b: bool,
         combo: u32
     },
+    Resync {
+        #[serde(with = "BigArray")]
+        field: [[bool; 10]; 40],
+        b2b: bool,
+        combo: u32,
+        hold: Option<Piece>,
+        queue: Vec<Piece>,
+        bag: enumset::EnumSet<Piece>
+    },
     NewPiece(Piece),
     NextMove(u32),
-    ForceAnalysisLine(Vec<FallingPiece>)
+    ForceAnalysisLine(Vec<FallingPiece>),
+    PlayMove(FallingPiece),
+    SetOptions(Options),
+    IncomingGarbage(evaluation::Incoming),
+    /// The opponent placed a piece that sent this many lines.
+    OpponentPlaced(u32),
+    OpponentState(evaluation::OpponentState),
+    AttackMultiplier(u32),
+    ScoreMultiplier(u32),
+    Pause,
+    Resume,
+    CancelMove,
+    /// Only sent by the desktop interface, which handles the deadline itself.
+    #[serde(skip)]
+    NextMoveBy(u32, std::time::Instant),
+    /// Only sent by the desktop interface.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    Snapshot(crossbeam_channel::Sender<Snapshot>),
+    /// Only sent by the desktop interface, whose bot thread answers it since it needs the
+    /// evaluator.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    ExplainCandidate(FallingPiece, crossbeam_channel::Sender<Option<Vec<(String, i32)>>>)
+}
+
+/// The state needed to start a bot where another left off: the position it was about to play
+/// from, its options and the garbage queued against it. The search tree is not included.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub board: Board,
+    pub options: Options,
+    pub incoming: evaluation::Incoming
+}
+
+impl Snapshot {
+    /// Writes the snapshot to a file as JSON.
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&json).map_err(|e| e.to_string())
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
@@ -77,16 +202,40 @@ impl Default for Options {
     fn default() -> Self {
         Options {
             mode: crate::moves::MovementMode::ZeroG,
+            timing: Default::default(),
             spawn_rule: SpawnRule::Row19Or20,
+            randomizer: RandomizerKind::SevenBag,
             use_hold: true,
             speculate: true,
+            speculation_depth: None,
+            speculation_width: None,
+            ponder: true,
             pcloop: None,
+            pc_finder: false,
             min_nodes: 0,
             max_nodes: 4_000_000_000,
-            threads: 1
+            threads: 1,
+            alternatives: 0,
+            candidate_interval: None,
+            preset: None,
+            move_time: None,
+            game_time: None,
+            time_increment: 0,
+            seed: None,
+            max_memory: None,
+            pps_limit: None,
+            pps_jitter: 0.0
         }
     }
 }
 
+impl Options {
+    /// The weight preset to use: `preset` if set, otherwise the one named by the
+    /// `COLD_CLEAR_PRESET` environment variable.
+    pub fn selected_preset(&self) -> Option<evaluation::Preset> {
+        self.preset.or_else(evaluation::Preset::from_env)
+    }
+}
+
 use serde_big_array::big_array;
 big_array!( BigArray; 40, );
\ No newline at end of file
@@ -1,6 +1,7 @@
 use libtetris::*;
 use opening_book::Book;
-use crate::evaluation::Evaluator;
+use crate::evaluation::{ Evaluator, Incoming };
+use crate::opponent::OpponentModel;
 use crate::{ Options, Info, Move, BotMsg };
 use serde::{ Serialize, Deserialize };
 use arrayvec::ArrayVec;
@@ -31,11 +32,20 @@ pub(crate) struct ModeSwitchedBot<'a, E: Evaluator> {
     options: Options,
     board: Board,
     do_move: Option<u32>,
-    book: Option<&'a Book>
+    hurry: bool,
+    paused: bool,
+    requested: bool,
+    book: Option<&'a Book>,
+    /// The garbage the frontend says is queued against the bot.
+    incoming: Incoming,
+    opponent: OpponentModel
 }
 
 impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
-    pub fn new(board: Board, options: Options, book: Option<&'a Book>) -> Self {
+    pub fn new(mut board: Board, options: Options, book: Option<&'a Book>) -> Self {
+        if board.randomizer.kind() != options.randomizer {
+            board.set_randomizer(options.randomizer);
+        }
         #[cfg(target_arch = "wasm32")]
         let mode = Mode::Normal(normal::BotState::new(board.clone(), options));
         #[cfg(not(target_arch = "wasm32"))]
@@ -51,7 +61,12 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
         ModeSwitchedBot {
             mode, options, board,
             do_move: None,
-            book
+            hurry: false,
+            paused: false,
+            requested: false,
+            book,
+            incoming: Incoming::default(),
+            opponent: OpponentModel::default()
         }
     }
 
@@ -81,6 +96,19 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                     )
                 }
             }
+            BotMsg::Resync { field, b2b, combo, hold, queue, bag } => {
+                if self.options.use_hold && hold.is_none() && queue.is_empty() {
+                    return
+                }
+                let board = resync_board(&self.board, field, b2b, combo, hold, queue, bag);
+                self.board = board.clone();
+                match &mut self.mode {
+                    Mode::Normal(bot) => bot.rebuild(board),
+                    Mode::PcLoop(_) => self.mode = Mode::Normal(
+                        normal::BotState::new(board, self.options)
+                    )
+                }
+            }
             BotMsg::NewPiece(piece) => {
                 self.board.add_next_piece(piece);
                 match &mut self.mode {
@@ -106,31 +134,230 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                     Mode::PcLoop(bot) => bot.add_next_piece(piece)
                 }
             }
-            BotMsg::NextMove(incoming) => self.do_move = Some(incoming),
+            BotMsg::NextMove(incoming) => {
+                self.move_requested();
+                self.do_move = Some(incoming);
+            }
             BotMsg::ForceAnalysisLine(path) => match &mut self.mode {
                 Mode::Normal(bot) => bot.force_analysis_line(path),
                 _ => {}
             }
+            BotMsg::PlayMove(placement) => {
+                match play_placement(&self.board, placement, self.options.use_hold) {
+                    Some(board) => self.board = board,
+                    None => return
+                }
+                match &mut self.mode {
+                    Mode::Normal(bot) => bot.play_move(placement),
+                    Mode::PcLoop(_) => self.mode = Mode::Normal(
+                        normal::BotState::new(self.board.clone(), self.options)
+                    )
+                }
+                self.opponent.own_piece();
+                self.update_incoming();
+            }
+            BotMsg::SetOptions(options) => {
+                let mut options = Options {
+                    threads: self.options.threads,
+                    ..options
+                };
+                // a tree with hold enabled needs a piece to start from
+                if options.use_hold && self.board.hold_piece.is_none() &&
+                        self.board.next_queue().next().is_none() {
+                    options.use_hold = self.options.use_hold;
+                }
+                let randomizer_changed = options.randomizer != self.options.randomizer;
+                if randomizer_changed {
+                    self.board.set_randomizer(options.randomizer);
+                }
+                self.options = options;
+                match &mut self.mode {
+                    Mode::Normal(bot) => {
+                        bot.set_options(options);
+                        if randomizer_changed {
+                            bot.rebuild(self.board.clone());
+                        }
+                    }
+                    Mode::PcLoop(_) => self.mode = Mode::Normal(
+                        normal::BotState::new(self.board.clone(), options)
+                    )
+                }
+            }
+            BotMsg::IncomingGarbage(incoming) => {
+                self.incoming = incoming;
+                self.update_incoming();
+            }
+            BotMsg::OpponentPlaced(attack) => {
+                self.opponent.observe(attack);
+                self.update_incoming();
+            }
+            BotMsg::OpponentState(opponent) => match &mut self.mode {
+                Mode::Normal(bot) => bot.set_opponent(opponent),
+                _ => {}
+            }
+            BotMsg::AttackMultiplier(multiplier) => match &mut self.mode {
+                Mode::Normal(bot) => bot.set_attack_multiplier(multiplier),
+                _ => {}
+            }
+            BotMsg::ScoreMultiplier(multiplier) => match &mut self.mode {
+                Mode::Normal(bot) => bot.set_score_multiplier(multiplier),
+                _ => {}
+            }
+            BotMsg::Pause => self.paused = true,
+            BotMsg::Resume => self.paused = false,
+            BotMsg::CancelMove => {
+                self.do_move = None;
+                self.hurry = false;
+                self.requested = false;
+            }
+            BotMsg::NextMoveBy(incoming, _) => {
+                self.move_requested();
+                self.do_move = Some(incoming);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            BotMsg::Snapshot(reply) => {
+                reply.send(self.snapshot()).ok();
+            }
+            // the desktop bot thread answers these itself, since it has the evaluator
+            #[cfg(not(target_arch = "wasm32"))]
+            BotMsg::ExplainCandidate(_, reply) => {
+                reply.send(None).ok();
+            }
+        }
+    }
+
+    /// Tells the search about the garbage queued against the bot. While there is none, the
+    /// opponent's expected next attack is planned for instead.
+    fn update_incoming(&mut self) {
+        let incoming = match self.opponent.expected_attack() {
+            Some(expected) if self.incoming.lines == 0 => expected,
+            _ => self.incoming
+        };
+        if let Mode::Normal(bot) = &mut self.mode {
+            bot.set_incoming(incoming);
+        }
+    }
+
+    /// Lets the bot think even when it doesn't ponder, because a move is about to be requested.
+    pub fn move_requested(&mut self) {
+        self.requested = true;
+        if let Mode::Normal(bot) = &mut self.mode {
+            bot.move_requested();
         }
     }
 
-    pub fn think(&mut self, eval: &E, send_move: impl FnOnce(Move, Info)) -> Vec<Task> {
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn snapshot(&self) -> crate::Snapshot {
+        crate::Snapshot {
+            board: self.board.clone(),
+            options: self.options,
+            incoming: self.incoming
+        }
+    }
+
+    pub fn plan(&self) -> Vec<(FallingPiece, LockResult)> {
+        match &self.mode {
+            Mode::Normal(bot) => bot.plan(),
+            Mode::PcLoop(_) => vec![]
+        }
+    }
+
+    pub fn memory_usage(&self) -> crate::MemoryUsage {
+        match &self.mode {
+            Mode::Normal(bot) => bot.memory_usage(),
+            Mode::PcLoop(_) => Default::default()
+        }
+    }
+
+    pub fn candidates(&self) -> Vec<normal::Candidate> {
+        match &self.mode {
+            Mode::Normal(bot) => bot.candidates(),
+            Mode::PcLoop(_) => vec![]
+        }
+    }
+
+    /// See `BotState::explain_candidate`. Always `None` in PC loop mode.
+    pub fn explain_candidate(
+        &self, eval: &E, placement: FallingPiece
+    ) -> Option<Vec<(String, i32)>> {
+        match &self.mode {
+            Mode::Normal(bot) => bot.explain_candidate(eval, placement),
+            Mode::PcLoop(_) => None
+        }
+    }
+
+    /// How dangerous the position is, from 0 for a low stack to 1 for a stack about to top out.
+    /// `incoming` lines of garbage queued against the bot count as part of the stack.
+    pub fn danger(&self, incoming: u32) -> f64 {
+        // rows filled before the position starts to get dangerous, and when it's as bad as it gets
+        const SAFE: f64 = 4.0;
+        const CRITICAL: f64 = 18.0;
+        let height = self.board.column_heights().iter().copied().max().unwrap_or(0);
+        let queued = match &self.mode {
+            Mode::Normal(bot) => incoming.max(bot.incoming().lines),
+            Mode::PcLoop(_) => incoming
+        };
+        let filled = (height + queued as i32) as f64;
+        ((filled - SAFE) / (CRITICAL - SAFE)).max(0.0).min(1.0)
+    }
+
+    /// Whether there is only one move the bot could make.
+    pub fn move_is_forced(&self) -> bool {
+        match &self.mode {
+            Mode::Normal(bot) => bot.candidate_count() == 1,
+            Mode::PcLoop(_) => false
+        }
+    }
+
+    /// Makes the requested move be provided as soon as any move is known, without waiting for the
+    /// minimum amount of thinking.
+    pub fn hurry(&mut self) {
+        if self.do_move.is_some() {
+            self.hurry = true;
+        }
+    }
+
+    /// Thinks and provides the requested move to `send_move` once it is decided, along with the
+    /// board the move is made on. Returns the thinking tasks to run.
+    pub fn think(
+        &mut self, eval: &E, send_move: impl FnOnce(Move, Info, &Board)
+    ) -> Vec<Task> {
+        if self.paused {
+            return vec![]
+        }
+
         let board = &mut self.board;
         let send_move = |mv: Move, info| {
+            let (hold, location) = (mv.hold, mv.expected_location);
+            send_move(mv, info, board);
             let next = board.advance_queue().unwrap();
-            if mv.hold {
+            if hold {
                 if board.hold(next).is_none() {
                     board.advance_queue();
                 }
             }
-            board.lock_piece(mv.expected_location).perfect_clear;
-            send_move(mv, info)
+            board.lock_piece(location);
+        };
+        // opening books are built for 7-bag sequences
+        let book = match self.options.randomizer {
+            RandomizerKind::SevenBag => self.book,
+            _ => None
         };
         match &mut self.mode {
             Mode::Normal(bot) => {
                 if let Some(incoming) = self.do_move {
-                    if bot.next_move(eval, self.book, incoming, send_move) {
+                    let moved = if self.hurry {
+                        bot.force_next_move(eval, book, incoming, send_move)
+                    } else {
+                        bot.next_move(eval, book, incoming, send_move)
+                    };
+                    if moved {
                         self.do_move = None;
+                        self.hurry = false;
+                        self.requested = false;
                         #[cfg(not(target_arch = "wasm32"))] {
                             if self.options.pcloop.is_some() && can_pc_loop(
                                 board, self.options.use_hold
@@ -141,13 +368,17 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                                     self.options.mode,
                                     self.options.pcloop.unwrap()
                                 ));
-                                fn nothing(_: Move, _: Info) {}
+                                fn nothing(_: Move, _: Info, _: &Board) {}
                                 return self.think(eval, nothing);
                             }
                         }
                     }
                 }
 
+                if !self.options.ponder && !self.requested {
+                    return vec![]
+                }
+
                 let mut thinks = vec![];
                 for _ in 0..10 {
                     if bot.outstanding_thinks >= self.options.threads {
@@ -169,6 +400,8 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                         Ok((mv, info)) => {
                             send_move(mv, Info::PcLoop(info));
                             self.do_move = None;
+                            self.hurry = false;
+                            self.requested = false;
                         }
                         Err(false) => {}
                         Err(true) => {
@@ -206,11 +439,64 @@ impl Task {
     }
 }
 
+/// Builds the board described by a `BotMsg::Resync`.
+pub(crate) fn resync_board(
+    old: &Board,
+    field: [[bool; 10]; 40],
+    b2b: bool,
+    combo: u32,
+    hold: Option<Piece>,
+    queue: Vec<Piece>,
+    bag: enumset::EnumSet<Piece>
+) -> Board {
+    let mut board = Board::new_with_state(field, bag, hold, b2b, combo);
+    for piece in queue {
+        board.add_next_piece(piece);
+    }
+    let randomizer = old.randomizer.kind();
+    if randomizer != RandomizerKind::SevenBag {
+        // the state of the other randomizers is rebuilt from the new queue
+        board.set_randomizer(randomizer);
+    }
+    board
+}
+
+/// Returns the board after the piece is placed the way the game would, or `None` if the placement
+/// couldn't have been played or would leave the bot without a piece to place next.
+fn play_placement(board: &Board, placement: FallingPiece, use_hold: bool) -> Option<Board> {
+    if board.obstructed(&placement) || !board.on_stack(&placement) {
+        return None
+    }
+    let mut board = board.clone();
+    let next = board.advance_queue()?;
+    if next != placement.kind.0 {
+        if !use_hold {
+            return None
+        }
+        let unheld = match board.hold(next) {
+            Some(unheld) => unheld,
+            None => board.advance_queue()?
+        };
+        if unheld != placement.kind.0 {
+            return None
+        }
+    }
+    board.lock_piece(placement);
+    if use_hold && board.hold_piece.is_none() && board.next_queue().next().is_none() {
+        return None
+    }
+    Some(board)
+}
+
 fn can_pc_loop(board: &Board, hold_enabled: bool) -> bool {
+    // the perfect clear finder assumes a 7-bag randomizer
+    if board.randomizer.kind() != RandomizerKind::SevenBag {
+        return false;
+    }
     if board.get_row(0) != <u16 as Row>::EMPTY {
         return false;
     }
-    let pieces = board.next_queue().count();
+    let pieces = board.next_queue().len();
     if hold_enabled {
         let pieces = pieces + board.hold_piece.is_some() as usize;
         pieces >= 11
@@ -247,4 +533,30 @@ pub mod pcloop {
     pub struct Info {
         pub plan: Vec<(FallingPiece, LockResult)>
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enumset::EnumSet;
+
+    #[test]
+    fn resync_takes_the_bag_of_the_new_queue() {
+        let mut old = Board::new();
+        for &piece in &[Piece::I, Piece::O, Piece::T] {
+            old.add_next_piece(piece);
+        }
+        let bag = Piece::S | Piece::Z | Piece::L | Piece::J;
+        let board = resync_board(
+            &old, [[false; 10]; 40], false, 0, None, vec![Piece::S, Piece::Z], bag
+        );
+        assert_eq!(board.remaining_bag(), Piece::L | Piece::J);
+        assert_eq!(board.next_bag(), bag);
+
+        let board = resync_board(
+            &old, [[false; 10]; 40], false, 0, None, vec![Piece::S], EnumSet::empty()
+        );
+        let mut remaining = EnumSet::all();
+        remaining.remove(Piece::S);
+        assert_eq!(board.remaining_bag(), remaining);
+    }
+}
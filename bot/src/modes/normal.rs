@@ -3,15 +3,21 @@ use enum_map::EnumMap;
 use libtetris::*;
 use opening_book::Book;
 // use crate::tree::{ ChildData, TreeState, NodeId };
-use crate::dag::{ DagState, NodeId, ChildData };
+use crate::dag::{ DagState, NodeId, ChildData, MoveCandidate, ArenaStats, MemoryUsage, Garbage };
 use crate::Options;
 pub use crate::moves::Move;
-use crate::evaluation::Evaluator;
+use crate::moves::InputList;
+use crate::evaluation::{ Evaluator, Evaluation, Placement, Incoming, OpponentState };
 
 pub struct BotState<E: Evaluator> {
     tree: DagState<E::Value, E::Reward>,
     options: Options,
     forced_analysis_lines: Vec<Vec<FallingPiece>>,
+    incoming: Incoming,
+    opponent: OpponentState,
+    attack_multiplier: u32,
+    score_multiplier: u32,
+    pondered_nodes: Option<u32>,
     pub outstanding_thinks: u32
 }
 
@@ -20,21 +26,39 @@ pub struct Thinker {
     node: NodeId,
     board: Board,
     options: Options,
+    incoming: Incoming,
+    opponent: OpponentState,
+    attack_multiplier: u32,
+    score_multiplier: u32,
+    root: bool,
+    /// The number of unknown pieces the children of the node depend on.
+    unknown_pieces: u32,
+    /// The number of pieces placed between the root and the node.
+    depth: u32
 }
 
 #[derive(Serialize, Deserialize)]
 pub enum ThinkResult<V, R> {
     Known(NodeId, Vec<ChildData<V, R>>),
-    Speculated(NodeId, EnumMap<Piece, Option<Vec<ChildData<V, R>>>>),
+    /// The children for each possible next piece, with the piece's weight under the randomizer.
+    Speculated(NodeId, EnumMap<Piece, Option<(u32, Vec<ChildData<V, R>>)>>),
     Unmark(NodeId)
 }
 
 impl<E: Evaluator> BotState<E> {
-    pub fn new(board: Board, options: Options) -> Self {
+    pub fn new(mut board: Board, options: Options) -> Self {
+        if board.randomizer.kind() != options.randomizer {
+            board.set_randomizer(options.randomizer);
+        }
         BotState {
-            tree: DagState::new(board, options.use_hold),
+            tree: DagState::new(board, options.use_hold, options.seed),
             options,
             forced_analysis_lines: vec![],
+            incoming: Incoming::default(),
+            opponent: OpponentState::default(),
+            attack_multiplier: 100,
+            score_multiplier: 1,
+            pondered_nodes: None,
             outstanding_thinks: 0
         }
     }
@@ -43,6 +67,13 @@ impl<E: Evaluator> BotState<E> {
     /// 
     /// Returns `Err(true)` if a thinking cycle can be preformed, but it couldn't find 
     pub fn think(&mut self) -> Result<Thinker, bool> {
+        if self.over_memory_limit() {
+            // no new thinking cycles until the ones in progress finish and memory can be shed
+            self.shed_memory();
+            if self.over_memory_limit() {
+                return Err(false)
+            }
+        }
         if (!self.min_thinking_reached() || self.tree.nodes() < self.options.max_nodes)
                 && !self.tree.is_dead() {
             if let Some((node, board)) = self.tree.find_and_mark_leaf(
@@ -50,8 +81,15 @@ impl<E: Evaluator> BotState<E> {
             ) {
                 self.outstanding_thinks += 1;
                 return Ok(Thinker {
+                    root: self.tree.is_root(&node),
+                    unknown_pieces: self.tree.unknown_pieces(&node),
+                    depth: self.tree.node_depth(&node),
                     node, board,
                     options: self.options,
+                    incoming: self.incoming,
+                    opponent: self.opponent,
+                    attack_multiplier: self.attack_multiplier,
+                    score_multiplier: self.score_multiplier
                 });
             } else {
                 return Err(true)
@@ -68,6 +106,27 @@ impl<E: Evaluator> BotState<E> {
             ThinkResult::Speculated(node, children) => self.tree.update_speculated(node, children),
             ThinkResult::Unmark(node) => self.tree.unmark(node)
         }
+        self.shed_memory();
+    }
+
+    fn over_memory_limit(&self) -> bool {
+        self.options.max_memory.map_or(false, |limit|
+            self.tree.memory_usage().bytes >= limit / 10 * 9
+        )
+    }
+
+    /// Throws away the deepest speculated generations until the tree is under 90% of
+    /// `Options::max_memory`. Nothing is shed while thinking cycles are in progress, since their
+    /// results could belong to the shed generations.
+    fn shed_memory(&mut self) {
+        if self.outstanding_thinks != 0 {
+            return
+        }
+        while self.over_memory_limit() {
+            if !self.tree.shed_deepest_generation() {
+                break
+            }
+        }
     }
 
     pub fn is_dead(&self) -> bool {
@@ -99,6 +158,52 @@ impl<E: Evaluator> BotState<E> {
         }
     }
 
+    /// Throws away the search tree and starts over from the given state.
+    pub fn rebuild(&mut self, board: Board) {
+        self.tree.rebuild(board);
+        self.forced_analysis_lines.clear();
+    }
+
+    /// Changes the options used for new thinking cycles. Changing hold or the movement rules
+    /// invalidates the existing search tree, so it is thrown away in that case.
+    pub fn set_options(&mut self, options: Options) {
+        let restart = options.use_hold != self.options.use_hold ||
+            options.mode != self.options.mode ||
+            options.spawn_rule != self.options.spawn_rule;
+        self.options = options;
+        if restart {
+            self.tree.restart_with_hold(options.use_hold);
+            self.forced_analysis_lines.clear();
+        }
+    }
+
+    /// Advances the search by a placement that was played without the bot choosing it.
+    pub fn play_move(&mut self, placement: FallingPiece) {
+        if !self.tree.play_move(placement) {
+            self.forced_analysis_lines.clear();
+        }
+    }
+
+    /// Records how many nodes the tree had when the next move was requested.
+    pub fn move_requested(&mut self) {
+        let nodes = self.tree.nodes();
+        self.pondered_nodes.get_or_insert(nodes);
+    }
+
+    /// The number of nodes in the search tree.
+    pub fn nodes(&self) -> u32 {
+        self.tree.nodes()
+    }
+
+    /// The board the bot is choosing the next move for.
+    pub fn board(&self) -> &Board {
+        self.tree.board()
+    }
+
+    pub fn candidate_count(&self) -> usize {
+        self.tree.get_next_candidates().len()
+    }
+
     pub fn min_thinking_reached(&self) -> bool {
         self.tree.nodes() > self.options.min_nodes &&
             self.forced_analysis_lines.is_empty() &&
@@ -115,7 +220,18 @@ impl<E: Evaluator> BotState<E> {
         if !self.min_thinking_reached() {
             return false
         }
+        self.force_next_move(eval, book, incoming, f)
+    }
 
+    /// Like `next_move`, but doesn't wait for the minimum amount of thinking or for forced
+    /// analysis lines to be analyzed. Still returns `false` if no move is known yet.
+    pub fn force_next_move(
+        &mut self,
+        eval: &E,
+        book: Option<&Book>,
+        incoming: u32,
+        f: impl FnOnce(Move, crate::Info)
+    ) -> bool {
         let candidates = self.tree.get_next_candidates();
         if candidates.is_empty() {
             return false
@@ -134,10 +250,32 @@ impl<E: Evaluator> BotState<E> {
                 }
             }
         }
-        if picked.is_none() && book_move.is_some() {
+        if picked.is_none() && book_move.take().is_some() {
+            // fall back to the search rather than claiming a book move
             dbg!("book picked a move we can't do?");
         }
+        if picked.is_none() && self.options.pc_finder && eval.pc_opportunity(self.tree.board()) {
+            // the search often misses perfect clears that are forced with the visible queue
+            let pc_move = crate::pcfinder::find_pc(self.tree.board(), &self.options)
+                .and_then(|moves| moves.into_iter().next());
+            if let Some(pc_move) = pc_move {
+                let location = pc_move.expected_location;
+                picked = candidates.iter()
+                    .find(|c| c.hold == pc_move.hold && c.mv.same_location(&location))
+                    .cloned();
+            }
+        }
+        let alternatives = if book_move.is_none() {
+            self.variations(&candidates)
+        } else {
+            vec![]
+        };
+        let scores: Vec<_> = candidates.iter().map(|c| (c.mv, c.evaluation.score())).collect();
         let child = picked.unwrap_or_else(|| eval.pick_move(candidates, incoming));
+        let second_best = scores.iter()
+            .filter(|&&(mv, _)| mv != child.mv)
+            .map(|&(_, score)| score)
+            .max();
 
         let plan = if book_move.is_none() {
             self.tree.get_plan()
@@ -145,6 +283,9 @@ impl<E: Evaluator> BotState<E> {
             vec![]
         };
 
+        let inputs = self.inputs(child.mv);
+
+        let pondered_nodes = self.pondered_nodes.take().unwrap_or(0);
         let info = match book_move {
             Some(mv) => crate::Info::Book(BookInfo {
                 name: "".to_string()
@@ -153,15 +294,16 @@ impl<E: Evaluator> BotState<E> {
                 nodes: if book_move.is_some() { 0 } else { self.tree.nodes() },
                 depth: if book_move.is_some() { 6 } else { self.tree.depth() as u32 },
                 original_rank: child.original_rank,
+                pondered_nodes,
+                speculated_nodes: self.tree.speculated_nodes(),
+                evaluation: child.evaluation.score(),
+                second_best,
+                time: 0,
                 plan,
+                alternatives,
+                explanation: self.explain(eval, &child, &inputs)
             })
         };
-
-        let inputs = crate::moves::find_moves(
-            self.tree.board(),
-            self.options.spawn_rule.spawn(child.mv.kind.0, self.tree.board()).unwrap(),
-            self.options.mode
-        ).into_iter().find(|p| p.location == child.mv).unwrap().inputs;
         let mv = Move {
             hold: child.hold,
             inputs: inputs.movements,
@@ -175,21 +317,145 @@ impl<E: Evaluator> BotState<E> {
         true
     }
 
+    /// Breaks the evaluation of one of the moves currently being considered down into named
+    /// terms, like `Info::explanation` does for the chosen move. Returns `None` if `placement`
+    /// isn't one of them.
+    pub fn explain_candidate(
+        &self, eval: &E, placement: FallingPiece
+    ) -> Option<Vec<(String, i32)>> {
+        let candidates = self.tree.get_next_candidates();
+        let candidate = candidates.iter().find(|c| c.mv == placement)?;
+        Some(self.explain(eval, candidate, &self.inputs(candidate.mv)))
+    }
+
+    /// The inputs that place the next piece at `location`.
+    fn inputs(&self, location: FallingPiece) -> InputList {
+        crate::moves::find_moves(
+            self.tree.board(),
+            self.options.spawn_rule.spawn(location.kind.0, self.tree.board()).unwrap(),
+            self.options.mode,
+            self.options.timing
+        ).into_iter().find(|p| p.location == location).unwrap().inputs
+    }
+
+    /// Explains the evaluation of a move from the root, rebuilding the placement the same way
+    /// the search did so the garbage queued against the bot is taken into account.
+    fn explain(
+        &self, eval: &E, candidate: &MoveCandidate<E::Value>, inputs: &InputList
+    ) -> Vec<(String, i32)> {
+        let move_time = inputs.time + if candidate.hold {
+            1 + self.options.timing.hold_delay(&inputs.movements)
+        } else {
+            0
+        };
+        let mut board = candidate.board.clone();
+        if let Some(garbage) = arriving_garbage(self.incoming, 1, &board, &candidate.lock) {
+            garbage.add_to(&mut board);
+        }
+        let root = self.tree.board();
+        let spawned = self.options.spawn_rule.spawn(candidate.mv.kind.0, root).unwrap();
+        let (soft_drop, hard_drop) = crate::moves::drop_distances(
+            root, spawned, &inputs.movements
+        );
+        eval.explain(&Placement {
+            lock: candidate.lock.clone(),
+            board,
+            move_time,
+            placed: candidate.mv.kind.0,
+            soft_drop,
+            hard_drop,
+            incoming: incoming_after(self.incoming, 1),
+            opponent: self.opponent,
+            attack_multiplier: self.attack_multiplier,
+            score_multiplier: self.score_multiplier
+        })
+    }
+
+    /// The sequence of moves the bot currently expects to make.
+    pub fn plan(&self) -> Vec<(FallingPiece, LockResult)> {
+        self.tree.get_plan()
+    }
+
+    /// The moves currently being considered, best first.
+    pub fn candidates(&self) -> Vec<Candidate> {
+        let mut candidates = self.tree.get_next_candidates();
+        candidates.sort_by(|a, b| b.evaluation.cmp(&a.evaluation));
+        candidates.into_iter()
+            .map(|c| Candidate {
+                hold: c.hold,
+                placement: c.mv,
+                evaluation: c.evaluation.score()
+            })
+            .collect()
+    }
+
+    fn variations(&self, candidates: &[MoveCandidate<E::Value>]) -> Vec<Variation> {
+        let mut best: Vec<_> = candidates.iter().collect();
+        best.sort_by(|a, b| b.evaluation.cmp(&a.evaluation));
+        best.into_iter()
+            .take(self.options.alternatives as usize)
+            .map(|c| Variation {
+                hold: c.hold,
+                placement: c.mv,
+                evaluation: c.evaluation.score(),
+                plan: self.tree.get_variation(c.mv)
+            })
+            .collect()
+    }
+
     pub fn force_analysis_line(&mut self, path: Vec<FallingPiece>) {
         self.forced_analysis_lines.push(path);
     }
+
+    pub fn incoming(&self) -> Incoming {
+        self.incoming
+    }
+
+    /// Sets the garbage queued against the bot. Only nodes expanded after this call see it.
+    pub fn set_incoming(&mut self, incoming: Incoming) {
+        self.incoming = incoming;
+    }
+
+    /// Sets the opponent's board. Only nodes expanded after this call see it.
+    pub fn set_opponent(&mut self, opponent: OpponentState) {
+        self.opponent = opponent;
+    }
+
+    /// Sets the percentage attacks are scaled by. Only nodes expanded after this call see it.
+    pub fn set_attack_multiplier(&mut self, multiplier: u32) {
+        self.attack_multiplier = multiplier;
+    }
+
+    /// Sets the factor points are multiplied by, such as the level in a Blitz game. Only nodes
+    /// expanded after this call see it.
+    pub fn set_score_multiplier(&mut self, multiplier: u32) {
+        self.score_multiplier = multiplier;
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.tree.memory_usage()
+    }
+
+    /// How often the search tree was able to reuse memory from discarded generations.
+    pub fn arena_stats(&self) -> ArenaStats {
+        self.tree.arena_stats()
+    }
 }
 
 impl Thinker {
     pub fn think<E: Evaluator>(self, eval: &E) -> ThinkResult<E::Value, E::Reward> {
+        span!("expand");
         if let Err(possibilities) = self.board.get_next_piece() {
             // Next unknown (implies hold is known) => Speculate
-            if self.options.speculate {
+            if self.may_speculate() {
                 let mut children = EnumMap::new();
                 for p in possibilities {
                     let mut b = self.board.clone();
                     b.add_next_piece(p);
-                    children[p] = Some(self.make_children(b, eval));
+                    // the frontend can set the bag without the randomizer knowing, so don't let a
+                    // piece the bag allows be impossible
+                    let weight = self.board.next_piece_weight(p).max(1);
+                    children[p] = Some((weight, self.speculated_children(b, eval)));
                 }
                 ThinkResult::Speculated(self.node, children)
             } else {
@@ -199,7 +465,7 @@ impl Thinker {
             if self.options.use_hold && self.board.hold_piece.is_none() &&
                     self.board.get_next_next_piece().is_none() {
                 // Next known, hold unknown => Speculate
-                if self.options.speculate {
+                if self.may_speculate() {
                     let mut children = EnumMap::new();
                     let possibilities = {
                         let mut b = self.board.clone();
@@ -209,7 +475,8 @@ impl Thinker {
                     for p in possibilities {
                         let mut b = self.board.clone();
                         b.add_next_piece(p);
-                        children[p] = Some(self.make_children(b, eval));
+                        let weight = self.board.next_piece_weight(p).max(1);
+                        children[p] = Some((weight, self.speculated_children(b, eval)));
                     }
                     ThinkResult::Speculated(self.node, children)
                 } else {
@@ -223,63 +490,171 @@ impl Thinker {
         }
     }
 
+    /// Whether the options allow speculating as many unknown pieces deep as this node needs.
+    fn may_speculate(&self) -> bool {
+        self.options.speculate && self.options.speculation_depth.map_or(
+            true, |depth| self.unknown_pieces <= depth
+        )
+    }
+
+    /// Like `make_children`, but only keeps the best `speculation_width` children once more than
+    /// one piece is unknown.
+    fn speculated_children<E: Evaluator>(
+        &self, board: Board, eval: &E
+    ) -> Vec<ChildData<E::Value, E::Reward>> {
+        let mut children = self.make_children(board, eval);
+        if let Some(width) = self.options.speculation_width {
+            if self.unknown_pieces > 1 {
+                children.sort_by_key(
+                    |c| std::cmp::Reverse(c.evaluation.clone() + c.reward.clone())
+                );
+                children.truncate(width as usize);
+            }
+        }
+        children
+    }
+
     fn make_children<E: Evaluator>(
         &self, mut board: Board, eval: &E
     ) -> Vec<ChildData<E::Value, E::Reward>> {
-        let mut children = vec![];
+        let mut placements = vec![];
+        let mut moves = vec![];
 
         let next = board.advance_queue().unwrap();
-        let spawned = match self.options.spawn_rule.spawn(next, &board) {
-            Some(spawned) => spawned,
-            None => return children
-        };
-
-        self.add_children(&mut children, &board, eval, spawned, false);
+        if let Some(spawned) = self.options.spawn_rule.spawn(next, &board) {
+            self.add_children(&mut placements, &mut moves, &board, spawned, false);
 
-        if self.options.use_hold {
-            let hold = board.hold(next).unwrap_or_else(|| board.advance_queue().unwrap());
-            if hold == next {
-                return children
+            if self.options.use_hold {
+                let hold = board.hold(next).unwrap_or_else(|| board.advance_queue().unwrap());
+                if hold != next {
+                    if let Some(spawned) = self.options.spawn_rule.spawn(hold, &board) {
+                        self.add_children(&mut placements, &mut moves, &board, spawned, true);
+                    }
+                }
             }
-            let spawned = match self.options.spawn_rule.spawn(hold, &board) {
-                Some(spawned) => spawned,
-                None => return children
-            };
-        
-            self.add_children(&mut children, &board, eval, spawned, true);
         }
 
-        children
+        span!("evaluation");
+        let chunk_size = if self.root { 8 } else { placements.len().max(1) };
+        let chunks: Vec<_> = placements.chunks(chunk_size).collect();
+        let evaluations: Vec<_> = self.map(chunks, |batch| eval.evaluate_batch(batch))
+            .into_iter()
+            .flatten()
+            .collect();
+        placements.into_iter()
+            .zip(moves)
+            .zip(evaluations)
+            .map(|((placement, (mv, garbage)), (evaluation, reward))| ChildData {
+                evaluation,
+                reward,
+                board: placement.board,
+                mv,
+                garbage
+            })
+            .collect()
     }
 
-    fn add_children<E: Evaluator>(
+    fn add_children(
         &self,
-        children: &mut Vec<ChildData<E::Value, E::Reward>>,
+        placements: &mut Vec<Placement>,
+        moves: &mut Vec<(FallingPiece, Option<Garbage>)>,
         board: &Board,
-        eval: &E,
         spawned: FallingPiece,
         hold: bool
     ) {
-        for mv in crate::moves::find_moves(&board, spawned, self.options.mode) {
+        let found = {
+            span!("movegen");
+            crate::moves::find_moves(&board, spawned, self.options.mode, self.options.timing)
+        };
+        let children = self.map(found, |mv| {
             let can_be_hd = board.above_stack(&mv.location) &&
             board.column_heights().iter().all(|&y| y < 18);
             let mut result = board.clone();
             let lock = result.lock_piece(mv.location);
             // Don't add deaths by lock out, don't add useless mini tspins
-            if !lock.locked_out && !(can_be_hd && lock.placement_kind == PlacementKind::MiniTspin) {
-                let move_time = mv.inputs.time + if hold { 1 } else { 0 };
-                let (evaluation, reward) = eval.evaluate(
-                    &lock, &result, move_time, spawned.kind.0
-                );
-                children.push(ChildData {
-                    evaluation,
-                    reward,
-                    board: result,
-                    mv: mv.location
-                });
+            if lock.locked_out || (can_be_hd && lock.placement_kind == PlacementKind::MiniTspin) {
+                return None
+            }
+            let move_time = mv.inputs.time + if hold {
+                1 + self.options.timing.hold_delay(&mv.inputs.movements)
+            } else {
+                0
+            };
+            let (soft_drop, hard_drop) = crate::moves::drop_distances(
+                board, spawned, &mv.inputs.movements
+            );
+            let placed = self.depth + 1;
+            let garbage = arriving_garbage(self.incoming, placed, &result, &lock);
+            if let Some(garbage) = garbage {
+                garbage.add_to(&mut result);
             }
+            Some((Placement {
+                lock,
+                board: result,
+                move_time,
+                placed: spawned.kind.0,
+                soft_drop,
+                hard_drop,
+                incoming: incoming_after(self.incoming, placed),
+                opponent: self.opponent,
+                attack_multiplier: self.attack_multiplier,
+                score_multiplier: self.score_multiplier
+            }, (mv.location, garbage)))
+        });
+        for (placement, mv) in children.into_iter().flatten() {
+            placements.push(placement);
+            moves.push(mv);
         }
     }
+
+    /// Applies `f` to every item, spreading the work across the thread pool when expanding the
+    /// root.
+    /// 
+    /// The root expansion decides how quickly the bot responds after a piece is placed, and no
+    /// other thinking cycles can run until it is done, so the other threads would be idle anyway.
+    fn map<T: Send, U: Send>(&self, items: Vec<T>, f: impl Fn(T) -> U + Send + Sync) -> Vec<U> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if self.root {
+                use rayon::prelude::*;
+                return items.into_par_iter().map(f).collect()
+            }
+        }
+        items.into_iter().map(f).collect()
+    }
+}
+
+/// The garbage still queued against the bot once `placed` pieces have been placed from the
+/// root.
+fn incoming_after(incoming: Incoming, placed: u32) -> Incoming {
+    if incoming.delay > placed {
+        Incoming { lines: incoming.lines, delay: incoming.delay - placed }
+    } else {
+        Incoming::default()
+    }
+}
+
+/// The garbage that arrives right after the `placed`th piece from the root locks, if any. It is
+/// added to the child's board, so the child is evaluated and searched from as the board will look
+/// rather than as if the garbage never lands.
+///
+/// Garbage arrives once `Incoming::delay` pieces have been placed, or after the first piece
+/// if there is no delay. The piece it arrives after cancels garbage with its attack, but
+/// attacks earlier in the line of play aren't known here. Where the holes will be isn't known
+/// either, so they are assumed to line up with the lowest column.
+fn arriving_garbage(
+    incoming: Incoming, placed: u32, board: &Board, lock: &LockResult
+) -> Option<Garbage> {
+    if incoming.lines == 0 || placed != incoming.delay.max(1) {
+        return None
+    }
+    let lines = incoming.lines.saturating_sub(lock.garbage_sent);
+    if lines == 0 {
+        return None
+    }
+    let heights = board.column_heights();
+    let column = (0..10).min_by_key(|&x| heights[x]).unwrap();
+    Some(Garbage { column: column as u8, lines: lines.min(40) as u8 })
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
@@ -287,10 +662,116 @@ pub struct Info {
     pub nodes: u32,
     pub depth: u32,
     pub original_rank: u32,
+    /// How many of the nodes were already in the tree when the move was requested.
+    pub pondered_nodes: u32,
+    /// How many of the nodes depend on pieces that weren't known yet.
+    pub speculated_nodes: u32,
+    /// The evaluation of the chosen move.
+    pub evaluation: i64,
+    /// The evaluation of the best move other than the chosen one, if there was one.
+    pub second_best: Option<i64>,
+    /// Milliseconds from the move being requested to it being provided. Only measured by the
+    /// desktop interface; always 0 on the web.
+    pub time: u32,
+    pub plan: Vec<(FallingPiece, LockResult)>,
+    /// The best moves, best first, up to `Options::alternatives` of them.
+    pub alternatives: Vec<Variation>,
+    /// The evaluation of the chosen move broken down by term; see `Evaluator::explain`.
+    pub explanation: Vec<(String, i32)>
+}
+
+/// A move the bot is currently considering; see `Interface::candidates`.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct Candidate {
+    pub hold: bool,
+    pub placement: FallingPiece,
+    pub evaluation: i64
+}
+
+/// One of the moves the bot considered and the line of play it expects to follow it.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct Variation {
+    pub hold: bool,
+    pub placement: FallingPiece,
+    pub evaluation: i64,
+    /// Starts with `placement`.
     pub plan: Vec<(FallingPiece, LockResult)>
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub struct BookInfo {
     pub name: String
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::Standard;
+
+    fn has_garbage(board: &Board, lines: i32) -> bool {
+        (0..lines).all(|y| board.get_row(y).count_ones() == 9)
+    }
+
+    #[test]
+    fn garbage_stays_on_the_boards_below_the_ply_it_arrives() {
+        let eval = Standard::default();
+        let mut board = Board::new();
+        for &piece in &[Piece::I, Piece::O, Piece::T, Piece::S, Piece::Z, Piece::L, Piece::J] {
+            board.add_next_piece(piece);
+        }
+        let mut bot = BotState::<Standard>::new(board, Options::default());
+        // arrives after the second piece, so it is on the grandchildren of the root and on the
+        // boards the search continues from below them
+        bot.set_incoming(Incoming { lines: 2, delay: 2 });
+        let mut grandchild_searched = false;
+        while !grandchild_searched {
+            let thinker = bot.think().unwrap();
+            let depth = thinker.depth;
+            assert_eq!(has_garbage(&thinker.board, 2), depth == 2);
+            grandchild_searched = depth == 2;
+            let result = thinker.think(&eval);
+            if let ThinkResult::Known(_, children) = &result {
+                if depth < 2 {
+                    assert!(children.iter().all(|c| has_garbage(&c.board, 2) == (depth == 1)));
+                }
+            }
+            bot.finish_thinking(result);
+        }
+    }
+
+    #[test]
+    fn memory_is_shed_once_thinks_in_progress_finish() {
+        const THREADS: u32 = 4;
+        let limit = 4 << 20;
+        let mut board = Board::new();
+        board.add_next_piece(Piece::T);
+        let mut bot = BotState::<Standard>::new(board, Options {
+            use_hold: false,
+            max_memory: Some(limit),
+            ..Options::default()
+        });
+        let (send, recv) = std::sync::mpsc::channel();
+        let mut shed = false;
+        while !shed {
+            while bot.outstanding_thinks < THREADS {
+                let thinker = match bot.think() {
+                    Ok(thinker) => thinker,
+                    Err(_) => break
+                };
+                assert!(bot.tree.memory_usage().bytes < limit / 10 * 9);
+                let send = send.clone();
+                std::thread::spawn(move || send.send(thinker.think(&Standard::default())));
+            }
+            assert!(bot.outstanding_thinks > 0, "thinking stopped without shedding memory");
+
+            let result = recv.recv().unwrap();
+            let before = bot.tree.memory_usage().bytes;
+            bot.finish_thinking(result);
+            let after = bot.tree.memory_usage().bytes;
+            if bot.outstanding_thinks == 0 {
+                assert!(after < limit / 10 * 9);
+            }
+            shed = after < before;
+        }
+    }
+}
@@ -83,7 +83,8 @@ impl PcLooper {
                 let placements = crate::moves::find_moves(
                     &b,
                     libtetris::SpawnRule::Row19Or20.spawn(placement.kind.0, &b).unwrap(),
-                    self.mode
+                    self.mode,
+                    Default::default()
                 );
 
                 let mut mv = None;
@@ -1,6 +1,10 @@
-use libtetris::{ Board, FallingPiece, Piece, RotationState, TspinStatus, PieceMovement };
+use libtetris::{
+    Board, FallingPiece, Piece, RotationState, TspinStatus, PieceMovement, Controller
+};
 use arrayvec::ArrayVec;
 use std::collections::{ HashMap, HashSet };
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
 use serde::{ Serialize, Deserialize };
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -30,12 +34,295 @@ pub enum MovementMode {
     HardDropOnly
 }
 
+/// How long piece movements take in the game being played, in ticks. The time each placement
+/// takes affects which path is chosen and is considered by the evaluator.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timing {
+    /// Ticks to soft drop one cell.
+    pub soft_drop_speed: u32,
+    /// Ticks to auto-shift one cell after the first.
+    pub auto_repeat_rate: u32,
+    /// Ticks a direction has to be held before the piece starts to auto-shift.
+    pub das: u32,
+    /// DAS cut delay: ticks auto-shifting is paused for after a rotation or hold.
+    pub das_cut_delay: u32,
+    /// Whether DAS can be charged before the piece spawns, during entry delay or by holding the
+    /// key through the previous piece's hard drop. Auto-shifting at the start of a piece then
+    /// doesn't wait for `das`.
+    pub carry_das: bool,
+    /// Ticks between the bot pressing a key and the game applying it. The input engine reacts to
+    /// the piece landing after a soft drop, so the input after it is this much later.
+    pub input_lag: u32,
+    /// Ticks a piece resting on the stack can go without an input before it locks, or `None` if
+    /// pieces only lock on hard drop. Movements that would have to wait longer than this on the
+    /// stack, like a spin that depends on input lag being short, aren't planned.
+    pub lock_delay: Option<u32>,
+    /// Whether the game has hard drop. Without it, every placement ends by soft dropping the
+    /// piece to the ground and waiting out `lock_delay`.
+    pub hard_drop: bool,
+    /// Whether the game has soft drop. Without it, pieces can't be moved once they drop, so only
+    /// placements reachable from above the stack are found, as with `MovementMode::HardDropOnly`.
+    pub soft_drop: bool
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        // 1 cell every 2 ticks is what the battle library's default game configuration has, and
+        // it's also pretty close to Puyo Puyo Tetris's versus mode.
+        Timing {
+            soft_drop_speed: 2,
+            auto_repeat_rate: 2,
+            das: 0,
+            das_cut_delay: 0,
+            carry_das: false,
+            input_lag: 0,
+            lock_delay: None,
+            hard_drop: true,
+            soft_drop: true
+        }
+    }
+}
+
+impl Timing {
+    /// Extra ticks the movements take if the piece was held first, because auto-shifting is
+    /// paused by `das_cut_delay` after holding.
+    pub fn hold_delay(&self, movements: &[PieceMovement]) -> u32 {
+        match movements {
+            [PieceMovement::Left, PieceMovement::Left, ..] |
+            [PieceMovement::Right, PieceMovement::Right, ..] => self.das_cut_delay,
+            _ => 0
+        }
+    }
+
+    /// Ticks from the first cell of an auto-shift until it starts repeating.
+    fn das_delay(&self, first_input: bool, after_rotation: bool) -> u32 {
+        let das = if first_input && self.carry_das { 0 } else { self.das };
+        das + if after_rotation { self.das_cut_delay } else { 0 }
+    }
+
+    /// Whether a piece resting on the stack can still be moved after `ticks` without input.
+    fn before_lock(&self, ticks: u32) -> bool {
+        self.lock_delay.map_or(true, |lock_delay| ticks < lock_delay)
+    }
+
+    /// Ticks from the last movement until the piece locks, after it falls `distance` cells.
+    fn lock_time(&self, distance: i32) -> u32 {
+        if self.hard_drop {
+            0
+        } else {
+            self.soft_drop_speed * distance as u32 + self.lock_delay.unwrap_or(0)
+        }
+    }
+}
+
+/// The handling settings of the game being played, in frames; see `controller_states`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Handling {
+    /// Frames a key is held for a single tap.
+    pub tap_frames: u32,
+    /// Frames every key is released for after an input.
+    pub release_frames: u32,
+    /// Delayed auto shift: frames after pressing a direction until the piece starts to
+    /// auto-shift.
+    pub das: u32,
+    /// Auto repeat rate: frames between auto-shifts. With 0, the piece shifts to the wall at once.
+    pub arr: u32,
+    /// DAS cut delay: frames auto-shifting is paused for after a rotation or hold.
+    pub das_cut_delay: u32,
+    /// Whether the game keeps DAS charged across pieces. Shifts at the start of a piece are then
+    /// assumed to be charged already; see `controller_states`.
+    pub carry_das: bool,
+    /// Frames to soft drop one cell.
+    pub soft_drop_speed: u32,
+    /// Hard drop lockout: the earliest frame after the piece spawns that hard drop may be pressed
+    /// on, for games that ignore hard drops right after a spawn.
+    pub hard_drop_delay: u32,
+    /// Drop protection: frames hard drop isn't pressed for after soft dropping the piece to the
+    /// ground, for games that ignore hard drops right after a soft drop lands.
+    pub soft_drop_protection: u32,
+    /// Whether the game has hard drop. Without it, moves end by holding soft drop until the piece
+    /// locks, `lock_delay` frames after it lands.
+    pub hard_drop: bool,
+    /// Frames a piece resting on the stack takes to lock.
+    pub lock_delay: u32
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            tap_frames: 1,
+            release_frames: 1,
+            das: 10,
+            arr: 2,
+            das_cut_delay: 0,
+            carry_das: false,
+            soft_drop_speed: 2,
+            hard_drop_delay: 0,
+            soft_drop_protection: 0,
+            hard_drop: true,
+            lock_delay: 30
+        }
+    }
+}
+
+/// Converts a move into the controller state for each frame needed to execute it, ending with the
+/// hard drop, or with the piece locking in games without hard drop.
+/// 
+/// `movements` are usually `Move::inputs` or `InputList::movements`. `spawned` is the piece the
+/// movements start from, which is the piece that comes out of hold if `hold` is set.
+/// 
+/// Runs of shifts in one direction are done by holding the key when the piece ends up against a
+/// wall or the stack, or when auto-shifting is faster than tapping. The piece shifts once when the
+/// key is pressed, again `das` frames later and then every `arr` frames. After a rotation or hold,
+/// auto-shifting waits another `das_cut_delay` frames.
+/// 
+/// With `carry_das`, a run of shifts at the start of the piece is held from the first frame and
+/// doesn't wait for `das`, so the caller has to press that direction at least `das` frames before
+/// the piece spawns, for example by keeping it held through the previous hard drop. The piece is
+/// assumed not to fall on its own while the inputs are executed, as the move generator assumes.
+///
+/// The hard drop waits with no keys pressed until at least `hard_drop_delay` frames after the
+/// first frame and `soft_drop_protection` frames after the last soft drop landed. Without
+/// `hard_drop`, soft drop is held instead until the piece has rested on the ground for
+/// `lock_delay` frames.
+pub fn controller_states(
+    board: &Board,
+    spawned: FallingPiece,
+    hold: bool,
+    movements: &[PieceMovement],
+    handling: Handling
+) -> Vec<Controller> {
+    fn press(frames: &mut Vec<Controller>, controller: Controller, held: u32, release: u32) {
+        // a key has to be released before it can be pressed again
+        if frames.last() == Some(&controller) {
+            frames.push(Controller::default());
+        }
+        for _ in 0..held.max(1) {
+            frames.push(controller);
+        }
+        for _ in 0..release {
+            frames.push(Controller::default());
+        }
+    }
+
+    let mut frames = vec![];
+    let tap = handling.tap_frames;
+    let release = handling.release_frames;
+    if hold {
+        press(&mut frames, Controller { hold: true, ..Default::default() }, tap, release);
+    }
+
+    let mut piece = spawned;
+    let mut after_rotation = hold;
+    let mut landed_at = None;
+    let mut i = 0;
+    while i < movements.len() {
+        let movement = movements[i];
+        let mut controller = Controller::default();
+        match movement {
+            PieceMovement::Left | PieceMovement::Right => {
+                let run = movements[i..].iter().take_while(|&&m| m == movement).count();
+                for _ in 0..run {
+                    movement.apply(&mut piece, board);
+                }
+                let at_wall = !movement.apply(&mut piece.clone(), board);
+                controller.left = movement == PieceMovement::Left;
+                controller.right = movement == PieceMovement::Right;
+
+                let das = if i == 0 && handling.carry_das { 0 } else { handling.das };
+                let dcd = if after_rotation { handling.das_cut_delay } else { 0 };
+                let held = das + dcd + handling.arr * (run.max(2) as u32 - 2) + 1;
+                let tapping = run as u32 * (tap.max(1) + release);
+                if run >= 2 && (handling.arr > 0 || at_wall) && held + release < tapping {
+                    press(&mut frames, controller, held, release);
+                } else {
+                    for _ in 0..run {
+                        press(&mut frames, controller, tap, release);
+                    }
+                }
+                after_rotation = false;
+                i += run;
+                continue
+            }
+            PieceMovement::Cw => {
+                movement.apply(&mut piece, board);
+                controller.rotate_right = true;
+                press(&mut frames, controller, tap, release);
+                after_rotation = true;
+            }
+            PieceMovement::Ccw => {
+                movement.apply(&mut piece, board);
+                controller.rotate_left = true;
+                press(&mut frames, controller, tap, release);
+                after_rotation = true;
+            }
+            PieceMovement::SonicDrop => {
+                let y = piece.y;
+                movement.apply(&mut piece, board);
+                controller.soft_drop = true;
+                let held = (y - piece.y) as u32 * handling.soft_drop_speed;
+                press(&mut frames, controller, held, release);
+                landed_at = Some(frames.len() as u32 - release);
+                after_rotation = false;
+            }
+        }
+        i += 1;
+    }
+
+    if !handling.hard_drop {
+        let y = piece.y;
+        piece.sonic_drop(board);
+        let held = (y - piece.y) as u32 * handling.soft_drop_speed + handling.lock_delay;
+        let controller = Controller { soft_drop: true, ..Default::default() };
+        press(&mut frames, controller, held, release);
+        return frames;
+    }
+
+    let protected_until = landed_at.map_or(0, |f| f + handling.soft_drop_protection);
+    let earliest = handling.hard_drop_delay.max(protected_until);
+    while (frames.len() as u32) < earliest {
+        frames.push(Controller::default());
+    }
+    press(&mut frames, Controller { hard_drop: true, ..Default::default() }, tap, release);
+    frames
+}
+
+/// The number of cells the piece is soft dropped and hard dropped when `movements` are played from
+/// `spawned` and the piece is then hard dropped, as counted for guideline drop points.
+pub fn drop_distances(
+    board: &Board,
+    mut piece: FallingPiece,
+    movements: &[PieceMovement]
+) -> (u32, u32) {
+    let mut soft_drop = 0;
+    for &movement in movements {
+        let y = piece.y;
+        movement.apply(&mut piece, board);
+        if movement == PieceMovement::SonicDrop {
+            soft_drop += (y - piece.y) as u32;
+        }
+    }
+    let y = piece.y;
+    piece.sonic_drop(board);
+    (soft_drop, (y - piece.y) as u32)
+}
+
 pub fn find_moves(
     board: &Board,
     mut spawned: FallingPiece,
-    mode: MovementMode
+    mode: MovementMode,
+    timing: Timing
 ) -> Vec<Placement> {
-    let mut locks = HashMap::with_capacity(1024);
+    let mode = match mode {
+        MovementMode::ZeroG | MovementMode::ZeroGComplete if !timing.soft_drop =>
+            MovementMode::HardDropOnly,
+        mode => mode
+    };
+
+    // a fixed hasher keeps the order of the moves the same between runs
+    let mut locks = HashMap::with_capacity_and_hasher(1024, Default::default());
     let mut checked = HashSet::with_capacity(1024);
     let mut check_queue = vec![];
     let fast_mode;
@@ -56,17 +343,24 @@ pub fn find_moves(
         // to lead to new placements. Use ZeroGComplete to get these missed positions.
         fast_mode = mode == MovementMode::ZeroG;
         for (mut place, mut inputs) in starts {
+            if timing.carry_das {
+                inputs.time = inputs.time.min(charged_shift_time(&inputs.movements, timing));
+            }
             let orig_y = place.y;
             place.sonic_drop(board);
             if !fast_mode {
                 checked.insert(place);
             }
-            lock_check(place, &mut locks, inputs.clone());
+            let mut locked = inputs.clone();
+            if mode != MovementMode::TwentyG {
+                locked.time += timing.lock_time(orig_y - place.y);
+            }
+            lock_check(place, &mut locks, locked);
             if mode != MovementMode::HardDropOnly {
                 // Initialize stack movement starting positions.
                 inputs.movements.push(PieceMovement::SonicDrop);
                 if mode != MovementMode::TwentyG {
-                    inputs.time += 2 * (orig_y - place.y) as u32;
+                    inputs.time += timing.soft_drop_speed * (orig_y - place.y) as u32;
                 }
                 check_queue.push(Placement { inputs, location: place });
             }
@@ -101,13 +395,13 @@ pub fn find_moves(
             attempt(
                 board, &moves, position,
                 &mut checked, &mut check_queue,
-                mode, fast_mode,
+                mode, timing, fast_mode,
                 PieceMovement::Left, false
             );
             attempt(
                 board, &moves, position,
                 &mut checked, &mut check_queue,
-                mode, fast_mode,
+                mode, timing, fast_mode,
                 PieceMovement::Right, false
             );
 
@@ -115,14 +409,14 @@ pub fn find_moves(
                 attempt(
                     board, &moves, position,
                     &mut checked, &mut check_queue,
-                    mode, fast_mode,
+                    mode, timing, fast_mode,
                     PieceMovement::Cw, false
                 );
 
                 attempt(
                     board, &moves, position,
                     &mut checked, &mut check_queue,
-                    mode, fast_mode,
+                    mode, timing, fast_mode,
                     PieceMovement::Ccw, false
                 );
             }
@@ -131,14 +425,14 @@ pub fn find_moves(
                 attempt(
                     board, &moves, position,
                     &mut checked, &mut check_queue,
-                    mode, fast_mode,
+                    mode, timing, fast_mode,
                     PieceMovement::Left, true
                 );
 
                 attempt(
                     board, &moves, position,
                     &mut checked, &mut check_queue,
-                    mode, fast_mode,
+                    mode, timing, fast_mode,
                     PieceMovement::Right, true
                 );
             }
@@ -146,13 +440,16 @@ pub fn find_moves(
             attempt(
                 board, &moves, position,
                 &mut checked, &mut check_queue,
-                mode, fast_mode,
+                mode, timing, fast_mode,
                 PieceMovement::SonicDrop, false
             );
         }
 
         let mut position = position;
+        let mut moves = moves;
+        let orig_y = position.y;
         position.sonic_drop(board);
+        moves.time += timing.lock_time(orig_y - position.y);
         lock_check(position, &mut locks, moves);
     }
 
@@ -161,7 +458,9 @@ pub fn find_moves(
 
 fn lock_check(
     piece: FallingPiece,
-    locks: &mut HashMap<([(i32, i32); 4], TspinStatus), Placement>,
+    locks: &mut HashMap<
+        ([(i32, i32); 4], TspinStatus), Placement, BuildHasherDefault<DefaultHasher>
+    >,
     moves: InputList
 ) {
     let mut cells = piece.cells();
@@ -186,32 +485,45 @@ fn attempt(
     checked: &mut HashSet<FallingPiece>,
     check_queue: &mut Vec<Placement>,
     mode: MovementMode,
+    timing: Timing,
     fast_mode: bool,
     input: PieceMovement,
     repeat: bool
 ) -> FallingPiece {
     let orig_y = piece.y;
+    let landed = moves.movements.last() == Some(&PieceMovement::SonicDrop)
+        && input != PieceMovement::SonicDrop;
+    if landed && !timing.before_lock(timing.input_lag + 1) {
+        // The piece locks before the game gets the input
+        return piece
+    }
     if input.apply(&mut piece, board) {
         let mut moves = moves.clone();
         if input == PieceMovement::SonicDrop {
-            // We don't actually know the soft drop speed, but 1 cell every 2 ticks is probably a
-            // decent guess - that's what the battle library's default game configuration has, and
-            // it's also pretty close to Puyo Puyo Tetris's versus mode.
-            moves.time += 2 * (orig_y - piece.y) as u32;
+            moves.time += timing.soft_drop_speed * (orig_y - piece.y) as u32;
         } else {
             moves.time += 1;
         }
-        if let Some(&m) = moves.movements.last() {
-            if m == input {
-                // Delay from releasing button before pressing it again
-                moves.time += 1;
-            }
+        if landed {
+            moves.time += timing.input_lag;
+        }
+        let first_input = moves.movements.is_empty();
+        let last = moves.movements.last().copied();
+        if last == Some(input) {
+            // Delay from releasing button before pressing it again
+            moves.time += 1;
         }
         moves.movements.push(input);
-        while repeat && !moves.movements.is_full() && input.apply(&mut piece, board) {
+        let after_rotation = last == Some(PieceMovement::Cw) || last == Some(PieceMovement::Ccw);
+        let mut das_delay = timing.das_delay(first_input, after_rotation);
+        while repeat && !moves.movements.is_full()
+                && (!board.on_stack(&piece)
+                    || timing.before_lock(timing.auto_repeat_rate + das_delay))
+                && input.apply(&mut piece, board) {
             // This is the DAS left/right case
             moves.movements.push(input);
-            moves.time += 2;
+            moves.time += timing.auto_repeat_rate + das_delay;
+            das_delay = 0;
         }
         if !fast_mode || piece.tspin != TspinStatus::None || !board.above_stack(&piece) {
             // 20G causes instant plummet, but we might actually be playing a high gravity mode
@@ -233,6 +545,20 @@ fn attempt(
     piece
 }
 
+/// Ticks to shift along `movements` by holding the key from spawn with DAS already charged, if they
+/// are two or more shifts in the same direction.
+fn charged_shift_time(movements: &[PieceMovement], timing: Timing) -> u32 {
+    match movements.first() {
+        Some(&first @ PieceMovement::Left) | Some(&first @ PieceMovement::Right)
+            if movements.len() >= 2 && movements.iter().all(|&m| m == first) =>
+        {
+            1 + timing.das_delay(true, false)
+                + timing.auto_repeat_rate * (movements.len() as u32 - 1)
+        }
+        _ => u32::MAX
+    }
+}
+
 fn zero_g_starts(p: Piece) -> Vec<(FallingPiece, InputList)> {
     use Piece::*;
     use RotationState::*;
@@ -336,4 +662,64 @@ fn start(
         movements: i.iter().copied().collect(),
         time
     })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t_piece(x: i32) -> FallingPiece {
+        FallingPiece {
+            kind: libtetris::PieceState(Piece::T, RotationState::North),
+            x,
+            y: 19,
+            tspin: TspinStatus::None
+        }
+    }
+
+    #[test]
+    fn single_shifts_are_tapped() {
+        let frames = controller_states(
+            &Board::new(), t_piece(4), false, &[PieceMovement::Left], Handling::default()
+        );
+        let left = Controller { left: true, ..Default::default() };
+        let hard_drop = Controller { hard_drop: true, ..Default::default() };
+        let none = Controller::default();
+        assert_eq!(frames, vec![left, none, hard_drop, none]);
+    }
+
+    #[test]
+    fn long_shifts_are_held_for_das_and_arr() {
+        let handling = Handling { das: 3, arr: 1, ..Default::default() };
+        let movements = [PieceMovement::Left; 4];
+        let frames = controller_states(&Board::new(), t_piece(5), false, &movements, handling);
+        // one shift on press, the second 3 frames later after DAS and the other two 1 frame apart
+        let held = 3 + 2 + 1;
+        assert!(frames[..held].iter().all(|c| c.left && !c.hard_drop));
+        assert_eq!(frames[held], Controller::default());
+        assert!(frames[held + 1].hard_drop);
+        assert_eq!(frames.len(), held + 3);
+
+        // tapping is faster when DAS is long
+        let handling = Handling { das: 20, ..handling };
+        let frames = controller_states(&Board::new(), t_piece(5), false, &movements, handling);
+        assert_eq!(frames.iter().filter(|c| c.left).count(), 4);
+        assert_eq!(frames.len(), 4 * 2 + 2);
+    }
+
+    #[test]
+    fn twenty_g_cannot_climb_over_tall_columns() {
+        let mut field = [[false; 10]; 40];
+        for row in &mut field[..10] {
+            row[2] = true;
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+
+        let reaches_left_side = |mode| find_moves(&board, t_piece(5), mode, Timing::default())
+            .iter()
+            .any(|p| p.location.cells().iter().any(|&(x, _)| x < 2));
+        assert!(reaches_left_side(MovementMode::ZeroG));
+        assert!(!reaches_left_side(MovementMode::TwentyG));
+    }
 }
\ No newline at end of file
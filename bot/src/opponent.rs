@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+use crate::evaluation::Incoming;
+
+/// Opponent pieces to observe before predicting its attacks.
+const MIN_PIECES: usize = 10;
+/// Opponent pieces the prediction is based on, so it follows changes in the opponent's pace.
+const WINDOW: usize = 50;
+
+/// What the bot has recently seen of its opponent, used to estimate when its next attack arrives
+/// and how big it is; see `Interface::observe_opponent`.
+///
+/// The opponent's speed is measured in pieces per piece the bot places rather than per second,
+/// since that is how the delay of incoming garbage is counted.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OpponentModel {
+    /// The lines sent by each of the opponent's last `WINDOW` placements, along with how many
+    /// pieces the bot had placed when it was observed.
+    placements: VecDeque<(u32, u32)>,
+    /// Pieces the bot has placed since the first observation.
+    own_pieces: u32,
+    /// Pieces the opponent has placed since its last attack.
+    since_attack: u32
+}
+
+impl OpponentModel {
+    /// Records an opponent placement that sent `attack` lines.
+    pub fn observe(&mut self, attack: u32) {
+        if self.placements.len() == WINDOW {
+            self.placements.pop_front();
+        }
+        self.placements.push_back((attack, self.own_pieces));
+        self.since_attack += 1;
+        if attack > 0 {
+            self.since_attack = 0;
+        }
+    }
+
+    /// Records a placement of the bot's own.
+    pub fn own_piece(&mut self) {
+        if !self.placements.is_empty() {
+            self.own_pieces += 1;
+        }
+    }
+
+    /// The opponent's expected next attack, as garbage incoming after the number of pieces the
+    /// bot can place until it is sent. `None` until enough has been observed to tell.
+    pub fn expected_attack(&self) -> Option<Incoming> {
+        let pieces = self.placements.len();
+        let own_pieces = self.own_pieces - self.placements.front().map_or(0, |&(_, own)| own);
+        let mut sizes: Vec<_> = self.placements.iter()
+            .map(|&(attack, _)| attack)
+            .filter(|&attack| attack > 0)
+            .collect();
+        if pieces < MIN_PIECES || own_pieces == 0 || sizes.is_empty() {
+            return None
+        }
+        // the median attack is the opponent's typical spike, without one huge spike skewing it
+        sizes.sort_unstable();
+        let lines = sizes[sizes.len() / 2];
+
+        let pieces_per_attack = pieces as f64 / sizes.len() as f64;
+        let remaining = (pieces_per_attack - self.since_attack as f64).max(0.0);
+        let speed = pieces as f64 / own_pieces as f64;
+        Some(Incoming {
+            lines,
+            // an overdue opponent is still at least a piece away from attacking
+            delay: ((remaining / speed).round() as u32).max(1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(model: &mut OpponentModel, attacks: &[u32]) {
+        for &attack in attacks {
+            model.observe(attack);
+            model.own_piece();
+        }
+    }
+
+    #[test]
+    fn prediction_follows_recent_attacks() {
+        let mut model = OpponentModel::default();
+        play(&mut model, &[4; WINDOW]);
+        play(&mut model, &[0, 1].repeat(WINDOW / 2));
+        assert_eq!(model.placements.len(), WINDOW);
+        assert_eq!(model.expected_attack().map(|i| i.lines), Some(1));
+    }
+
+    #[test]
+    fn overdue_opponent_is_not_predicted_to_attack_immediately() {
+        let mut model = OpponentModel::default();
+        play(&mut model, &[0, 0, 0, 0, 2].repeat(2));
+        play(&mut model, &[0; 20]);
+        assert_eq!(model.expected_attack(), Some(Incoming { lines: 2, delay: 1 }));
+    }
+}
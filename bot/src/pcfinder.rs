@@ -0,0 +1,41 @@
+use libtetris::*;
+use crate::{ Move, Options };
+use crate::puzzle::{ Puzzle, Goal };
+
+/// The most states searched for each perfect clear height, so a move is never delayed for long.
+const MAX_NODES: u32 = 200_000;
+
+/// Searches for a perfect clear of at most 4 rows using only the pieces in the hold slot and the
+/// next queue, returning the moves that reach it.
+///
+/// The search tries the lowest perfect clear first. Placements above the perfect clear height are
+/// never tried, and boards whose empty cells can't be split into tetrominoes are abandoned, which
+/// keeps the search exact but small enough to run before each move.
+pub fn find_pc(board: &Board, options: &Options) -> Option<Vec<Move>> {
+    let highest = *board.column_heights().iter().max().unwrap();
+    if highest > 4 {
+        return None
+    }
+    let filled: i32 = (0..highest).map(|y| board.get_row(y).count_ones() as i32).sum();
+    if filled == 0 {
+        return None
+    }
+    let queue: Vec<_> = board.next_queue().collect();
+    let pieces = queue.len() as i32 + board.hold_piece.is_some() as i32;
+    for height in highest.max(1)..=4 {
+        let empty = height * 10 - filled;
+        if empty % 4 != 0 || empty / 4 > pieces {
+            continue
+        }
+        let mut puzzle = Puzzle::new(board.clone(), queue.clone(), Goal::PerfectClear);
+        puzzle.use_hold = options.use_hold;
+        puzzle.mode = options.mode;
+        puzzle.spawn_rule = options.spawn_rule;
+        puzzle.max_height = Some(height);
+        puzzle.max_nodes = MAX_NODES;
+        if let Ok(moves) = puzzle.solve() {
+            return Some(moves)
+        }
+    }
+    None
+}
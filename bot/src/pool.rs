@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use libtetris::*;
+use opening_book::Book;
+use crate::evaluation::Evaluator;
+use crate::{ Interface, Options };
+
+/// Launches bots that share one set of worker threads.
+/// 
+/// Every bot launched with `Interface::launch` creates `Options::threads` worker threads of its
+/// own, so running many bots at once oversubscribes the CPU. Bots launched from a pool do all of
+/// their thinking on the pool's threads instead, keeping the total within the pool's budget no
+/// matter how many bots are running. Each bot still has a mostly idle thread of its own that
+/// handles its messages, and `Options::threads` still limits how many thinking cycles one bot
+/// runs at once.
+/// 
+/// The returned `Interface`s are used the same way as standalone ones. The pool's threads stop
+/// once the pool and all bots launched from it have been dropped.
+pub struct BotPool {
+    pool: Arc<rayon::ThreadPool>
+}
+
+impl BotPool {
+    /// Creates a pool with the given number of worker threads.
+    pub fn new(threads: usize) -> Self {
+        if threads == 0 {
+            panic!("Invalid number of threads: 0");
+        }
+        BotPool {
+            pool: Arc::new(rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build().unwrap())
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    /// Launches a bot thinking on this pool's threads. See `Interface::launch`.
+    pub fn launch(
+        &self,
+        board: Board,
+        options: Options,
+        evaluator: impl Evaluator + Send + 'static,
+        book: Option<Arc<Book>>
+    ) -> Interface {
+        Interface::launch_in(Some(self.pool.clone()), board, options, evaluator, book)
+    }
+}
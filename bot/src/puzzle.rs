@@ -0,0 +1,288 @@
+use std::collections::HashSet;
+use libtetris::*;
+use serde::{ Serialize, Deserialize };
+use crate::Move;
+use crate::moves::{ MovementMode, Timing };
+
+/// What a puzzle asks for.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Goal {
+    /// Empty the whole board. The queue doesn't have to be used up.
+    PerfectClear,
+    /// Clear every one of these rows of the starting board, counted from the bottom.
+    ClearRows(Vec<i32>),
+    /// Place every piece in the queue without topping out.
+    Survive
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum PuzzleError {
+    /// Every sequence of placements was tried and none of them reach the goal.
+    Unsolvable,
+    /// The search gave up after visiting `Puzzle::max_nodes` states.
+    NodeLimit
+}
+
+/// A board with an exact, finite queue and a goal to reach using only those pieces.
+///
+/// Unlike the bot, which plans for an endless queue and picks the move that looks best, the
+/// puzzle solver searches every sequence of placements until one reaches the goal. States that
+/// were already reached through a different order of placements are skipped, and placements
+/// leaving a lower stack are tried first.
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    /// The starting board. Its hold piece is available; its next queue is ignored.
+    pub board: Board,
+    pub queue: Vec<Piece>,
+    pub goal: Goal,
+    pub use_hold: bool,
+    pub mode: MovementMode,
+    pub spawn_rule: SpawnRule,
+    /// Placements leaving the stack higher than this are never tried. For perfect clears this is
+    /// the number of rows the perfect clear will clear.
+    pub max_height: Option<i32>,
+    pub max_nodes: u32
+}
+
+impl Puzzle {
+    pub fn new(board: Board, queue: Vec<Piece>, goal: Goal) -> Self {
+        Puzzle {
+            board, queue, goal,
+            use_hold: true,
+            mode: MovementMode::ZeroG,
+            spawn_rule: SpawnRule::Row19Or20,
+            max_height: None,
+            max_nodes: 1_000_000
+        }
+    }
+
+    /// Searches for a sequence of moves reaching the goal. The moves are in the order they are
+    /// played; `Move::hold` tells whether to hold before the move.
+    pub fn solve(&self) -> Result<Vec<Move>, PuzzleError> {
+        let mut search = Search {
+            puzzle: self,
+            visited: HashSet::new(),
+            nodes: 0,
+            path: vec![]
+        };
+        let rows = (0..40).collect();
+        let height = self.max_height.unwrap_or(0);
+        match search.search(self.board.clone(), self.board.hold_piece, 0, rows, height) {
+            Ok(true) => Ok(search.path),
+            Ok(false) => Err(PuzzleError::Unsolvable),
+            Err(e) => Err(e)
+        }
+    }
+}
+
+struct Search<'a> {
+    puzzle: &'a Puzzle,
+    visited: HashSet<([[bool; 10]; 40], Option<Piece>, usize, Vec<i32>)>,
+    nodes: u32,
+    path: Vec<Move>
+}
+
+impl Search<'_> {
+    /// `rows` holds the starting board row each current row came from, or -1 for new rows.
+    /// `height` is the number of rows a perfect clear still has to clear, which goes down as
+    /// lines are cleared.
+    fn search(
+        &mut self, board: Board, hold: Option<Piece>, next: usize, rows: Vec<i32>, height: i32
+    ) -> Result<bool, PuzzleError> {
+        let puzzle = self.puzzle;
+        let queue = &puzzle.queue;
+        let pieces_left = queue.len() - next + hold.is_some() as usize;
+        let highest = *board.column_heights().iter().max().unwrap();
+        let height = height.max(highest);
+        match &puzzle.goal {
+            Goal::PerfectClear => {
+                if highest == 0 {
+                    return Ok(true);
+                }
+                // every row up to the top of the stack has to be filled
+                let filled: usize = board.get_field().iter()
+                    .map(|row| row.iter().filter(|&&c| c).count())
+                    .sum();
+                let empty = 10 * height as usize - filled;
+                if empty > 4 * pieces_left {
+                    return Ok(false);
+                }
+                let pieces = queue[next..].iter().chain(hold.as_ref());
+                if !column_parity_reachable(&board, height, pieces) {
+                    return Ok(false);
+                }
+                if puzzle.max_height.is_some() && !walls_divisible(&board, height) {
+                    return Ok(false);
+                }
+            }
+            Goal::ClearRows(targets) => if targets.iter().all(|t| !rows.contains(t)) {
+                return Ok(true);
+            }
+            Goal::Survive => if next == queue.len() {
+                return Ok(true);
+            }
+        }
+        if next >= queue.len() {
+            return Ok(false);
+        }
+
+        if !self.visited.insert((board.get_field(), hold, next, rows.clone())) {
+            return Ok(false);
+        }
+        self.nodes += 1;
+        if self.nodes > puzzle.max_nodes {
+            return Err(PuzzleError::NodeLimit);
+        }
+
+        // (piece to place, hold afterwards, next queue index afterwards, whether hold was used)
+        let current = queue[next];
+        let mut options = vec![(current, hold, next + 1, false)];
+        if puzzle.use_hold {
+            match hold {
+                Some(held) if held != current => {
+                    options.push((held, Some(current), next + 1, true));
+                }
+                Some(_) => {}
+                None => if let Some(&after) = queue.get(next + 1) {
+                    options.push((after, Some(current), next + 2, true));
+                }
+            }
+        }
+
+        for (piece, hold, next, used_hold) in options {
+            let spawned = match puzzle.spawn_rule.spawn(piece, &board) {
+                Some(spawned) => spawned,
+                None => continue
+            };
+            let mut children = vec![];
+            let moves = crate::moves::find_moves(&board, spawned, puzzle.mode, Timing::default());
+            for mv in moves {
+                let mut result = board.clone();
+                let lock = result.lock_piece(mv.location);
+                let height = height - lock.cleared_lines.len() as i32;
+                // the rows a perfect clear needs shrink with every line cleared along the way
+                let max_height = match puzzle.goal {
+                    Goal::PerfectClear => puzzle.max_height.map(|_| height),
+                    _ => puzzle.max_height
+                };
+                if lock.locked_out || max_height.map_or(false, |max| {
+                    result.column_heights().iter().any(|&h| h > max)
+                }) {
+                    continue
+                }
+                let mut rows = rows.clone();
+                for &y in lock.cleared_lines.iter().rev() {
+                    rows.remove(y as usize);
+                    rows.push(-1);
+                }
+                children.push((mv, result, rows, height));
+            }
+            children.sort_by_key(|(_, result, _, _)| {
+                *result.column_heights().iter().max().unwrap()
+            });
+
+            for (mv, result, rows, height) in children {
+                self.path.push(Move {
+                    inputs: mv.inputs.movements,
+                    expected_location: mv.location,
+                    hold: used_hold
+                });
+                if self.search(result, hold, next, rows, height)? {
+                    return Ok(true);
+                }
+                self.path.pop();
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Checks that the remaining pieces can even out the empty cells in even and odd columns below
+/// `height`.
+///
+/// Line clears don't change which columns the empty cells are in. An I piece covers 4 cells of
+/// one column parity when vertical, T, L and J pieces can cover 3 of one and 1 of the other,
+/// and every other placement covers 2 of each.
+fn column_parity_reachable<'p>(
+    board: &Board, height: i32, pieces: impl Iterator<Item=&'p Piece>
+) -> bool {
+    let mut imbalance = 0i32;
+    for y in 0..height {
+        let row = board.get_row(y);
+        if row.is_full() {
+            continue
+        }
+        for x in 0..10 {
+            if !row.get(x) {
+                imbalance += if x % 2 == 0 { 1 } else { -1 };
+            }
+        }
+    }
+    let reachable: i32 = pieces.map(|&p| match p {
+        Piece::I => 4,
+        Piece::T | Piece::L | Piece::J => 2,
+        Piece::O | Piece::S | Piece::Z => 0
+    }).sum();
+    imbalance.abs() <= reachable
+}
+
+/// Checks that the empty cells below `height` can still be filled exactly with tetrominoes.
+///
+/// A column that is filled in every row below `height` that isn't full separates the empty cells
+/// on either side, even after line clears, so the empty cells between two such walls must be a
+/// multiple of 4.
+fn walls_divisible(board: &Board, height: i32) -> bool {
+    let rows: Vec<_> = (0..height).map(|y| board.get_row(y)).filter(|r| !r.is_full()).collect();
+    let mut empty = 0;
+    for x in 0..10 {
+        let column_empty = rows.iter().filter(|r| !r.get(x)).count();
+        if column_empty == 0 {
+            if empty % 4 != 0 {
+                return false
+            }
+            empty = 0;
+        }
+        empty += column_empty;
+    }
+    empty % 4 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board_with_rows(rows: &[[bool; 10]]) -> Board {
+        let mut field = [[false; 10]; 40];
+        field[..rows.len()].copy_from_slice(rows);
+        let mut board = Board::new();
+        board.set_field(field);
+        board
+    }
+
+    #[test]
+    fn perfect_clear_height_drops_after_line_clears() {
+        // the first I clears the bottom row, leaving a single row that the second I finishes
+        let row = [false, false, false, false, true, true, true, true, true, true];
+        let mut puzzle = Puzzle::new(
+            board_with_rows(&[row, row]), vec![Piece::I, Piece::I], Goal::PerfectClear
+        );
+        puzzle.max_height = Some(2);
+        assert_eq!(puzzle.solve().map(|moves| moves.len()), Ok(2));
+    }
+
+    #[test]
+    fn column_parity_prunes_unreachable_boards() {
+        // only a vertical I fills the empty column, which nothing else in the queue can replace
+        let row = [false, true, true, true, true, true, true, true, true, true];
+        let mut puzzle = Puzzle::new(
+            board_with_rows(&[row; 4]), vec![Piece::O], Goal::PerfectClear
+        );
+        puzzle.max_height = Some(4);
+        puzzle.max_nodes = 0;
+        assert_eq!(puzzle.solve(), Err(PuzzleError::Unsolvable));
+
+        puzzle.queue = vec![Piece::I];
+        puzzle.max_nodes = 1_000;
+        assert_eq!(puzzle.solve().map(|moves| moves.len()), Ok(1));
+    }
+}
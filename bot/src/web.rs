@@ -128,12 +128,138 @@ impl Interface {
         }
     }
 
+    /// Replaces the whole game state, including the hold piece and the next queue.
+    /// 
+    /// Use this to recover when the game has diverged from what the bot expects, for example
+    /// after a misdrop or a missed piece. The bot keeps its options and evaluator but throws away
+    /// its search tree. `queue` starts with the piece the bot should place next. With the 7-bag
+    /// randomizer, `bag` holds the pieces that were left in the bag before the first piece of
+    /// `queue` was drawn from it, or is empty for a full bag; the other randomizers rebuild their
+    /// state from `queue` instead. If hold is enabled and both `hold` and `queue` are empty, this
+    /// does nothing.
+    pub fn resync(
+        &self,
+        field: [[bool; 10]; 40],
+        b2b_active: bool,
+        combo: u32,
+        hold: Option<Piece>,
+        queue: Vec<Piece>,
+        bag: enumset::EnumSet<Piece>
+    ) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::Resync {
+                field, b2b: b2b_active, combo, hold, queue, bag
+            }).unwrap();
+        }
+    }
+
+    /// Tells the bot that the given placement was played, whether or not the bot suggested it.
+    /// 
+    /// Use this for analysis and for games where a human and the bot take turns controlling the
+    /// same board. If the bot had already considered the placement, it keeps the relevant part of
+    /// its search tree; otherwise it starts over from the resulting position. A pending move
+    /// request is then answered for the new position. Placements that couldn't have been played
+    /// with the current piece or hold piece are ignored.
+    pub fn play_move(&self, placement: FallingPiece) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::PlayMove(placement)).unwrap();
+        }
+    }
+
+    /// Changes the options of the running bot. New thinking cycles use the new options.
+    /// 
+    /// The number of threads can't be changed and keeps its original value. Changing `use_hold`,
+    /// `mode` or `spawn_rule` makes the bot throw away its search tree. Hold can't be enabled
+    /// while the queue is empty and no piece is held.
+    pub fn set_options(&self, options: Options) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::SetOptions(options)).unwrap();
+        }
+    }
+
     /// Specifies a line that Cold Clear should analyze before making any moves.
     pub fn force_analysis_line(&self, path: Vec<FallingPiece>) {
         if let Some(worker) = &self.0 {
             worker.send(&BotMsg::ForceAnalysisLine(path)).unwrap();
         }
     }
+
+    /// Tells the bot how many lines of garbage are queued against it and how many pieces it can
+    /// place before they arrive.
+    /// 
+    /// The bot plays more defensively the more garbage is queued and the sooner it arrives. Call
+    /// this again with zero lines once the garbage has been received or cancelled.
+    pub fn set_incoming_garbage(&self, lines: u32, delay: u32) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::IncomingGarbage(crate::evaluation::Incoming {
+                lines, delay
+            })).unwrap();
+        }
+    }
+
+    /// Tells the bot its opponent placed a piece that sent `attack` lines of garbage, or 0 if the
+    /// piece didn't attack. While no garbage is queued against the bot, it plays as if the
+    /// opponent's expected next attack were incoming.
+    pub fn observe_opponent(&self, attack: u32) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::OpponentPlaced(attack)).unwrap();
+        }
+    }
+
+    /// Tells the bot how tall the opponent's stack is and how much garbage is queued against it,
+    /// for evaluators that plan spikes.
+    pub fn set_opponent_state(&self, height: u32, incoming: u32) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::OpponentState(crate::evaluation::OpponentState {
+                height, incoming
+            })).unwrap();
+        }
+    }
+
+    /// Tells the bot the percentage its attacks are currently scaled by, for example when margin
+    /// time has made attacks stronger. The default is 100.
+    pub fn set_attack_multiplier(&self, multiplier: u32) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::AttackMultiplier(multiplier)).unwrap();
+        }
+    }
+
+    /// Tells the bot the factor points are currently multiplied by, such as the level in a Blitz
+    /// game. The default is 1.
+    pub fn set_score_multiplier(&self, multiplier: u32) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::ScoreMultiplier(multiplier)).unwrap();
+        }
+    }
+
+    /// Stops the bot from thinking until `resume` is called, for example while the game is
+    /// paused.
+    /// 
+    /// Thinking cycles that are already running are allowed to finish. Moves are not provided
+    /// while paused; a move requested while paused is provided after resuming.
+    pub fn pause(&self) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::Pause).unwrap();
+        }
+    }
+
+    /// Resumes thinking after `pause`.
+    pub fn resume(&self) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::Resume).unwrap();
+        }
+    }
+
+    /// Cancels a move requested with `request_next_move` that hasn't been provided yet. The bot
+    /// keeps its search tree and continues thinking.
+    /// 
+    /// If the move was provided before the bot received the cancellation, it can still be
+    /// retrieved with `poll_next_move`.
+    pub fn cancel_next_move(&self) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::CancelMove).unwrap();
+        }
+    }
 }
 
 fn bot_thread<E>(
@@ -168,7 +294,7 @@ fn bot_thread<E>(
         // (books tend to be very large, possibly not useful?)
 
         loop {
-            let new_tasks = state.think(&eval, |mv, info| send.send(&Some((mv, info))));
+            let new_tasks = state.think(&eval, |mv, info, _| send.send(&Some((mv, info))));
             for task in new_tasks {
                 task_send.send(task).ok().unwrap();
             }
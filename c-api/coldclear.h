@@ -1,7 +1,14 @@
+#ifndef COLDCLEAR_H
+#define COLDCLEAR_H
+
 #include <stdbool.h>
 #include <stdint.h>
 #include <stddef.h>
 
+#ifdef __cplusplus
+extern "C" {
+#endif
+
 typedef struct CCAsyncBot CCAsyncBot;
 
 typedef enum CCPiece {
@@ -32,6 +39,14 @@ typedef enum CCSpawnRule {
     CC_ROW_21_AND_FALL,
 } CCSpawnRule;
 
+typedef enum CCRandomizer {
+    CC_SEVEN_BAG,
+    CC_FOURTEEN_BAG,
+    CC_MEMORYLESS,
+    /* History of 4 pieces with rerolls, as in Tetris: The Grand Master */
+    CC_TGM
+} CCRandomizer;
+
 typedef enum CCBotPollStatus {
     CC_MOVE_PROVIDED,
     CC_WAITING,
@@ -71,6 +86,8 @@ typedef struct CCMove {
     uint32_t nodes;
     uint32_t depth;
     uint32_t original_rank;
+    /* How many of the nodes were already searched when the move was requested */
+    uint32_t pondered_nodes;
 } CCMove;
 
 typedef struct CCOptions {
@@ -82,6 +99,21 @@ typedef struct CCOptions {
     uint32_t threads;
     bool use_hold;
     bool speculate;
+    /* Whether to keep thinking while no move is requested. Disable to save CPU between moves. */
+    bool ponder;
+    /* The randomizer generating the pieces, which the bot assumes when guessing upcoming pieces */
+    CCRandomizer randomizer;
+    /* Limit on the approximate memory used by the search tree in bytes, or 0 for no limit. */
+    uint64_t max_memory;
+    /* Most pieces per second the bot provides moves at, or 0 for no limit. */
+    double pps_limit;
+    /* How much the time between moves varies under pps_limit, as a fraction of the average. */
+    double pps_jitter;
+    /* How many unknown pieces deep the bot speculates, or 0 for no limit. */
+    uint32_t speculation_depth;
+    /* Placements kept per possible piece when speculating past the first unknown piece, or 0 to
+     * keep all of them. */
+    uint32_t speculation_width;
 } CCOptions;
 
 typedef struct CCWeights {
@@ -122,6 +154,27 @@ typedef struct CCWeights {
     bool use_bag;
     bool timed_jeopardy;
     bool stack_pc_damage;
+    /* Heights above which the top_half/jeopardy and top_quarter weights start to apply. */
+    int32_t top_half_start;
+    int32_t top_quarter_start;
+    /* When incoming garbage could push the center columns above this height, the bot picks the
+     * move with the biggest spike instead of the best evaluated move. */
+    int32_t panic_height;
+    /* Column to keep the well in, from 0 (left wall) to 9 (right wall), or -1 to use the lowest
+     * column. */
+    int32_t preferred_well;
+    int32_t imminent_tslot;
+    int32_t dependencies;
+    int32_t pc_opportunity;
+    int32_t garbage_rows;
+    int32_t garbage_hole_depth;
+    int32_t b2b_break;
+    int32_t hard_clear;
+    int32_t incoming_height;
+    /* Apply when an attack could top out the opponent, and to back-to-back and combo kept going
+     * the closer the opponent is to topping out. See cc_set_opponent_state. */
+    int32_t lethal_spike;
+    int32_t banked_spike;
 } CCWeights;
 
 /* Launches a bot thread with a blank board, empty queue, and all seven pieces in the bag, using the
@@ -167,6 +220,47 @@ void cc_destroy_async(CCAsyncBot *bot);
  */
 void cc_reset_async(CCAsyncBot *bot, bool *field, bool b2b, uint32_t combo);
 
+/* Replaces the whole game state, including the hold piece and the next queue.
+ * 
+ * Use this to recover when the game has diverged from what the bot expects, for example after a
+ * misdrop or a missed piece, instead of destroying and relaunching the bot. The bot keeps its
+ * options and weights but throws away its search tree.
+ * 
+ * `hold` may be `NULL` if there is no hold piece. `queue` points to an array of `queue_length`
+ * pieces starting with the piece the bot should place next. `bag_remain` is a bit field of the
+ * pieces that were left in the bag before the first piece of the queue was drawn from it, as in
+ * `cc_launch_with_board_async`. If hold is enabled and both the hold piece and queue are empty,
+ * this does nothing.
+ */
+void cc_resync_async(
+    CCAsyncBot *bot, bool *field, bool b2b, uint32_t combo, CCPiece *hold, CCPiece *queue,
+    uint32_t queue_length, uint32_t bag_remain
+);
+
+/* Changes the options of the running bot. New thinking cycles use the new options.
+ * 
+ * The number of threads can't be changed and keeps its original value. Changing `use_hold`,
+ * `mode` or `spawn_rule` makes the bot throw away its search tree. Hold can't be enabled while
+ * the queue is empty and no piece is held.
+ */
+void cc_set_options_async(CCAsyncBot *bot, CCOptions *options);
+
+/* Tells the bot that the given placement was played, whether or not the bot suggested it.
+ * 
+ * Use this for analysis and for games where a human and the bot take turns controlling the same
+ * board. If the bot had already considered the placement, it keeps the relevant part of its search
+ * tree; otherwise it starts over from the resulting position. A pending move request is then
+ * answered for the new position. Placements that couldn't have been played with the current piece
+ * or hold piece are ignored.
+ * 
+ * The placement is given by the cells it occupies, with (0, 0) being the bottom left. Returns
+ * false if the cells don't form the given piece.
+ */
+bool cc_play_move_async(
+    CCAsyncBot *bot, CCPiece piece, CCTspinStatus tspin, uint8_t expected_x[4],
+    uint8_t expected_y[4]
+);
+
 /* Adds a new piece to the end of the queue.
  * 
  * If speculation is enabled, the piece must be in the bag. For example, if you start a new
@@ -198,6 +292,48 @@ void cc_add_next_piece_async(CCAsyncBot *bot, CCPiece piece);
  */
 void cc_request_next_move(CCAsyncBot *bot, uint32_t incoming);
 
+/* Tells the bot how many lines of garbage are queued against it and how many pieces it can place
+ * before they arrive. The bot plays more defensively the more garbage is queued and the sooner it
+ * arrives. Call this again with zero lines once the garbage has been received or cancelled.
+ */
+void cc_set_incoming_garbage(CCAsyncBot *bot, uint32_t lines, uint32_t delay);
+
+/* Tells the bot its opponent placed a piece that sent `attack` lines of garbage, or 0 if it didn't
+ * attack. While no garbage is queued against the bot, it plays as if the opponent's expected next
+ * attack were incoming.
+ */
+void cc_observe_opponent(CCAsyncBot *bot, uint32_t attack);
+
+/* Tells the bot the height of the opponent's highest column and how many lines of garbage are
+ * queued against it, for the lethal_spike and banked_spike weights.
+ */
+void cc_set_opponent_state(CCAsyncBot *bot, uint32_t height, uint32_t incoming);
+
+/* Tells the bot the percentage its attacks are currently scaled by, for example when margin time
+ * has made attacks stronger. The default is 100.
+ */
+void cc_set_attack_multiplier(CCAsyncBot *bot, uint32_t multiplier);
+
+/* Stops the bot from thinking until `cc_resume_async` is called, for example while the game is
+ * paused. Moves are not provided while paused; a move requested while paused is provided after
+ * resuming.
+ */
+void cc_pause_async(CCAsyncBot *bot);
+
+/* Resumes thinking after `cc_pause_async`. */
+void cc_resume_async(CCAsyncBot *bot);
+
+/* Cancels a move requested with `cc_request_next_move` that hasn't been provided yet. The bot keeps
+ * its search tree and continues thinking. If the move was provided before the bot received the
+ * cancellation, it can still be retrieved with `cc_poll_next_move`.
+ */
+void cc_cancel_next_move(CCAsyncBot *bot);
+
+/* Writes the number of nodes in the bot's search tree and approximately how many bytes it takes up
+ * to `nodes` and `bytes`. The numbers can be slightly out of date.
+ */
+void cc_memory_usage(CCAsyncBot *bot, uint32_t *nodes, uint64_t *bytes);
+
 /* Checks to see if the bot has provided the previously requested move yet.
  * 
  * The returned move contains both a path and the expected location of the placed piece. The
@@ -243,3 +379,16 @@ void cc_default_weights(CCWeights *weights);
 
 /* Returns the fast game config weights in the weights parameter */
 void cc_fast_weights(CCWeights *weights);
+
+/* Returns the weights of a named preset in the weights parameter.
+ *
+ * Valid names are "default", "fast", "aggressive", "defensive", "pc-hunter", "downstacker" and
+ * "4-wide". Returns false and leaves the weights untouched if the name is not recognized.
+ */
+bool cc_preset_weights(const char *name, CCWeights *weights);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -1,6 +1,10 @@
 use std::mem::MaybeUninit;
+use std::ffi::CStr;
+use std::os::raw::c_char;
 use enumset::EnumSet;
-use libtetris::{ Piece, TspinStatus, PieceMovement, SpawnRule, FallingPiece, LockResult, Board };
+use libtetris::{
+    Piece, TspinStatus, PieceMovement, SpawnRule, RandomizerKind, FallingPiece, LockResult, Board
+};
 use cold_clear::moves::MovementMode;
 use cold_clear::PcPriority;
 
@@ -99,6 +103,13 @@ cenum! {
         CC_PC_FASTEST => Some(PcPriority::Fastest),
         CC_PC_ATTACK => Some(PcPriority::HighestAttack)
     }
+
+    enum CCRandomizer => RandomizerKind {
+        CC_SEVEN_BAG => RandomizerKind::SevenBag,
+        CC_FOURTEEN_BAG => RandomizerKind::FourteenBag,
+        CC_MEMORYLESS => RandomizerKind::Memoryless,
+        CC_TGM => RandomizerKind::Tgm
+    }
 }
 
 #[repr(C)]
@@ -121,6 +132,7 @@ struct CCMove {
     nodes: u32,
     depth: u32,
     original_rank: u32,
+    pondered_nodes: u32,
 }
 
 #[repr(C)]
@@ -143,6 +155,13 @@ struct CCOptions {
     threads: u32,
     use_hold: bool,
     speculate: bool,
+    ponder: bool,
+    randomizer: CCRandomizer,
+    max_memory: u64,
+    pps_limit: f64,
+    pps_jitter: f64,
+    speculation_depth: u32,
+    speculation_width: u32,
 }
 
 #[repr(C)]
@@ -184,6 +203,20 @@ struct CCWeights {
     use_bag: bool,
     timed_jeopardy: bool,
     stack_pc_damage: bool,
+    top_half_start: i32,
+    top_quarter_start: i32,
+    panic_height: i32,
+    preferred_well: i32,
+    imminent_tslot: i32,
+    dependencies: i32,
+    pc_opportunity: i32,
+    garbage_rows: i32,
+    garbage_hole_depth: i32,
+    b2b_break: i32,
+    hard_clear: i32,
+    incoming_height: i32,
+    lethal_spike: i32,
+    banked_spike: i32,
 }
 
 fn convert_hold(hold: *mut CCPiece) -> Option<Piece> {
@@ -201,10 +234,34 @@ fn convert_from_c_options(options: &CCOptions) -> cold_clear::Options {
         min_nodes: options.min_nodes,
         use_hold: options.use_hold,
         speculate: options.speculate,
+        ponder: options.ponder,
         pcloop: options.pcloop.into(),
+        pc_finder: false,
         mode: options.mode.into(),
         spawn_rule: options.spawn_rule.into(),
-        threads: options.threads
+        randomizer: options.randomizer.into(),
+        threads: options.threads,
+        alternatives: 0,
+        candidate_interval: None,
+        timing: Default::default(),
+        preset: None,
+        move_time: None,
+        game_time: None,
+        time_increment: 0,
+        seed: None,
+        max_memory: if options.max_memory == 0 { None } else { Some(options.max_memory) },
+        pps_limit: if options.pps_limit > 0.0 { Some(options.pps_limit) } else { None },
+        pps_jitter: options.pps_jitter,
+        speculation_depth: if options.speculation_depth == 0 {
+            None
+        } else {
+            Some(options.speculation_depth)
+        },
+        speculation_width: if options.speculation_width == 0 {
+            None
+        } else {
+            Some(options.speculation_width)
+        }
     }
 }
 
@@ -218,15 +275,21 @@ fn convert_from_c_weights(weights: &CCWeights) -> cold_clear::evaluation::Standa
         top_half: weights.top_half,
         top_quarter: weights.top_quarter,
         jeopardy: weights.jeopardy,
+        incoming_height: weights.incoming_height,
         cavity_cells: weights.cavity_cells,
         cavity_cells_sq: weights.cavity_cells_sq,
         overhang_cells: weights.overhang_cells,
         overhang_cells_sq: weights.overhang_cells_sq,
         covered_cells: weights.covered_cells,
         covered_cells_sq: weights.covered_cells_sq,
+        dependencies: weights.dependencies,
+        garbage_rows: weights.garbage_rows,
+        garbage_hole_depth: weights.garbage_hole_depth,
         tslot: weights.tslot,
         well_depth: weights.well_depth,
         max_well_depth: weights.max_well_depth,
+        pc_opportunity: weights.pc_opportunity,
+        imminent_tslot: weights.imminent_tslot,
         well_column: weights.well_column,
 
         b2b_clear: weights.b2b_clear,
@@ -243,10 +306,21 @@ fn convert_from_c_weights(weights: &CCWeights) -> cold_clear::evaluation::Standa
         combo_garbage: weights.combo_garbage,
         move_time: weights.move_time,
         wasted_t: weights.wasted_t,
+        b2b_break: weights.b2b_break,
+        hard_clear: weights.hard_clear,
+        lethal_spike: weights.lethal_spike,
+        banked_spike: weights.banked_spike,
 
         use_bag: weights.use_bag,
         timed_jeopardy: weights.timed_jeopardy,
         stack_pc_damage: weights.stack_pc_damage,
+        top_half_start: weights.top_half_start,
+        top_quarter_start: weights.top_quarter_start,
+        panic_height: weights.panic_height,
+        preferred_well: match weights.preferred_well {
+            column @ 0..=9 => Some(column as usize),
+            _ => None
+        },
         sub_name: None
     }
 }
@@ -284,6 +358,55 @@ extern "C" fn cc_reset_async(
     bot.reset(*field, b2b, combo);
 }
 
+#[no_mangle]
+unsafe extern "C" fn cc_resync_async(
+    bot: &mut CCAsyncBot,
+    field: &[[bool; 10]; 40],
+    b2b: bool,
+    combo: u32,
+    hold: *mut CCPiece,
+    queue: *const CCPiece,
+    queue_length: u32,
+    bag_remain: u32
+) {
+    let queue = if queue.is_null() {
+        vec![]
+    } else {
+        std::slice::from_raw_parts(queue, queue_length as usize).iter()
+            .map(|&p| p.into())
+            .collect()
+    };
+    bot.resync(
+        *field, b2b, combo, convert_hold(hold), queue, EnumSet::from_bits(bag_remain as u128)
+    );
+}
+
+#[no_mangle]
+extern "C" fn cc_set_options_async(bot: &mut CCAsyncBot, options: &CCOptions) {
+    bot.set_options(convert_from_c_options(options));
+}
+
+#[no_mangle]
+extern "C" fn cc_play_move_async(
+    bot: &mut CCAsyncBot,
+    piece: CCPiece,
+    tspin: CCTspinStatus,
+    expected_x: &[u8; 4],
+    expected_y: &[u8; 4]
+) -> bool {
+    let mut cells = [(0, 0); 4];
+    for i in 0..4 {
+        cells[i] = (expected_x[i] as i32, expected_y[i] as i32);
+    }
+    match FallingPiece::from_cells(piece.into(), cells, tspin.into()) {
+        Some(placement) => {
+            bot.play_move(placement);
+            true
+        }
+        None => false
+    }
+}
+
 #[no_mangle]
 extern "C" fn cc_add_next_piece_async(bot: &mut CCAsyncBot, piece: CCPiece) {
     bot.add_next_piece(piece.into());
@@ -294,6 +417,48 @@ extern "C" fn cc_request_next_move(bot: &mut CCAsyncBot, incoming: u32) {
     bot.request_next_move(incoming);
 }
 
+#[no_mangle]
+extern "C" fn cc_set_incoming_garbage(bot: &mut CCAsyncBot, lines: u32, delay: u32) {
+    bot.set_incoming_garbage(lines, delay);
+}
+
+#[no_mangle]
+extern "C" fn cc_observe_opponent(bot: &mut CCAsyncBot, attack: u32) {
+    bot.observe_opponent(attack);
+}
+
+#[no_mangle]
+extern "C" fn cc_set_opponent_state(bot: &mut CCAsyncBot, height: u32, incoming: u32) {
+    bot.set_opponent_state(height, incoming);
+}
+
+#[no_mangle]
+extern "C" fn cc_set_attack_multiplier(bot: &mut CCAsyncBot, multiplier: u32) {
+    bot.set_attack_multiplier(multiplier);
+}
+
+#[no_mangle]
+extern "C" fn cc_pause_async(bot: &mut CCAsyncBot) {
+    bot.pause();
+}
+
+#[no_mangle]
+extern "C" fn cc_resume_async(bot: &mut CCAsyncBot) {
+    bot.resume();
+}
+
+#[no_mangle]
+extern "C" fn cc_cancel_next_move(bot: &mut CCAsyncBot) {
+    bot.cancel_next_move();
+}
+
+#[no_mangle]
+extern "C" fn cc_memory_usage(bot: &mut CCAsyncBot, nodes: &mut u32, bytes: &mut u64) {
+    let usage = bot.memory_usage();
+    *nodes = usage.nodes;
+    *bytes = usage.bytes;
+}
+
 fn convert_plan_placement(
     (falling_piece, lock_result): &(FallingPiece, LockResult)
 ) -> CCPlanPlacement {
@@ -367,6 +532,11 @@ fn convert(m: cold_clear::Move, info: cold_clear::Info) -> CCMove {
             cold_clear::Info::Normal(info) => info.original_rank as u32,
             cold_clear::Info::PcLoop(_) => 0,
             cold_clear::Info::Book(_) => 0,
+        },
+        pondered_nodes: match &info {
+            cold_clear::Info::Normal(info) => info.pondered_nodes,
+            cold_clear::Info::PcLoop(_) => 0,
+            cold_clear::Info::Book(_) => 0,
         }
     }
 }
@@ -414,10 +584,17 @@ unsafe extern "C" fn cc_default_options(options: *mut CCOptions) {
         min_nodes: o.min_nodes,
         use_hold: o.use_hold,
         speculate: o.speculate,
+        ponder: o.ponder,
         pcloop: o.pcloop.into(),
         mode: o.mode.into(),
         spawn_rule: o.spawn_rule.into(),
-        threads: o.threads
+        threads: o.threads,
+        randomizer: o.randomizer.into(),
+        max_memory: o.max_memory.unwrap_or(0),
+        pps_limit: o.pps_limit.unwrap_or(0.0),
+        pps_jitter: o.pps_jitter,
+        speculation_depth: o.speculation_depth.unwrap_or(0),
+        speculation_width: o.speculation_width.unwrap_or(0)
     });
 }
 
@@ -431,15 +608,21 @@ fn convert_weights(w: cold_clear::evaluation::Standard) -> CCWeights {
         top_half: w.top_half,
         top_quarter: w.top_quarter,
         jeopardy: w.jeopardy,
+        incoming_height: w.incoming_height,
         cavity_cells: w.cavity_cells,
         cavity_cells_sq: w.cavity_cells_sq,
         overhang_cells: w.overhang_cells,
         overhang_cells_sq: w.overhang_cells_sq,
         covered_cells: w.covered_cells,
         covered_cells_sq: w.covered_cells_sq,
+        dependencies: w.dependencies,
+        garbage_rows: w.garbage_rows,
+        garbage_hole_depth: w.garbage_hole_depth,
         tslot: w.tslot,
         well_depth: w.well_depth,
         max_well_depth: w.max_well_depth,
+        pc_opportunity: w.pc_opportunity,
+        imminent_tslot: w.imminent_tslot,
         well_column: w.well_column,
 
         b2b_clear: w.b2b_clear,
@@ -456,10 +639,18 @@ fn convert_weights(w: cold_clear::evaluation::Standard) -> CCWeights {
         combo_garbage: w.combo_garbage,
         move_time: w.move_time,
         wasted_t: w.wasted_t,
+        b2b_break: w.b2b_break,
+        hard_clear: w.hard_clear,
+        lethal_spike: w.lethal_spike,
+        banked_spike: w.banked_spike,
 
         use_bag: w.use_bag,
         timed_jeopardy: w.timed_jeopardy,
-        stack_pc_damage: w.stack_pc_damage
+        stack_pc_damage: w.stack_pc_damage,
+        top_half_start: w.top_half_start,
+        top_quarter_start: w.top_quarter_start,
+        panic_height: w.panic_height,
+        preferred_well: w.preferred_well.map_or(-1, |column| column as i32)
     }
 }
 
@@ -471,4 +662,17 @@ unsafe extern "C" fn cc_default_weights(weights: *mut CCWeights) {
 #[no_mangle]
 unsafe extern "C" fn cc_fast_weights(weights: *mut CCWeights) {
     weights.write(convert_weights(cold_clear::evaluation::Standard::fast_config()));
-}
\ No newline at end of file
+}
+
+#[no_mangle]
+unsafe extern "C" fn cc_preset_weights(name: *const c_char, weights: *mut CCWeights) -> bool {
+    let preset = CStr::from_ptr(name).to_str().ok()
+        .and_then(cold_clear::evaluation::Preset::from_name);
+    match preset {
+        Some(preset) => {
+            weights.write(convert_weights(cold_clear::evaluation::Standard::preset(preset)));
+            true
+        }
+        None => false
+    }
+}
@@ -1,26 +1,30 @@
 use libtetris::{ Board, ColoredRow, FallingPiece, Controller };
-use battle::{ Event, PieceMoveExecutor };
+use battle::{ Event, PieceMoveExecutor, TrainingRecord };
 use std::time::{ Instant, Duration };
 use cold_clear::evaluation::Evaluator;
 
-pub struct BotInput<E: Evaluator> {
+pub struct BotInput<'a, E: Evaluator> {
     pub controller: Controller,
     executing: Option<(FallingPiece, PieceMoveExecutor)>,
     time_budget: Duration,
     bot: cold_clear::BotState<E>,
-    eval: E
+    eval: &'a E,
+    /// The moves chosen so far, if training data is being recorded. Their outcome is filled in
+    /// once the game ends.
+    pub records: Option<Vec<TrainingRecord>>
 }
 
 const THINK_AMOUNT: Duration = Duration::from_millis(4);
 
-impl<E: Evaluator> BotInput<E> {
-    pub fn new(board: Board, eval: E) -> Self {
+impl<'a, E: Evaluator> BotInput<'a, E> {
+    pub fn new(board: Board, eval: &'a E, record: bool) -> Self {
         let mut this = BotInput {
             controller: Controller::default(),
             executing: None,
             time_budget: Duration::new(0, 0),
             bot: cold_clear::BotState::new(board, Default::default()),
-            eval
+            eval,
+            records: if record { Some(vec![]) } else { None }
         };
         for _ in 0..180 {
             // equivalent of 3 realtime seconds of thinking
@@ -35,7 +39,7 @@ impl<E: Evaluator> BotInput<E> {
             let start = Instant::now();
             match self.bot.think() {
                 Ok(thinker) => {
-                    self.bot.finish_thinking(thinker.think(&self.eval));
+                    self.bot.finish_thinking(thinker.think(self.eval));
                 }
                 Err(_) => {
                     // can't think anymore
@@ -60,7 +64,20 @@ impl<E: Evaluator> BotInput<E> {
                     self.bot.add_next_piece(*new_in_queue);
                     if self.executing.is_none() {
                         let exec = &mut self.executing;
-                        self.bot.next_move(&self.eval, None, incoming, |mv, inf| {
+                        let records = &mut self.records;
+                        let board = self.bot.board().clone();
+                        self.bot.next_move(self.eval, None, incoming, |mv, inf| {
+                            if let (Some(records), cold_clear::Info::Normal(normal)) =
+                                (records, &inf)
+                            {
+                                records.push(TrainingRecord {
+                                    board,
+                                    placement: mv.expected_location,
+                                    hold: mv.hold,
+                                    evaluation: normal.evaluation,
+                                    won: false
+                                });
+                            }
                             info = Some(inf);
                             *exec = Some((
                                 mv.expected_location,
@@ -1,84 +1,256 @@
 use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicU64, Ordering };
 use serde::{ Serialize, Deserialize };
-use battle::{ Replay, Battle, GameConfig };
-use cold_clear::evaluation::Evaluator;
-use rand::prelude::*;
+use battle::{
+    Replay, Battle, GameConfig, GameStats, Seeds, StatsRecorder, Summary, TrainingRecord
+};
+use cold_clear::evaluation::{ DynEvaluator, Evaluator, EvaluatorConfig };
+use cold_clear::Opener;
 use statrs::distribution::{ Binomial, Univariate };
-use libflate::deflate;
+use std::io::Write;
 
 mod input;
 use input::BotInput;
+mod sprt;
+use sprt::{ Sprt, elo_estimate };
+mod tournament;
+
+/// Command line settings.
+///
+/// `--p1 FILE` and `--p2 FILE` load the configurations to compare from JSON `EvaluatorConfig`s.
+/// Without them, the standard weights are compared to `changed::Standard`.
+/// `--games N` sets the most games to play (default 20000). Games are played in pairs on the
+/// same seed with the sides swapped, the first pair on `--seed N` (default 0) and each following
+/// pair on the next seed.
+/// `--elo0 E` and `--elo1 E` set the hypotheses of the SPRT, that p2 is `E` Elo stronger than p1
+/// (default 0 and 5), and `--alpha A` and `--beta B` its error rates (default 0.05). The
+/// comparison stops as soon as either hypothesis is accepted.
+/// `--threads N` sets the number of games played at once (default 12).
+/// `--tournament FILE` plays a tournament between the `tournament::Entrant`s listed in `FILE`
+/// instead, with `--pairs N` pairs of games per pairing (default 10). Every entrant meets every
+/// other unless `--swiss N` is given, in which case `N` Swiss rounds are played.
+/// `--training-data FILE` writes every move both bots make to `FILE` as a `TrainingRecord`, along
+/// with who won the game, for training networks on.
+struct Settings {
+    p1: Option<EvaluatorConfig>,
+    p2: Option<EvaluatorConfig>,
+    games: u32,
+    seed: u64,
+    elo0: f64,
+    elo1: f64,
+    alpha: f64,
+    beta: f64,
+    threads: usize,
+    tournament: Option<String>,
+    pairs: u32,
+    swiss: Option<u32>,
+    training_data: Option<String>
+}
+
+fn parse_args() -> Settings {
+    let mut settings = Settings {
+        p1: None,
+        p2: None,
+        games: 20000,
+        seed: 0,
+        elo0: 0.0,
+        elo1: 5.0,
+        alpha: 0.05,
+        beta: 0.05,
+        threads: 12,
+        tournament: None,
+        pairs: 10,
+        swiss: None,
+        training_data: None
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--p1" => settings.p1 = Some(read_config(args.next(), "--p1")),
+            "--p2" => settings.p2 = Some(read_config(args.next(), "--p2")),
+            "--games" => settings.games = parse_number(args.next(), "--games"),
+            "--seed" => settings.seed = parse_number(args.next(), "--seed"),
+            "--elo0" => settings.elo0 = parse_number(args.next(), "--elo0"),
+            "--elo1" => settings.elo1 = parse_number(args.next(), "--elo1"),
+            "--alpha" => settings.alpha = parse_number(args.next(), "--alpha"),
+            "--beta" => settings.beta = parse_number(args.next(), "--beta"),
+            "--threads" => settings.threads = parse_number(args.next(), "--threads"),
+            "--tournament" => settings.tournament = Some(
+                args.next().expect("--tournament requires a file")
+            ),
+            "--pairs" => settings.pairs = parse_number(args.next(), "--pairs"),
+            "--swiss" => settings.swiss = Some(parse_number(args.next(), "--swiss")),
+            "--training-data" => settings.training_data = Some(
+                args.next().expect("--training-data requires a file")
+            ),
+            _ => eprintln!("ignoring unknown argument {}", arg)
+        }
+    }
+    settings
+}
+
+fn parse_number<T: std::str::FromStr>(arg: Option<String>, flag: &str) -> T {
+    arg.and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| panic!("{} requires a number", flag))
+}
+
+fn read_config(arg: Option<String>, flag: &str) -> EvaluatorConfig {
+    let path = arg.unwrap_or_else(|| panic!("{} requires a file", flag));
+    let file = std::fs::File::open(&path).unwrap_or_else(
+        |e| panic!("could not open {}: {}", path, e)
+    );
+    serde_json::from_reader(file).unwrap_or_else(
+        |e| panic!("{} contained invalid data: {}", path, e)
+    )
+}
 
 fn main() {
     use cold_clear::evaluation::Standard;
     use cold_clear::evaluation::changed;
 
-    let p1_eval = Standard::default();
-
-    let p2_eval = changed::Standard::default();
+    let settings = parse_args();
+    if let Some(path) = &settings.tournament {
+        tournament::run(path, &settings);
+        return
+    }
+    let record = settings.training_data.is_some();
+    let mut training_data = settings.training_data.as_ref().map(|path| std::io::BufWriter::new(
+        std::fs::File::create(path).unwrap_or_else(|e| panic!("could not create {}: {}", path, e))
+    ));
+    let sprt = Sprt::new(settings.elo0, settings.elo1, settings.alpha, settings.beta);
 
     let (send, recv) = std::sync::mpsc::channel();
 
-    for _ in 0..12 {
-        let p1_eval = p1_eval.clone();
-        let p2_eval = p2_eval.clone();
+    let next_pair = Arc::new(AtomicU64::new(settings.seed));
+    for _ in 0..settings.threads {
+        let p1_eval: Box<DynEvaluator> = match &settings.p1 {
+            Some(config) => config.build(),
+            None => Box::new(Standard::default())
+        };
+        let p2_eval: Box<DynEvaluator> = match &settings.p2 {
+            Some(config) => config.build(),
+            None => Box::new(changed::Standard::default())
+        };
+        let next_pair = next_pair.clone();
         let send = send.clone();
         std::thread::spawn(move || loop {
-            if send.send(do_battle(p1_eval.clone(), p2_eval.clone())).is_err() {
+            let seed = next_pair.fetch_add(1, Ordering::SeqCst);
+            let first = do_battle(&p1_eval, &p2_eval, record, seed);
+            let second = do_battle(&p2_eval, &p1_eval, record, seed);
+            if send.send((false, first)).is_err() || send.send((true, second)).is_err() {
                 break
-            };
+            }
         });
     }
 
+    // openers in ./openers/ are recognized in every game for per-opener win rates
+    let openers = load_openers("openers");
+
     let mut p1_wins = 0;
     let mut p2_wins = 0;
+    let mut stats = vec![];
+
+    // per-game stats are written as they come in so partial runs can still be analyzed
+    let mut csv = std::fs::File::create("stats.csv").unwrap();
+    writeln!(csv, "{}", GameStats::CSV_HEADER).unwrap();
 
-    let games = 20000;
+    let games = settings.games;
+    let mut result = None;
 
-    while p1_wins + p2_wins < games {
+    while p1_wins + p2_wins < games && result.is_none() {
         match recv.recv() {
-            Ok((replay, p1_won)) => {
-                if p1_won {
+            Ok((swapped, (replay, first_won, mut game_stats, records))) => {
+                // stats and results are always from the point of view of p1's configuration
+                if swapped {
+                    std::mem::swap(&mut game_stats.p1, &mut game_stats.p2);
+                }
+                if first_won != swapped {
                     p1_wins += 1;
                 } else {
                     p2_wins += 1;
                 }
 
-                let mut encoder = deflate::Encoder::new(
-                    std::fs::File::create("recent-game.dat"
-                ).unwrap());
-                bincode::serialize_into(&mut encoder, &replay).unwrap();
-                encoder.finish().unwrap();
+                replay.save("recent-game.dat").unwrap();
+
+                for player in [&mut game_stats.p1, &mut game_stats.p2].iter_mut() {
+                    player.opener = Opener::recognize(&openers, &player.opening)
+                        .map(|o| o.name.clone());
+                }
+                writeln!(csv, "{}", game_stats.to_csv()).unwrap();
+                if let Some(out) = &mut training_data {
+                    for record in &records {
+                        record.write(out).unwrap();
+                    }
+                    out.flush().unwrap();
+                }
+                stats.push(game_stats);
 
+                let (elo, low, high) = elo_estimate(p2_wins, p1_wins);
+                let (lower, upper) = sprt.bounds();
                 println!("{} of {}", p1_wins + p2_wins, games);
                 println!("{} - {}", p1_wins, p2_wins);
+                println!(
+                    "p2 Elo {:+.1} ({:+.1} to {:+.1}), LLR {:.2} ({:.2} to {:.2})",
+                    elo, low, high, sprt.llr(p2_wins, p1_wins), lower, upper
+                );
+                result = sprt.result(p2_wins, p1_wins);
             },
             Err(_) => break
         }
     }
-    let distr = Binomial::new(0.5, p1_wins + p2_wins).unwrap();
+    match result {
+        Some(true) => println!("H1 accepted: p2 is at least {} Elo stronger", settings.elo1),
+        Some(false) => println!("H0 accepted: p2 is at most {} Elo stronger", settings.elo0),
+        None => println!("No hypothesis accepted after {} games", p1_wins + p2_wins)
+    }
+    let distr = Binomial::new(0.5, (p1_wins + p2_wins) as u64).unwrap();
     let p = distr.cdf(p1_wins as f64);
     println!("p = {:.4}", p);
+
+    let summary = Summary::new(&stats);
+    serde_json::to_writer_pretty(std::fs::File::create("stats.json").unwrap(), &summary).unwrap();
 }
 
-fn do_battle(p1: impl Evaluator + Clone, p2: impl Evaluator + Clone) -> (InfoReplay, bool) {
-    let mut battle = Battle::new(
-        GameConfig::default(), GameConfig::default(),
-        thread_rng().gen(), thread_rng().gen(), thread_rng().gen()
-    );
+fn load_openers(dir: &str) -> Vec<Opener> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![]
+    };
+    entries.filter_map(|entry| {
+        let path = entry.ok()?.path();
+        match Opener::load(&path) {
+            Ok(opener) => Some(opener),
+            Err(e) => {
+                eprintln!("Could not load opener {}: {}", path.display(), e);
+                None
+            }
+        }
+    }).collect()
+}
+
+/// Plays a game on the pieces and garbage of `seed`.
+fn do_battle<E: Evaluator>(
+    p1: &E, p2: &E, record: bool, seed: u64
+) -> (InfoReplay, bool, GameStats, Vec<TrainingRecord>) {
+    let seeds = Seeds::from_master(seed);
+    let mut battle = Battle::with_seeds(GameConfig::default(), GameConfig::default(), &seeds);
 
     battle.replay.p1_name = format!("Cold Clear\n{}", p1.name());
     battle.replay.p2_name = format!("Cold Clear\n{}", p2.name());
 
-    let mut p1 = BotInput::new(battle.player_1.board.to_compressed(), p1);
-    let mut p2 = BotInput::new(battle.player_2.board.to_compressed(), p2);
+    let mut p1 = BotInput::new(battle.player_1.board.to_compressed(), p1, record);
+    let mut p2 = BotInput::new(battle.player_2.board.to_compressed(), p2, record);
 
     let mut p1_info_updates = VecDeque::new();
     let mut p2_info_updates = VecDeque::new();
+    let mut stats = StatsRecorder::new();
 
     let p1_won;
     'battle: loop {
         let update = battle.update(p1.controller, p2.controller);
+        stats.update(&update);
         p1_info_updates.push_back(p1.update(
             &battle.player_1.board, &update.player_1.events,
             battle.player_1.garbage_queue
@@ -116,11 +288,17 @@ fn do_battle(p1: impl Evaluator + Clone, p2: impl Evaluator + Clone) -> (InfoRep
         p2_info_updates.push_back(None);
     }
 
+    let records = p1.records.into_iter().flatten()
+        .map(|record| TrainingRecord { won: p1_won, ..record })
+        .chain(p2.records.into_iter().flatten()
+            .map(|record| TrainingRecord { won: !p1_won, ..record }))
+        .collect();
+
     (InfoReplay {
         replay: battle.replay,
         p1_info_updates,
         p2_info_updates
-    }, p1_won)
+    }, p1_won, stats.finish(), records)
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -128,4 +306,10 @@ pub struct InfoReplay {
     pub replay: Replay,
     pub p1_info_updates: VecDeque<Option<cold_clear::Info>>,
     pub p2_info_updates: VecDeque<Option<cold_clear::Info>>
+}
+
+impl InfoReplay {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        self.replay.save_with(&(&self.p1_info_updates, &self.p2_info_updates), path)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,67 @@
+/// A sequential probability ratio test of whether the second configuration is stronger than the
+/// first, for stopping a comparison as soon as the result is significant.
+///
+/// The null hypothesis is that the second configuration is `elo0` Elo stronger than the first and
+/// the alternative that it is `elo1` Elo stronger. Games are treated as independent wins and
+/// losses, even though they are played in pairs on the same seeds.
+pub struct Sprt {
+    p0: f64,
+    p1: f64,
+    lower: f64,
+    upper: f64
+}
+
+impl Sprt {
+    /// `alpha` and `beta` are the chances of wrongly accepting the alternative and the null
+    /// hypothesis respectively.
+    pub fn new(elo0: f64, elo1: f64, alpha: f64, beta: f64) -> Self {
+        Sprt {
+            p0: win_probability(elo0),
+            p1: win_probability(elo1),
+            lower: (beta / (1.0 - alpha)).ln(),
+            upper: ((1.0 - beta) / alpha).ln()
+        }
+    }
+
+    /// The log-likelihood ratio of the alternative against the null hypothesis, given the wins
+    /// and losses of the second configuration.
+    pub fn llr(&self, wins: u32, losses: u32) -> f64 {
+        wins as f64 * (self.p1 / self.p0).ln()
+            + losses as f64 * ((1.0 - self.p1) / (1.0 - self.p0)).ln()
+    }
+
+    pub fn bounds(&self) -> (f64, f64) {
+        (self.lower, self.upper)
+    }
+
+    /// `Some(true)` if the alternative hypothesis is accepted, `Some(false)` if the null
+    /// hypothesis is accepted and `None` if more games are needed.
+    pub fn result(&self, wins: u32, losses: u32) -> Option<bool> {
+        let llr = self.llr(wins, losses);
+        if llr >= self.upper {
+            Some(true)
+        } else if llr <= self.lower {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+fn win_probability(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+fn elo(score: f64) -> f64 {
+    let score = score.max(1e-6).min(1.0 - 1e-6);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Estimates how many Elo stronger the second configuration is from its wins and losses,
+/// returning the estimate and the bounds of its 95% confidence interval.
+pub fn elo_estimate(wins: u32, losses: u32) -> (f64, f64, f64) {
+    let games = (wins + losses).max(1) as f64;
+    let score = wins as f64 / games;
+    let margin = 1.96 * (score * (1.0 - score) / games).sqrt();
+    (elo(score), elo(score - margin), elo(score + margin))
+}
@@ -0,0 +1,206 @@
+use std::collections::HashSet;
+use std::sync::{ Arc, Mutex };
+use serde::Deserialize;
+use cold_clear::evaluation::{ DynEvaluator, EvaluatorConfig };
+use crate::{ Settings, do_battle };
+
+/// A configuration taking part in a tournament. Tournament files are JSON lists of these.
+#[derive(Deserialize)]
+pub struct Entrant {
+    pub name: String,
+    pub config: EvaluatorConfig
+}
+
+/// Plays a tournament between the entrants listed in the file at `path` and prints the
+/// crosstable with ratings.
+///
+/// Every pairing plays `settings.pairs` pairs of games, each pair on a fresh seed with the sides
+/// swapped. Without `settings.swiss` every entrant meets every other; with it, that many Swiss
+/// rounds are played instead, pairing entrants with similar scores that haven't met yet.
+pub(crate) fn run(path: &str, settings: &Settings) {
+    let file = std::fs::File::open(path).unwrap_or_else(
+        |e| panic!("could not open {}: {}", path, e)
+    );
+    let entrants: Vec<Entrant> = serde_json::from_reader(file).unwrap_or_else(
+        |e| panic!("{} contained invalid data: {}", path, e)
+    );
+    assert!(entrants.len() >= 2, "a tournament needs at least two entrants");
+    let evaluators = Arc::new(entrants.iter().map(|e| e.config.build()).collect::<Vec<_>>());
+
+    let mut table = Crosstable::new(entrants.len());
+    let mut seed = settings.seed;
+    match settings.swiss {
+        None => {
+            let mut pairings = vec![];
+            for a in 0..entrants.len() {
+                for b in a+1..entrants.len() {
+                    pairings.push((a, b));
+                }
+            }
+            play(&evaluators, &pairings, settings, &mut seed, &mut table);
+        }
+        Some(rounds) => {
+            let mut met = HashSet::new();
+            for round in 0..rounds {
+                let pairings = swiss_pairings(&table, &met);
+                println!("Round {} of {}", round + 1, rounds);
+                for &(a, b) in &pairings {
+                    println!("  {} vs {}", entrants[a].name, entrants[b].name);
+                    met.insert((a, b));
+                    met.insert((b, a));
+                }
+                play(&evaluators, &pairings, settings, &mut seed, &mut table);
+            }
+        }
+    }
+
+    table.print(&entrants);
+}
+
+/// Plays every pairing on a pool of `settings.threads` threads, recording the results in `table`.
+fn play(
+    evaluators: &Arc<Vec<Box<DynEvaluator>>>,
+    pairings: &[(usize, usize)],
+    settings: &Settings,
+    seed: &mut u64,
+    table: &mut Crosstable
+) {
+    let mut games = vec![];
+    for &(a, b) in pairings {
+        for _ in 0..settings.pairs {
+            games.push((a, b, *seed));
+            games.push((b, a, *seed));
+            *seed += 1;
+        }
+    }
+    let total = games.len();
+    // popped from the back, so reverse to play in schedule order
+    games.reverse();
+    let queue = Arc::new(Mutex::new(games));
+
+    let (send, recv) = std::sync::mpsc::channel();
+    for _ in 0..settings.threads {
+        let queue = queue.clone();
+        let evaluators = evaluators.clone();
+        let send = send.clone();
+        std::thread::spawn(move || loop {
+            let next = queue.lock().unwrap().pop();
+            let (p1, p2, seed) = match next {
+                Some(game) => game,
+                None => break
+            };
+            let (_, p1_won, _, _) = do_battle(&evaluators[p1], &evaluators[p2], false, seed);
+            if send.send((p1, p2, p1_won)).is_err() {
+                break
+            }
+        });
+    }
+    drop(send);
+
+    for (i, (p1, p2, p1_won)) in recv.into_iter().enumerate() {
+        if p1_won {
+            table.wins[p1][p2] += 1;
+        } else {
+            table.wins[p2][p1] += 1;
+        }
+        println!("{} of {}", i + 1, total);
+    }
+}
+
+/// Pairs entrants in order of score, each with the next entrant it hasn't met yet. When every
+/// remaining entrant has already been met, the rematch is allowed. With an odd number of entrants
+/// the lowest scoring unpaired one sits the round out.
+fn swiss_pairings(table: &Crosstable, met: &HashSet<(usize, usize)>) -> Vec<(usize, usize)> {
+    let ratings = table.ratings();
+    let mut order: Vec<_> = (0..table.wins.len()).collect();
+    order.sort_by(|&a, &b| table.score(b).cmp(&table.score(a))
+        .then(ratings[b].partial_cmp(&ratings[a]).unwrap()));
+
+    let mut pairings = vec![];
+    while order.len() >= 2 {
+        let a = order.remove(0);
+        let opponent = order.iter().position(|&b| !met.contains(&(a, b))).unwrap_or(0);
+        pairings.push((a, order.remove(opponent)));
+    }
+    pairings
+}
+
+struct Crosstable {
+    /// `wins[a][b]` is the number of games `a` won against `b`.
+    wins: Vec<Vec<u32>>
+}
+
+impl Crosstable {
+    fn new(entrants: usize) -> Self {
+        Crosstable {
+            wins: vec![vec![0; entrants]; entrants]
+        }
+    }
+
+    fn score(&self, entrant: usize) -> u32 {
+        self.wins[entrant].iter().sum()
+    }
+
+    /// Bradley-Terry ratings on the Elo scale, averaging 0.
+    ///
+    /// Every pairing that was played counts an extra drawn game so that entrants that won or lost
+    /// every game still get finite ratings.
+    fn ratings(&self) -> Vec<f64> {
+        let n = self.wins.len();
+        let played = |a: usize, b: usize| (self.wins[a][b] + self.wins[b][a]) as f64;
+        let prior = |a: usize, b: usize| if played(a, b) > 0.0 { 1.0 } else { 0.0 };
+        let mut strength = vec![1.0; n];
+        for _ in 0..1000 {
+            let mut next: Vec<f64> = (0..n).map(|a| {
+                let opponents = (0..n).filter(|&b| b != a);
+                let wins: f64 = opponents.clone()
+                    .map(|b| self.wins[a][b] as f64 + prior(a, b) / 2.0)
+                    .sum();
+                let denominator: f64 = opponents
+                    .map(|b| (played(a, b) + prior(a, b)) / (strength[a] + strength[b]))
+                    .sum();
+                if denominator > 0.0 { wins / denominator } else { strength[a] }
+            }).collect();
+            let mean_log = next.iter().map(|s| s.ln()).sum::<f64>() / n as f64;
+            for s in &mut next {
+                *s /= mean_log.exp();
+            }
+            let converged = next.iter().zip(&strength).all(|(a, b)| (a - b).abs() < 1e-9);
+            strength = next;
+            if converged {
+                break
+            }
+        }
+        strength.iter().map(|s| 400.0 * s.log10()).collect()
+    }
+
+    fn print(&self, entrants: &[Entrant]) {
+        let ratings = self.ratings();
+        let mut order: Vec<_> = (0..entrants.len()).collect();
+        order.sort_by(|&a, &b| ratings[b].partial_cmp(&ratings[a]).unwrap());
+
+        let width = entrants.iter().map(|e| e.name.len()).max().unwrap_or(0).max(4);
+        print!("{:>3}  {:width$}  {:>7}  {:>9}", "#", "Name", "Rating", "Score", width = width);
+        for i in 0..order.len() {
+            print!("  {:>7}", i + 1);
+        }
+        println!();
+        for (rank, &a) in order.iter().enumerate() {
+            let lost: u32 = (0..entrants.len()).map(|b| self.wins[b][a]).sum();
+            print!(
+                "{:>3}  {:width$}  {:>+7.1}  {:>9}",
+                rank + 1, entrants[a].name, ratings[a],
+                format!("{}-{}", self.score(a), lost),
+                width = width
+            );
+            for &b in &order {
+                if a == b || self.wins[a][b] + self.wins[b][a] == 0 {
+                    print!("  {:>7}", "");
+                } else {
+                    print!("  {:>7}", format!("{}-{}", self.wins[a][b], self.wins[b][a]));
+                }
+            }
+            println!();
+        }
+    }
+}
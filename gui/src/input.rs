@@ -1,5 +1,5 @@
 use libtetris::*;
-use battle::{ Event, PieceMoveExecutor };
+use battle::{ Event, Humanizer, PieceMoveExecutor };
 use game_util::glutin::VirtualKeyCode;
 use gilrs::{ Gamepad, Axis, Button };
 use serde::{ Serialize, Deserialize };
@@ -16,16 +16,25 @@ pub struct BotInput {
     interface: cold_clear::Interface,
     executing: Option<(FallingPiece, PieceMoveExecutor)>,
     controller: Controller,
-    speed_limit: u32
+    speed_limit: u32,
+    humanizer: Option<Humanizer>,
+    hold_enabled: bool
 }
 
 impl BotInput {
-    pub fn new(interface: cold_clear::Interface, speed_limit: u32) -> Self {
+    pub fn new(
+        interface: cold_clear::Interface,
+        speed_limit: u32,
+        humanizer: Option<Humanizer>,
+        hold_enabled: bool
+    ) -> Self {
         BotInput {
             interface,
             executing: None,
             controller: Default::default(),
             speed_limit,
+            humanizer,
+            hold_enabled
         }
     }
 }
@@ -49,15 +58,24 @@ impl InputSource for BotInput {
                 Event::GarbageAdded(_) => {
                     self.interface.reset(board.get_field(), board.b2b_bonus, board.combo);
                 }
+                Event::AttackMultiplier(multiplier) => {
+                    self.interface.set_attack_multiplier(*multiplier);
+                }
                 _ => {}
             }
         }
         let mut info = None;
         if let Ok((mv, i)) = self.interface.poll_next_move() {
             info = Some(i);
+            let inputs = mv.inputs.into_iter().collect();
+            let executor = match &mut self.humanizer {
+                Some(humanizer) => humanizer.executor(
+                    board, mv.expected_location.kind.0, mv.hold, inputs, self.speed_limit
+                ),
+                None => PieceMoveExecutor::new(mv.hold, inputs, self.speed_limit)
+            };
             self.executing = Some((
-                mv.expected_location,
-                PieceMoveExecutor::new(mv.hold, mv.inputs.into_iter().collect(), self.speed_limit)
+                mv.expected_location, executor.with_hold_enabled(self.hold_enabled)
             ));
         }
         if let Some((expected, ref mut executor)) = self.executing {
@@ -8,8 +8,8 @@ use gilrs::{ Gilrs, Gamepad, GamepadId };
 use battle::GameConfig;
 use std::collections::HashSet;
 use std::io::prelude::*;
-use cold_clear::evaluation::Evaluator;
-use cold_clear::Book;
+use cold_clear::evaluation::{ Evaluator, EvaluatorConfig };
+use cold_clear::{ Book, Opener };
 
 mod player_draw;
 mod battle_ui;
@@ -17,9 +17,11 @@ mod res;
 mod realtime;
 mod replay;
 mod input;
+mod survival;
 
 use realtime::RealtimeGame;
 use replay::ReplayGame;
+use survival::SurvivalGame;
 
 struct CCGui<'a> {
     log: LogFile,
@@ -120,7 +122,19 @@ impl game_util::Game for CCGui<'_> {
 
 fn main() {
     let mut log = LogFile::default();
-    let replay_file = std::env::args().skip(1).next();
+    let mut args = std::env::args().skip(1);
+    let replay_file = args.next();
+    if replay_file.as_deref() == Some("--verify") {
+        let file = args.next().expect("--verify requires a replay file");
+        match replay::verify(&file) {
+            Ok(()) => println!("{} plays back as recorded", file),
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                std::process::exit(1);
+            }
+        }
+        return
+    }
 
     let mut events = EventsLoop::new();
 
@@ -142,7 +156,7 @@ fn main() {
         gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
     }
 
-    let Options { p1, p2 } = read_options().unwrap_or_else(|e| {
+    let Options { p1, p2, survival } = read_options().unwrap_or_else(|e| {
         writeln!(log, "An error occured while loading options.yaml: {}", e).ok();
         Options::default()
     });
@@ -167,6 +181,10 @@ fn main() {
         lsize,
         res: res::Resources::load(),
         state: match replay_file {
+            Some(ref f) if f == "--survival" => Box::new(SurvivalGame::new(
+                Box::new(move |board| p1.to_player(board)),
+                p1_game_config, survival
+            )),
             Some(f) => Box::new(ReplayGame::new(f)),
             None => Box::new(RealtimeGame::new(
                 Box::new(move |board| p1.to_player(board)),
@@ -216,7 +234,10 @@ fn read_options() -> Result<Options, Box<dyn std::error::Error>> {
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct Options {
     p1: PlayerConfig<cold_clear::evaluation::Standard>,
-    p2: PlayerConfig<cold_clear::evaluation::Standard>
+    p2: PlayerConfig<cold_clear::evaluation::Standard>,
+    /// The garbage schedule of survival mode, which is started with `--survival`.
+    #[serde(default)]
+    survival: battle::SurvivalSchedule
 }
 
 impl Default for Options {
@@ -225,7 +246,8 @@ impl Default for Options {
         p2.is_bot = true;
         Options {
             p1: PlayerConfig::default(),
-            p2
+            p2,
+            survival: Default::default()
         }
     }
 }
@@ -239,51 +261,103 @@ struct PlayerConfig<E: Default> {
     is_bot: bool,
 }
 
-impl<E: Evaluator + Default + Clone + 'static> PlayerConfig<E> {
+impl PlayerConfig<cold_clear::evaluation::Standard> {
     pub fn to_player(&self, board: libtetris::Board) -> (Box<dyn input::InputSource>, String) {
         use crate::input::BotInput;
         if self.is_bot {
-            let mut name = format!("Cold Clear\n{}", self.bot_config.weights.name());
-            if self.bot_config.speed_limit != 0 {
+            let evaluator = match self.bot_config.options.selected_preset() {
+                Some(preset) => EvaluatorConfig::Preset(preset).build(),
+                None => match &self.bot_config.evaluator {
+                    Some(config) => config.build(),
+                    None => EvaluatorConfig::Standard(self.bot_config.weights.clone()).build()
+                }
+            };
+            let mut name = format!("Cold Clear\n{}", evaluator.name());
+            if let Some(skill) = self.bot_config.skill {
+                name.push_str(&format!("\nSkill {:.0}%", skill * 100.0));
+            } else if self.bot_config.speed_limit != 0 {
                 name.push_str(
                     &format!("\n{:.1}%", 100.0 / (self.bot_config.speed_limit + 1) as f32)
                 );
             }
             (Box::new(BotInput::new(cold_clear::Interface::launch(
                 board,
-                self.bot_config.options,
-                self.bot_config.weights.clone(),
-                self.bot_config.book_path.as_ref().and_then(|path| {
-                    let mut book_cache = self.bot_config.book_cache.borrow_mut();
-                    match &*book_cache {
-                        Some(b) => Some(b.clone()),
-                        None => {
-                            let buf = std::io::BufReader::new(std::fs::File::open(path).ok()?);
-                            let book = Book::load(buf).ok()?;
-                            let book = std::sync::Arc::new(book);
-                            *book_cache = Some(book.clone());
-                            Some(book)
-                        }
-                    }
-                })
-            ), self.bot_config.speed_limit)), name)
+                self.bot_options(),
+                evaluator,
+                self.bot_config.book()
+            ), self.bot_config.speed_limit, self.bot_config.skill.map(
+                |skill| battle::Humanizer::new(skill, rand::random())
+            ), self.game.hold_enabled)), name)
         } else {
             (Box::new(self.controls), "Human".to_owned())
         }
     }
+
+    /// The bot options adjusted to the mechanics of the game the bot is playing.
+    fn bot_options(&self) -> cold_clear::Options {
+        let mut options = self.bot_config.options;
+        options.timing = cold_clear::moves::Timing {
+            soft_drop_speed: self.game.soft_drop_speed,
+            auto_repeat_rate: self.game.auto_repeat_rate,
+            hard_drop: self.game.hard_drop_enabled,
+            soft_drop: self.game.soft_drop_enabled,
+            ..Default::default()
+        };
+        options.randomizer = self.game.randomizer;
+        options.use_hold &= self.game.hold_enabled;
+        if self.game.instant_gravity() {
+            options.mode = cold_clear::moves::MovementMode::TwentyG;
+        }
+        options
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(default)]
 struct BotConfig<E> {
     weights: E,
+    /// Overrides `weights` with any of the built-in evaluators.
+    evaluator: Option<EvaluatorConfig>,
     options: cold_clear::Options,
     speed_limit: u32,
+    /// Makes the bot play like a human of this skill, from 0 to 1, with reaction delay, wasted
+    /// inputs and misdrops. The bot still inputs no faster than `speed_limit`.
+    skill: Option<f64>,
     book_path: Option<String>,
+    /// JSON files describing openers for the bot to follow, such as TKI or the DT cannon.
+    openers: Vec<String>,
     #[serde(skip)]
     book_cache: std::cell::RefCell<Option<std::sync::Arc<Book>>>
 }
 
+impl<E> BotConfig<E> {
+    fn book(&self) -> Option<std::sync::Arc<Book>> {
+        if self.book_path.is_none() && self.openers.is_empty() {
+            return None;
+        }
+        let mut book_cache = self.book_cache.borrow_mut();
+        if let Some(book) = &*book_cache {
+            return Some(book.clone());
+        }
+        let mut book = match &self.book_path {
+            Some(path) => {
+                let buf = std::io::BufReader::new(std::fs::File::open(path).ok()?);
+                Book::load(buf).ok()?
+            }
+            None => Book::default()
+        };
+        for path in &self.openers {
+            match Opener::load(path) {
+                Ok(opener) => book.add_opener(opener),
+                Err(e) => eprintln!("Could not load opener {}: {}", path, e)
+            }
+        }
+        let book = std::sync::Arc::new(book);
+        *book_cache = Some(book.clone());
+        Some(book)
+    }
+}
+
 #[derive(Default)]
 struct LogFile(Vec<u8>);
 
@@ -79,8 +79,9 @@ impl PlayerDrawState {
         }
         for event in &update.events {
             match event {
-                Event::PiecePlaced { piece, locked, .. } => {
+                Event::PiecePlaced { piece, locked, finesse_faults, .. } => {
                     self.statistics.update(&locked);
+                    self.statistics.finesse_faults += *finesse_faults as u64;
                     for &(x, y) in &piece.cells() {
                         self.board[y as usize].set(x as usize, piece.kind.0.color());
                     }
@@ -243,7 +244,8 @@ impl PlayerDrawState {
             ("T-Spin 1", format!("{}", self.statistics.tspin_singles)),
             ("T-Spin 2", format!("{}", self.statistics.tspin_doubles)),
             ("T-Spin 3", format!("{}", self.statistics.tspin_triples)),
-            ("Perfect", format!("{}", self.statistics.perfect_clears))
+            ("Perfect", format!("{}", self.statistics.perfect_clears)),
+            ("Finesse", format!("{}", self.statistics.finesse_faults))
         ];
         if let Some(ref info) = self.info {
             // Bot info
@@ -361,6 +363,23 @@ fn cell_color_to_color(cell_color: CellColor) -> [u8; 4] {
         CellColor::L => [255, 143, 32, 0xFF],
         CellColor::J => [96, 96, 255, 0xFF],
         CellColor::I => [32, 255, 255, 0xFF],
-        CellColor::T => [143, 32, 255, 0xFF]
+        CellColor::T => [143, 32, 255, 0xFF],
+        CellColor::Custom(id) => custom_color(id)
     }
-}
\ No newline at end of file
+}
+
+/// Spreads custom color ids around the hue circle so that consecutive ids look different.
+fn custom_color(id: u8) -> [u8; 4] {
+    let hue = (id as f32 * 137.5 % 360.0) / 60.0;
+    let rising = (255.0 * (hue % 1.0)) as u8;
+    let falling = 255 - rising;
+    let [r, g, b] = match hue as u32 {
+        0 => [255, rising, 32],
+        1 => [falling, 255, 32],
+        2 => [32, 255, rising],
+        3 => [32, falling, 255],
+        4 => [rising, 32, 255],
+        _ => [255, 32, falling]
+    };
+    [r.max(32), g.max(32), b.max(32), 0xFF]
+}
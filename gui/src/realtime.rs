@@ -10,7 +10,7 @@ use crate::battle_ui::BattleUi;
 use crate::input::InputSource;
 use crate::replay::InfoReplay;
 
-type InputFactory = dyn Fn(Board) -> (Box<dyn InputSource>, String);
+pub type InputFactory = dyn Fn(Board) -> (Box<dyn InputSource>, String);
 
 pub struct RealtimeGame {
     ui: BattleUi,
@@ -75,21 +75,11 @@ impl crate::State for RealtimeGame {
     ) -> Option<Box<dyn crate::State>> {
         let do_update = match self.state {
             State::GameOver(0) => {
-                let r: Result<(), Box<dyn std::error::Error>> = (|| {
-                    let mut encoder = libflate::deflate::Encoder::new(
-                        std::fs::File::create("replay.dat"
-                    )?);
-                    bincode::serialize_into(
-                        &mut encoder,
-                        &InfoReplay {
-                            replay: self.battle.replay.clone(),
-                            p1_info_updates: self.p1_info_updates.clone(),
-                            p2_info_updates: self.p2_info_updates.clone()
-                        }
-                    )?;
-                    encoder.finish().into_result()?;
-                    Ok(())
-                })();
+                let r = InfoReplay {
+                    replay: self.battle.replay.clone(),
+                    p1_info_updates: self.p1_info_updates.clone(),
+                    p2_info_updates: self.p2_info_updates.clone()
+                }.save("replay.dat");
                 if let Err(e) = r {
                     writeln!(log, "Failure saving replay: {}", e).ok();
                 }
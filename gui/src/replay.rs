@@ -1,10 +1,6 @@
-use battle::Replay;
-use serde::{ Serialize, Deserialize };
+use battle::{ Replay, ReplayPlayer };
 use std::collections::{ HashSet, VecDeque };
-use std::path::PathBuf;
-use std::fs::File;
-use battle::Battle;
-use libtetris::Controller;
+use std::path::{ Path, PathBuf };
 use gilrs::Gamepad;
 use game_util::glutin::VirtualKeyCode;
 use crate::battle_ui::BattleUi;
@@ -12,9 +8,8 @@ use crate::res::Resources;
 
 pub struct ReplayGame {
     ui: BattleUi,
-    battle: Battle,
+    player: ReplayPlayer,
     file: PathBuf,
-    updates: VecDeque<(Controller, Controller)>,
     p1_info_updates: VecDeque<Option<cold_clear::Info>>,
     p2_info_updates: VecDeque<Option<cold_clear::Info>>,
     start_delay: u32
@@ -25,18 +20,12 @@ impl ReplayGame {
         let file = file.into();
         let InfoReplay {
             replay, p1_info_updates, p2_info_updates
-        } = bincode::deserialize_from(
-            libflate::deflate::Decoder::new(File::open(&file).unwrap())
-        ).unwrap();
-        let battle = Battle::new(
-            replay.p1_config, replay.p2_config,
-            replay.p1_seed, replay.p2_seed,
-            replay.garbage_seed
-        );
+        } = InfoReplay::load(&file).unwrap();
+        let (p1_name, p2_name) = (replay.p1_name.clone(), replay.p2_name.clone());
+        let player = ReplayPlayer::new(replay);
         ReplayGame {
-            ui: BattleUi::new(&battle, replay.p1_name, replay.p2_name),
-            battle,
-            updates: replay.updates,
+            ui: BattleUi::new(&player.battle, p1_name, p2_name),
+            player,
             p1_info_updates, p2_info_updates,
             start_delay: 500,
             file
@@ -54,8 +43,7 @@ impl crate::State for ReplayGame {
         _p2: Option<Gamepad>
     ) -> Option<Box<dyn crate::State>> {
         if self.start_delay == 0 {
-            if let Some((p1_controller, p2_controller)) = self.updates.pop_front() {
-                let update = self.battle.update(p1_controller, p2_controller);
+            if let Some(update) = self.player.next() {
                 self.ui.update(
                     res, update,
                     self.p1_info_updates.pop_front().flatten(),
@@ -64,28 +52,15 @@ impl crate::State for ReplayGame {
             } else {
                 let replay;
                 loop {
-                    match std::fs::File::open(&self.file) {
-                        Ok(f) => {
-                            match bincode::deserialize_from(libflate::deflate::Decoder::new(f)) {
-                                Ok(r) => {
-                                    replay = r;
-                                    break
-                                }
-                                Err(_) => {}
-                            }
-                        }
-                        Err(_) => {}
+                    if let Ok(r) = InfoReplay::load(&self.file) {
+                        replay = r;
+                        break
                     }
                 }
                 let InfoReplay { replay, p1_info_updates, p2_info_updates } = replay;
-                let battle = Battle::new(
-                    replay.p1_config, replay.p2_config,
-                    replay.p1_seed, replay.p2_seed,
-                    replay.garbage_seed
-                );
-                self.ui = BattleUi::new(&battle, replay.p1_name, replay.p2_name);
-                self.battle = battle;
-                self.updates = replay.updates;
+                let (p1_name, p2_name) = (replay.p1_name.clone(), replay.p2_name.clone());
+                self.player = ReplayPlayer::new(replay);
+                self.ui = BattleUi::new(&self.player.battle, p1_name, p2_name);
                 self.p1_info_updates = p1_info_updates;
                 self.p2_info_updates = p2_info_updates;
                 self.start_delay = 180;
@@ -115,9 +90,28 @@ impl crate::State for ReplayGame {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Re-simulates a saved replay, checking the game against the checkpoints recorded with it.
+pub fn verify(file: &str) -> Result<(), String> {
+    let replay = Replay::load(file)?;
+    ReplayPlayer::new(replay).verify().map_err(|d| d.to_string())?;
+    Ok(())
+}
+
+#[derive(Clone, Debug)]
 pub struct InfoReplay {
     pub replay: Replay,
     pub p1_info_updates: VecDeque<Option<cold_clear::Info>>,
     pub p2_info_updates: VecDeque<Option<cold_clear::Info>>
+}
+
+impl InfoReplay {
+    /// Saves the replay with the bot info of both players stored after it.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        self.replay.save_with(&(&self.p1_info_updates, &self.p2_info_updates), path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let (replay, (p1_info_updates, p2_info_updates)) = Replay::load_with(path)?;
+        Ok(InfoReplay { replay, p1_info_updates, p2_info_updates })
+    }
 }
\ No newline at end of file
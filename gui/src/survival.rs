@@ -0,0 +1,122 @@
+use game_util::prelude::*;
+use game_util::glutin::VirtualKeyCode;
+use battle::{ GameConfig, PlayerUpdate, Survival, SurvivalSchedule };
+use std::collections::HashSet;
+use rand::prelude::*;
+use gilrs::Gamepad;
+use crate::res::Resources;
+use crate::player_draw::PlayerDrawState;
+use crate::input::InputSource;
+use crate::realtime::InputFactory;
+
+/// Player 1 plays alone against garbage arriving on an accelerating schedule.
+pub struct SurvivalGame {
+    survival: Survival,
+    graphics: PlayerDrawState,
+    input_factory: Box<InputFactory>,
+    input: Box<dyn InputSource>,
+    config: GameConfig,
+    schedule: SurvivalSchedule,
+    /// The longest game survived so far, in ticks.
+    best: u32,
+    state: State
+}
+
+enum State {
+    Playing,
+    GameOver(u32),
+    Starting(u32)
+}
+
+impl SurvivalGame {
+    pub fn new(input: Box<InputFactory>, config: GameConfig, schedule: SurvivalSchedule) -> Self {
+        let survival = Survival::new(config, schedule, thread_rng().gen());
+        let (p1_input, name) = input(survival.game.board.to_compressed());
+        SurvivalGame {
+            graphics: PlayerDrawState::new(survival.game.board.next_queue(), name),
+            survival,
+            input_factory: input,
+            input: p1_input,
+            config, schedule,
+            best: 0,
+            state: State::Starting(180)
+        }
+    }
+
+    fn restart(&mut self) {
+        self.survival = Survival::new(self.config, self.schedule, thread_rng().gen());
+        let (input, name) = (self.input_factory)(self.survival.game.board.to_compressed());
+        self.graphics = PlayerDrawState::new(self.survival.game.board.next_queue(), name);
+        self.input = input;
+    }
+}
+
+impl crate::State for SurvivalGame {
+    fn update(
+        &mut self,
+        _log: &mut crate::LogFile,
+        _res: &mut Resources,
+        keys: &HashSet<VirtualKeyCode>,
+        p1: Option<Gamepad>,
+        _p2: Option<Gamepad>
+    ) -> Option<Box<dyn crate::State>> {
+        match self.state {
+            State::GameOver(0) => {
+                self.restart();
+                self.state = State::Starting(180);
+            }
+            State::GameOver(ref mut delay) => *delay -= 1,
+            State::Starting(0) => self.state = State::Playing,
+            State::Starting(ref mut delay) => *delay -= 1,
+            State::Playing => {
+                let controller = self.input.controller(keys, p1);
+                let update = self.survival.update(controller);
+                let info = self.input.update(
+                    &self.survival.game.board, &update.events, update.garbage_queue
+                );
+                if update.finished {
+                    self.best = self.best.max(update.time);
+                    self.state = State::GameOver(300);
+                }
+                self.graphics.update(PlayerUpdate {
+                    events: update.events,
+                    garbage_queue: update.garbage_queue
+                }, info, update.time);
+            }
+        }
+        None
+    }
+
+    fn render(&mut self, res: &mut Resources) {
+        let time = self.survival.time;
+        res.text.draw_text(
+            &format!("{}:{:02}", time / 60 / 60, time / 60 % 60),
+            20.0, 1.5,
+            game_util::Alignment::Center,
+            [0xFF; 4], 1.0, 0
+        );
+        res.text.draw_text(
+            &format!("Best {}:{:02}", self.best / 60 / 60, self.best / 60 % 60),
+            20.0, 3.0,
+            game_util::Alignment::Center,
+            [0xFF; 4], 1.0, 0
+        );
+
+        if let State::Starting(timer) = self.state {
+            res.text.draw_text(
+                &format!("{}", timer / 60 + 1),
+                19.5, 12.25,
+                game_util::Alignment::Center,
+                [0xFF; 4], 3.0, 0
+            );
+        }
+
+        self.graphics.draw(res, 10.0+1.0);
+
+        res.sprite_batch.render(Transform3D::ortho(
+            0.0, 40.0,
+            0.0, 23.0,
+            -1.0, 1.0
+        ));
+    }
+}
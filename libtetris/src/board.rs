@@ -2,19 +2,67 @@ use arrayvec::ArrayVec;
 use enumset::EnumSet;
 use std::collections::VecDeque;
 use std::iter::DoubleEndedIterator;
-use serde::{ Serialize, Deserialize };
+use serde::{ Serialize, Deserialize, Deserializer };
 
 use crate::*;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// The board of a player.
+///
+/// The bot searches with the default `u16` rows, which store only which cells are filled and
+/// keep the boards it clones during search small. Frontends use `ColoredRow` to also keep the
+/// color of every cell, and convert with `to_compressed` when handing the board to the bot.
+#[derive(Clone, Debug, Serialize)]
 pub struct Board<R=u16> {
     cells: ArrayVec<[R; 40]>,
     column_heights: [i32; 10],
+    /// The number of empty cells below the top of every column, kept up to date as pieces lock
+    /// so evaluators don't have to scan the field for holes. Not serialized, but recomputed from
+    /// the rows when a board is deserialized.
+    #[serde(skip)]
+    column_holes: [i32; 10],
     pub combo: u32,
     pub b2b_bonus: bool,
     pub hold_piece: Option<Piece>,
     next_pieces: VecDeque<Piece>,
+    /// The pieces that can come next. For the 7-bag randomizer, the pieces left in the bag.
     pub bag: EnumSet<Piece>,
+    #[serde(default)]
+    pub randomizer: RandomizerState,
+}
+
+/// The serialized form of a `Board`, from which the column heights and holes are recomputed.
+#[derive(Deserialize)]
+struct BoardData<R> {
+    cells: ArrayVec<[R; 40]>,
+    #[allow(dead_code)]
+    column_heights: [i32; 10],
+    combo: u32,
+    b2b_bonus: bool,
+    hold_piece: Option<Piece>,
+    next_pieces: VecDeque<Piece>,
+    bag: EnumSet<Piece>,
+    #[serde(default)]
+    randomizer: RandomizerState,
+}
+
+impl<'de, R: Row + Deserialize<'de>> Deserialize<'de> for Board<R> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = BoardData::deserialize(deserializer)?;
+        let mut board = Board {
+            cells: data.cells,
+            column_heights: [0; 10],
+            column_holes: [0; 10],
+            combo: data.combo,
+            b2b_bonus: data.b2b_bonus,
+            hold_piece: data.hold_piece,
+            next_pieces: data.next_pieces,
+            bag: data.bag,
+            randomizer: data.randomizer,
+        };
+        let rows = board.cells.len();
+        board.recompute_heights(rows);
+        Ok(board)
+    }
 }
 
 pub trait Row: Copy + Clone + 'static {
@@ -23,6 +71,8 @@ pub trait Row: Copy + Clone + 'static {
     fn is_full(&self) -> bool;
     fn is_empty(&self) -> bool;
     fn cell_color(&self, x: usize) -> CellColor;
+    /// The filled cells of the row as a bitmask, with bit `x` set if cell `x` is filled.
+    fn bits(&self) -> u16;
 
     const EMPTY: &'static Self;
     const SOLID: &'static Self;
@@ -34,11 +84,13 @@ impl<R: Row> Board<R> {
         Board {
             cells: [*R::EMPTY; 40].into(),
             column_heights: [0; 10],
+            column_holes: [0; 10],
             combo: 0,
             b2b_bonus: false,
             hold_piece: None,
             next_pieces: VecDeque::new(),
             bag: EnumSet::all(),
+            randomizer: RandomizerState::SevenBag,
         }
     }
 
@@ -47,6 +99,7 @@ impl<R: Row> Board<R> {
         let mut board = Board {
             cells: [*R::EMPTY; 40].into(),
             column_heights: [0; 10],
+            column_holes: [0; 10],
             combo: combo,
             b2b_bonus: b2b,
             hold_piece: hold,
@@ -56,19 +109,82 @@ impl<R: Row> Board<R> {
             } else {
                 bag_remain
             },
+            randomizer: RandomizerState::SevenBag,
         };
         board.set_field(field);
         board
     }
 
-    /// Randomly selects a piece from the bag.
+    /// Randomly selects the next piece using the board's randomizer.
     /// 
     /// This function does not remove the generated piece from the bag.
     /// Use add_next_piece() to add it to the queue.
     pub fn generate_next_piece(&self, rng: &mut impl rand::Rng) -> Piece {
-        use rand::prelude::*;
-        let choices: ArrayVec<[_; 7]> = self.bag.iter().collect();
-        *choices.choose(rng).unwrap()
+        match &self.randomizer {
+            RandomizerState::SevenBag => self.bag.generate(rng),
+            RandomizerState::FourteenBag(r) => r.generate(rng),
+            RandomizerState::Memoryless(r) => r.generate(rng),
+            RandomizerState::Tgm(r) => r.generate(rng)
+        }
+    }
+
+    /// Switches to a different randomizer. The pieces already in the queue are counted towards
+    /// the new randomizer's state.
+    pub fn set_randomizer(&mut self, kind: RandomizerKind) {
+        self.randomizer = kind.start();
+        self.bag = match &self.randomizer {
+            RandomizerState::SevenBag => EnumSet::all(),
+            _ => self.possible_next_pieces()
+        };
+        let queue: Vec<_> = self.next_pieces.drain(..).collect();
+        for piece in queue {
+            self.add_next_piece(piece);
+        }
+    }
+
+    fn possible_next_pieces(&self) -> EnumSet<Piece> {
+        match &self.randomizer {
+            RandomizerState::SevenBag => self.bag,
+            RandomizerState::FourteenBag(r) => r.possible(),
+            RandomizerState::Memoryless(r) => r.possible(),
+            RandomizerState::Tgm(r) => r.possible()
+        }
+    }
+
+    /// The board flipped horizontally, with every piece mirrored as by `Piece::mirrored`: the
+    /// colors of the field, the hold piece, the queue, the bag and the randomizer's state.
+    pub fn mirrored(&self) -> Self {
+        let mut board = self.clone();
+        for (row, mirrored) in self.cells.iter().zip(board.cells.iter_mut()) {
+            for x in 0..10 {
+                mirrored.set(9 - x, row.cell_color(x).mirrored());
+            }
+        }
+        board.column_heights.reverse();
+        board.column_holes.reverse();
+        board.hold_piece = self.hold_piece.map(Piece::mirrored);
+        board.next_pieces = self.next_pieces.iter().map(|&p| p.mirrored()).collect();
+        board.bag = self.bag.iter().map(Piece::mirrored).collect();
+        board.randomizer = self.randomizer.mirrored();
+        board
+    }
+
+    /// How likely `piece` is to follow the last known piece of the queue, relative to the other
+    /// pieces; see `Randomizer::weight`.
+    pub fn next_piece_weight(&self, piece: Piece) -> u32 {
+        match &self.randomizer {
+            RandomizerState::SevenBag => self.bag.weight(piece),
+            RandomizerState::FourteenBag(r) => r.weight(piece),
+            RandomizerState::Memoryless(r) => r.weight(piece),
+            RandomizerState::Tgm(r) => r.weight(piece)
+        }
+    }
+
+    /// The pieces that can follow the last known piece of the queue. With the 7-bag randomizer,
+    /// these are the pieces left in the current bag after every revealed piece was taken out, and
+    /// a full bag once the last piece of a bag has been revealed.
+    pub fn remaining_bag(&self) -> EnumSet<Piece> {
+        self.bag
     }
 
     /// Retrieves the next piece in the queue.
@@ -80,47 +196,78 @@ impl<R: Row> Board<R> {
 
     /// Retrieves the piece after the next piece in the queue if it is known.
     pub fn get_next_next_piece(&self) -> Option<Piece> {
-        self.next_pieces.get(1).copied()
+        self.peek_next_piece(1)
     }
 
     /// Adds the piece to the next queue and removes it from the bag.
     /// 
-    /// If the bag becomes empty, the bag is refilled.
+    /// If the bag becomes empty, the bag is refilled. For randomizers other than the 7-bag, the
+    /// bag is replaced with the pieces that can come after this one.
     pub fn add_next_piece(&mut self, piece: Piece) {
-        self.bag.remove(piece);
-        if self.bag.is_empty() {
-            self.bag = EnumSet::all();
+        match &mut self.randomizer {
+            RandomizerState::SevenBag => self.bag.observe(piece),
+            RandomizerState::FourteenBag(r) => r.observe(piece),
+            RandomizerState::Memoryless(r) => r.observe(piece),
+            RandomizerState::Tgm(r) => r.observe(piece)
         }
+        self.bag = self.possible_next_pieces();
         self.next_pieces.push_back(piece);
     }
 
-    fn remove_cleared_lines(&mut self) -> ArrayVec<[i32; 4]> {
-        let mut cleared = ArrayVec::new();
-        let mut lineno = 0;
-        self.cells.retain(|r| {
-            let full = r.is_full();
-            if full {
-                cleared.push(lineno);
+    /// Removes the full rows below `top`, returning the rows that were cleared. Usually only the
+    /// rows a piece was just locked into are full, but boards set up through `set_field` or
+    /// imported states can already have full rows. At most 4 rows are cleared at once; any others
+    /// are cleared by the next piece.
+    fn remove_cleared_lines(&mut self, top: usize) -> ArrayVec<[i32; 4]> {
+        let mut cleared = ArrayVec::<[i32; 4]>::new();
+        for y in 0..top.min(40) {
+            if self.cells[y].is_full() && !cleared.is_full() {
+                cleared.push(y as i32);
             }
-            lineno += 1;
-            !full
-        });
+        }
+        if cleared.is_empty() {
+            return cleared;
+        }
 
+        for &y in cleared.iter().rev() {
+            self.cells.remove(y as usize);
+        }
         for _ in 0..cleared.len() {
             self.cells.push(*R::EMPTY);
         }
-        for x in 0..10 {
-            self.column_heights[x] -= cleared.len() as i32;
-            while self.column_heights[x] > 0 &&
-                    !self.cells[self.column_heights[x] as usize-1].get(x) {
-                self.column_heights[x] -= 1;
+        self.recompute_heights(top);
+        cleared
+    }
+
+    /// Recomputes the column heights and holes from the rows below `top`, finding the highest
+    /// filled cell of every column from the row bitmasks instead of checking cell by cell.
+    fn recompute_heights(&mut self, top: usize) {
+        self.column_heights = [0; 10];
+        let mut filled = [0; 10];
+        let mut remaining = *u16::SOLID;
+        for y in (0..top.min(40)).rev() {
+            let bits = self.cells[y].bits();
+            let mut found = bits & remaining;
+            remaining &= !found;
+            while found != 0 {
+                self.column_heights[found.trailing_zeros() as usize] = y as i32 + 1;
+                found &= found - 1;
+            }
+            let mut cells = bits;
+            while cells != 0 {
+                filled[cells.trailing_zeros() as usize] += 1;
+                cells &= cells - 1;
             }
         }
-        cleared
+        for x in 0..10 {
+            self.column_holes[x] = self.column_heights[x] - filled[x];
+        }
     }
 
+    #[inline]
     pub fn occupied(&self, x: i32, y: i32) -> bool {
-        x < 0 || y < 0 || x >= 10 || y >= 40 || (self.cells[y as usize].get(x as usize))
+        // negative coordinates wrap around to large unsigned values
+        x as u32 >= 10 || y as u32 >= 40 || self.cells[y as usize].get(x as usize)
     }
 
     pub fn get_row(&self, y: i32) -> &R {
@@ -133,10 +280,34 @@ impl<R: Row> Board<R> {
         }
     }
 
+    #[inline]
     pub fn obstructed(&self, piece: &FallingPiece) -> bool {
-        piece.cells()
-            .iter()
-            .any(|&(x, y)| self.occupied(x, y))
+        let cells = piece.cells();
+        self.obstructed_fast(&cells).unwrap_or_else(
+            || cells.iter().any(|&(x, y)| self.occupied(x, y))
+        )
+    }
+
+    /// Checks the cells against the rows they are in as bitmasks, merging cells in the same row
+    /// into one test. Returns `None` if a cell is out of bounds.
+    #[inline]
+    fn obstructed_fast(&self, cells: &[(i32, i32); 4]) -> Option<bool> {
+        let mut masks = [(0usize, 0u16); 4];
+        let mut rows = 0;
+        for &(x, y) in cells {
+            if x as u32 >= 10 || y as u32 >= 40 {
+                return None;
+            }
+            let y = y as usize;
+            match masks[..rows].iter_mut().find(|(row, _)| *row == y) {
+                Some((_, mask)) => *mask |= 1 << x,
+                None => {
+                    masks[rows] = (y, 1 << x);
+                    rows += 1;
+                }
+            }
+        }
+        Some(masks[..rows].iter().any(|&(y, mask)| self.cells[y].bits() & mask != 0))
     }
 
     pub fn above_stack(&self, piece: &FallingPiece) -> bool {
@@ -151,6 +322,19 @@ impl<R: Row> Board<R> {
             .any(|&(x, y)| self.occupied(x, y - 1))
     }
 
+    /// Where `piece` comes to rest if it's dropped straight down, which is where frontends draw
+    /// the ghost piece.
+    pub fn ghost(&self, piece: &FallingPiece) -> FallingPiece {
+        let mut ghost = *piece;
+        ghost.sonic_drop(self);
+        ghost
+    }
+
+    /// The number of rows `piece` falls if it's dropped straight down.
+    pub fn drop_distance(&self, piece: &FallingPiece) -> i32 {
+        piece.y - self.ghost(piece).y
+    }
+
     /// Does all logic associated with locking a piece.
     /// 
     /// Clears lines, detects clear kind, calculates garbage, maintains combo and back-to-back
@@ -159,20 +343,27 @@ impl<R: Row> Board<R> {
         let mut locked_out = true;
         for &(x, y) in &piece.cells() {
             self.cells[y as usize].set(x as usize, piece.kind.0.color());
-            if self.column_heights[x as usize] < y+1 {
-                self.column_heights[x as usize] = y+1;
+            let height = &mut self.column_heights[x as usize];
+            if *height <= y {
+                // every empty cell skipped over below the new cell becomes a hole
+                self.column_holes[x as usize] += y - *height;
+                *height = y+1;
+            } else {
+                self.column_holes[x as usize] -= 1;
             }
             if y < 20 {
                 locked_out = false;
             }
         }
-        let cleared = self.remove_cleared_lines();
+        let top = self.column_heights.iter().copied().max().unwrap_or(0) as usize;
+        let cleared = self.remove_cleared_lines(top);
 
         let placement_kind = PlacementKind::get(cleared.len(), piece.tspin);
 
         let mut garbage_sent = placement_kind.garbage();
 
         let mut did_b2b = false;
+        let mut b2b_broken = false;
         if placement_kind.is_clear() {
             if placement_kind.is_hard() {
                 if self.b2b_bonus {
@@ -181,6 +372,7 @@ impl<R: Row> Board<R> {
                 }
                 self.b2b_bonus = true;
             } else {
+                b2b_broken = self.b2b_bonus;
                 self.b2b_bonus = false;
             }
 
@@ -204,6 +396,7 @@ impl<R: Row> Board<R> {
             placement_kind, garbage_sent, perfect_clear, locked_out,
             combo: if self.combo == 0 { None } else { Some(self.combo-1) },
             b2b: did_b2b,
+            b2b_broken,
             cleared_lines: cleared
         };
 
@@ -219,10 +412,18 @@ impl<R: Row> Board<R> {
         hold
     }
 
-    pub fn next_queue<'a>(&'a self) -> impl DoubleEndedIterator<Item=Piece> + 'a {
+    /// The known pieces of the next queue, starting with the next piece, without advancing it.
+    pub fn next_queue<'a>(
+        &'a self
+    ) -> impl DoubleEndedIterator<Item=Piece> + ExactSizeIterator + Clone + 'a {
         self.next_pieces.iter().copied()
     }
 
+    /// The piece `index` pieces into the next queue, where 0 is the next piece, if it is known.
+    pub fn peek_next_piece(&self, index: usize) -> Option<Piece> {
+        self.next_pieces.get(index).copied()
+    }
+
     /// Returns the piece that should be spawned, or None if the queue is empty.
     pub fn advance_queue(&mut self) -> Option<Piece> {
         self.next_pieces.pop_front()
@@ -232,12 +433,23 @@ impl<R: Row> Board<R> {
         &self.column_heights
     }
 
+    /// The number of empty cells below the highest filled cell of every column.
+    pub fn column_holes(&self) -> &[i32; 10] {
+        &self.column_holes
+    }
+
+    /// The number of filled cells in row `y`.
+    pub fn row_fill(&self, y: i32) -> u32 {
+        self.get_row(y).bits().count_ones()
+    }
+
     pub fn add_garbage(&mut self, col: usize) -> bool {
         let mut row = *R::EMPTY;
         for x in 0..10 {
             if x == col {
                 if self.column_heights[x] != 0 {
                     self.column_heights[x] += 1;
+                    self.column_holes[x] += 1;
                 }
             } else {
                 row.set(x, CellColor::Garbage);
@@ -261,38 +473,48 @@ impl<R: Row> Board<R> {
             b2b_bonus: self.b2b_bonus,
             combo: self.combo,
             column_heights: self.column_heights,
+            column_holes: self.column_holes,
             next_pieces: self.next_pieces.clone(),
             hold_piece: self.hold_piece,
-            bag: self.bag
+            bag: self.bag,
+            randomizer: self.randomizer.clone()
         }
     }
 
     pub fn set_field(&mut self, field: [[bool; 10]; 40]) {
         self.cells.clear();
-        self.column_heights = [0; 10];
         for y in 0..40 {
             let mut r = *R::EMPTY;
             for x in 0..10 {
                 if field[y][x] {
                     r.set(x, CellColor::Garbage);
-                    self.column_heights[x] = y as i32 + 1;
                 }
             }
             self.cells.push(r)
         }
+        self.recompute_heights(40);
     }
 
     pub fn get_field(&self) -> [[bool; 10]; 40] {
         let mut field = [[false; 10]; 40];
-        for y in 0..40 {
+        for (y, row) in self.cells.iter().enumerate() {
+            let bits = row.bits();
             for x in 0..10 {
-                field[y][x] = self.occupied(x as i32, y as i32)
+                field[y][x] = bits & 1 << x != 0;
             }
         }
         field
     }
 
+    /// The 7-bag the first piece of the queue was drawn from.
+    /// 
+    /// The other randomizers have no bag to speak of, and their state from before the queue was
+    /// revealed isn't kept, so for them this is the same as `remaining_bag`: the pieces that can
+    /// follow the last piece of the queue, not the ones the first piece was drawn from.
     pub fn next_bag(&self) -> EnumSet<Piece> {
+        if self.randomizer.kind() != RandomizerKind::SevenBag {
+            return self.bag;
+        }
         let mut bag = self.bag;
         for p in self.next_queue().rev() {
             if bag == EnumSet::all() {
@@ -334,6 +556,11 @@ impl Row for u16 {
         }
     }
 
+    #[inline]
+    fn bits(&self) -> u16 {
+        *self
+    }
+
     const SOLID: &'static u16 = &0b11111_11111;
     const EMPTY: &'static u16 = &0;
 }
@@ -368,6 +595,77 @@ impl Row for ColoredRow {
         self.0.iter().all(|&c| c == CellColor::Empty)
     }
 
+    fn bits(&self) -> u16 {
+        let mut bits = 0;
+        for x in 0..10 {
+            if self.get(x) {
+                bits |= 1 << x;
+            }
+        }
+        bits
+    }
+
     const SOLID: &'static Self = &ColoredRow([CellColor::Unclearable; 10]);
     const EMPTY: &'static Self = &ColoredRow([CellColor::Empty; 10]);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_bag_is_the_bag_of_the_first_queued_piece() {
+        let mut board = Board::<u16>::new();
+        for &piece in &[Piece::I, Piece::O, Piece::T, Piece::L, Piece::J, Piece::S, Piece::Z] {
+            board.add_next_piece(piece);
+        }
+        board.add_next_piece(Piece::T);
+        let mut rest = EnumSet::all();
+        rest.remove(Piece::T);
+        assert_eq!(board.next_bag(), EnumSet::all());
+        assert_eq!(board.remaining_bag(), rest);
+    }
+
+    #[test]
+    fn next_bag_follows_the_queue_for_other_randomizers() {
+        let mut board = Board::<u16>::new();
+        board.set_randomizer(RandomizerKind::FourteenBag);
+        for &piece in &[Piece::T, Piece::T, Piece::I] {
+            board.add_next_piece(piece);
+        }
+        assert!(!board.next_bag().contains(Piece::T));
+        assert_eq!(board.next_bag(), board.remaining_bag());
+    }
+
+    #[test]
+    fn lock_piece_clears_full_rows_it_does_not_touch() {
+        let mut field = [[false; 10]; 40];
+        field[0] = [true; 10];
+        let mut board = Board::<u16>::new();
+        board.set_field(field);
+        let lock = board.lock_piece(FallingPiece {
+            kind: PieceState(Piece::O, RotationState::North),
+            x: 4,
+            y: 5,
+            tspin: TspinStatus::None
+        });
+        assert_eq!(&lock.cleared_lines[..], &[0]);
+        assert_eq!(board.column_heights()[0], 0);
+        assert!(!board.get_row(0).is_full());
+    }
+
+    #[test]
+    fn column_holes_are_recomputed_when_deserializing() {
+        let mut field = [[false; 10]; 40];
+        field[0] = [true; 10];
+        field[0][3] = false;
+        field[2][3] = true;
+        let mut board = Board::<u16>::new();
+        board.set_field(field);
+        let json = serde_json::to_string(&board).unwrap();
+        assert!(!json.contains("column_holes"));
+        let board: Board = serde_json::from_str(&json).unwrap();
+        assert_eq!(board.column_holes()[3], 2);
+        assert_eq!(board.column_heights()[3], 3);
+    }
+}
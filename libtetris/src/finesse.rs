@@ -0,0 +1,61 @@
+use std::collections::{ HashSet, VecDeque };
+use crate::{ Board, FallingPiece, SpawnRule };
+
+/// The fewest key presses needed to hard drop a piece into the columns and orientation of
+/// `target` on an empty board, using taps, held shifts to the wall and the two rotation keys.
+/// The hard drop itself isn't counted.
+///
+/// Positions that look the same count as the same, so for example a vertical I piece can be
+/// reached through either rotation. Returns `None` if `target` can't be reached.
+pub fn finesse_presses(target: &FallingPiece) -> Option<u32> {
+    let board = Board::<u16>::new();
+    let spawned = SpawnRule::Row19Or20.spawn(target.kind.0, &board)?;
+    let goal = footprint(target);
+
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(spawned);
+    queue.push_back((spawned, 0));
+    while let Some((piece, presses)) = queue.pop_front() {
+        if footprint(&piece) == goal {
+            return Some(presses);
+        }
+        let mut next = vec![];
+        for &dx in &[-1, 1] {
+            let mut tapped = piece;
+            if tapped.shift(&board, dx, 0) {
+                next.push(tapped);
+                let mut held = tapped;
+                while held.shift(&board, dx, 0) {}
+                next.push(held);
+            }
+        }
+        let mut rotated = piece;
+        if rotated.cw(&board) {
+            next.push(rotated);
+        }
+        let mut rotated = piece;
+        if rotated.ccw(&board) {
+            next.push(rotated);
+        }
+        for mut piece in next {
+            // t-spin status doesn't matter for a hard drop
+            piece.tspin = crate::TspinStatus::None;
+            if seen.insert(piece) {
+                queue.push_back((piece, presses + 1));
+            }
+        }
+    }
+    None
+}
+
+/// The cells of the piece with its lowest cell moved to row 0, sorted.
+fn footprint(piece: &FallingPiece) -> [(i32, i32); 4] {
+    let mut cells = piece.cells();
+    let min_y = cells.iter().map(|&(_, y)| y).min().unwrap();
+    for (_, y) in cells.iter_mut() {
+        *y -= min_y;
+    }
+    cells.sort();
+    cells
+}
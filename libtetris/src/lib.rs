@@ -1,10 +1,14 @@
 mod board;
 mod piece;
 mod lock_data;
+mod randomizer;
+mod finesse;
 
 pub use board::*;
 pub use piece::*;
 pub use lock_data::*;
+pub use randomizer::*;
+pub use finesse::*;
 
 #[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
 pub struct Controller {
@@ -17,17 +21,33 @@ pub struct Controller {
     pub hold: bool
 }
 
+impl Controller {
+    fn to_bits(self) -> u8 {
+        (self.left as u8)         << 1 |
+        (self.right as u8)        << 2 |
+        (self.rotate_left as u8)  << 3 |
+        (self.rotate_right as u8) << 4 |
+        (self.hold as u8)         << 5 |
+        (self.soft_drop as u8)    << 6 |
+        (self.hard_drop as u8)    << 7
+    }
+
+    fn from_bits(v: u64) -> Self {
+        Controller {
+            left:         (v >> 1) & 1 != 0,
+            right:        (v >> 2) & 1 != 0,
+            rotate_left:  (v >> 3) & 1 != 0,
+            rotate_right: (v >> 4) & 1 != 0,
+            hold:         (v >> 5) & 1 != 0,
+            soft_drop:    (v >> 6) & 1 != 0,
+            hard_drop:    (v >> 7) & 1 != 0,
+        }
+    }
+}
+
 impl serde::Serialize for Controller {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        serializer.serialize_u8(
-            (self.left as u8)         << 1 |
-            (self.right as u8)        << 2 |
-            (self.rotate_left as u8)  << 3 |
-            (self.rotate_right as u8) << 4 |
-            (self.hold as u8)         << 5 |
-            (self.soft_drop as u8)    << 6 |
-            (self.hard_drop as u8)    << 7
-        )
+        serializer.serialize_u8(self.to_bits())
     }
 }
 
@@ -40,17 +60,91 @@ impl<'de> serde::Deserialize<'de> for Controller {
                 write!(formatter, "a byte-sized bit vector")
             }
             fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Controller, E> {
-                Ok(Controller {
-                    left:         (v >> 1) & 1 != 0,
-                    right:        (v >> 2) & 1 != 0,
-                    rotate_left:  (v >> 3) & 1 != 0,
-                    rotate_right: (v >> 4) & 1 != 0,
-                    hold:         (v >> 5) & 1 != 0,
-                    soft_drop:    (v >> 6) & 1 != 0,
-                    hard_drop:    (v >> 7) & 1 != 0,
-                })
+                Ok(Controller::from_bits(v))
             }
         }
         deserializer.deserialize_u8(ControllerDeserializer)
     }
-}
\ No newline at end of file
+}
+/// A controller state held for a number of frames, for storing inputs without one `Controller`
+/// per frame.
+///
+/// This is version 2 of the controller encoding: the bit vector of a `Controller` with bit 0 set,
+/// followed by the number of frames. Human readable formats also accept a plain version 1
+/// bit vector, which is read as a single frame. Since bit 0 is otherwise unused, a version 2 bit
+/// vector still reads as the same `Controller`.
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub struct ControllerRun {
+    pub controller: Controller,
+    pub frames: u32
+}
+
+const CONTROLLER_V2: u8 = 1;
+
+impl ControllerRun {
+    /// Combines consecutive frames with the same controller state into runs.
+    pub fn encode(frames: impl IntoIterator<Item = Controller>) -> Vec<ControllerRun> {
+        let mut runs: Vec<ControllerRun> = vec![];
+        for controller in frames {
+            match runs.last_mut() {
+                Some(run) if run.controller == controller => run.frames += 1,
+                _ => runs.push(ControllerRun { controller, frames: 1 })
+            }
+        }
+        runs
+    }
+
+    /// Expands runs back into one controller state per frame.
+    pub fn decode(runs: &[ControllerRun]) -> impl Iterator<Item = Controller> + '_ {
+        runs.iter().flat_map(|run| std::iter::repeat(run.controller).take(run.frames as usize))
+    }
+}
+
+impl serde::Serialize for ControllerRun {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeTuple;
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&(self.controller.to_bits() | CONTROLLER_V2))?;
+        tuple.serialize_element(&self.frames)?;
+        tuple.end()
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ControllerRun {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RunDeserializer;
+        impl<'de> serde::de::Visitor<'de> for RunDeserializer {
+            type Value = ControllerRun;
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(formatter, "a controller bit vector and a frame count")
+            }
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<ControllerRun, E> {
+                Ok(ControllerRun {
+                    controller: Controller::from_bits(v),
+                    frames: 1
+                })
+            }
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self, mut seq: A
+            ) -> Result<ControllerRun, A::Error> {
+                use serde::de::Error;
+                let bits: u8 = seq.next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+                if bits & CONTROLLER_V2 == 0 {
+                    return Err(A::Error::custom("controller run is missing the version 2 flag"));
+                }
+                let frames = seq.next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+                Ok(ControllerRun {
+                    controller: Controller::from_bits(bits as u64),
+                    frames
+                })
+            }
+        }
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(RunDeserializer)
+        } else {
+            deserializer.deserialize_tuple(2, RunDeserializer)
+        }
+    }
+}
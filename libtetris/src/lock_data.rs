@@ -8,12 +8,42 @@ pub struct LockResult {
     pub placement_kind: PlacementKind,
     pub locked_out: bool,
     pub b2b: bool,
+    /// Whether this placement ended an active back-to-back chain.
+    pub b2b_broken: bool,
     pub perfect_clear: bool,
     pub combo: Option<u32>,
     pub garbage_sent: u32,
     pub cleared_lines: ArrayVec<[i32; 4]>
 }
 
+impl LockResult {
+    /// The guideline score awarded for this placement at the given level, after the piece was
+    /// soft dropped by `soft_drop` cells and hard dropped by `hard_drop` cells.
+    /// 
+    /// Back-to-back clears are worth 1.5 times as much, combos award 50 points per combo step, and
+    /// perfect clears add a bonus depending on the number of lines cleared. Every cell soft dropped
+    /// is worth 1 point and every cell hard dropped 2 points, regardless of the level.
+    pub fn score(&self, level: u32, soft_drop: u32, hard_drop: u32) -> u32 {
+        let mut score = self.placement_kind.points();
+        if self.b2b {
+            score = score * 3 / 2;
+        }
+        if let Some(combo) = self.combo {
+            score += 50 * combo;
+        }
+        if self.perfect_clear {
+            score += match self.cleared_lines.len() {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if self.b2b => 3200,
+                _ => 2000
+            };
+        }
+        score * level.max(1) + soft_drop + 2 * hard_drop
+    }
+}
+
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum PlacementKind {
     None,
@@ -43,6 +73,26 @@ impl PlacementKind {
         }
     }
 
+    /// The guideline score this clear kind awards at level 1, not counting back-to-back, combo
+    /// and perfect clear bonuses.
+    pub fn points(self) -> u32 {
+        use PlacementKind::*;
+        match self {
+            None => 0,
+            MiniTspin => 100,
+            Clear1 => 100,
+            MiniTspin1 => 200,
+            Clear2 => 300,
+            MiniTspin2 => 400,
+            Tspin => 400,
+            Clear3 => 500,
+            Clear4 => 800,
+            Tspin1 => 800,
+            Tspin2 => 1200,
+            Tspin3 => 1600
+        }
+    }
+
     /// Whether or not this placement does back-to-backs.
     pub fn is_hard(self) -> bool {
         use PlacementKind::*;
@@ -149,7 +199,11 @@ pub struct Statistics {
     pub mini_tspin_singles: u64,
     pub mini_tspin_doubles: u64,
     pub perfect_clears: u64,
-    pub max_combo: u64
+    pub max_combo: u64,
+    /// Key presses beyond the fewest needed, counted for pieces that could have been hard
+    /// dropped into place; see `finesse_presses`.
+    #[serde(default)]
+    pub finesse_faults: u64
 }
 
 impl Statistics {
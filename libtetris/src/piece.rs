@@ -47,9 +47,10 @@ impl FallingPiece {
     }
 
     pub fn sonic_drop<R: Row>(&mut self, board: &Board<R>) -> bool {
-        let drop_by = self.cells()
+        let heights = board.column_heights();
+        let drop_by = self.kind.drop_profile()
             .iter()
-            .map(|&(x, y)| y - board.column_heights()[x as usize])
+            .map(|&(dx, dy)| self.y + dy - heights[(self.x + dx) as usize])
             .min().unwrap();
         if drop_by > 0 {
             self.tspin = TspinStatus::None;
@@ -142,6 +143,40 @@ impl FallingPiece {
         }
         true
     }
+
+    /// The placement flipped horizontally on the board; see `Piece::mirrored`. The t-spin status
+    /// is kept, since the mirrored spin is the same kind of spin.
+    pub fn mirrored(&self) -> FallingPiece {
+        let kind = self.kind.mirrored();
+        let cells = self.cells();
+        let offsets = kind.cells();
+        // the cells of the mirrored piece are the cells of this piece with x flipped
+        let right = cells.iter().map(|&(x, _)| x).max().unwrap();
+        let bottom = cells.iter().map(|&(_, y)| y).min().unwrap();
+        FallingPiece {
+            kind,
+            x: 9 - right - offsets.iter().map(|&(x, _)| x).min().unwrap(),
+            y: bottom - offsets.iter().map(|&(_, y)| y).min().unwrap(),
+            tspin: self.tspin
+        }
+    }
+
+    /// Finds a placement of the piece occupying exactly the given cells, for example to convert a
+    /// placement observed in a game.
+    pub fn from_cells(piece: Piece, cells: [(i32, i32); 4], tspin: TspinStatus) -> Option<Self> {
+        use RotationState::*;
+        for &rotation in &[North, East, South, West] {
+            let kind = PieceState(piece, rotation);
+            let (dx, dy) = kind.cells()[0];
+            for &(x, y) in &cells {
+                let placement = FallingPiece { kind, x: x - dx, y: y - dy, tspin };
+                if placement.cells().iter().all(|c| cells.contains(c)) {
+                    return Some(placement)
+                }
+            }
+        }
+        None
+    }
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -149,7 +184,23 @@ pub enum CellColor {
     I, O, T, L, J, S, Z,
     Garbage,
     Unclearable,
-    Empty
+    Empty,
+    /// A color the frontend assigns a meaning to, such as a skin or a player's color in a
+    /// multiplayer render. Kept through locking and line clears like any other color.
+    Custom(u8)
+}
+
+impl CellColor {
+    /// The color of the mirrored piece; see `Piece::mirrored`.
+    pub fn mirrored(self) -> CellColor {
+        match self {
+            CellColor::L => CellColor::J,
+            CellColor::J => CellColor::L,
+            CellColor::S => CellColor::Z,
+            CellColor::Z => CellColor::S,
+            other => other
+        }
+    }
 }
 
 #[derive(Debug, Hash, EnumSetType, Enum, Serialize, Deserialize)]
@@ -193,6 +244,16 @@ impl RotationState {
         }
     }
 
+    /// The rotation state of the mirrored piece, which swaps east and west.
+    pub fn mirrored(self) -> RotationState {
+        use RotationState::*;
+        match self {
+            East => West,
+            West => East,
+            other => other
+        }
+    }
+
     pub fn mini_tspin_corners(self) -> [(i32, i32); 2] {
         use RotationState::*;
         match self {
@@ -223,6 +284,10 @@ impl PieceState {
         self.1.ccw()
     }
 
+    pub fn mirrored(self) -> PieceState {
+        PieceState(self.0.mirrored(), self.1.mirrored())
+    }
+
     /// Returns the cells this piece and orientation occupy relative to rotation point 1, as well
     /// as the connection directions, in no particular order.
     #[inline(always)]
@@ -250,6 +315,38 @@ impl PieceState {
         CELLS[index]
     }
 
+    /// Returns the lowest cell of every column this piece and orientation occupies, relative to
+    /// rotation point 1.
+    /// 
+    /// Only these cells can land on the stack, so the distance a piece can drop is found by
+    /// comparing them against the column heights instead of checking all four cells.
+    #[inline(always)]
+    pub fn drop_profile(&self) -> &'static [(i32, i32)] {
+        const PROFILES: &'static [&'static [(i32, i32)]] = &[
+            // I
+            &[(-1, 0), (0, 0), (1, 0), (2, 0)], &[(-2, 0), (-1, 0), (0, 0), (1, 0)],
+            &[(0, -2)], &[(0, -1)],
+            // O
+            &[(0, 0), (1, 0)], &[(-1, -1), (0, -1)], &[(0, -1), (1, -1)], &[(-1, 0), (0, 0)],
+            // T
+            &[(-1, 0), (0, 0), (1, 0)], &[(-1, 0), (0, -1), (1, 0)],
+            &[(0, -1), (1, 0)], &[(-1, 0), (0, -1)],
+            // L
+            &[(-1, 0), (0, 0), (1, 0)], &[(-1, -1), (0, 0), (1, 0)],
+            &[(0, -1), (1, -1)], &[(-1, 1), (0, -1)],
+            // J
+            &[(-1, 0), (0, 0), (1, 0)], &[(-1, 0), (0, 0), (1, -1)],
+            &[(0, -1), (1, 1)], &[(-1, -1), (0, -1)],
+            // S
+            &[(-1, 0), (0, 0), (1, 1)], &[(-1, -1), (0, -1), (1, 0)],
+            &[(0, 0), (1, -1)], &[(-1, 0), (0, -1)],
+            // Z
+            &[(-1, 1), (0, 0), (1, 0)], &[(-1, 0), (0, -1), (1, -1)],
+            &[(0, -1), (1, 0)], &[(-1, -1), (0, 0)]
+        ];
+        PROFILES[self.0 as usize * 4 + self.1 as usize]
+    }
+
     pub fn cells_with_connections(&self) -> [(i32, i32, EnumSet<Direction>); 4] {
         use Direction::*;
         let rotate = |d: EnumSet<_>| match self.1 {
@@ -342,6 +439,17 @@ impl rand::distributions::Distribution<Piece> for rand::distributions::Standard
 }
 
 impl Piece {
+    /// The piece whose shape is this piece flipped horizontally, swapping L with J and S with Z.
+    pub fn mirrored(self) -> Piece {
+        match self {
+            Piece::L => Piece::J,
+            Piece::J => Piece::L,
+            Piece::S => Piece::Z,
+            Piece::Z => Piece::S,
+            other => other
+        }
+    }
+
     pub fn to_char(self) -> char {
         match self {
             Piece::I => 'I',
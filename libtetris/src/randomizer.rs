@@ -0,0 +1,261 @@
+use arrayvec::ArrayVec;
+use enumset::EnumSet;
+use rand::prelude::*;
+use serde::{ Serialize, Deserialize };
+
+use crate::*;
+
+/// Generates the sequence of pieces in the next queue.
+pub trait Randomizer {
+    /// The pieces that can be generated next.
+    fn possible(&self) -> EnumSet<Piece>;
+
+    /// Randomly selects the next piece.
+    ///
+    /// This does not update the state; `observe` should be called with the piece once it is added
+    /// to the queue.
+    fn generate(&self, rng: &mut impl Rng) -> Piece;
+
+    /// How likely `piece` is to be generated next, relative to the weights of the other pieces.
+    /// Pieces that can't be generated next have a weight of 0.
+    fn weight(&self, piece: Piece) -> u32;
+
+    /// Updates the state after `piece` was added to the queue.
+    fn observe(&mut self, piece: Piece);
+}
+
+/// Selects the randomizer that generates the pieces of a game.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum RandomizerKind {
+    SevenBag,
+    FourteenBag,
+    Memoryless,
+    Tgm
+}
+
+impl Default for RandomizerKind {
+    fn default() -> Self {
+        RandomizerKind::SevenBag
+    }
+}
+
+impl RandomizerKind {
+    pub fn start(self) -> RandomizerState {
+        match self {
+            RandomizerKind::SevenBag => RandomizerState::SevenBag,
+            RandomizerKind::FourteenBag => RandomizerState::FourteenBag(FourteenBag::default()),
+            RandomizerKind::Memoryless => RandomizerState::Memoryless(Memoryless),
+            RandomizerKind::Tgm => RandomizerState::Tgm(HistoryReroll::default())
+        }
+    }
+}
+
+/// The state of the randomizer of a board.
+///
+/// The state of the 7-bag randomizer is the board's `bag`, which is kept as the set of possible
+/// next pieces for the other randomizers.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RandomizerState {
+    SevenBag,
+    FourteenBag(FourteenBag),
+    Memoryless(Memoryless),
+    Tgm(HistoryReroll)
+}
+
+impl Default for RandomizerState {
+    fn default() -> Self {
+        RandomizerState::SevenBag
+    }
+}
+
+impl RandomizerState {
+    pub fn kind(&self) -> RandomizerKind {
+        match self {
+            RandomizerState::SevenBag => RandomizerKind::SevenBag,
+            RandomizerState::FourteenBag(_) => RandomizerKind::FourteenBag,
+            RandomizerState::Memoryless(_) => RandomizerKind::Memoryless,
+            RandomizerState::Tgm(_) => RandomizerKind::Tgm
+        }
+    }
+
+    /// The state the randomizer would be in if every piece it dealt had been mirrored; see
+    /// `Piece::mirrored`.
+    pub fn mirrored(&self) -> Self {
+        match self {
+            RandomizerState::FourteenBag(r) => {
+                let mut remaining = [0; 7];
+                for piece in EnumSet::<Piece>::all() {
+                    remaining[piece.mirrored() as usize] = r.remaining[piece as usize];
+                }
+                RandomizerState::FourteenBag(FourteenBag { remaining })
+            }
+            RandomizerState::Tgm(r) => {
+                let mut history = r.history;
+                for piece in &mut history {
+                    *piece = piece.mirrored();
+                }
+                RandomizerState::Tgm(HistoryReroll { history, started: r.started })
+            }
+            other => other.clone()
+        }
+    }
+}
+
+/// The 7-bag randomizer, whose state is the set of pieces left in the bag. Every piece is dealt
+/// once per bag and the bag is refilled when it becomes empty.
+impl Randomizer for EnumSet<Piece> {
+    fn possible(&self) -> EnumSet<Piece> {
+        *self
+    }
+
+    fn generate(&self, rng: &mut impl Rng) -> Piece {
+        let choices: ArrayVec<[_; 7]> = self.iter().collect();
+        *choices.choose(rng).unwrap()
+    }
+
+    fn weight(&self, piece: Piece) -> u32 {
+        self.contains(piece) as u32
+    }
+
+    fn observe(&mut self, piece: Piece) {
+        self.remove(piece);
+        if self.is_empty() {
+            *self = EnumSet::all();
+        }
+    }
+}
+
+/// A bag containing two of every piece.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct FourteenBag {
+    remaining: [u8; 7]
+}
+
+impl Default for FourteenBag {
+    fn default() -> Self {
+        FourteenBag {
+            remaining: [2; 7]
+        }
+    }
+}
+
+impl Randomizer for FourteenBag {
+    fn possible(&self) -> EnumSet<Piece> {
+        EnumSet::all().iter().filter(|&p| self.remaining[p as usize] > 0).collect()
+    }
+
+    fn generate(&self, rng: &mut impl Rng) -> Piece {
+        let total: u8 = self.remaining.iter().sum();
+        let mut index = rng.gen_range(0, total);
+        for piece in EnumSet::<Piece>::all() {
+            let count = self.remaining[piece as usize];
+            if index < count {
+                return piece;
+            }
+            index -= count;
+        }
+        unreachable!()
+    }
+
+    fn weight(&self, piece: Piece) -> u32 {
+        self.remaining[piece as usize] as u32
+    }
+
+    fn observe(&mut self, piece: Piece) {
+        let count = &mut self.remaining[piece as usize];
+        *count = count.saturating_sub(1);
+        if self.remaining.iter().all(|&c| c == 0) {
+            self.remaining = [2; 7];
+        }
+    }
+}
+
+/// Every piece is equally likely regardless of what came before.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Memoryless;
+
+impl Randomizer for Memoryless {
+    fn possible(&self) -> EnumSet<Piece> {
+        EnumSet::all()
+    }
+
+    fn generate(&self, rng: &mut impl Rng) -> Piece {
+        rng.gen()
+    }
+
+    fn weight(&self, _: Piece) -> u32 {
+        1
+    }
+
+    fn observe(&mut self, _: Piece) {}
+}
+
+/// The number of times the TGM randomizer rolls for a piece that isn't in its history.
+const TGM_ROLLS: usize = 4;
+
+/// The randomizer of Tetris: The Grand Master. A piece that is one of the last 4 pieces is
+/// rerolled up to 3 times, and the first piece is never S, Z or O.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryReroll {
+    history: [Piece; 4],
+    started: bool
+}
+
+impl Default for HistoryReroll {
+    fn default() -> Self {
+        HistoryReroll {
+            history: [Piece::Z; 4],
+            started: false
+        }
+    }
+}
+
+impl Randomizer for HistoryReroll {
+    fn possible(&self) -> EnumSet<Piece> {
+        if self.started {
+            EnumSet::all()
+        } else {
+            Piece::I | Piece::J | Piece::L | Piece::T
+        }
+    }
+
+    fn generate(&self, rng: &mut impl Rng) -> Piece {
+        if !self.started {
+            return self.possible().generate(rng);
+        }
+        let mut piece = rng.gen();
+        for _ in 1..TGM_ROLLS {
+            if !self.history.contains(&piece) {
+                break
+            }
+            piece = rng.gen();
+        }
+        piece
+    }
+
+    fn weight(&self, piece: Piece) -> u32 {
+        if !self.started {
+            return self.possible().weight(piece);
+        }
+        // Out of 7^TGM_ROLLS equally likely sequences of rolls, a piece in the history is only
+        // kept if every roll lands in the history, while a piece that isn't is kept the first time
+        // it's rolled.
+        let mut history = EnumSet::new();
+        for &p in &self.history {
+            history.insert(p);
+        }
+        let in_history = history.len() as u32;
+        let rolls = TGM_ROLLS as u32;
+        if history.contains(piece) {
+            in_history.pow(rolls - 1)
+        } else {
+            (0..rolls).map(|i| in_history.pow(i) * 7u32.pow(rolls - 1 - i)).sum()
+        }
+    }
+
+    fn observe(&mut self, piece: Piece) {
+        self.started = true;
+        self.history.rotate_left(1);
+        self.history[3] = piece;
+    }
+}
@@ -0,0 +1,244 @@
+use opening_book::{ BookBuilder, Position, possible_sequences };
+use libtetris::{ Board, FallingPiece, Piece };
+use cold_clear::evaluation::{ DynEvaluator, EvaluatorConfig };
+use cold_clear::puzzle::{ Goal, Puzzle };
+use battle::{ AttackTable, GameConfig };
+use enumset::EnumSet;
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+/// Command line settings.
+///
+/// `--evaluator FILE` loads the evaluator to search with from a JSON `EvaluatorConfig`.
+/// `--game FILE` loads a JSON `GameConfig` whose attack table is used to value book lines, so
+/// books can be regenerated for games with different attack tables.
+/// `--pieces N` sets how many pieces deep the book goes (default 7, the first bag).
+/// `--nodes N` sets the number of nodes searched for every queue (default 10000).
+/// `--pc` builds a perfect clear opener book by solving every 10 piece queue exactly instead of
+/// searching.
+/// `--threads N` sets the number of threads (default all cores).
+/// `--output FILE` sets where the book is written (default `book.ccbook`).
+struct Settings {
+    evaluator: EvaluatorConfig,
+    attack_table: AttackTable,
+    pieces: usize,
+    nodes: u32,
+    pc: bool,
+    threads: usize,
+    output: String
+}
+
+fn parse_args() -> Settings {
+    let mut settings = Settings {
+        evaluator: EvaluatorConfig::default(),
+        attack_table: AttackTable::default(),
+        pieces: 7,
+        nodes: 10_000,
+        pc: false,
+        threads: 0,
+        output: "book.ccbook".to_owned()
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--evaluator" => {
+                let path = args.next().expect("--evaluator requires a file");
+                settings.evaluator = read_json(&path);
+            }
+            "--game" => {
+                let path = args.next().expect("--game requires a file");
+                let config: GameConfig = read_json(&path);
+                settings.attack_table = config.attack_table;
+            }
+            "--pieces" => settings.pieces = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--pieces requires a positive number"),
+            "--nodes" => settings.nodes = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--nodes requires a positive number"),
+            "--pc" => settings.pc = true,
+            "--threads" => settings.threads = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--threads requires a positive number"),
+            "--output" => settings.output = args.next().expect("--output requires a file"),
+            _ => eprintln!("ignoring unknown argument {}", arg)
+        }
+    }
+    settings
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &str) -> T {
+    let file = std::fs::File::open(path).unwrap_or_else(
+        |e| panic!("could not open {}: {}", path, e)
+    );
+    serde_json::from_reader(file).unwrap_or_else(
+        |e| panic!("{} contained invalid data: {}", path, e)
+    )
+}
+
+fn main() {
+    let settings = parse_args();
+    rayon::ThreadPoolBuilder::new().num_threads(settings.threads).build_global().unwrap();
+
+    let t = std::time::Instant::now();
+    let mut book = if settings.pc {
+        pc_book(&settings)
+    } else {
+        search_book(&settings)
+    };
+    println!("Took {:?} to generate moves", t.elapsed());
+
+    let t = std::time::Instant::now();
+    book.recalculate_graph();
+    println!("Took {:?} to calculate", t.elapsed());
+
+    let root: Position = Board::new().into();
+    let value = book.value_of_position(root);
+    println!("Root value {} with {} long moves", value.value, value.long_moves);
+
+    let t = std::time::Instant::now();
+    let compiled = book.compile(&[root]);
+    compiled.save(std::io::BufWriter::new(
+        std::fs::File::create(&settings.output).unwrap()
+    )).unwrap();
+    println!("Took {:?} to compile and save {}", t.elapsed(), settings.output);
+}
+
+/// Builds a book by searching every queue of every position the bot reaches from an empty board.
+///
+/// The last move of every line is valued by the attack the bot's plan sends from there on, so
+/// attack sent by earlier book moves isn't counted.
+fn search_book(settings: &Settings) -> BookBuilder {
+    let eval = settings.evaluator.build();
+    let mut book = BookBuilder::new();
+    let root: Position = Board::new().into();
+    let mut seen = HashSet::new();
+    seen.insert(root);
+    let mut frontier = vec![root];
+    for depth in 0..settings.pieces {
+        let last = depth + 1 == settings.pieces;
+        let queues: Vec<_> = frontier.iter()
+            .flat_map(|&pos| pos.next_possibilities().into_iter().flat_map(move |(next, bag)| {
+                possible_sequences(vec![], bag).into_iter()
+                    .map(move |(queue, _)| (pos, next, queue))
+            }))
+            .collect();
+        println!(
+            "Searching piece {} ({} positions, {} queues)",
+            depth + 1, frontier.len(), queues.len()
+        );
+        let results: Vec<_> = queues.into_par_iter()
+            .filter_map(|(pos, next, queue)| {
+                search(board_for(pos, next, &queue), &eval, settings)
+                    .map(|(mv, attack)| (pos, mv, attack))
+            })
+            .collect();
+
+        let mut next_frontier = vec![];
+        for (pos, mv, attack) in results {
+            book.add_move(pos, mv, if last { Some(attack as f32) } else { None });
+            let child = pos.advance(mv).0;
+            if !last && seen.insert(child) {
+                next_frontier.push(child);
+            }
+        }
+        frontier = next_frontier;
+    }
+    book
+}
+
+/// Builds a board in `pos` where `next` are the current piece and the hold piece (or the piece
+/// after the current one if nothing is held), followed by `queue`.
+fn board_for(pos: Position, next: EnumSet<Piece>, queue: &[Piece]) -> Board {
+    let mut field = [[false; 10]; 40];
+    for (y, &row) in pos.rows().iter().enumerate() {
+        for x in 0..10 {
+            field[y][x] = row & 1 << x != 0;
+        }
+    }
+    let mut board = Board::new_with_state(field, pos.bag(), pos.extra(), false, 0);
+    match pos.extra() {
+        Some(hold) => board.add_next_piece((next - hold).iter().next().unwrap_or(hold)),
+        None => for p in next {
+            board.add_next_piece(p);
+        }
+    }
+    for &p in queue {
+        board.add_next_piece(p);
+    }
+    board
+}
+
+/// Searches the board and returns the bot's move along with the attack its plan sends.
+fn search(
+    board: Board, eval: &Box<DynEvaluator>, settings: &Settings
+) -> Option<(FallingPiece, u32)> {
+    let options = cold_clear::Options {
+        max_nodes: settings.nodes,
+        ..Default::default()
+    };
+    let mut bot = cold_clear::BotState::<Box<DynEvaluator>>::new(board, options);
+    while let Ok(thinker) = bot.think() {
+        bot.finish_thinking(thinker.think(eval));
+    }
+    let plan = bot.plan();
+    let &(mv, _) = plan.first()?;
+    let attack = plan.iter().map(|(_, lock)| settings.attack_table.attack(lock)).sum();
+    Some((mv, attack))
+}
+
+/// Builds a perfect clear opener book by solving every queue of the first 10 pieces.
+///
+/// Solutions are valued by the attack they send in total, perfect clear included.
+fn pc_book(settings: &Settings) -> BookBuilder {
+    let queues = bag_sequences(10);
+    println!("Solving {} queues", queues.len());
+    let solutions: Vec<_> = queues.into_par_iter()
+        .filter_map(|queue| {
+            let mut puzzle = Puzzle::new(Board::new(), queue, Goal::PerfectClear);
+            puzzle.max_height = Some(4);
+            puzzle.solve().ok()
+        })
+        .collect();
+    println!("Found {} solutions", solutions.len());
+
+    let mut book = BookBuilder::new();
+    for solution in solutions {
+        let mut board = Board::<u16>::new();
+        let mut pos: Position = (&board).into();
+        let mut attack = 0;
+        let mut placements = vec![];
+        for mv in &solution {
+            attack += settings.attack_table.attack(&board.lock_piece(mv.expected_location));
+            placements.push((pos, mv.expected_location));
+            pos = pos.advance(mv.expected_location).0;
+        }
+        if let Some((&(last_pos, last), rest)) = placements.split_last() {
+            for &(pos, mv) in rest {
+                book.add_move(pos, mv, None);
+            }
+            book.add_move(last_pos, last, Some(attack as f32));
+        }
+    }
+    book
+}
+
+/// Every sequence of `len` pieces that a 7-bag randomizer can deal starting from a fresh bag.
+fn bag_sequences(len: usize) -> Vec<Vec<Piece>> {
+    fn extend(q: &mut Vec<Piece>, bag: EnumSet<Piece>, len: usize, out: &mut Vec<Vec<Piece>>) {
+        if q.len() == len {
+            out.push(q.clone());
+            return
+        }
+        for p in bag {
+            let rest = bag - p;
+            q.push(p);
+            extend(q, if rest.is_empty() { EnumSet::all() } else { rest }, len, out);
+            q.pop();
+        }
+    }
+
+    let mut result = vec![];
+    extend(&mut vec![], EnumSet::all(), len, &mut result);
+    result
+}
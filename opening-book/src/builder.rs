@@ -231,7 +231,10 @@ impl BookBuilder {
                 moves
             });
         }
-        Book(book)
+        Book {
+            positions: book,
+            openers: vec![]
+        }
     }
 
     fn build_position(&mut self, pos: &Position) -> Vec<(Sequence, Option<CompactPiece>)> {
@@ -6,13 +6,22 @@ use std::io::prelude::*;
 
 const NEXT_PIECES: usize = 4;
 
+mod opener;
+pub use opener::{ Opener, OpenerMove };
+
 #[cfg(feature = "builder")]
 mod builder;
 #[cfg(feature = "builder")]
 pub use builder::BookBuilder;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Book(HashMap<Position, Vec<(Sequence, Option<CompactPiece>)>>);
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Book {
+    positions: HashMap<Position, Vec<(Sequence, Option<CompactPiece>)>>,
+    /// Hand-written openers, which take precedence over the computed positions. They are loaded
+    /// from their own JSON files and aren't part of the saved book.
+    #[serde(skip)]
+    openers: Vec<Opener>
+}
 
 impl Book {
     pub fn load(from: impl Read) -> Result<Self, bincode::Error> {
@@ -26,7 +35,22 @@ impl Book {
         Ok(())
     }
 
+    /// A book containing only hand-written openers.
+    pub fn from_openers(openers: Vec<Opener>) -> Self {
+        Book {
+            positions: HashMap::new(),
+            openers
+        }
+    }
+
+    pub fn add_opener(&mut self, opener: Opener) {
+        self.openers.push(opener);
+    }
+
     pub fn suggest_move(&self, state: &Board) -> Option<FallingPiece> {
+        if let Some(mv) = self.openers.iter().find_map(|o| o.suggest_move(state)) {
+            return Some(mv);
+        }
         let position = state.into();
         let mut next = EnumSet::empty();
         let mut q = state.next_queue();
@@ -44,7 +68,7 @@ impl Book {
         &self, pos: Position, next: EnumSet<Piece>, queue: [Piece; NEXT_PIECES]
     ) -> Option<FallingPiece> {
         let to_find = Sequence { next, queue };
-        let moves = self.0.get(&pos)?;
+        let moves = self.positions.get(&pos)?;
         match moves.binary_search_by_key(&to_find, |&(s,_)| s) {
             Result::Ok(i) => moves[i].1.map(Into::into),
             Result::Err(i) => moves[i-1].1.map(Into::into)
@@ -52,9 +76,10 @@ impl Book {
     }
 
     pub fn merge(&mut self, other: Book) {
-        for (pos, data) in other.0 {
-            self.0.entry(pos).or_insert(data);
+        for (pos, data) in other.positions {
+            self.positions.entry(pos).or_insert(data);
         }
+        self.openers.extend(other.openers);
     }
 }
 
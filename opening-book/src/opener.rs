@@ -0,0 +1,129 @@
+use libtetris::{ Board, FallingPiece, Piece, PieceState, RotationState, TspinStatus };
+use serde::{ Serialize, Deserialize };
+
+/// A hand-written opener such as TKI, the DT cannon or the perfect clear opener.
+///
+/// An opener is a list of moves, each giving the placement to make on one board state. Openers
+/// that branch on the order of the first bags have several moves for the same board, each
+/// requiring a different piece order; the first move that applies is used. Once no move applies
+/// the opener is over and the bot goes back to searching.
+///
+/// Openers are stored as JSON, for example
+/// `{"name": "TKI", "moves": [{"field": [], "piece": "I", "rotation": "North", "x": 4, "y": 0}]}`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Opener {
+    pub name: String,
+    pub moves: Vec<OpenerMove>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OpenerMove {
+    /// The board the move is made on, top row first, with `#` for filled cells and `.` for empty
+    /// ones. Rows above the ones given are empty.
+    pub field: Vec<String>,
+    /// Pieces that must come in this order, counting the hold piece as coming first. Other
+    /// pieces may come between them.
+    #[serde(default)]
+    pub order: Vec<Piece>,
+    pub piece: Piece,
+    pub rotation: RotationState,
+    /// The position of the piece, as in `FallingPiece`.
+    pub x: i32,
+    pub y: i32
+}
+
+impl Opener {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let opener: Opener = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        for mv in &opener.moves {
+            if mv.field.len() > 40 {
+                return Err("fields can have at most 40 rows".to_owned());
+            }
+            if let Some(row) = mv.field.iter().find(|r| r.len() != 10) {
+                return Err(format!("row {:?} doesn't have 10 cells", row));
+            }
+        }
+        Ok(opener)
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        Opener::parse(&std::fs::read_to_string(path).map_err(|e| e.to_string())?)
+    }
+
+    /// The move this opener makes on `board`, if the opener applies to it.
+    pub fn suggest_move(&self, board: &Board) -> Option<FallingPiece> {
+        let mut queue = board.next_queue();
+        let current = queue.next()?;
+        let available = match board.hold_piece {
+            Some(hold) => [current, hold],
+            None => [current, queue.next()?]
+        };
+        let sequence: Vec<_> = board.hold_piece.into_iter().chain(board.next_queue()).collect();
+        let field = board.get_field();
+        self.moves.iter()
+            .find(|mv| available.contains(&mv.piece)
+                && mv.matches_field(&field)
+                && is_subsequence(&mv.order, &sequence))
+            .map(OpenerMove::placement)
+    }
+
+    /// The number of placements at the start of a game that followed this opener, or `None` if
+    /// the game left the opener before reaching its end. Garbage received is not taken into
+    /// account, so openers are only recognized up to the first garbage.
+    pub fn followed_for(&self, placements: &[FallingPiece]) -> Option<usize> {
+        let mut board = Board::<u16>::new();
+        for (i, placement) in placements.iter().enumerate() {
+            let field = board.get_field();
+            let mut applicable = self.moves.iter()
+                .filter(|mv| mv.matches_field(&field))
+                .peekable();
+            if applicable.peek().is_none() {
+                // the opener is over
+                return Some(i).filter(|&i| i > 0);
+            }
+            if !applicable.any(|mv| mv.placement().same_location(placement)) {
+                return None;
+            }
+            board.lock_piece(*placement);
+        }
+        Some(placements.len()).filter(|&n| n > 0)
+    }
+
+    /// Recognizes the opener a game started with from its first placements. When several openers
+    /// fit, the one followed for the most placements is picked.
+    pub fn recognize<'a>(
+        openers: &'a [Opener], placements: &[FallingPiece]
+    ) -> Option<&'a Opener> {
+        openers.iter()
+            .filter_map(|o| o.followed_for(placements).map(|n| (n, o)))
+            .max_by_key(|&(n, _)| n)
+            .map(|(_, o)| o)
+    }
+}
+
+impl OpenerMove {
+    pub fn placement(&self) -> FallingPiece {
+        FallingPiece {
+            kind: PieceState(self.piece, self.rotation),
+            x: self.x,
+            y: self.y,
+            tspin: TspinStatus::None
+        }
+    }
+
+    fn matches_field(&self, field: &[[bool; 10]; 40]) -> bool {
+        let height = self.field.len();
+        field.iter().enumerate().all(|(y, row)| {
+            let expected = match height.checked_sub(y + 1) {
+                Some(i) => self.field[i].as_bytes(),
+                None => b".........."
+            };
+            row.iter().zip(expected).all(|(&filled, &c)| filled == (c == b'#'))
+        })
+    }
+}
+
+fn is_subsequence(order: &[Piece], sequence: &[Piece]) -> bool {
+    let mut sequence = sequence.iter();
+    order.iter().all(|p| sequence.any(|s| s == p))
+}
@@ -1,9 +1,9 @@
 use libtetris::{ Board, ColoredRow, FallingPiece, Controller };
-use battle::{ Battle, Replay, Event, PieceMoveExecutor, GameConfig };
+use battle::{ Battle, Replay, Event, PieceMoveExecutor, GameConfig, Seeds };
 use cold_clear::evaluation::Evaluator;
-use rand::prelude::*;
 use serde::{ Serialize, Deserialize };
 use std::collections::VecDeque;
+use std::path::Path;
 
 pub struct BotInput<E: Evaluator> {
     pub controller: Controller,
@@ -15,11 +15,20 @@ pub struct BotInput<E: Evaluator> {
 const THINK_AMOUNT: usize = 10;
 
 impl<E: Evaluator> BotInput<E> {
-    pub fn new(board: Board, eval: E) -> Self {
+    pub fn new(board: Board, eval: E, seed: u64) -> Self {
+        let options = cold_clear::Options {
+            randomizer: board.randomizer.kind(),
+            seed: Some(seed),
+            ..Default::default()
+        };
+        BotInput::with_options(board, eval, options)
+    }
+
+    pub fn with_options(board: Board, eval: E, options: cold_clear::Options) -> Self {
         let mut this = BotInput {
             controller: Controller::default(),
             executing: None,
-            bot: cold_clear::BotState::new(board, Default::default()),
+            bot: cold_clear::BotState::new(board, options),
             eval
         };
         for _ in 0..180 {
@@ -67,6 +76,9 @@ impl<E: Evaluator> BotInput<E> {
                 Event::GarbageAdded(_) => {
                     self.bot.reset(board.get_field(), board.b2b_bonus, board.combo);
                 }
+                Event::AttackMultiplier(multiplier) => {
+                    self.bot.set_attack_multiplier(*multiplier);
+                }
                 _ => {}
             }
         }
@@ -81,21 +93,55 @@ impl<E: Evaluator> BotInput<E> {
         }
         info
     }
+
+    /// Sets the factor points are multiplied by, such as the level in a Blitz game.
+    pub fn set_score_multiplier(&mut self, multiplier: u32) {
+        self.bot.set_score_multiplier(multiplier);
+    }
 }
 
+/// Bot options for playing a single player game with `config`, so the bot's timing model matches
+/// the game's handling.
+pub fn solo_options(config: &GameConfig, board: &Board, seed: u64) -> cold_clear::Options {
+    cold_clear::Options {
+        randomizer: board.randomizer.kind(),
+        use_hold: config.hold_enabled,
+        timing: cold_clear::moves::Timing {
+            soft_drop_speed: config.soft_drop_speed,
+            auto_repeat_rate: config.auto_repeat_rate,
+            hard_drop: config.hard_drop_enabled,
+            soft_drop: config.soft_drop_enabled,
+            ..Default::default()
+        },
+        seed: Some(seed),
+        ..Default::default()
+    }
+}
+
+/// Plays a game between two bots. Every random choice in the game, including those of the bots'
+/// search, is derived from `seed`, so the same seed and evaluators always play the same game.
 pub fn do_battle(
-    p1: impl Evaluator + Clone, p2: impl Evaluator + Clone
+    p1: impl Evaluator + Clone, p2: impl Evaluator + Clone, seed: u64
+) -> Option<(InfoReplay, bool)> {
+    do_battle_observed(p1, p2, seed, |_, _| {})
+}
+
+/// Like `do_battle`, but calls `observe` with each player's board whenever a piece spawns. The
+/// first argument of `observe` is true for player 1.
+pub fn do_battle_observed(
+    p1: impl Evaluator + Clone,
+    p2: impl Evaluator + Clone,
+    seed: u64,
+    mut observe: impl FnMut(bool, &Board<ColoredRow>)
 ) -> Option<(InfoReplay, bool)> {
-    let mut battle = Battle::new(
-        GameConfig::default(), GameConfig::default(),
-        thread_rng().gen(), thread_rng().gen(), thread_rng().gen()
-    );
+    let seeds = Seeds::from_master(seed);
+    let mut battle = Battle::with_seeds(GameConfig::default(), GameConfig::default(), &seeds);
 
     battle.replay.p1_name = format!("Cold Clear\n{}", p1.name());
     battle.replay.p2_name = format!("Cold Clear\n{}", p2.name());
 
-    let mut p1 = BotInput::new(battle.player_1.board.to_compressed(), p1);
-    let mut p2 = BotInput::new(battle.player_2.board.to_compressed(), p2);
+    let mut p1 = BotInput::new(battle.player_1.board.to_compressed(), p1, seeds.p1_bot);
+    let mut p2 = BotInput::new(battle.player_2.board.to_compressed(), p2, seeds.p2_bot);
 
     let mut p1_info_updates = VecDeque::new();
     let mut p2_info_updates = VecDeque::new();
@@ -121,6 +167,7 @@ pub fn do_battle(
                     p1_won = false;
                     break 'battle;
                 }
+                PieceSpawned { .. } => observe(true, &battle.player_1.board),
                 _ => {}
             }
         }
@@ -131,6 +178,7 @@ pub fn do_battle(
                     p1_won = true;
                     break 'battle;
                 }
+                PieceSpawned { .. } => observe(false, &battle.player_2.board),
                 _ => {}
             }
         }
@@ -158,4 +206,10 @@ pub struct InfoReplay {
     pub replay: Replay,
     pub p1_info_updates: VecDeque<Option<cold_clear::Info>>,
     pub p2_info_updates: VecDeque<Option<cold_clear::Info>>
+}
+
+impl InfoReplay {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        self.replay.save_with(&(&self.p1_info_updates, &self.p2_info_updates), path)
+    }
 }
\ No newline at end of file
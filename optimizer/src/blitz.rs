@@ -0,0 +1,57 @@
+use battle::{ Blitz, GameConfig, Seeds };
+use cold_clear::evaluation::ScoreAttack;
+use rand::prelude::*;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use crate::battle::{ BotInput, solo_options };
+
+/// Plays a 1 minute Blitz game, returning the score reached. The bot is told the level whenever
+/// it goes up. Every random choice, including those of the bot's search, is derived from `seed`.
+pub fn do_blitz(eval: ScoreAttack, config: GameConfig, seed: u64) -> u32 {
+    let mut blitz = Blitz::new(config, seed);
+    let board = blitz.game.board.to_compressed();
+    let options = solo_options(&config, &board, Seeds::from_master(seed).p1_bot);
+    let mut bot = BotInput::with_options(board, eval, options);
+    while !blitz.is_finished() {
+        let update = blitz.update(bot.controller);
+        if update.leveled_up {
+            bot.set_score_multiplier(update.level);
+        }
+        bot.update(&blitz.game.board, &update.events, 0);
+    }
+    blitz.score
+}
+
+/// Plays `games` Blitz games with `eval` on the default game configuration and reports the
+/// scores.
+pub fn run_blitzes(games: usize, threads: usize, eval: ScoreAttack) {
+    let config = GameConfig::default();
+    let played = Arc::new(AtomicUsize::new(0));
+    let (send, recv) = channel();
+    for _ in 0..threads.max(1) {
+        let played = played.clone();
+        let send = send.clone();
+        let eval = eval.clone();
+        std::thread::spawn(move || {
+            while played.fetch_add(1, Ordering::SeqCst) < games {
+                if send.send(do_blitz(eval.clone(), config, thread_rng().gen())).is_err() {
+                    break
+                }
+            }
+        });
+    }
+    drop(send);
+
+    let mut scores = vec![];
+    for score in recv {
+        println!("Blitz {}: {}", scores.len() + 1, score);
+        scores.push(score);
+    }
+
+    if let Some(&best) = scores.iter().max() {
+        let average = scores.iter().map(|&s| s as u64).sum::<u64>() as f64 / scores.len() as f64;
+        println!("Best: {}", best);
+        println!("Average: {:.0}", average);
+    }
+}
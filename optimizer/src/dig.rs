@@ -0,0 +1,62 @@
+use battle::{ DigRace, GameConfig, Seeds };
+use cold_clear::evaluation::Standard;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use crate::battle::{ BotInput, solo_options };
+
+/// Rows of cheese kept at the bottom of the board.
+const CHEESE_ROWS: u32 = 10;
+
+/// Plays a 2 minute dig race, returning the cheese rows dug per minute. Every random choice,
+/// including the cheese layout and the bot's search, is derived from `seed`.
+pub fn do_dig_race(eval: Standard, config: GameConfig, seed: u64) -> f64 {
+    let mut race = DigRace::new(config, CHEESE_ROWS, seed);
+    let board = race.game.board.to_compressed();
+    let options = solo_options(&config, &board, Seeds::from_master(seed).p1_bot);
+    let mut bot = BotInput::with_options(board, eval, options);
+    while !race.is_finished() {
+        let update = race.update(bot.controller);
+        bot.update(&race.game.board, &update.events, 0);
+    }
+    race.lines_per_minute()
+}
+
+/// Plays `games` dig races with `eval` on the default game configuration and reports the rows
+/// dug per minute. Game `i` uses the seed `seed + i`, so runs with the same seed dig through the
+/// same cheese and can be compared fairly.
+pub fn run_dig_races(games: usize, threads: usize, eval: Standard, seed: u64) {
+    let config = GameConfig::default();
+    let next = Arc::new(AtomicUsize::new(0));
+    let (send, recv) = channel();
+    for _ in 0..threads.max(1) {
+        let next = next.clone();
+        let send = send.clone();
+        let eval = eval.clone();
+        std::thread::spawn(move || loop {
+            let game = next.fetch_add(1, Ordering::SeqCst);
+            if game >= games {
+                break
+            }
+            let result = do_dig_race(eval.clone(), config, seed.wrapping_add(game as u64));
+            if send.send((game, result)).is_err() {
+                break
+            }
+        });
+    }
+    drop(send);
+
+    let mut results = vec![];
+    for (game, lpm) in recv {
+        println!(
+            "Dig race {} (seed {}): {:.1} lines per minute",
+            game, seed.wrapping_add(game as u64), lpm
+        );
+        results.push(lpm);
+    }
+
+    if !results.is_empty() {
+        let average = results.iter().sum::<f64>() / results.len() as f64;
+        println!("Average: {:.1} lines per minute", average);
+    }
+}
@@ -1,19 +1,143 @@
 use serde::{ Serialize, Deserialize };
 use cold_clear::evaluation::Standard;
 use rand::prelude::*;
-use libflate::deflate;
-use std::sync::{ Arc, Mutex };
-use std::collections::VecDeque;
-use std::sync::mpsc::channel;
 
 mod battle;
 mod mutate;
+mod surface;
+mod sprint;
+mod ultra;
+mod blitz;
+mod dig;
+mod survival;
+mod runner;
 
 use mutate::Mutateable;
+use runner::{ Matchup, run_battles };
 
 const BATTLES: usize = 6;
 
+/// Command line settings.
+/// 
+/// `--threads N` sets the number of games played at once (default 12).
+/// `--baseline FILE` loads a weight set from a JSON file; members are then ranked by the number of
+/// games they win against it instead of by playing a round robin against each other.
+/// `--surface-table GAMES` plays that many self-play games with the baseline (or default) weights
+/// and writes the resulting surface table to `surface-table.txt` instead of optimizing.
+/// `--sprint GAMES` plays that many 40 line sprints with the sprint evaluator and reports the
+/// times instead of optimizing.
+/// `--ultra GAMES` plays that many 2 minute score attack games with the score attack evaluator and
+/// reports the scores instead of optimizing.
+/// `--blitz GAMES` plays that many 1 minute Blitz games with the score attack evaluator and
+/// reports the scores instead of optimizing.
+/// `--dig GAMES` plays that many dig races with the baseline (or downstacker) weights and reports
+/// the rows dug per minute instead of optimizing.
+/// `--survival GAMES` plays that many survival games with the baseline (or default) weights and
+/// reports how long each lasted instead of optimizing.
+/// `--seed N` sets the seed of the first dig race or survival game (default 0).
+struct Settings {
+    threads: usize,
+    baseline: Option<Standard>,
+    surface_table: Option<usize>,
+    sprint: Option<usize>,
+    ultra: Option<usize>,
+    blitz: Option<usize>,
+    dig: Option<usize>,
+    survival: Option<usize>,
+    seed: u64
+}
+
+fn parse_args() -> Settings {
+    let mut settings = Settings {
+        threads: 12,
+        baseline: None,
+        surface_table: None,
+        sprint: None,
+        ultra: None,
+        blitz: None,
+        dig: None,
+        survival: None,
+        seed: 0
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--threads" => settings.threads = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--threads requires a positive number"),
+            "--baseline" => {
+                let path = args.next().expect("--baseline requires a file");
+                let file = std::fs::File::open(&path).unwrap_or_else(
+                    |e| panic!("could not open {}: {}", path, e)
+                );
+                settings.baseline = Some(serde_json::from_reader(file).unwrap_or_else(
+                    |e| panic!("{} contained invalid data: {}", path, e)
+                ));
+            }
+            "--surface-table" => settings.surface_table = Some(args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--surface-table requires a number of games")),
+            "--sprint" => settings.sprint = Some(args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--sprint requires a number of games")),
+            "--ultra" => settings.ultra = Some(args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--ultra requires a number of games")),
+            "--blitz" => settings.blitz = Some(args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--blitz requires a number of games")),
+            "--dig" => settings.dig = Some(args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--dig requires a number of games")),
+            "--survival" => settings.survival = Some(args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--survival requires a number of games")),
+            "--seed" => settings.seed = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--seed requires a number"),
+            _ => eprintln!("ignoring unknown argument {}", arg)
+        }
+    }
+    settings
+}
+
 fn main() {
+    let settings = parse_args();
+
+    if let Some(games) = settings.surface_table {
+        let weights = settings.baseline.clone().unwrap_or_default();
+        let table = surface::build_table(games, settings.threads, weights);
+        std::fs::write("surface-table.txt", table.to_text()).unwrap();
+        return
+    }
+
+    if let Some(games) = settings.sprint {
+        sprint::run_sprints(games, settings.threads, Default::default());
+        return
+    }
+
+    if let Some(games) = settings.ultra {
+        ultra::run_ultras(games, settings.threads, Default::default());
+        return
+    }
+
+    if let Some(games) = settings.blitz {
+        blitz::run_blitzes(games, settings.threads, Default::default());
+        return
+    }
+
+    if let Some(games) = settings.dig {
+        let weights = settings.baseline.clone().unwrap_or_else(Standard::downstacker);
+        dig::run_dig_races(games, settings.threads, weights, settings.seed);
+        return
+    }
+
+    if let Some(games) = settings.survival {
+        let weights = settings.baseline.clone().unwrap_or_default();
+        survival::run_survivals(games, settings.threads, weights, settings.seed);
+        return
+    }
+
     let mut population = match std::fs::File::open("pop.json") {
         Ok(file) => serde_json::from_reader(file).unwrap_or_else(|e| {
             eprintln!("pop.json contained invalid data: {}", e);
@@ -22,71 +146,61 @@ fn main() {
         Err(_) => new_population::<Standard>()
     };
 
-    let matchups = Arc::new(Mutex::new((true, VecDeque::new())));
-    let (send, game_results) = channel();
-    for _ in 0..12 {
-        let matchups = matchups.clone();
-        let send = send.clone();
-        std::thread::spawn(move || {
-            loop {
-                let (p1, p1_e, p2, p2_e) = {
-                    let (active, ref mut queue) = *matchups.lock().unwrap();
-                    if !active { break }
-                    match queue.pop_front() {
-                        Some(v) => v,
-                        None => continue
-                    }
-                };
-                if let Some((replay, p1_won)) = battle::do_battle(p1_e, p2_e) {
-                    send.send(Some((if p1_won { p1 } else { p2 }, replay))).ok();
-                } else {
-                    send.send(None).ok();
-                }
-            }
-        });
-    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(settings.threads.max(1))
+        .build()
+        .unwrap();
 
     loop {
-        let mut count = 0;
-        {
-            let mut matchups = matchups.lock().unwrap();
-            for i in 0..population.members.len() {
+        let mut matchups = vec![];
+        let matchup = |p1: usize, p1_eval: &Standard, p2: usize, p2_eval: &Standard| Matchup {
+            p1, p1_eval: p1_eval.clone(), p2, p2_eval: p2_eval.clone()
+        };
+        match &settings.baseline {
+            Some(baseline) => for i in 0..population.members.len() {
+                // the baseline gets an index that doesn't belong to any member so its wins
+                // aren't counted
+                let baseline_id = population.members.len();
+                for _ in 0..BATTLES {
+                    matchups.push(matchup(i, &population.members[i], baseline_id, baseline));
+                    matchups.push(matchup(baseline_id, baseline, i, &population.members[i]));
+                }
+            }
+            None => for i in 0..population.members.len() {
                 for j in 0..population.members.len() {
                     if i == j { continue }
                     for _ in 0..BATTLES {
-                        matchups.1.push_back((
-                            i, population.members[i].clone(),
-                            j, population.members[j].clone()
+                        matchups.push(matchup(
+                            i, &population.members[i], j, &population.members[j]
                         ));
-                        count += 1;
                     }
                 }
             }
         }
 
-        let mut results = vec![];
-        for i in 0..population.members.len() {
-            results.push((i, 0));
-        }
-        for i in 0..count {
-            if let Some((winner, replay)) = game_results.recv().unwrap() {
-                results[winner].1 += 1;
-
-                let mut encoder = deflate::Encoder::new(
-                    std::fs::File::create("recent-game.dat").unwrap()
-                );
-                bincode::serialize_into(&mut encoder, &replay).unwrap();
-                encoder.finish().unwrap();
-            }
-            if (i+1) % 80 == 0 {
-                println!("Completed game {} of {}", i+1, count);
-            }
+        let battles = run_battles(&pool, &matchups, population.members.len(), thread_rng().gen());
+        println!(
+            "Played {} games ({} draws) at {:.2} games per second",
+            matchups.len(), battles.draws, battles.games_per_second
+        );
+        if let Some(replay) = battles.replay {
+            replay.save("recent-game.dat").unwrap();
         }
 
+        let mut results: Vec<_> = battles.wins.iter()
+            .enumerate()
+            .map(|(i, &wins)| (i, wins as i32))
+            .collect();
         results.sort_by_key(|(_, score)| -score);
         println!("Gen {} Results:", population.generation);
         for &(num, score) in &results {
-            println!("{}: {} wins", population.members[num].name(), score);
+            match settings.baseline {
+                Some(_) => println!(
+                    "{}: {} of {} wins against baseline",
+                    population.members[num].name(), score, 2 * BATTLES
+                ),
+                None => println!("{}: {} wins", population.members[num].name(), score)
+            }
         }
         println!();
 
@@ -107,10 +221,12 @@ fn main() {
             while p1 == p2 {
                 p2 = thread_rng().sample(&weighted);
             }
-            new_population.members[i] = Standard::crossover(
-                &population.members[p1], &population.members[p2],
-                format!("Gen {} #{}", new_population.generation, i-5)
-            );
+            let name = format!("Gen {} #{}", new_population.generation, i-5);
+            new_population.members[i] = if thread_rng().gen_bool(0.2) {
+                Standard::mutate(&population.members[p1], name)
+            } else {
+                Standard::crossover(&population.members[p1], &population.members[p2], name)
+            };
         }
 
         serde_json::to_writer(std::fs::File::create("pop.json").unwrap(), &new_population).unwrap();
@@ -129,8 +245,6 @@ fn main() {
 
         population = new_population;
     }
-
-    matchups.lock().unwrap().0 = false;
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
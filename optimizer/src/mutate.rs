@@ -6,6 +6,9 @@ pub trait Mutateable : Default {
 
     fn crossover(parent1: &Self, parent2: &Self, sub_name: String) -> Self;
 
+    /// Produces a copy of `parent` with some of its genes perturbed.
+    fn mutate(parent: &Self, sub_name: String) -> Self;
+
     fn name(&self) -> &str;
 }
 
@@ -20,12 +23,16 @@ impl Mutateable for Standard {
             top_half: thread_rng().gen_range(-999, 1000),
             top_quarter: thread_rng().gen_range(-999, 1000),
             jeopardy: thread_rng().gen_range(-999, 1000),
+            incoming_height: thread_rng().gen_range(-999, 1000),
             cavity_cells: thread_rng().gen_range(-999, 1000),
             cavity_cells_sq: thread_rng().gen_range(-999, 1000),
             overhang_cells: thread_rng().gen_range(-999, 1000),
             overhang_cells_sq: thread_rng().gen_range(-999, 1000),
             covered_cells: thread_rng().gen_range(-999, 1000),
             covered_cells_sq: thread_rng().gen_range(-999, 1000),
+            dependencies: thread_rng().gen_range(-999, 1000),
+            garbage_rows: thread_rng().gen_range(-999, 1000),
+            garbage_hole_depth: thread_rng().gen_range(-999, 1000),
             tslot: [
                 thread_rng().gen_range(-999, 1000),
                 thread_rng().gen_range(-999, 1000),
@@ -34,6 +41,8 @@ impl Mutateable for Standard {
             ],
             well_depth: thread_rng().gen_range(-999, 1000),
             max_well_depth: thread_rng().gen_range(-999, 1000),
+            pc_opportunity: thread_rng().gen_range(-999, 1000),
+            imminent_tslot: thread_rng().gen_range(-999, 1000),
             well_column: [
                 thread_rng().gen_range(-999, 1000),
                 thread_rng().gen_range(-999, 1000),
@@ -49,6 +58,10 @@ impl Mutateable for Standard {
 
             move_time: thread_rng().gen_range(-999, 1000),
             wasted_t: thread_rng().gen_range(-999, 1000),
+            b2b_break: thread_rng().gen_range(-999, 1000),
+            hard_clear: thread_rng().gen_range(-999, 1000),
+            lethal_spike: thread_rng().gen_range(-999, 1000),
+            banked_spike: thread_rng().gen_range(-999, 1000),
             b2b_clear: thread_rng().gen_range(-999, 1000),
             clear1: thread_rng().gen_range(-999, 1000),
             clear2: thread_rng().gen_range(-999, 1000),
@@ -65,6 +78,10 @@ impl Mutateable for Standard {
             use_bag: true,
             timed_jeopardy: true,
             stack_pc_damage: false,
+            top_half_start: 10,
+            top_quarter_start: 15,
+            panic_height: 20,
+            preferred_well: None,
             sub_name: Some(sub_name)
         }
     }
@@ -79,12 +96,18 @@ impl Mutateable for Standard {
             top_half: crossover_gene(parent1.top_half, parent2.top_half),
             top_quarter: crossover_gene(parent1.top_quarter, parent2.top_quarter),
             jeopardy: crossover_gene(parent1.jeopardy, parent2.jeopardy),
+            incoming_height: crossover_gene(parent1.incoming_height, parent2.incoming_height),
             cavity_cells: crossover_gene(parent1.cavity_cells, parent2.cavity_cells),
             cavity_cells_sq: crossover_gene(parent1.cavity_cells_sq, parent2.cavity_cells_sq),
             overhang_cells: crossover_gene(parent1.overhang_cells, parent2.overhang_cells),
             overhang_cells_sq: crossover_gene(parent1.overhang_cells_sq, parent2.overhang_cells_sq),
             covered_cells: crossover_gene(parent1.covered_cells, parent2.covered_cells),
             covered_cells_sq: crossover_gene(parent1.covered_cells_sq, parent2.covered_cells_sq),
+            dependencies: crossover_gene(parent1.dependencies, parent2.dependencies),
+            garbage_rows: crossover_gene(parent1.garbage_rows, parent2.garbage_rows),
+            garbage_hole_depth: crossover_gene(
+                parent1.garbage_hole_depth, parent2.garbage_hole_depth
+            ),
             tslot: [
                 crossover_gene(parent1.tslot[0], parent2.tslot[0]),
                 crossover_gene(parent1.tslot[1], parent2.tslot[1]),
@@ -93,6 +116,8 @@ impl Mutateable for Standard {
             ],
             well_depth: crossover_gene(parent1.well_depth, parent2.well_depth),
             max_well_depth: crossover_gene(parent1.max_well_depth, parent2.max_well_depth),
+            pc_opportunity: crossover_gene(parent1.pc_opportunity, parent2.pc_opportunity),
+            imminent_tslot: crossover_gene(parent1.imminent_tslot, parent2.imminent_tslot),
             well_column: [
                 crossover_gene(parent1.well_column[0], parent2.well_column[0]),
                 crossover_gene(parent1.well_column[1], parent2.well_column[1]),
@@ -108,6 +133,10 @@ impl Mutateable for Standard {
 
             move_time: crossover_gene(parent1.move_time, parent2.move_time),
             wasted_t: crossover_gene(parent1.wasted_t, parent2.wasted_t),
+            b2b_break: crossover_gene(parent1.b2b_break, parent2.b2b_break),
+            hard_clear: crossover_gene(parent1.hard_clear, parent2.hard_clear),
+            lethal_spike: crossover_gene(parent1.lethal_spike, parent2.lethal_spike),
+            banked_spike: crossover_gene(parent1.banked_spike, parent2.banked_spike),
             b2b_clear: crossover_gene(parent1.b2b_clear, parent2.b2b_clear),
             clear1: crossover_gene(parent1.clear1, parent2.clear1),
             clear2: crossover_gene(parent1.clear2, parent2.clear2),
@@ -124,10 +153,23 @@ impl Mutateable for Standard {
             use_bag: true,
             timed_jeopardy: true,
             stack_pc_damage: false,
+            top_half_start: 10,
+            top_quarter_start: 15,
+            panic_height: 20,
+            preferred_well: None,
             sub_name: Some(sub_name)
         }
     }
 
+    fn mutate(parent: &Self, sub_name: String) -> Self {
+        let mut child = parent.clone();
+        for gene in genes_mut(&mut child) {
+            *gene = mutate_gene(*gene);
+        }
+        child.sub_name = Some(sub_name);
+        child
+    }
+
     fn name(&self) -> &str {
         self.sub_name.as_ref().map(|s| &**s).unwrap_or("")
     }
@@ -147,4 +189,35 @@ fn crossover_gene(v1: i32, v2: i32) -> i32 {
     } else {
         v
     }
-}
\ No newline at end of file
+}
+
+fn genes_mut(s: &mut Standard) -> Vec<&mut i32> {
+    let mut genes = vec![
+        &mut s.back_to_back, &mut s.bumpiness, &mut s.bumpiness_sq, &mut s.row_transitions,
+        &mut s.height, &mut s.top_half, &mut s.top_quarter, &mut s.jeopardy,
+        &mut s.cavity_cells, &mut s.cavity_cells_sq, &mut s.overhang_cells,
+        &mut s.overhang_cells_sq, &mut s.covered_cells, &mut s.covered_cells_sq,
+        &mut s.well_depth, &mut s.max_well_depth,
+        &mut s.move_time, &mut s.wasted_t, &mut s.b2b_clear,
+        &mut s.clear1, &mut s.clear2, &mut s.clear3, &mut s.clear4,
+        &mut s.tspin1, &mut s.tspin2, &mut s.tspin3, &mut s.mini_tspin1, &mut s.mini_tspin2,
+        &mut s.perfect_clear, &mut s.combo_garbage,
+        &mut s.dependencies, &mut s.imminent_tslot, &mut s.pc_opportunity,
+        &mut s.garbage_rows, &mut s.garbage_hole_depth, &mut s.b2b_break, &mut s.hard_clear,
+        &mut s.incoming_height,
+        &mut s.lethal_spike, &mut s.banked_spike
+    ];
+    genes.extend(s.tslot.iter_mut());
+    genes.extend(s.well_column.iter_mut());
+    genes
+}
+
+fn mutate_gene(v: i32) -> i32 {
+    // most genes are left alone, some are nudged, and very few are rerolled entirely
+    let v = match thread_rng().gen_range(0, 100) {
+        0..=79 => v,
+        80..=97 => v + thread_rng().gen_range(-50, 51),
+        _ => thread_rng().gen_range(-999, 1000)
+    };
+    v.max(-999).min(999)
+}
@@ -0,0 +1,71 @@
+use cold_clear::evaluation::Evaluator;
+use rayon::prelude::*;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::time::Instant;
+use crate::battle::{ InfoReplay, do_battle };
+
+/// A game to be played between two members of the population.
+///
+/// The indices are only used to attribute wins; an index outside of the population (such as the
+/// baseline's) plays normally but its wins aren't counted.
+pub struct Matchup<E> {
+    pub p1: usize,
+    pub p1_eval: E,
+    pub p2: usize,
+    pub p2_eval: E
+}
+
+pub struct BattleResults {
+    /// The number of games won by every member of the population.
+    pub wins: Vec<u32>,
+    /// The number of games that went on too long to have a winner.
+    pub draws: usize,
+    pub games_per_second: f64,
+    /// The replay of the last game that had a winner.
+    pub replay: Option<InfoReplay>
+}
+
+/// Plays all of the matchups on `pool`, many games at once.
+///
+/// Game `i` is played with the seed `seed + i`, so every game has its own random state no matter
+/// which thread plays it and the same seed always plays the same games.
+pub fn run_battles<E: Evaluator + Clone>(
+    pool: &rayon::ThreadPool, matchups: &[Matchup<E>], members: usize, seed: u64
+) -> BattleResults {
+    let start = Instant::now();
+    let completed = AtomicUsize::new(0);
+    let outcomes: Vec<_> = pool.install(|| matchups.par_iter()
+        .enumerate()
+        .map(|(i, m)| {
+            let result = do_battle(
+                m.p1_eval.clone(), m.p2_eval.clone(), seed.wrapping_add(i as u64)
+            );
+            let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+            if done % 80 == 0 {
+                println!("Completed game {} of {}", done, matchups.len());
+            }
+            result.map(|(replay, p1_won)| (if p1_won { m.p1 } else { m.p2 }, replay))
+        })
+        .collect()
+    );
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let mut results = BattleResults {
+        wins: vec![0; members],
+        draws: 0,
+        games_per_second: if elapsed > 0.0 { matchups.len() as f64 / elapsed } else { 0.0 },
+        replay: None
+    };
+    for outcome in outcomes {
+        match outcome {
+            Some((winner, replay)) => {
+                if winner < members {
+                    results.wins[winner] += 1;
+                }
+                results.replay = Some(replay);
+            }
+            None => results.draws += 1
+        }
+    }
+    results
+}
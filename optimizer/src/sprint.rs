@@ -0,0 +1,94 @@
+use battle::{ Event, Game, GameConfig, Seeds };
+use cold_clear::evaluation::Sprint;
+use rand::prelude::*;
+use rand_pcg::Pcg64Mcg;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use crate::battle::BotInput;
+
+const SPRINT_LINES: usize = 40;
+
+/// 15 minutes
+const TIME_LIMIT: u32 = 54000;
+
+/// Plays a 40 line sprint, returning the number of ticks it took, or `None` if the bot topped out
+/// or ran out of time. The bot's timing model and the evaluator's delays are set from `config`.
+pub fn do_sprint(eval: Sprint, config: GameConfig, seed: u64) -> Option<u32> {
+    let seeds = Seeds::from_master(seed);
+    let mut piece_rng = Pcg64Mcg::from_seed(seeds.p1_pieces);
+    let mut garbage_rng = Pcg64Mcg::from_seed(seeds.garbage);
+    let mut game = Game::new(config, &mut piece_rng);
+
+    let board = game.board.to_compressed();
+    let options = crate::battle::solo_options(&config, &board, seeds.p1_bot);
+    let eval = Sprint {
+        piece_delay: config.spawn_delay,
+        line_clear_delay: config.line_clear_delay,
+        ..eval
+    };
+    let mut bot = BotInput::with_options(board, eval, options);
+
+    let mut lines = 0;
+    for time in 1..=TIME_LIMIT {
+        let events = game.update(bot.controller, &mut piece_rng, &mut garbage_rng);
+        bot.update(&game.board, &events, 0);
+        for event in &events {
+            match event {
+                Event::PiecePlaced { locked, .. } => {
+                    lines += locked.cleared_lines.len();
+                    if lines >= SPRINT_LINES {
+                        return Some(time);
+                    }
+                }
+                Event::GameOver => return None,
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// Plays `games` sprints with `eval` on the default game configuration and reports the times.
+pub fn run_sprints(games: usize, threads: usize, eval: Sprint) {
+    let config = GameConfig::default();
+    let played = Arc::new(AtomicUsize::new(0));
+    let (send, recv) = channel();
+    for _ in 0..threads.max(1) {
+        let played = played.clone();
+        let send = send.clone();
+        let eval = eval.clone();
+        std::thread::spawn(move || {
+            while played.fetch_add(1, Ordering::SeqCst) < games {
+                if send.send(do_sprint(eval.clone(), config, thread_rng().gen())).is_err() {
+                    break
+                }
+            }
+        });
+    }
+    drop(send);
+
+    let mut times = vec![];
+    let mut failed = 0;
+    for result in recv {
+        match result {
+            Some(ticks) => {
+                println!("Sprint {}: {:.2}s", times.len() + failed + 1, ticks as f64 / 60.0);
+                times.push(ticks);
+            }
+            None => {
+                println!("Sprint {}: failed", times.len() + failed + 1);
+                failed += 1;
+            }
+        }
+    }
+
+    if let Some(&best) = times.iter().min() {
+        let average = times.iter().sum::<u32>() as f64 / times.len() as f64;
+        println!("Best: {:.2}s", best as f64 / 60.0);
+        println!("Average: {:.2}s", average / 60.0);
+    }
+    if failed > 0 {
+        println!("{} of {} sprints failed", failed, games);
+    }
+}
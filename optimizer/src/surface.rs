@@ -0,0 +1,64 @@
+use cold_clear::evaluation::Standard;
+use cold_clear::evaluation::surface::{ self, SurfaceTable };
+use std::sync::{ Arc, Mutex };
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use rand::prelude::*;
+
+/// Builds a surface table by playing `games` self-play games with `weights`.
+/// 
+/// Each pattern is scored by how often the player whose surface contained it went on to win,
+/// from -100 (always lost) to 100 (always won). Rarely seen patterns are pulled towards 0.
+pub fn build_table(games: usize, threads: usize, weights: Standard) -> SurfaceTable {
+    let counts = Arc::new(Mutex::new(vec![(0u64, 0u64); surface::PATTERNS]));
+    let played = Arc::new(AtomicUsize::new(0));
+
+    let workers: Vec<_> = (0..threads.max(1)).map(|_| {
+        let counts = counts.clone();
+        let played = played.clone();
+        let weights = weights.clone();
+        std::thread::spawn(move || {
+            while played.fetch_add(1, Ordering::SeqCst) < games {
+                let mut p1_seen = vec![];
+                let mut p2_seen = vec![];
+                let result = crate::battle::do_battle_observed(
+                    weights.clone(), weights.clone(),
+                    thread_rng().gen(),
+                    |p1, board| {
+                        let board = board.to_compressed();
+                        let seen = if p1 { &mut p1_seen } else { &mut p2_seen };
+                        seen.extend((0..7).map(|x| surface::pattern(&board, x)));
+                    }
+                );
+                let p1_won = match result {
+                    Some((_, p1_won)) => p1_won,
+                    None => continue
+                };
+
+                let mut counts = counts.lock().unwrap();
+                for &p in &p1_seen {
+                    counts[p].0 += p1_won as u64;
+                    counts[p].1 += 1;
+                }
+                for &p in &p2_seen {
+                    counts[p].0 += !p1_won as u64;
+                    counts[p].1 += 1;
+                }
+                drop(counts);
+
+                let done = played.load(Ordering::SeqCst).min(games);
+                println!("Played game {} of {}", done, games);
+            }
+        })
+    }).collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    let counts = counts.lock().unwrap();
+    SurfaceTable {
+        scores: counts.iter().map(|&(wins, total)| {
+            let rate = (wins as f64 + 1.0) / (total as f64 + 2.0);
+            ((rate - 0.5) * 200.0).round() as i32
+        }).collect()
+    }
+}
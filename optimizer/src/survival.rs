@@ -0,0 +1,59 @@
+use battle::{ GameConfig, Seeds, Survival, SurvivalSchedule };
+use cold_clear::evaluation::Standard;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use crate::battle::{ BotInput, solo_options };
+
+/// Plays a survival game with the default schedule, returning the ticks survived. Every random
+/// choice, including the garbage holes and the bot's search, is derived from `seed`.
+pub fn do_survival(eval: Standard, config: GameConfig, seed: u64) -> u32 {
+    let mut survival = Survival::new(config, SurvivalSchedule::default(), seed);
+    let board = survival.game.board.to_compressed();
+    let options = solo_options(&config, &board, Seeds::from_master(seed).p1_bot);
+    let mut bot = BotInput::with_options(board, eval, options);
+    while !survival.is_finished() {
+        let update = survival.update(bot.controller);
+        bot.update(&survival.game.board, &update.events, update.garbage_queue);
+    }
+    survival.time
+}
+
+/// Plays `games` survival games with `eval` on the default game configuration and reports how
+/// long each lasted. Game `i` uses the seed `seed + i`, so runs with the same seed face the same
+/// pieces and garbage and can be compared fairly.
+pub fn run_survivals(games: usize, threads: usize, eval: Standard, seed: u64) {
+    let config = GameConfig::default();
+    let next = Arc::new(AtomicUsize::new(0));
+    let (send, recv) = channel();
+    for _ in 0..threads.max(1) {
+        let next = next.clone();
+        let send = send.clone();
+        let eval = eval.clone();
+        std::thread::spawn(move || loop {
+            let game = next.fetch_add(1, Ordering::SeqCst);
+            if game >= games {
+                break
+            }
+            let result = do_survival(eval.clone(), config, seed.wrapping_add(game as u64));
+            if send.send((game, result)).is_err() {
+                break
+            }
+        });
+    }
+    drop(send);
+
+    let mut results = vec![];
+    for (game, ticks) in recv {
+        println!(
+            "Survival {} (seed {}): {:.1} seconds",
+            game, seed.wrapping_add(game as u64), ticks as f64 / 60.0
+        );
+        results.push(ticks);
+    }
+
+    if !results.is_empty() {
+        let average = results.iter().map(|&t| t as f64).sum::<f64>() / results.len() as f64;
+        println!("Average: {:.1} seconds", average / 60.0);
+    }
+}
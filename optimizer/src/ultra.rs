@@ -0,0 +1,54 @@
+use battle::{ GameConfig, Seeds, Ultra };
+use cold_clear::evaluation::ScoreAttack;
+use rand::prelude::*;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use crate::battle::{ BotInput, solo_options };
+
+/// Plays a 2 minute Ultra game, returning the score reached. Every random choice, including
+/// those of the bot's search, is derived from `seed`.
+pub fn do_ultra(eval: ScoreAttack, config: GameConfig, seed: u64) -> u32 {
+    let mut ultra = Ultra::new(config, seed);
+    let board = ultra.game.board.to_compressed();
+    let options = solo_options(&config, &board, Seeds::from_master(seed).p1_bot);
+    let mut bot = BotInput::with_options(board, eval, options);
+    while !ultra.is_finished() {
+        let update = ultra.update(bot.controller);
+        bot.update(&ultra.game.board, &update.events, 0);
+    }
+    ultra.score
+}
+
+/// Plays `games` Ultra games with `eval` on the default game configuration and reports the
+/// scores.
+pub fn run_ultras(games: usize, threads: usize, eval: ScoreAttack) {
+    let config = GameConfig::default();
+    let played = Arc::new(AtomicUsize::new(0));
+    let (send, recv) = channel();
+    for _ in 0..threads.max(1) {
+        let played = played.clone();
+        let send = send.clone();
+        let eval = eval.clone();
+        std::thread::spawn(move || {
+            while played.fetch_add(1, Ordering::SeqCst) < games {
+                if send.send(do_ultra(eval.clone(), config, thread_rng().gen())).is_err() {
+                    break
+                }
+            }
+        });
+    }
+    drop(send);
+
+    let mut scores = vec![];
+    for score in recv {
+        println!("Ultra {}: {}", scores.len() + 1, score);
+        scores.push(score);
+    }
+
+    if let Some(&best) = scores.iter().max() {
+        let average = scores.iter().map(|&s| s as u64).sum::<u64>() as f64 / scores.len() as f64;
+        println!("Best: {}", best);
+        println!("Average: {:.0}", average);
+    }
+}
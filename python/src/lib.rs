@@ -0,0 +1,342 @@
+#![cfg(feature = "python")]
+
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::PyBytes;
+use libtetris::{ Piece, PieceMovement, FallingPiece, TspinStatus };
+use cold_clear::moves::MovementMode;
+use cold_clear::evaluation::{ Preset, Standard };
+
+fn parse_piece(name: &str) -> PyResult<Piece> {
+    match name {
+        "I" => Ok(Piece::I),
+        "O" => Ok(Piece::O),
+        "T" => Ok(Piece::T),
+        "L" => Ok(Piece::L),
+        "J" => Ok(Piece::J),
+        "S" => Ok(Piece::S),
+        "Z" => Ok(Piece::Z),
+        _ => Err(PyValueError::new_err(format!("unknown piece: {}", name)))
+    }
+}
+
+fn piece_name(piece: Piece) -> &'static str {
+    match piece {
+        Piece::I => "I",
+        Piece::O => "O",
+        Piece::T => "T",
+        Piece::L => "L",
+        Piece::J => "J",
+        Piece::S => "S",
+        Piece::Z => "Z"
+    }
+}
+
+fn movement_name(movement: PieceMovement) -> &'static str {
+    match movement {
+        PieceMovement::Left => "left",
+        PieceMovement::Right => "right",
+        PieceMovement::Cw => "cw",
+        PieceMovement::Ccw => "ccw",
+        PieceMovement::SonicDrop => "drop"
+    }
+}
+
+fn convert_field(rows: Vec<Vec<bool>>) -> PyResult<[[bool; 10]; 40]> {
+    if rows.len() > 40 || rows.iter().any(|r| r.len() != 10) {
+        return Err(PyValueError::new_err("the field must have at most 40 rows of 10 cells"));
+    }
+    let mut field = [[false; 10]; 40];
+    for (y, row) in rows.into_iter().enumerate() {
+        for (x, cell) in row.into_iter().enumerate() {
+            field[y][x] = cell;
+        }
+    }
+    Ok(field)
+}
+
+/// A playfield. Row 0 is the bottom row.
+#[pyclass]
+#[derive(Clone)]
+struct Board {
+    inner: libtetris::Board
+}
+
+#[pymethods]
+impl Board {
+    #[new]
+    fn new() -> Self {
+        Board {
+            inner: libtetris::Board::new()
+        }
+    }
+
+    /// The field as a list of 40 rows of 10 booleans.
+    fn field(&self) -> Vec<Vec<bool>> {
+        self.inner.get_field().iter().map(|row| row.to_vec()).collect()
+    }
+
+    /// The field as 400 bytes in row-major order, one byte per cell. Use
+    /// `numpy.frombuffer(board.field_bytes(), dtype=numpy.uint8).reshape(40, 10)` to get an array.
+    fn field_bytes<'p>(&self, py: Python<'p>) -> &'p PyBytes {
+        let mut bytes = Vec::with_capacity(400);
+        for row in self.inner.get_field().iter() {
+            bytes.extend(row.iter().map(|&c| c as u8));
+        }
+        PyBytes::new(py, &bytes)
+    }
+
+    fn set_field(&mut self, rows: Vec<Vec<bool>>) -> PyResult<()> {
+        self.inner.set_field(convert_field(rows)?);
+        Ok(())
+    }
+
+    fn column_heights(&self) -> Vec<i32> {
+        self.inner.column_heights().to_vec()
+    }
+
+    fn add_next_piece(&mut self, piece: &str) -> PyResult<()> {
+        self.inner.add_next_piece(parse_piece(piece)?);
+        Ok(())
+    }
+
+    fn next_queue(&self) -> Vec<&'static str> {
+        self.inner.next_queue().map(piece_name).collect()
+    }
+
+    #[getter]
+    fn hold(&self) -> Option<&'static str> {
+        self.inner.hold_piece.map(piece_name)
+    }
+
+    #[getter]
+    fn b2b(&self) -> bool {
+        self.inner.b2b_bonus
+    }
+
+    #[setter]
+    fn set_b2b(&mut self, b2b: bool) {
+        self.inner.b2b_bonus = b2b;
+    }
+
+    #[getter]
+    fn combo(&self) -> u32 {
+        self.inner.combo
+    }
+
+    #[setter]
+    fn set_combo(&mut self, combo: u32) {
+        self.inner.combo = combo;
+    }
+}
+
+/// Bot options. See `cold_clear::Options` for the meaning of each field.
+#[pyclass]
+#[derive(Clone)]
+struct Options {
+    #[pyo3(get, set)]
+    use_hold: bool,
+    #[pyo3(get, set)]
+    speculate: bool,
+    #[pyo3(get, set)]
+    ponder: bool,
+    #[pyo3(get, set)]
+    min_nodes: u32,
+    #[pyo3(get, set)]
+    max_nodes: u32,
+    #[pyo3(get, set)]
+    threads: u32,
+    /// Milliseconds between updates of `Bot.candidates()`, or None to disable them.
+    #[pyo3(get, set)]
+    candidate_interval: Option<u32>,
+    /// One of "0g", "20g" or "hard drop only".
+    #[pyo3(get, set)]
+    mode: String,
+    /// The name of a weight preset, or None for the one named by the COLD_CLEAR_PRESET environment
+    /// variable or else the default weights.
+    #[pyo3(get, set)]
+    preset: Option<String>
+}
+
+#[pymethods]
+impl Options {
+    #[new]
+    fn new() -> Self {
+        let options = cold_clear::Options::default();
+        Options {
+            use_hold: options.use_hold,
+            speculate: options.speculate,
+            ponder: options.ponder,
+            min_nodes: options.min_nodes,
+            max_nodes: options.max_nodes,
+            threads: options.threads,
+            candidate_interval: options.candidate_interval,
+            mode: "0g".to_owned(),
+            preset: None
+        }
+    }
+}
+
+impl Options {
+    fn convert(&self) -> PyResult<cold_clear::Options> {
+        let mode = match &*self.mode {
+            "0g" => MovementMode::ZeroG,
+            "20g" => MovementMode::TwentyG,
+            "hard drop only" => MovementMode::HardDropOnly,
+            _ => return Err(PyValueError::new_err(format!("unknown mode: {}", self.mode)))
+        };
+        let preset = match &self.preset {
+            Some(name) => Some(name.parse::<Preset>().map_err(PyValueError::new_err)?),
+            None => None
+        };
+        Ok(cold_clear::Options {
+            use_hold: self.use_hold,
+            speculate: self.speculate,
+            ponder: self.ponder,
+            min_nodes: self.min_nodes,
+            max_nodes: self.max_nodes,
+            threads: self.threads,
+            candidate_interval: self.candidate_interval,
+            mode,
+            preset,
+            ..Default::default()
+        })
+    }
+}
+
+/// A move chosen by the bot: whether to hold first, the inputs to perform and the cells the piece
+/// is expected to occupy once placed.
+type PyMove = (bool, Vec<&'static str>, Vec<(i32, i32)>);
+
+fn convert_move(mv: cold_clear::Move) -> PyMove {
+    (
+        mv.hold,
+        mv.inputs.iter().copied().map(movement_name).collect(),
+        mv.expected_location.cells().to_vec()
+    )
+}
+
+/// A bot running on its own thread. See `cold_clear::Interface`.
+#[pyclass]
+struct Bot {
+    interface: cold_clear::Interface
+}
+
+#[pymethods]
+impl Bot {
+    #[new]
+    fn new(board: Option<Board>, options: Option<Options>) -> PyResult<Self> {
+        let board = board.map_or_else(libtetris::Board::new, |b| b.inner);
+        let options = options.unwrap_or_else(Options::new).convert()?;
+        let evaluator = options.selected_preset().map_or_else(Standard::default, Standard::preset);
+        Ok(Bot {
+            interface: cold_clear::Interface::launch(board, options, evaluator, None)
+        })
+    }
+
+    fn add_next_piece(&self, piece: &str) -> PyResult<()> {
+        self.interface.add_next_piece(parse_piece(piece)?);
+        Ok(())
+    }
+
+    fn request_next_move(&self, incoming: u32) {
+        self.interface.request_next_move(incoming);
+    }
+
+    /// Returns the requested move if it is ready, otherwise None.
+    fn poll_next_move(&self) -> Option<PyMove> {
+        self.interface.poll_next_move().ok().map(|(mv, _)| convert_move(mv))
+    }
+
+    /// Waits for the requested move. Returns None if the bot is dead.
+    fn block_next_move(&self, py: Python) -> Option<PyMove> {
+        let interface = &self.interface;
+        py.allow_threads(|| interface.block_next_move()).map(|(mv, _)| convert_move(mv))
+    }
+
+    fn reset(&self, field: Vec<Vec<bool>>, b2b: bool, combo: u32) -> PyResult<()> {
+        self.interface.reset(convert_field(field)?, b2b, combo);
+        Ok(())
+    }
+
+    /// Changes the options of the running bot. The number of threads and the weight preset can't
+    /// be changed.
+    fn set_options(&self, options: Options) -> PyResult<()> {
+        let options = options.convert()?;
+        self.interface.set_options(options);
+        Ok(())
+    }
+
+    /// Replaces the whole game state after the game diverged from what the bot expected. `bag` is
+    /// the pieces left in the bag before the first piece of `queue` was drawn, or empty for a full
+    /// bag.
+    fn resync(
+        &self,
+        field: Vec<Vec<bool>>,
+        b2b: bool,
+        combo: u32,
+        hold: Option<&str>,
+        queue: Vec<&str>,
+        bag: Vec<&str>
+    ) -> PyResult<()> {
+        let hold = hold.map(parse_piece).transpose()?;
+        let queue = queue.into_iter().map(parse_piece).collect::<PyResult<_>>()?;
+        let bag = bag.into_iter().map(parse_piece).collect::<PyResult<_>>()?;
+        self.interface.resync(convert_field(field)?, b2b, combo, hold, queue, bag);
+        Ok(())
+    }
+
+    /// Tells the bot that a placement was played, whether or not the bot suggested it. The
+    /// placement is given as the 4 cells the piece occupies and whether it was a T-spin: "none",
+    /// "mini" or "full".
+    fn play_move(&self, piece: &str, cells: Vec<(i32, i32)>, tspin: Option<&str>) -> PyResult<()> {
+        let tspin = match tspin.unwrap_or("none") {
+            "none" => TspinStatus::None,
+            "mini" => TspinStatus::Mini,
+            "full" => TspinStatus::Full,
+            t => return Err(PyValueError::new_err(format!("unknown tspin status: {}", t)))
+        };
+        let placement = match &*cells {
+            &[a, b, c, d] => FallingPiece::from_cells(parse_piece(piece)?, [a, b, c, d], tspin),
+            _ => None
+        }.ok_or_else(|| PyValueError::new_err("the cells don't form the piece"))?;
+        self.interface.play_move(placement);
+        Ok(())
+    }
+
+    /// The moves the bot is currently leaning towards, best first, as (hold, cells, evaluation)
+    /// tuples. Only updated if the `candidate_interval` option is set.
+    fn candidates(&self) -> Vec<(bool, Vec<(i32, i32)>, i64)> {
+        self.interface.candidates().into_iter()
+            .map(|c| (c.hold, c.placement.cells().to_vec(), c.evaluation))
+            .collect()
+    }
+
+    fn set_incoming_garbage(&self, lines: u32, delay: u32) {
+        self.interface.set_incoming_garbage(lines, delay);
+    }
+
+    /// Tells the bot its opponent placed a piece that sent `attack` lines, so it can prepare for
+    /// the opponent's next attack.
+    fn observe_opponent(&self, attack: u32) {
+        self.interface.observe_opponent(attack);
+    }
+
+    /// Tells the bot how tall the opponent's stack is and how much garbage is queued against it.
+    fn set_opponent_state(&self, height: u32, incoming: u32) {
+        self.interface.set_opponent_state(height, incoming);
+    }
+
+    /// Sets the percentage the bot's attacks are scaled by, for example during margin time.
+    fn set_attack_multiplier(&self, multiplier: u32) {
+        self.interface.set_attack_multiplier(multiplier);
+    }
+}
+
+#[pymodule]
+fn coldclear(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Board>()?;
+    m.add_class::<Options>()?;
+    m.add_class::<Bot>()?;
+    Ok(())
+}
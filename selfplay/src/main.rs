@@ -0,0 +1,256 @@
+use battle::{ Battle, GameConfig, Replay, Seeds, TrainingRecord };
+use cold_clear::evaluation::{ Evaluator, EvaluatorConfig };
+use rayon::prelude::*;
+use serde::{ Serialize, Deserialize };
+use std::collections::VecDeque;
+use std::path::Path;
+use std::io::{ BufWriter, Write };
+use std::sync::Mutex;
+use std::time::Instant;
+
+mod noise;
+use noise::Noisy;
+mod player;
+use player::Player;
+
+/// Command line settings.
+///
+/// `--games N` sets the number of games to play (default 1000). Game `i` is played with the seed
+/// `SEED + i`, where `--seed SEED` defaults to 0, so runs with the same settings play the same
+/// games.
+/// `--noise T` sets the temperature moves are picked at random with (default 0, no noise); see
+/// `Noisy`.
+/// `--think N` sets the thinking cycles per frame (default 10) and `--nodes N` the most nodes the
+/// search tree grows to.
+/// `--evaluator FILE` loads the evaluator both players use from a JSON `EvaluatorConfig`.
+/// `--threads N` sets the number of games played at once (default all cores).
+/// `--output FILE` sets where the training data is written (default `training.dat`), as
+/// `TrainingRecord`s.
+/// `--replays DIR` also writes the replay of every game to `DIR/SEED.dat`.
+struct Settings {
+    games: usize,
+    seed: u64,
+    noise: f64,
+    think: u32,
+    nodes: u32,
+    evaluator: EvaluatorConfig,
+    threads: usize,
+    output: String,
+    replays: Option<String>
+}
+
+fn parse_args() -> Settings {
+    let mut settings = Settings {
+        games: 1000,
+        seed: 0,
+        noise: 0.0,
+        think: 10,
+        nodes: cold_clear::Options::default().max_nodes,
+        evaluator: EvaluatorConfig::default(),
+        threads: 0,
+        output: "training.dat".to_owned(),
+        replays: None
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--games" => settings.games = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--games requires a number"),
+            "--seed" => settings.seed = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--seed requires a number"),
+            "--noise" => settings.noise = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--noise requires a number"),
+            "--think" => settings.think = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--think requires a positive number"),
+            "--nodes" => settings.nodes = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--nodes requires a positive number"),
+            "--evaluator" => {
+                let path = args.next().expect("--evaluator requires a file");
+                let file = std::fs::File::open(&path).unwrap_or_else(
+                    |e| panic!("could not open {}: {}", path, e)
+                );
+                settings.evaluator = serde_json::from_reader(file).unwrap_or_else(
+                    |e| panic!("{} contained invalid data: {}", path, e)
+                );
+            }
+            "--threads" => settings.threads = args.next()
+                .and_then(|v| v.parse().ok())
+                .expect("--threads requires a positive number"),
+            "--output" => settings.output = args.next().expect("--output requires a file"),
+            "--replays" => settings.replays = Some(
+                args.next().expect("--replays requires a directory")
+            ),
+            _ => eprintln!("ignoring unknown argument {}", arg)
+        }
+    }
+    settings
+}
+
+/// What has been played so far, shared between the threads playing games.
+struct Progress<W> {
+    output: W,
+    games: usize,
+    draws: usize,
+    moves: usize
+}
+
+fn main() {
+    let settings = parse_args();
+    let eval = settings.evaluator.build();
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(settings.threads).build().unwrap();
+    if let Some(dir) = &settings.replays {
+        std::fs::create_dir_all(dir).unwrap_or_else(
+            |e| panic!("could not create {}: {}", dir, e)
+        );
+    }
+    let output = std::fs::File::create(&settings.output).unwrap_or_else(
+        |e| panic!("could not create {}: {}", settings.output, e)
+    );
+    let progress = Mutex::new(Progress {
+        output: BufWriter::new(output),
+        games: 0,
+        draws: 0,
+        moves: 0
+    });
+
+    let start = Instant::now();
+    pool.install(|| (0..settings.games).into_par_iter().for_each(|i| {
+        let seed = settings.seed.wrapping_add(i as u64);
+        let game = play(&eval, &settings, seed);
+        if let (Some(dir), Some((replay, _))) = (&settings.replays, &game) {
+            let path = format!("{}/{}.dat", dir, seed);
+            replay.save(&path).unwrap();
+        }
+
+        let mut progress = progress.lock().unwrap();
+        progress.games += 1;
+        match game {
+            Some((_, records)) => {
+                progress.moves += records.len();
+                for record in &records {
+                    record.write(&mut progress.output).unwrap();
+                }
+            }
+            None => progress.draws += 1
+        }
+        if progress.games % 50 == 0 {
+            progress.output.flush().unwrap();
+            report(&progress, start);
+        }
+    }));
+
+    let mut progress = progress.into_inner().unwrap();
+    progress.output.flush().unwrap();
+    report(&progress, start);
+}
+
+fn report<W>(progress: &Progress<W>, start: Instant) {
+    let elapsed = start.elapsed().as_secs_f64();
+    println!(
+        "{} games ({} draws), {} moves in {:.0}s: {:.2} games/s, {:.0} moves/s",
+        progress.games, progress.draws, progress.moves, elapsed,
+        progress.games as f64 / elapsed, progress.moves as f64 / elapsed
+    );
+}
+
+/// Plays a game of the evaluator against itself, returning the replay and the training data, or
+/// `None` if the game went on too long to have a winner.
+fn play<E: Evaluator>(
+    eval: &E, settings: &Settings, seed: u64
+) -> Option<(InfoReplay, Vec<TrainingRecord>)> {
+    let seeds = Seeds::from_master(seed);
+    let mut battle = Battle::with_seeds(GameConfig::default(), GameConfig::default(), &seeds);
+
+    battle.replay.p1_name = format!("Cold Clear\n{}", eval.name());
+    battle.replay.p2_name = format!("Cold Clear\n{}", eval.name());
+
+    let options = |board: &libtetris::Board, seed| cold_clear::Options {
+        randomizer: board.randomizer.kind(),
+        max_nodes: settings.nodes,
+        seed: Some(seed),
+        ..Default::default()
+    };
+    let p1_board = battle.player_1.board.to_compressed();
+    let p2_board = battle.player_2.board.to_compressed();
+    let mut p1 = Player::new(
+        p1_board.clone(),
+        Noisy::new(eval, settings.noise, seeds.p1_bot),
+        options(&p1_board, seeds.p1_bot),
+        settings.think
+    );
+    let mut p2 = Player::new(
+        p2_board.clone(),
+        Noisy::new(eval, settings.noise, seeds.p2_bot),
+        options(&p2_board, seeds.p2_bot),
+        settings.think
+    );
+
+    let mut p1_info_updates = VecDeque::new();
+    let mut p2_info_updates = VecDeque::new();
+
+    let p1_won;
+    'battle: loop {
+        let update = battle.update(p1.controller, p2.controller);
+        p1_info_updates.push_back(p1.update(
+            &battle.player_1.board,
+            &update.player_1.events,
+            battle.player_1.garbage_queue
+        ));
+        p2_info_updates.push_back(p2.update(
+            &battle.player_2.board,
+            &update.player_2.events,
+            battle.player_2.garbage_queue
+        ));
+
+        for event in &update.player_1.events {
+            if let battle::Event::GameOver = event {
+                p1_won = false;
+                break 'battle;
+            }
+        }
+        for event in &update.player_2.events {
+            if let battle::Event::GameOver = event {
+                p1_won = true;
+                break 'battle;
+            }
+        }
+
+        if battle.replay.updates.len() > 54000 { // 15 minutes
+            return None
+        }
+    }
+
+    for _ in 0..180 {
+        battle.replay.updates.push_back(Default::default());
+        p1_info_updates.push_back(None);
+        p2_info_updates.push_back(None);
+    }
+
+    let records = p1.records.into_iter()
+        .map(|record| TrainingRecord { won: p1_won, ..record })
+        .chain(p2.records.into_iter().map(|record| TrainingRecord { won: !p1_won, ..record }))
+        .collect();
+    Some((InfoReplay {
+        replay: battle.replay,
+        p1_info_updates,
+        p2_info_updates
+    }, records))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InfoReplay {
+    pub replay: Replay,
+    pub p1_info_updates: VecDeque<Option<cold_clear::Info>>,
+    pub p2_info_updates: VecDeque<Option<cold_clear::Info>>
+}
+
+impl InfoReplay {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        self.replay.save_with(&(&self.p1_info_updates, &self.p2_info_updates), path)
+    }
+}
@@ -0,0 +1,69 @@
+use cold_clear::evaluation::{ Evaluation, Evaluator, MoveCandidate, Placement };
+use libtetris::{ Board, LockResult, Piece };
+use rand::prelude::*;
+use rand::distributions::WeightedIndex;
+use rand_pcg::Pcg64Mcg;
+use std::sync::Mutex;
+
+/// Wraps an evaluator so moves are picked at random, weighted by how good the search thinks they
+/// are, letting self-play games explore moves the bot wouldn't normally make.
+///
+/// A move scoring `d` below the best move is picked with weight `exp(-d / temperature)`. With a
+/// temperature of 0 the wrapped evaluator picks the move as usual.
+pub struct Noisy<'a, E> {
+    eval: &'a E,
+    temperature: f64,
+    rng: Mutex<Pcg64Mcg>
+}
+
+impl<'a, E> Noisy<'a, E> {
+    pub fn new(eval: &'a E, temperature: f64, seed: u64) -> Self {
+        Noisy {
+            eval,
+            temperature,
+            rng: Mutex::new(Pcg64Mcg::seed_from_u64(seed))
+        }
+    }
+}
+
+impl<E: Evaluator> Evaluator for Noisy<'_, E> {
+    type Value = E::Value;
+    type Reward = E::Reward;
+
+    fn name(&self) -> String {
+        self.eval.name()
+    }
+
+    fn evaluate(
+        &self, lock: &LockResult, board: &Board, move_time: u32, placed: Piece
+    ) -> (Self::Value, Self::Reward) {
+        self.eval.evaluate(lock, board, move_time, placed)
+    }
+
+    fn evaluate_batch(&self, batch: &[Placement]) -> Vec<(Self::Value, Self::Reward)> {
+        self.eval.evaluate_batch(batch)
+    }
+
+    fn pick_move(
+        &self, candidates: Vec<MoveCandidate<Self::Value>>, incoming: u32
+    ) -> MoveCandidate<Self::Value> {
+        if self.temperature <= 0.0 {
+            return self.eval.pick_move(candidates, incoming)
+        }
+        let best = candidates.iter().map(|c| c.evaluation.score()).max().unwrap();
+        let weights = candidates.iter()
+            .map(|c| ((c.evaluation.score() - best) as f64 / self.temperature).exp());
+        // the best move always has weight 1, so the weights are valid
+        let sampler = WeightedIndex::new(weights).unwrap();
+        let index = self.rng.lock().unwrap().sample(sampler);
+        candidates.into_iter().nth(index).unwrap()
+    }
+
+    fn explain(&self, placement: &Placement) -> Vec<(String, i32)> {
+        self.eval.explain(placement)
+    }
+
+    fn pc_opportunity(&self, board: &Board) -> bool {
+        self.eval.pc_opportunity(board)
+    }
+}
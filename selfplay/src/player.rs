@@ -0,0 +1,99 @@
+use libtetris::{ Board, ColoredRow, FallingPiece, Controller };
+use battle::{ Event, PieceMoveExecutor, TrainingRecord };
+use cold_clear::evaluation::Evaluator;
+
+pub struct Player<E: Evaluator> {
+    pub controller: Controller,
+    executing: Option<(FallingPiece, PieceMoveExecutor)>,
+    bot: cold_clear::BotState<E>,
+    eval: E,
+    think_amount: u32,
+    /// Every move chosen so far. Their outcome is filled in once the game ends.
+    pub records: Vec<TrainingRecord>
+}
+
+impl<E: Evaluator> Player<E> {
+    /// Creates a player that performs `think_amount` thinking cycles every frame.
+    pub fn new(board: Board, eval: E, options: cold_clear::Options, think_amount: u32) -> Self {
+        let mut this = Player {
+            controller: Controller::default(),
+            executing: None,
+            bot: cold_clear::BotState::new(board, options),
+            eval,
+            think_amount,
+            records: vec![]
+        };
+        for _ in 0..180 {
+            // equivalent of 3 realtime seconds of thinking
+            this.think();
+        }
+        this
+    }
+
+    fn think(&mut self) {
+        for _ in 0..self.think_amount {
+            match self.bot.think() {
+                Ok(thinker) => {
+                    self.bot.finish_thinking(thinker.think(&self.eval));
+                }
+                Err(_) => {
+                    // can't think anymore
+                    break
+                }
+            }
+        }
+    }
+
+    pub fn update(
+        &mut self, board: &Board<ColoredRow>, events: &[Event], incoming: u32
+    ) -> Option<cold_clear::Info> {
+        self.think();
+
+        let mut info = None;
+        for event in events {
+            match event {
+                Event::PieceSpawned { new_in_queue } => {
+                    self.bot.add_next_piece(*new_in_queue);
+                    if self.executing.is_none() {
+                        let exec = &mut self.executing;
+                        let records = &mut self.records;
+                        let board = self.bot.board().clone();
+                        self.bot.next_move(&self.eval, None, incoming, |mv, inf| {
+                            if let cold_clear::Info::Normal(normal) = &inf {
+                                records.push(TrainingRecord {
+                                    board,
+                                    placement: mv.expected_location,
+                                    hold: mv.hold,
+                                    evaluation: normal.evaluation,
+                                    won: false
+                                });
+                            }
+                            info = Some(inf);
+                            *exec = Some((
+                                mv.expected_location,
+                                PieceMoveExecutor::new(mv.hold, mv.inputs.into_iter().collect(), 0)
+                            ));
+                        });
+                    }
+                }
+                Event::GarbageAdded(_) => {
+                    self.bot.reset(board.get_field(), board.b2b_bonus, board.combo);
+                }
+                Event::AttackMultiplier(multiplier) => {
+                    self.bot.set_attack_multiplier(*multiplier);
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((expected, ref mut executor)) = self.executing {
+            if let Some(loc) = executor.update(&mut self.controller, board, events) {
+                if loc != expected {
+                    self.bot.reset(board.get_field(), board.b2b_bonus, board.combo);
+                }
+                self.executing = None;
+            }
+        }
+        info
+    }
+}
@@ -0,0 +1,299 @@
+use libtetris::{ Board, FallingPiece, Piece, PieceMovement, TspinStatus };
+use cold_clear::evaluation::EvaluatorConfig;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{ Arc, Mutex };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use tonic::{ Request, Response, Status, Streaming };
+use tokio_stream::Stream;
+
+mod proto {
+    tonic::include_proto!("cold_clear");
+}
+use proto::cold_clear_server::{ ColdClear, ColdClearServer };
+use proto::{
+    CreateSessionRequest, CreateSessionResponse, UpdateStateRequest, UpdateStateResponse,
+    SuggestRequest, SuggestResponse, PlayRequest, EndSessionRequest, EndSessionResponse
+};
+
+type Sessions = Arc<Mutex<HashMap<String, Arc<cold_clear::Interface>>>>;
+
+/// Serves the `ColdClear` service described in `proto/cold_clear.proto` on `address` until the
+/// process is killed.
+pub fn serve(address: &str, pool: Option<Arc<cold_clear::BotPool>>) {
+    let address = address.parse().unwrap_or_else(
+        |e| panic!("invalid address {}: {}", address, e)
+    );
+    let service = Service {
+        sessions: Default::default(),
+        next_id: AtomicU64::new(0),
+        pool
+    };
+    println!("Serving gRPC on {}", address);
+    tokio::runtime::Runtime::new().unwrap().block_on(
+        tonic::transport::Server::builder()
+            .add_service(ColdClearServer::new(service))
+            .serve(address)
+    ).unwrap_or_else(|e| panic!("could not serve on {}: {}", address, e));
+}
+
+struct Service {
+    sessions: Sessions,
+    next_id: AtomicU64,
+    pool: Option<Arc<cold_clear::BotPool>>
+}
+
+#[tonic::async_trait]
+impl ColdClear for Service {
+    async fn create_session(
+        &self, request: Request<CreateSessionRequest>
+    ) -> Result<Response<CreateSessionResponse>, Status> {
+        let request = request.into_inner();
+        let mut board = Board::new();
+        if let Some(state) = request.board {
+            board.set_field(convert_rows(&state.rows)?);
+            board.b2b_bonus = state.b2b;
+            board.combo = state.combo;
+            if let Some(queue) = state.queue {
+                let (hold, next, bag) = convert_queue(queue)?;
+                board.hold_piece = hold;
+                if !bag.is_empty() {
+                    board.bag = bag.into_iter().collect();
+                }
+                for piece in next {
+                    board.add_next_piece(piece);
+                }
+            }
+        }
+        let options: cold_clear::Options = parse_json(&request.options, "options")?;
+        let evaluator: EvaluatorConfig = parse_json(&request.evaluator, "evaluator")?;
+        if options.threads == 0 {
+            return Err(Status::invalid_argument("threads must be at least 1"));
+        }
+
+        let interface = match &self.pool {
+            Some(pool) => pool.launch(board, options, evaluator.build(), None),
+            None => cold_clear::Interface::launch(board, options, evaluator.build(), None)
+        };
+        let session = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        self.sessions.lock().unwrap().insert(session.clone(), Arc::new(interface));
+        Ok(Response::new(CreateSessionResponse { session }))
+    }
+
+    async fn update_state(
+        &self, request: Request<UpdateStateRequest>
+    ) -> Result<Response<UpdateStateResponse>, Status> {
+        update_state(&self.sessions, request.into_inner())?;
+        Ok(Response::new(UpdateStateResponse {}))
+    }
+
+    async fn suggest(
+        &self, request: Request<SuggestRequest>
+    ) -> Result<Response<SuggestResponse>, Status> {
+        suggest(&self.sessions, request.into_inner()).await.map(Response::new)
+    }
+
+    type PlayStream = Pin<Box<
+        dyn Stream<Item = Result<SuggestResponse, Status>> + Send + Sync + 'static
+    >>;
+
+    async fn play(
+        &self, request: Request<Streaming<PlayRequest>>
+    ) -> Result<Response<Self::PlayStream>, Status> {
+        use proto::play_request::Request as Play;
+
+        let mut inbound = request.into_inner();
+        let sessions = self.sessions.clone();
+        let (send, recv) = tokio::sync::mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                let result = match inbound.message().await {
+                    Ok(Some(PlayRequest { request: Some(Play::Update(update)) })) =>
+                        update_state(&sessions, update).map(|_| None),
+                    Ok(Some(PlayRequest { request: Some(Play::Suggest(request)) })) =>
+                        suggest(&sessions, request).await.map(Some),
+                    Ok(Some(_)) => Err(Status::invalid_argument("empty request")),
+                    Ok(None) => break,
+                    Err(e) => Err(e)
+                };
+                match result {
+                    Ok(None) => {}
+                    Ok(Some(response)) => if send.send(Ok(response)).await.is_err() {
+                        break
+                    }
+                    Err(e) => {
+                        send.send(Err(e)).await.ok();
+                        break
+                    }
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(tokio_stream::wrappers::ReceiverStream::new(recv))))
+    }
+
+    async fn end_session(
+        &self, request: Request<EndSessionRequest>
+    ) -> Result<Response<EndSessionResponse>, Status> {
+        let session = request.into_inner().session;
+        match self.sessions.lock().unwrap().remove(&session) {
+            Some(_) => Ok(Response::new(EndSessionResponse {})),
+            None => Err(unknown_session(&session))
+        }
+    }
+}
+
+fn update_state(sessions: &Sessions, request: UpdateStateRequest) -> Result<(), Status> {
+    let interface = session(sessions, &request.session)?;
+    if let Some(state) = request.board {
+        let field = convert_rows(&state.rows)?;
+        match state.queue {
+            Some(queue) => {
+                let (hold, next, bag) = convert_queue(queue)?;
+                interface.resync(
+                    field, state.b2b, state.combo, hold, next, bag.into_iter().collect()
+                );
+            }
+            None => interface.reset(field, state.b2b, state.combo)
+        }
+    }
+    if let Some(placement) = request.played {
+        interface.play_move(convert_placement(placement)?);
+    }
+    for piece in request.next_pieces {
+        interface.add_next_piece(convert_piece(piece)?);
+    }
+    if !request.options.is_empty() {
+        interface.set_options(parse_json(&request.options, "options")?);
+    }
+    Ok(())
+}
+
+async fn suggest(sessions: &Sessions, request: SuggestRequest) -> Result<SuggestResponse, Status> {
+    let interface = session(sessions, &request.session)?;
+    Ok(match interface.next_move_async(request.incoming).await {
+        Some((mv, info)) => SuggestResponse {
+            session: request.session,
+            dead: false,
+            hold: mv.hold,
+            inputs: mv.inputs.iter().map(|&input| input_to_proto(input) as i32).collect(),
+            expected_location: Some(proto::Placement {
+                piece: piece_to_proto(mv.expected_location.kind.0) as i32,
+                cells: mv.expected_location.cells().iter()
+                    .map(|&(x, y)| proto::Cell { x, y })
+                    .collect(),
+                tspin: match mv.expected_location.tspin {
+                    TspinStatus::None => proto::Tspin::None,
+                    TspinStatus::Mini => proto::Tspin::Mini,
+                    TspinStatus::Full => proto::Tspin::Full
+                } as i32
+            }),
+            info: serde_json::to_string(&info).unwrap()
+        },
+        None => SuggestResponse {
+            session: request.session,
+            dead: true,
+            ..Default::default()
+        }
+    })
+}
+
+fn session(sessions: &Sessions, session: &str) -> Result<Arc<cold_clear::Interface>, Status> {
+    sessions.lock().unwrap().get(session).cloned().ok_or_else(|| unknown_session(session))
+}
+
+fn unknown_session(session: &str) -> Status {
+    Status::not_found(format!("no session {}", session))
+}
+
+/// Parses `json`, using the default value if it is empty.
+fn parse_json<T: Default + serde::de::DeserializeOwned>(
+    json: &str, what: &str
+) -> Result<T, Status> {
+    if json.is_empty() {
+        return Ok(T::default());
+    }
+    serde_json::from_str(json).map_err(
+        |e| Status::invalid_argument(format!("invalid {}: {}", what, e))
+    )
+}
+
+fn convert_rows(rows: &[u32]) -> Result<[[bool; 10]; 40], Status> {
+    if rows.len() > 40 || rows.iter().any(|&row| row >= 1 << 10) {
+        return Err(Status::invalid_argument("the board must have at most 40 rows of 10 cells"));
+    }
+    let mut field = [[false; 10]; 40];
+    for (cells, &row) in field.iter_mut().zip(rows) {
+        for (x, cell) in cells.iter_mut().enumerate() {
+            *cell = row & (1 << x) != 0;
+        }
+    }
+    Ok(field)
+}
+
+/// Returns the hold piece, the next queue and the bag the first piece of the queue was drawn from.
+fn convert_queue(
+    queue: proto::Queue
+) -> Result<(Option<Piece>, Vec<Piece>, Vec<Piece>), Status> {
+    let hold = match queue.hold_piece {
+        Some(proto::queue::HoldPiece::Hold(piece)) => Some(convert_piece(piece)?),
+        None => None
+    };
+    let next = queue.next.into_iter().map(convert_piece).collect::<Result<_, _>>()?;
+    let bag = queue.bag.into_iter().map(convert_piece).collect::<Result<_, _>>()?;
+    Ok((hold, next, bag))
+}
+
+fn convert_placement(placement: proto::Placement) -> Result<FallingPiece, Status> {
+    let piece = convert_piece(placement.piece)?;
+    if placement.cells.len() != 4 {
+        return Err(Status::invalid_argument("a placement must have 4 cells"));
+    }
+    let mut cells = [(0, 0); 4];
+    for (cell, c) in cells.iter_mut().zip(&placement.cells) {
+        *cell = (c.x, c.y);
+    }
+    let tspin = match proto::Tspin::from_i32(placement.tspin) {
+        Some(proto::Tspin::None) => TspinStatus::None,
+        Some(proto::Tspin::Mini) => TspinStatus::Mini,
+        Some(proto::Tspin::Full) => TspinStatus::Full,
+        None => return Err(Status::invalid_argument("invalid t-spin status"))
+    };
+    FallingPiece::from_cells(piece, cells, tspin).ok_or_else(
+        || Status::invalid_argument("the cells don't form the piece")
+    )
+}
+
+fn convert_piece(piece: i32) -> Result<Piece, Status> {
+    Ok(match proto::Piece::from_i32(piece) {
+        Some(proto::Piece::I) => Piece::I,
+        Some(proto::Piece::O) => Piece::O,
+        Some(proto::Piece::T) => Piece::T,
+        Some(proto::Piece::L) => Piece::L,
+        Some(proto::Piece::J) => Piece::J,
+        Some(proto::Piece::S) => Piece::S,
+        Some(proto::Piece::Z) => Piece::Z,
+        None => return Err(Status::invalid_argument(format!("invalid piece {}", piece)))
+    })
+}
+
+fn piece_to_proto(piece: Piece) -> proto::Piece {
+    match piece {
+        Piece::I => proto::Piece::I,
+        Piece::O => proto::Piece::O,
+        Piece::T => proto::Piece::T,
+        Piece::L => proto::Piece::L,
+        Piece::J => proto::Piece::J,
+        Piece::S => proto::Piece::S,
+        Piece::Z => proto::Piece::Z
+    }
+}
+
+fn input_to_proto(input: PieceMovement) -> proto::Input {
+    match input {
+        PieceMovement::Left => proto::Input::Left,
+        PieceMovement::Right => proto::Input::Right,
+        PieceMovement::Cw => proto::Input::Cw,
+        PieceMovement::Ccw => proto::Input::Ccw,
+        PieceMovement::SonicDrop => proto::Input::SonicDrop
+    }
+}
@@ -0,0 +1,319 @@
+use serde::{ Serialize, Deserialize };
+use libtetris::{ Board, Piece, FallingPiece, TspinStatus };
+use cold_clear::evaluation::EvaluatorConfig;
+use std::collections::HashMap;
+use std::net::{ TcpListener, TcpStream };
+use std::sync::Arc;
+use std::io::ErrorKind;
+use std::time::Duration;
+use tungstenite::Message;
+
+#[cfg(feature = "grpc")]
+mod grpc;
+
+/// Messages sent by the client. Every message names the bot instance it is meant for, so one
+/// connection can drive several bots at once.
+///
+/// Messages are JSON objects with a `type` field, for example
+/// `{"type": "next_piece", "bot": "p1", "piece": "T"}`.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    /// Starts a new bot, replacing any existing bot with the same name.
+    NewGame {
+        bot: String,
+        /// 40 rows of 10 cells, bottom row first. Defaults to an empty field.
+        #[serde(default)]
+        field: Option<Vec<Vec<bool>>>,
+        #[serde(default)]
+        queue: Vec<Piece>,
+        #[serde(default)]
+        options: cold_clear::Options,
+        #[serde(default)]
+        evaluator: EvaluatorConfig
+    },
+    /// Replaces the bot's board, for example after garbage was received or the client placed a
+    /// piece somewhere other than where the bot expected. If `queue` is given, the hold piece and
+    /// next queue are replaced as well, and `bag` lists the pieces left in the bag before the
+    /// first piece of `queue` was drawn from it, empty for a full bag.
+    BoardUpdate {
+        bot: String,
+        field: Vec<Vec<bool>>,
+        #[serde(default)]
+        b2b: bool,
+        #[serde(default)]
+        combo: u32,
+        #[serde(default)]
+        hold: Option<Piece>,
+        #[serde(default)]
+        queue: Option<Vec<Piece>>,
+        #[serde(default)]
+        bag: Vec<Piece>
+    },
+    NextPiece {
+        bot: String,
+        piece: Piece
+    },
+    /// Asks the bot for its next move. The bot assumes the move it suggests is played; send a
+    /// `board_update` if it wasn't. The suggestion is sent once the bot has decided, and other
+    /// messages keep being handled in the meantime.
+    RequestSuggestion {
+        bot: String,
+        #[serde(default)]
+        incoming: u32
+    },
+    /// Changes the options of a running bot. The number of threads can't be changed.
+    SetOptions {
+        bot: String,
+        options: cold_clear::Options
+    },
+    /// Tells the bot a placement was played even if it wasn't the bot's suggestion, for example
+    /// when a human is playing. `cells` are the 4 `[x, y]` cells the piece occupies.
+    PlayMove {
+        bot: String,
+        piece: Piece,
+        cells: [(i32, i32); 4],
+        #[serde(default)]
+        tspin: Option<TspinStatus>
+    },
+    EndGame {
+        bot: String
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Response {
+    Suggestion {
+        bot: String,
+        #[serde(rename = "move")]
+        mv: cold_clear::Move,
+        info: cold_clear::Info
+    },
+    /// The bot can't provide any more moves, usually because it has topped out.
+    Dead {
+        bot: String
+    },
+    Error {
+        message: String
+    }
+}
+
+/// Usage: `server [--grpc] [address] [threads]`. If `threads` is given, all bots share that many
+/// worker threads; otherwise each bot uses its own `options.threads`.
+///
+/// With `--grpc`, the gRPC service in `proto/cold_clear.proto` is served instead of the websocket
+/// protocol. This requires the `grpc` feature.
+fn main() {
+    let mut args: Vec<_> = std::env::args().skip(1).collect();
+    let grpc = args.first().map_or(false, |arg| arg == "--grpc");
+    if grpc {
+        args.remove(0);
+    }
+    let address = args.get(0).cloned().unwrap_or_else(|| "127.0.0.1:9123".to_owned());
+    let pool = args.get(1).map(|threads| Arc::new(cold_clear::BotPool::new(
+        threads.parse().unwrap_or_else(|e| panic!("invalid thread count {}: {}", threads, e))
+    )));
+    if grpc {
+        #[cfg(feature = "grpc")]
+        return grpc::serve(&address, pool);
+        #[cfg(not(feature = "grpc"))]
+        panic!("the server was built without the grpc feature");
+    }
+
+    let listener = TcpListener::bind(&address).unwrap_or_else(
+        |e| panic!("could not listen on {}: {}", address, e)
+    );
+    println!("Listening on {}", address);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let pool = pool.clone();
+                std::thread::spawn(move || handle_connection(stream, pool));
+            }
+            Err(e) => eprintln!("Error accepting connection: {}", e)
+        }
+    }
+}
+
+fn handle_connection(stream: TcpStream, pool: Option<Arc<cold_clear::BotPool>>) {
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Error during websocket handshake: {}", e);
+            return
+        }
+    };
+
+    // reads time out so that suggestions can be sent as soon as the bots decide on them
+    if let Err(e) = socket.get_ref().set_read_timeout(Some(POLL_INTERVAL)) {
+        eprintln!("Error setting up connection: {}", e);
+        return
+    }
+
+    let mut bots = HashMap::new();
+    // bots with a suggestion requested that hasn't been sent yet, in the order requested
+    let mut pending = vec![];
+    loop {
+        let mut responses = vec![];
+        match socket.read_message() {
+            Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                Ok(request) => responses.extend(
+                    handle_request(&mut bots, &mut pending, pool.as_deref(), request)
+                ),
+                Err(e) => responses.push(Response::Error {
+                    message: format!("invalid request: {}", e)
+                })
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {}
+            Err(_) => break
+        }
+
+        pending.retain(|bot: &String| match bots.get(bot) {
+            Some(interface) => match interface.poll_next_move() {
+                Ok((mv, info)) => {
+                    responses.push(Response::Suggestion { bot: bot.clone(), mv, info });
+                    false
+                }
+                Err(cold_clear::BotPollState::Waiting) => true,
+                Err(cold_clear::BotPollState::Dead) => {
+                    responses.push(Response::Dead { bot: bot.clone() });
+                    false
+                }
+            },
+            None => false
+        });
+
+        for response in responses {
+            let text = serde_json::to_string(&response).unwrap();
+            if socket.write_message(Message::Text(text)).is_err() {
+                return
+            }
+        }
+    }
+}
+
+/// How long a connection waits for a message before checking whether its bots have decided on
+/// the suggestions requested from them.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+fn handle_request(
+    bots: &mut HashMap<String, cold_clear::Interface>,
+    pending: &mut Vec<String>,
+    pool: Option<&cold_clear::BotPool>,
+    request: Request
+) -> Option<Response> {
+    match request {
+        Request::NewGame { bot, field, queue, options, evaluator } => {
+            let mut board = Board::new();
+            if let Some(field) = field {
+                match convert_field(field) {
+                    Ok(field) => board.set_field(field),
+                    Err(message) => return Some(Response::Error { message })
+                }
+            }
+            for piece in queue {
+                board.add_next_piece(piece);
+            }
+            if options.threads == 0 {
+                return Some(Response::Error {
+                    message: "threads must be at least 1".to_owned()
+                });
+            }
+            let interface = match pool {
+                Some(pool) => pool.launch(board, options, evaluator.build(), None),
+                None => cold_clear::Interface::launch(board, options, evaluator.build(), None)
+            };
+            // suggestions requested from a replaced bot will never be sent
+            pending.retain(|b| b != &bot);
+            bots.insert(bot, interface);
+            None
+        }
+        Request::BoardUpdate { bot, field, b2b, combo, hold, queue, bag } => {
+            let field = match convert_field(field) {
+                Ok(field) => field,
+                Err(message) => return Some(Response::Error { message })
+            };
+            match bots.get(&bot) {
+                Some(interface) => {
+                    match queue {
+                        Some(queue) => interface.resync(
+                            field, b2b, combo, hold, queue, bag.into_iter().collect()
+                        ),
+                        None => interface.reset(field, b2b, combo)
+                    }
+                    None
+                }
+                None => Some(unknown_bot(bot))
+            }
+        }
+        Request::NextPiece { bot, piece } => match bots.get(&bot) {
+            Some(interface) => {
+                interface.add_next_piece(piece);
+                None
+            }
+            None => Some(unknown_bot(bot))
+        }
+        Request::RequestSuggestion { bot, incoming } => match bots.get(&bot) {
+            Some(interface) => {
+                interface.request_next_move(incoming);
+                pending.push(bot);
+                None
+            }
+            None => Some(unknown_bot(bot))
+        }
+        Request::SetOptions { bot, options } => match bots.get(&bot) {
+            Some(interface) => {
+                interface.set_options(options);
+                None
+            }
+            None => Some(unknown_bot(bot))
+        }
+        Request::PlayMove { bot, piece, cells, tspin } => {
+            let tspin = tspin.unwrap_or(TspinStatus::None);
+            let placement = match FallingPiece::from_cells(piece, cells, tspin) {
+                Some(placement) => placement,
+                None => return Some(Response::Error {
+                    message: "the cells don't form the piece".to_owned()
+                })
+            };
+            match bots.get(&bot) {
+                Some(interface) => {
+                    interface.play_move(placement);
+                    None
+                }
+                None => Some(unknown_bot(bot))
+            }
+        }
+        Request::EndGame { bot } => match bots.remove(&bot) {
+            Some(_) => {
+                pending.retain(|b| b != &bot);
+                None
+            }
+            None => Some(unknown_bot(bot))
+        }
+    }
+}
+
+fn unknown_bot(bot: String) -> Response {
+    Response::Error {
+        message: format!("no bot named {}", bot)
+    }
+}
+
+fn convert_field(rows: Vec<Vec<bool>>) -> Result<[[bool; 10]; 40], String> {
+    if rows.len() > 40 || rows.iter().any(|r| r.len() != 10) {
+        return Err("the field must have at most 40 rows of 10 cells".to_owned());
+    }
+    let mut field = [[false; 10]; 40];
+    for (y, row) in rows.into_iter().enumerate() {
+        for (x, cell) in row.into_iter().enumerate() {
+            field[y][x] = cell;
+        }
+    }
+    Ok(field)
+}